@@ -76,6 +76,7 @@ use crate::{
                 surface::{BlendShape, Surface, SurfaceResource},
                 BatchingMode, RenderPath,
             },
+            navmesh::{NavmeshRegion, NavmeshRegionShape},
             node::Node,
             particle_system::{
                 emitter::{
@@ -382,6 +383,9 @@ pub fn make_property_editors_container(sender: MessageSender) -> PropertyEditorD
     container.register_inheritable_vec_collection::<BlendShape>();
     container.register_inheritable_inspectable::<BlendShape>();
 
+    container.register_inheritable_vec_collection::<NavmeshRegion>();
+    container.register_inheritable_inspectable::<NavmeshRegion>();
+
     container.register_inheritable_option::<ColorGradingLut>();
     container.register_inheritable_option::<Biquad>();
     container.register_inheritable_option::<SkyBox>();
@@ -405,6 +409,7 @@ pub fn make_property_editors_container(sender: MessageSender) -> PropertyEditorD
     container.register_inheritable_enum::<DistanceModel, _>();
     container.register_inheritable_enum::<sound::Renderer, _>();
     container.register_inheritable_enum::<RenderPath, _>();
+    container.register_inheritable_enum::<NavmeshRegionShape, _>();
 
     container.insert(EnumPropertyEditorDefinition::<Vec<ScriptRecord>>::new_optional());
     container.insert(VecCollectionPropertyEditorDefinition::<ScriptRecord>::new());
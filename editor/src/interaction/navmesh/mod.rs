@@ -22,7 +22,7 @@ use crate::command::{Command, CommandGroup};
 use crate::fyrox::graph::SceneGraph;
 use crate::fyrox::{
     core::{
-        algebra::{Vector2, Vector3},
+        algebra::{Matrix4, Vector2, Vector3},
         color::Color,
         math::{ray::CylinderKind, TriangleEdge},
         pool::Handle,
@@ -292,10 +292,18 @@ impl InteractionMode for EditNavmeshMode {
                     self.plane_kind = plane_kind;
                     self.drag_context = Some(DragContext::MoveSelection { initial_positions });
                 }
-            } else if let Some(navmesh) = graph
-                .try_get_of_type::<NavigationalMesh>(selection.navmesh_node())
-                .map(|n| n.navmesh_ref())
+            } else if let Some(navigational_mesh) =
+                graph.try_get_of_type::<NavigationalMesh>(selection.navmesh_node())
             {
+                // Vertices are stored in the local space of the node, but picking is done in the
+                // world space.
+                let navmesh = navigational_mesh.navmesh_ref();
+                let vertices = navmesh
+                    .vertices()
+                    .iter()
+                    .map(|vertex| navigational_mesh.local_to_world(*vertex))
+                    .collect::<Vec<_>>();
+
                 let mut new_selection = if engine
                     .user_interfaces
                     .first_mut()
@@ -308,7 +316,7 @@ impl InteractionMode for EditNavmeshMode {
                 };
 
                 let mut picked = false;
-                for (index, vertex) in navmesh.vertices().iter().enumerate() {
+                for (index, vertex) in vertices.iter().enumerate() {
                     if ray
                         .sphere_intersection(vertex, settings.navmesh.vertex_radius)
                         .is_some()
@@ -322,8 +330,8 @@ impl InteractionMode for EditNavmeshMode {
                 if !picked {
                     for triangle in navmesh.triangles().iter() {
                         for edge in &triangle.edges() {
-                            let begin = navmesh.vertices()[edge.a as usize];
-                            let end = navmesh.vertices()[edge.b as usize];
+                            let begin = vertices[edge.a as usize];
+                            let end = vertices[edge.b as usize];
                             if ray
                                 .cylinder_intersection(
                                     &begin,
@@ -458,10 +466,18 @@ impl InteractionMode for EditNavmeshMode {
         );
 
         if let Some(selection) = fetch_selection(editor_selection) {
-            if let Some(mut navmesh) = graph
-                .try_get_mut_of_type::<NavigationalMesh>(selection.navmesh_node())
-                .map(|n| n.navmesh_mut())
+            if let Some(navigational_mesh) =
+                graph.try_get_mut_of_type::<NavigationalMesh>(selection.navmesh_node())
             {
+                // The gizmo moves in the world space, but the vertices are stored in the local
+                // space of the node.
+                let offset = navigational_mesh
+                    .global_transform()
+                    .try_inverse()
+                    .unwrap_or_else(Matrix4::identity)
+                    .transform_vector(&offset);
+                let mut navmesh = navigational_mesh.navmesh_mut();
+
                 // If we're dragging single edge it is possible to enter edge duplication mode by
                 // holding Shift key. This is the main navmesh construction mode.
                 if selection.entities().len() == 1 {
@@ -532,16 +548,22 @@ impl InteractionMode for EditNavmeshMode {
             let mut gizmo_visible = false;
             let mut gizmo_position = Default::default();
 
-            if let Some(navmesh) = scene
+            if let Some(navigational_mesh) = scene
                 .graph
-                .try_get_mut_of_type::<NavigationalMesh>(selection.navmesh_node())
-                .map(|n| n.navmesh_mut())
+                .try_get_of_type::<NavigationalMesh>(selection.navmesh_node())
             {
+                // Vertices are stored in the local space of the node, but drawn in the world
+                // space.
+                let navmesh = navigational_mesh.navmesh_ref();
+                let vertex_position =
+                    |index: usize| navigational_mesh.local_to_world(navmesh.vertices()[index]);
+
                 if let Some(DragContext::EdgeDuplication {
                     vertices,
                     opposite_edge,
                 }) = self.drag_context.as_ref()
                 {
+                    let vertices = vertices.map(|vertex| navigational_mesh.local_to_world(vertex));
                     for vertex in vertices.iter() {
                         scene.drawing_context.draw_sphere(
                             *vertex,
@@ -552,9 +574,9 @@ impl InteractionMode for EditNavmeshMode {
                         );
                     }
 
-                    let ob = navmesh.vertices()[opposite_edge.a as usize];
+                    let ob = vertex_position(opposite_edge.a as usize);
                     let nb = vertices[0];
-                    let oe = navmesh.vertices()[opposite_edge.b as usize];
+                    let oe = vertex_position(opposite_edge.b as usize);
                     let ne = vertices[1];
 
                     scene.drawing_context.add_line(fyrox::scene::debug::Line {
@@ -578,10 +600,10 @@ impl InteractionMode for EditNavmeshMode {
                 if let Some(first) = selection.first() {
                     gizmo_visible = true;
                     gizmo_position = match *first {
-                        NavmeshEntity::Vertex(v) => navmesh.vertices()[v],
+                        NavmeshEntity::Vertex(v) => vertex_position(v),
                         NavmeshEntity::Edge(edge) => {
-                            let a = navmesh.vertices()[edge.a as usize];
-                            let b = navmesh.vertices()[edge.b as usize];
+                            let a = vertex_position(edge.a as usize);
+                            let b = vertex_position(edge.b as usize);
                             (a + b).scale(0.5)
                         }
                     };
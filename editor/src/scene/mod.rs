@@ -358,10 +358,14 @@ impl GameScene {
             } else if let Some(navmesh) = node.query_component_ref::<NavigationalMesh>() {
                 if settings.navmesh.draw_all {
                     let selection = editor_selection.as_navmesh();
+                    // Picking works in world space, so the vertices are drawn there as well.
+                    let vertex_position = |index: usize| {
+                        navmesh.local_to_world(navmesh.navmesh_ref().vertices()[index])
+                    };
 
-                    for (index, vertex) in navmesh.navmesh_ref().vertices().iter().enumerate() {
+                    for index in 0..navmesh.navmesh_ref().vertices().len() {
                         ctx.draw_sphere(
-                            *vertex,
+                            vertex_position(index),
                             10,
                             10,
                             settings.navmesh.vertex_radius,
@@ -378,8 +382,8 @@ impl GameScene {
                     for triangle in navmesh.navmesh_ref().triangles().iter() {
                         for edge in &triangle.edges() {
                             ctx.add_line(Line {
-                                begin: navmesh.navmesh_ref().vertices()[edge.a as usize],
-                                end: navmesh.navmesh_ref().vertices()[edge.b as usize],
+                                begin: vertex_position(edge.a as usize),
+                                end: vertex_position(edge.b as usize),
                                 color: selection.map_or(Color::GREEN, |s| {
                                     if s.contains_edge(*edge) {
                                         Color::RED
@@ -742,6 +742,31 @@ impl Graph {
         self.lightmap.as_ref()
     }
 
+    /// Combines the navmeshes of all globally enabled [`navmesh::NavigationalMesh`] nodes of the
+    /// graph into a single [`navmesh::AggregatedNavmesh`], so the paths could go across multiple
+    /// navigational meshes. The navmeshes are transformed to world space using the global
+    /// transforms of their nodes. Vertices of adjacent navmeshes, that are closer than
+    /// `weld_tolerance`, are welded together. The aggregated navmesh is a snapshot, it must be
+    /// collected again if any of the source navmeshes has changed.
+    pub fn collect_navmeshes(&self, weld_tolerance: f32) -> navmesh::AggregatedNavmesh {
+        let mut aggregated = navmesh::AggregatedNavmesh::new();
+        for (handle, node) in self.pool.pair_iter() {
+            if let Some(navigational_mesh) = node.cast::<navmesh::NavigationalMesh>() {
+                if navigational_mesh.is_globally_enabled() {
+                    let transform = navigational_mesh.global_transform();
+                    if transform == Matrix4::identity() {
+                        aggregated.add(handle, &navigational_mesh.navmesh_ref(), weld_tolerance);
+                    } else {
+                        let mut world_navmesh = navigational_mesh.navmesh_ref().clone();
+                        world_navmesh.transform(&transform);
+                        aggregated.add(handle, &world_navmesh, weld_tolerance);
+                    }
+                }
+            }
+        }
+        aggregated
+    }
+
     fn apply_lightmap(&mut self) {
         // Re-apply lightmap if any. This has to be done after resolve because we must patch surface
         // data at this stage, but if we'd do this before we wouldn't be able to do this because
@@ -1672,6 +1697,7 @@ mod test {
         core::{
             algebra::{Matrix4, Vector3},
             futures::executor::block_on,
+            math::TriangleDefinition,
             pool::Handle,
             reflect::prelude::*,
             type_traits::prelude::*,
@@ -1682,17 +1708,23 @@ mod test {
         resource::model::{Model, ModelResourceExtension},
         scene::{
             base::BaseBuilder,
+            debug::SceneDrawingContext,
             graph::Graph,
             mesh::{
                 surface::{SurfaceBuilder, SurfaceData, SurfaceResource},
                 MeshBuilder,
             },
+            navmesh::{
+                NavigationalMeshBuilder, NavmeshRegion, NavmeshRegionShape,
+                NAVMESH_OFFMESH_LINK_COLOR,
+            },
             node::Node,
             pivot::{Pivot, PivotBuilder},
             transform::TransformBuilder,
             Scene, SceneLoader,
         },
         script::ScriptTrait,
+        utils::navmesh::{test::make_two_squares_navmesh, Navmesh, NavmeshAgent},
     };
     use fyrox_resource::untyped::ResourceKind;
     use std::{fs, path::Path, sync::Arc};
@@ -1806,6 +1838,276 @@ mod test {
         );
     }
 
+    // A flat quad made of two triangles, that spans from `x` to `x + width` along X and from 0 to
+    // 1 along Z.
+    fn make_quad_navmesh(x: f32, width: f32) -> Navmesh {
+        Navmesh::new(
+            vec![TriangleDefinition([0, 1, 2]), TriangleDefinition([0, 2, 3])],
+            vec![
+                Vector3::new(x, 0.0, 0.0),
+                Vector3::new(x, 0.0, 1.0),
+                Vector3::new(x + width, 0.0, 1.0),
+                Vector3::new(x + width, 0.0, 0.0),
+            ],
+        )
+    }
+
+    #[test]
+    fn test_collect_navmeshes() {
+        let mut graph = Graph::new();
+        let first = NavigationalMeshBuilder::new(BaseBuilder::new())
+            .with_navmesh(make_quad_navmesh(0.0, 1.0))
+            .build(&mut graph);
+        let second = NavigationalMeshBuilder::new(BaseBuilder::new())
+            .with_navmesh(make_quad_navmesh(1.0, 1.0))
+            .build(&mut graph);
+        // Disabled navmeshes are ignored.
+        NavigationalMeshBuilder::new(BaseBuilder::new().with_enabled(false))
+            .with_navmesh(make_quad_navmesh(5.0, 1.0))
+            .build(&mut graph);
+        graph.update_hierarchical_data();
+
+        let aggregated = graph.collect_navmeshes(0.01);
+        assert_eq!(aggregated.sources(), &[first, second]);
+        assert_eq!(aggregated.triangles().len(), 4);
+        assert_eq!(aggregated.vertices().len(), 6);
+        assert_eq!(
+            aggregated.source_at(Vector3::new(0.5, 0.0, 0.5)),
+            Some(first)
+        );
+        assert_eq!(
+            aggregated.source_at(Vector3::new(1.5, 0.0, 0.5)),
+            Some(second)
+        );
+
+        // Agents could path across the boundary of the navmeshes.
+        let mut agent = NavmeshAgent::new();
+        agent.set_position(Vector3::new(0.5, 0.0, 0.5));
+        agent.set_target(Vector3::new(1.5, 0.0, 0.5));
+        agent.set_speed(1.0);
+        for _ in 0..30 {
+            agent.update(0.1, &aggregated).unwrap();
+        }
+        assert!(agent.target_reached());
+    }
+
+    #[test]
+    fn test_navmesh_world_transform() {
+        let navmesh = make_quad_navmesh(0.0, 2.0);
+
+        let mut graph = Graph::new();
+        let handle = NavigationalMeshBuilder::new(
+            BaseBuilder::new().with_local_transform(
+                TransformBuilder::new()
+                    .with_local_position(Vector3::new(10.0, 1.0, 0.0))
+                    .build(),
+            ),
+        )
+        .with_navmesh(navmesh)
+        .build(&mut graph);
+        graph.update_hierarchical_data();
+
+        let navigational_mesh = graph[handle].as_navigational_mesh();
+        let world = Vector3::new(10.5, 1.0, 0.5);
+        assert!(
+            navigational_mesh
+                .world_to_local(world)
+                .metric_distance(&Vector3::new(0.5, 0.0, 0.5))
+                < 1.0e-5
+        );
+        assert_eq!(
+            navigational_mesh.local_to_world(Vector3::new(0.5, 0.0, 0.5)),
+            world
+        );
+
+        // The agent works in world space through the node.
+        let mut agent = NavmeshAgent::new();
+        agent.set_speed(1.0);
+        navigational_mesh.set_agent_position(&mut agent, world);
+        navigational_mesh.set_agent_target(&mut agent, Vector3::new(11.5, 1.0, 0.5));
+        for _ in 0..30 {
+            navigational_mesh.update_agent(&mut agent, 0.1).unwrap();
+        }
+        assert!(agent.target_reached());
+        assert!(
+            navigational_mesh
+                .agent_position(&agent)
+                .metric_distance(&Vector3::new(11.5, 1.0, 0.5))
+                < 0.1
+        );
+
+        // Debug drawing is done in world space.
+        let mut ctx = SceneDrawingContext::default();
+        navigational_mesh.draw(&mut ctx);
+        assert!(!ctx.lines.is_empty());
+        assert!(ctx
+            .lines
+            .iter()
+            .all(|line| line.begin.x >= 10.0 && line.begin.y == 1.0));
+
+        // Bounding box follows the node.
+        let bounding_box = graph[handle].world_bounding_box();
+        assert_eq!(bounding_box.min, Vector3::new(10.0, 1.0, 0.0));
+        assert_eq!(bounding_box.max, Vector3::new(12.0, 1.0, 1.0));
+
+        // Aggregated navmeshes are in world space.
+        let aggregated = graph.collect_navmeshes(0.01);
+        assert_eq!(aggregated.source_at(world), Some(handle));
+        assert!(aggregated.vertices().iter().all(|v| v.x >= 10.0));
+
+        // Every island has its own color.
+        let islands = NavigationalMeshBuilder::new(BaseBuilder::new())
+            .with_navmesh(make_two_squares_navmesh())
+            .build(&mut graph);
+        let mut ctx = SceneDrawingContext::default();
+        graph[islands]
+            .as_navigational_mesh()
+            .draw_components(&mut ctx);
+        let mut colors = ctx.lines.iter().map(|line| line.color).collect::<Vec<_>>();
+        colors.dedup();
+        assert_eq!(colors.len(), 2);
+    }
+
+    #[test]
+    fn test_navmesh_offmesh_link_arrow() {
+        // A one-way link between the squares of a Z-up navmesh.
+        let mut navmesh = make_two_squares_navmesh();
+        navmesh.add_offmesh_link(
+            Vector3::new(0.5, 1.0, 0.5),
+            Vector3::new(2.5, 1.0, 0.5),
+            false,
+        );
+        navmesh.transform(&Matrix4::new_rotation(
+            Vector3::x() * std::f32::consts::FRAC_PI_2,
+        ));
+        navmesh.set_up_axis(Vector3::z());
+
+        let mut graph = Graph::new();
+        let handle = NavigationalMeshBuilder::new(BaseBuilder::new())
+            .with_navmesh(navmesh)
+            .build(&mut graph);
+        graph.update_hierarchical_data();
+
+        let mut ctx = SceneDrawingContext::default();
+        graph[handle].as_navigational_mesh().draw(&mut ctx);
+        let arrow = ctx
+            .lines
+            .iter()
+            .filter(|line| line.color == NAVMESH_OFFMESH_LINK_COLOR)
+            .skip(1)
+            .collect::<Vec<_>>();
+        assert_eq!(arrow.len(), 2);
+        // The link is horizontal, so is its arrow.
+        assert!(arrow
+            .iter()
+            .all(|line| (line.end.z - line.begin.z).abs() < 1.0e-5));
+    }
+
+    #[test]
+    fn test_navmesh_sharing() {
+        let navmesh = make_quad_navmesh(0.0, 1.0);
+        let mut graph = Graph::new();
+        let handle = NavigationalMeshBuilder::new(BaseBuilder::new())
+            .with_navmesh(navmesh)
+            .build(&mut graph);
+        let original = graph[handle].as_navigational_mesh_mut();
+        assert!(!original.is_navmesh_shared());
+
+        // Clones share the same navmesh.
+        let mut copy = original.clone();
+        assert!(original.is_navmesh_shared());
+        assert!(copy.is_navmesh_shared());
+        assert!(Arc::ptr_eq(&original.navmesh(), &copy.navmesh()));
+
+        // Reading does not copy the navmesh.
+        assert_eq!(copy.navmesh_ref().triangle_cost(0), Some(1.0));
+        assert!(copy.is_navmesh_shared());
+
+        // The modified clone gets its own copy.
+        copy.navmesh_mut().set_triangle_cost(0, 3.0);
+        assert!(!copy.is_navmesh_shared());
+        assert!(!original.is_navmesh_shared());
+        assert!(!Arc::ptr_eq(&original.navmesh(), &copy.navmesh()));
+        assert_eq!(copy.navmesh_ref().triangle_cost(0), Some(3.0));
+        assert_eq!(original.navmesh_ref().triangle_cost(0), Some(1.0));
+
+        // Off-thread handles are not clones.
+        let handle = original.navmesh();
+        assert!(!original.is_navmesh_shared());
+        original.navmesh_mut().set_triangle_cost(1, 2.0);
+        assert_eq!(handle.read().triangle_cost(1), Some(2.0));
+    }
+
+    #[test]
+    fn test_navmesh_regions() {
+        let navmesh = make_quad_navmesh(0.0, 2.0);
+        let region = |x: f32| NavmeshRegion {
+            name: "Mud".to_string(),
+            position: Vector3::new(x, 0.0, 0.5),
+            shape: NavmeshRegionShape::Box {
+                half_extents: Vector3::new(0.5, 0.5, 0.5),
+            },
+            flags: 0b10,
+            cost: 4.0,
+        };
+
+        let mut graph = Graph::new();
+        let handle = NavigationalMeshBuilder::new(BaseBuilder::new())
+            .with_navmesh(navmesh)
+            .with_regions(vec![region(0.5)])
+            .build(&mut graph);
+
+        // Regions are stamped when the node is built.
+        let navigational_mesh = graph[handle].as_navigational_mesh_mut();
+        assert_eq!(
+            navigational_mesh.navmesh_ref().triangle_flags(0),
+            Some(0b10)
+        );
+        assert_eq!(navigational_mesh.navmesh_ref().triangle_cost(0), Some(4.0));
+        assert_eq!(navigational_mesh.navmesh_ref().triangle_flags(1), Some(0));
+        assert_eq!(navigational_mesh.navmesh_ref().triangle_cost(1), Some(1.0));
+
+        // Stamping does not copy a shared navmesh, if it is stamped already.
+        let mut clone = navigational_mesh.clone();
+        clone.apply_regions();
+        assert!(clone.is_navmesh_shared());
+        drop(clone);
+
+        // Regions edited via reflection are stamped onto a copy of the shared navmesh.
+        let mut clone = navigational_mesh.clone();
+        clone.get_resolve_path_mut::<f32>("regions[0].cost", &mut |cost| {
+            *cost.unwrap() = 8.0;
+        });
+        clone.apply_regions();
+        assert!(!clone.is_navmesh_shared());
+        assert_eq!(clone.navmesh_ref().triangle_cost(0), Some(8.0));
+        assert_eq!(navigational_mesh.navmesh_ref().triangle_cost(0), Some(4.0));
+        drop(clone);
+
+        // Moving the region re-stamps the triangles on the next update.
+        navigational_mesh.set_regions(vec![region(1.5)]);
+        graph.update(Default::default(), 0.0, Default::default());
+        let navigational_mesh = graph[handle].as_navigational_mesh_mut();
+        assert_eq!(navigational_mesh.navmesh_ref().triangle_flags(0), Some(0));
+        assert_eq!(navigational_mesh.navmesh_ref().triangle_cost(0), Some(1.0));
+        assert_eq!(
+            navigational_mesh.navmesh_ref().triangle_flags(1),
+            Some(0b10)
+        );
+        assert_eq!(navigational_mesh.navmesh_ref().triangle_cost(1), Some(4.0));
+
+        // Removing all the regions resets the triangles to defaults.
+        navigational_mesh.set_regions(Vec::new());
+        navigational_mesh.apply_regions();
+        assert_eq!(navigational_mesh.navmesh_ref().triangle_flags(1), Some(0));
+        assert_eq!(navigational_mesh.navmesh_ref().triangle_cost(1), Some(1.0));
+
+        // Manual changes are kept, when there are no regions.
+        navigational_mesh.navmesh_mut().set_triangle_cost(0, 2.0);
+        navigational_mesh.apply_regions();
+        assert_eq!(navigational_mesh.navmesh_ref().triangle_cost(0), Some(2.0));
+    }
+
     #[test]
     fn graph_init_test() {
         let graph = Graph::new();
@@ -1822,6 +2124,76 @@ mod test {
         assert_eq!(graph.pool.alive_count(), 4);
     }
 
+    #[test]
+    fn test_navmesh_from_mesh_node() {
+        let floor = Matrix4::new_rotation(Vector3::x() * std::f32::consts::FRAC_PI_2)
+            * Matrix4::new_scaling(2.0);
+        let ceiling = Matrix4::new_rotation(Vector3::x() * -std::f32::consts::FRAC_PI_2)
+            * Matrix4::new_scaling(2.0);
+        let surface = |transform: Matrix4<f32>| {
+            SurfaceBuilder::new(SurfaceResource::new_ok(
+                ResourceKind::Embedded,
+                SurfaceData::make_quad(&transform),
+            ))
+            .build()
+        };
+
+        let mut graph = Graph::new();
+        // Two adjacent floor quads, a wall and a ceiling, every one is a separate surface.
+        let mesh = MeshBuilder::new(
+            BaseBuilder::new().with_local_transform(
+                TransformBuilder::new()
+                    .with_local_position(Vector3::new(5.0, 0.0, 0.0))
+                    .build(),
+            ),
+        )
+        .with_surfaces(vec![
+            surface(floor),
+            surface(Matrix4::new_translation(&Vector3::new(2.0, 0.0, 0.0)) * floor),
+            surface(
+                Matrix4::new_translation(&Vector3::new(0.0, 1.0, 1.0)) * Matrix4::new_scaling(2.0),
+            ),
+            surface(Matrix4::new_translation(&Vector3::new(0.0, 2.0, 0.0)) * ceiling),
+        ])
+        .build(&mut graph);
+        graph.update_hierarchical_data();
+
+        // The geometry of all the surfaces is used.
+        let handle = NavigationalMeshBuilder::from_mesh_node(&graph, mesh).build(&mut graph);
+        let navmesh = graph[handle].as_navigational_mesh().navmesh_ref().clone();
+        assert_eq!(navmesh.triangles().len(), 8);
+        assert!(navmesh.vertices().iter().all(|v| v.x >= 4.0 && v.x <= 8.0));
+
+        // The wall is too steep and the ceiling faces down, the floor quads are connected across
+        // the surfaces.
+        let handle = NavigationalMeshBuilder::from_mesh_node_with_max_slope(
+            &graph,
+            mesh,
+            45.0f32.to_radians(),
+        )
+        .build(&mut graph);
+        let navmesh = graph[handle].as_navigational_mesh().navmesh_ref().clone();
+        assert_eq!(navmesh.triangles().len(), 4);
+        assert!(navmesh.vertices().iter().all(|v| v.y.abs() < 1e-5));
+        assert_eq!(navmesh.connected_components().len(), 1);
+
+        // The slope is measured against the given up axis.
+        let navmesh =
+            Navmesh::from_mesh_node(&graph, mesh, -Vector3::y(), Some(45.0f32.to_radians()));
+        assert_eq!(navmesh.triangles().len(), 2);
+        assert!(navmesh.vertices().iter().all(|v| (v.y - 2.0).abs() < 1e-5));
+        assert_eq!(navmesh.up_axis(), -Vector3::y());
+
+        // Other nodes produce an empty navmesh.
+        let pivot = PivotBuilder::new(BaseBuilder::new()).build(&mut graph);
+        let handle = NavigationalMeshBuilder::from_mesh_node(&graph, pivot).build(&mut graph);
+        assert!(graph[handle]
+            .as_navigational_mesh()
+            .navmesh_ref()
+            .triangles()
+            .is_empty());
+    }
+
     #[test]
     fn test_graph_search() {
         let mut graph = Graph::new();
@@ -23,7 +23,8 @@
 
 use crate::{
     core::{
-        color::Color,
+        algebra::{Matrix4, Point3, UnitQuaternion, Vector3},
+        color::{Color, Hsv},
         math::aabb::AxisAlignedBoundingBox,
         parking_lot::RwLock,
         pool::Handle,
@@ -37,32 +38,150 @@ use crate::{
         base::{Base, BaseBuilder},
         debug::{Line, SceneDrawingContext},
         graph::Graph,
-        node::{Node, NodeTrait},
+        node::{Node, NodeTrait, UpdateContext},
+    },
+    utils::{
+        astar::PathKind,
+        navmesh::{
+            Navmesh, NavmeshAgent, NavmeshPathError, OffMeshLinkHandle, NAVMESH_STEP_GAP_TOLERANCE,
+        },
     },
-    utils::navmesh::Navmesh,
 };
 use fyrox_core::parking_lot::{RwLockReadGuard, RwLockWriteGuard};
 use fyrox_graph::BaseSceneGraph;
 use std::{
+    f32::consts::FRAC_PI_2,
     ops::{Deref, DerefMut},
     sync::Arc,
 };
+use strum_macros::{AsRefStr, EnumString, VariantNames};
 
+// Clones of the container share the same navmesh, the navmesh is copied only when one of the
+// clones is about to be modified.
 #[derive(Clone, Default, Reflect, Debug)]
-pub(crate) struct Container(Arc<RwLock<Navmesh>>);
+pub(crate) struct Container {
+    navmesh: Arc<RwLock<Navmesh>>,
+    // Shared by all the clones of the container. Unlike `navmesh`, it is never handed out, so
+    // off-thread handles of the navmesh do not count as clones.
+    #[reflect(hidden)]
+    clones: Arc<()>,
+}
+
+impl Container {
+    fn new(navmesh: Navmesh) -> Self {
+        Self {
+            navmesh: Arc::new(RwLock::new(navmesh)),
+            clones: Default::default(),
+        }
+    }
+
+    fn is_shared(&self) -> bool {
+        Arc::strong_count(&self.clones) > 1
+    }
+
+    fn make_unique(&mut self) {
+        if self.is_shared() {
+            let navmesh = self.navmesh.read().clone();
+            *self = Self::new(navmesh);
+        }
+    }
+}
 
 impl PartialEq for Container {
     fn eq(&self, other: &Self) -> bool {
-        *self.0.read() == *other.0.read()
+        Arc::ptr_eq(&self.navmesh, &other.navmesh) || *self.navmesh.read() == *other.navmesh.read()
     }
 }
 
 impl Visit for Container {
     fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
-        self.0.write().visit(name, visitor)
+        if visitor.is_reading() {
+            self.make_unique();
+        }
+        self.navmesh.write().visit(name, visitor)
+    }
+}
+
+/// A shape of a [`NavmeshRegion`].
+#[derive(
+    Clone, Debug, PartialEq, Visit, Reflect, AsRefStr, EnumString, VariantNames, TypeUuidProvider,
+)]
+#[type_uuid(id = "307de966-d71b-4314-9f57-44d2f50bc518")]
+pub enum NavmeshRegionShape {
+    /// Axis-aligned box.
+    Box {
+        /// Half of the size of the box along each axis.
+        half_extents: Vector3<f32>,
+    },
+    /// Sphere.
+    Sphere {
+        /// Radius of the sphere.
+        radius: f32,
+    },
+}
+
+impl Default for NavmeshRegionShape {
+    fn default() -> Self {
+        Self::Box {
+            half_extents: Vector3::repeat(0.5),
+        }
     }
 }
 
+/// A volumetric brush, that assigns area flags and traversal cost to every triangle of a
+/// [`NavigationalMesh`], whose centroid is inside the brush. Regions are defined in the local
+/// space of the navigational mesh (the same space as the vertices of the inner navmesh). See
+/// [`NavigationalMesh::set_regions`] for more info.
+#[derive(Clone, Debug, PartialEq, Visit, Reflect, TypeUuidProvider)]
+#[type_uuid(id = "ec1e9466-775a-465c-9fea-80516d66c842")]
+pub struct NavmeshRegion {
+    /// Name of the region. It is used only for identification purposes.
+    pub name: String,
+    /// Position of the center of the region in the local space of the navigational mesh.
+    pub position: Vector3<f32>,
+    /// Shape of the region.
+    pub shape: NavmeshRegionShape,
+    /// Area flags of the region. See [`Navmesh::set_triangle_flags`] for more info.
+    pub flags: u32,
+    /// Traversal cost multiplier of the region. See [`Navmesh::set_triangle_cost`] for more
+    /// info.
+    pub cost: f32,
+}
+
+impl Default for NavmeshRegion {
+    fn default() -> Self {
+        Self {
+            name: "Region".to_string(),
+            position: Default::default(),
+            shape: Default::default(),
+            flags: 0,
+            cost: 1.0,
+        }
+    }
+}
+
+impl NavmeshRegion {
+    /// Checks whether the given point (in the local space of the navigational mesh) is inside
+    /// the region.
+    pub fn contains(&self, point: Vector3<f32>) -> bool {
+        let offset = point - self.position;
+        match self.shape {
+            NavmeshRegionShape::Box { half_extents } => {
+                offset.x.abs() <= half_extents.x
+                    && offset.y.abs() <= half_extents.y
+                    && offset.z.abs() <= half_extents.z
+            }
+            NavmeshRegionShape::Sphere { radius } => offset.norm_squared() <= radius * radius,
+        }
+    }
+}
+
+#[derive(Clone, Debug, Default)]
+struct StampedRegions {
+    regions: Vec<NavmeshRegion>,
+    topology_revision: u64,
+}
+
 /// Navigational mesh (navmesh for short) is a surface which can be used for path finding. Unlike [A* Pathfinder](crate::utils::astar),
 /// it can build arbitrary paths on a surface of large polygons, making a path from point A to point B linear (standard pathfinder builds
 /// path only from vertex to vertex). Navmeshes should be used when you have an arbitrary "walkable" surface, for example, a game level
@@ -150,11 +269,29 @@ impl Visit for Container {
 ///     scene.graph[handle].as_navigational_mesh_mut()
 /// }
 /// ```
+///
+/// ## Regions
+///
+/// Area flags and traversal costs of the triangles could be painted using volumetric brushes
+/// (see [`NavmeshRegion`]), instead of setting them per triangle. The regions are stamped onto the
+/// navmesh automatically, every time when they or the triangles of the navmesh change. See
+/// [`Self::set_regions`] for more info.
+///
+/// ## Sharing
+///
+/// Navmeshes could be huge, so clones of a navigational mesh (for example, instances of a prefab
+/// or copies of a scene) share the same inner navmesh, instead of copying it. The navmesh is
+/// copied only when one of the clones is modified (copy-on-write), see
+/// [`Self::is_navmesh_shared`] for more info.
 #[derive(Debug, Clone, Visit, Reflect, Default)]
 pub struct NavigationalMesh {
     base: Base,
-    #[reflect(read_only)]
     navmesh: InheritableVariable<Container>,
+    #[visit(optional)]
+    regions: InheritableVariable<Vec<NavmeshRegion>>,
+    #[visit(skip)]
+    #[reflect(hidden)]
+    stamped_regions: Option<StampedRegions>,
 }
 
 impl TypeUuidProvider for NavigationalMesh {
@@ -181,11 +318,17 @@ impl NodeTrait for NavigationalMesh {
     crate::impl_query_component!();
 
     fn local_bounding_box(&self) -> AxisAlignedBoundingBox {
-        self.base.local_bounding_box()
+        let bounding_box = self.navmesh_ref().bounding_box();
+        if bounding_box.is_valid() {
+            bounding_box
+        } else {
+            self.base.local_bounding_box()
+        }
     }
 
     fn world_bounding_box(&self) -> AxisAlignedBoundingBox {
-        self.base.world_bounding_box()
+        self.local_bounding_box()
+            .transform(&self.global_transform())
     }
 
     fn id(&self) -> Uuid {
@@ -193,39 +336,292 @@ impl NodeTrait for NavigationalMesh {
     }
 
     fn debug_draw(&self, ctx: &mut SceneDrawingContext) {
-        let navmesh = self.navmesh.0.read();
-
-        for vertex in navmesh.vertices().iter() {
-            ctx.draw_sphere(*vertex, 6, 6, 0.1, Color::GREEN);
-        }
+        self.draw(ctx);
+    }
 
-        for triangle in navmesh.triangles().iter() {
-            for edge in &triangle.edges() {
-                ctx.add_line(Line {
-                    begin: navmesh.vertices()[edge.a as usize],
-                    end: navmesh.vertices()[edge.b as usize],
-                    color: Color::GREEN,
-                });
-            }
-        }
+    fn update(&mut self, _context: &mut UpdateContext) {
+        self.stamp_regions(false);
     }
 }
 
 impl NavigationalMesh {
     /// Returns a reference to the inner navigational mesh.
     pub fn navmesh_ref(&self) -> RwLockReadGuard<Navmesh> {
-        self.navmesh.0.read()
+        self.navmesh.navmesh.read()
     }
 
-    /// Returns a reference to the inner navigational mesh.
+    /// Returns a reference to the inner navigational mesh. If the navmesh is shared with other
+    /// clones of the navigational mesh, it is copied first, so the changes do not affect the
+    /// clones.
     pub fn navmesh_mut(&mut self) -> RwLockWriteGuard<Navmesh> {
-        self.navmesh.0.write()
+        self.navmesh.get_value_mut_silent().make_unique();
+        self.navmesh.navmesh.write()
     }
 
     /// Returns a shared reference to the inner navigational mesh. It could be used to perform
     /// off-thread path calculations.
+    ///
+    /// Keep in mind, that the returned handle is not tracked by the navigational mesh. If the
+    /// navmesh is shared with other clones of the navigational mesh (see
+    /// [`Self::is_navmesh_shared`]), the next modification (using [`Self::navmesh_mut`],
+    /// [`Self::set_regions`] or re-stamping of the regions) silently detaches the handle: the
+    /// navigational mesh gets a new copy of the navmesh, while the handle keeps pointing to the
+    /// old one. Request a new handle after such modifications, or compare the handles using
+    /// [`Arc::ptr_eq`] to detect the detachment.
     pub fn navmesh(&self) -> Arc<RwLock<Navmesh>> {
-        self.navmesh.0.clone()
+        self.navmesh.navmesh.clone()
+    }
+
+    /// Returns `true` if the inner navmesh is shared with other clones of the navigational mesh.
+    /// Cloning a navigational mesh is cheap, since the clones share the same navmesh until one of
+    /// them is modified using [`Self::navmesh_mut`] or [`Self::set_regions`]. The modified clone
+    /// gets its own copy of the navmesh, the other clones keep the original one.
+    pub fn is_navmesh_shared(&self) -> bool {
+        self.navmesh.is_shared()
+    }
+
+    /// Transforms the given point from the local space of the navigational mesh (the space of the
+    /// vertices of the inner navmesh) to the world space, using the global transform of the node.
+    pub fn local_to_world(&self, point: Vector3<f32>) -> Vector3<f32> {
+        self.global_transform()
+            .transform_point(&Point3::from(point))
+            .coords
+    }
+
+    /// Transforms the given point from the world space to the local space of the navigational
+    /// mesh (the space of the vertices of the inner navmesh), using the inverse of the global
+    /// transform of the node.
+    pub fn world_to_local(&self, point: Vector3<f32>) -> Vector3<f32> {
+        self.global_transform()
+            .try_inverse()
+            .unwrap_or_else(Matrix4::identity)
+            .transform_point(&Point3::from(point))
+            .coords
+    }
+
+    /// Sets a new target (in world space) of the given agent. The agent itself works in the local
+    /// space of the navigational mesh, so the same navmesh could be placed anywhere in the scene.
+    /// All the distances of the agent (speed, radius, etc.) are in the local space as well. Use
+    /// [`Self::update_agent`] and [`Self::agent_position`] to drive the agent.
+    pub fn set_agent_target(&self, agent: &mut NavmeshAgent, target: Vector3<f32>) {
+        agent.set_target(self.world_to_local(target));
+    }
+
+    /// Sets a new position (in world space) of the given agent. See [`Self::set_agent_target`] for
+    /// more info.
+    pub fn set_agent_position(&self, agent: &mut NavmeshAgent, position: Vector3<f32>) {
+        agent.set_position(self.world_to_local(position));
+    }
+
+    /// Returns the position (in world space) of the given agent. See [`Self::set_agent_target`]
+    /// for more info.
+    pub fn agent_position(&self, agent: &NavmeshAgent) -> Vector3<f32> {
+        self.local_to_world(agent.position())
+    }
+
+    /// Updates the given agent using the inner navmesh. See [`NavmeshAgent::update`] and
+    /// [`Self::set_agent_target`] for more info.
+    pub fn update_agent(
+        &self,
+        agent: &mut NavmeshAgent,
+        dt: f32,
+    ) -> Result<PathKind, NavmeshPathError> {
+        agent.update(dt, &self.navmesh_ref())
+    }
+
+    /// Sets a new set of region brushes. Every triangle, whose centroid is inside one or more
+    /// regions, gets the flags of all these regions combined together and the cost of the last of
+    /// them. The rest of the triangles get default flags (`0`) and default cost (`1.0`). Keep in
+    /// mind, that the regions overwrite the flags and costs, that were set manually using
+    /// [`Navmesh::set_triangle_flags`] and [`Navmesh::set_triangle_cost`].
+    ///
+    /// The regions are stamped onto the navmesh on the next update of the scene graph, or
+    /// immediately, using [`Self::apply_regions`]. They are also re-stamped automatically when the
+    /// triangles of the inner navmesh were rebuilt (see [`Navmesh::topology_revision`]).
+    pub fn set_regions(&mut self, regions: Vec<NavmeshRegion>) -> Vec<NavmeshRegion> {
+        // The regions are stamped onto the navmesh, the clones must not be affected.
+        self.navmesh.get_value_mut_silent().make_unique();
+        self.regions.set_value_and_mark_modified(regions)
+    }
+
+    /// Returns a slice with all the region brushes of the navigational mesh. See
+    /// [`Self::set_regions`] for more info.
+    pub fn regions(&self) -> &[NavmeshRegion] {
+        &self.regions
+    }
+
+    /// Stamps the region brushes onto the inner navmesh immediately, without waiting for the next
+    /// update of the scene graph. See [`Self::set_regions`] for more info.
+    pub fn apply_regions(&mut self) {
+        self.stamp_regions(true);
+    }
+
+    fn stamp_regions(&mut self, force: bool) {
+        let topology_revision = self.navmesh_ref().topology_revision();
+        match self.stamped_regions.as_ref() {
+            Some(stamped)
+                if !force
+                    && stamped.topology_revision == topology_revision
+                    && stamped.regions == *self.regions =>
+            {
+                return
+            }
+            // Keep the flags and costs untouched, if there were no regions at all.
+            None if self.regions.is_empty() => return,
+            _ => (),
+        }
+
+        let (changes, _) = self
+            .navmesh_ref()
+            .stamp_areas(u32::MAX, Some(1.0), |centroid| {
+                let mut area = None;
+                for region in self.regions.iter() {
+                    if region.contains(centroid) {
+                        let (flags, _) = area.unwrap_or((0, None));
+                        area = Some((flags | region.flags, Some(region.cost)));
+                    }
+                }
+                area
+            });
+
+        // Clones, that share the navmesh, keep it shared, if it is stamped already. Otherwise the
+        // navmesh is copied first, so the clones are not affected.
+        if !changes.is_empty() {
+            self.navmesh_mut().set_triangle_areas(changes);
+        }
+
+        self.stamped_regions = if self.regions.is_empty() {
+            // Flags and costs are reset to defaults, stop tracking until new regions are added.
+            None
+        } else {
+            Some(StampedRegions {
+                regions: (*self.regions).clone(),
+                topology_revision,
+            })
+        };
+    }
+
+    /// Draws the navigational mesh into the given drawing context. Internal edges are drawn
+    /// using [`NAVMESH_EDGE_COLOR`], border edges are drawn using [`NAVMESH_BORDER_COLOR`] and
+    /// off-mesh links are drawn using [`NAVMESH_OFFMESH_LINK_COLOR`]. One-way off-mesh links have
+    /// an arrow at their end.
+    /// Region brushes are drawn using [`NAVMESH_REGION_COLOR`].
+    /// The navmesh is drawn in world space, using the global transform of the node.
+    /// The method only pushes lines into the context, so it is cheap enough to be called every
+    /// frame.
+    pub fn draw(&self, ctx: &mut SceneDrawingContext) {
+        let transform = self.global_transform();
+        draw_navmesh(&self.navmesh_ref(), &transform, ctx, false);
+        for region in self.regions.iter() {
+            match region.shape {
+                NavmeshRegionShape::Box { half_extents } => ctx.draw_oob(
+                    &AxisAlignedBoundingBox::from_min_max(
+                        region.position - half_extents,
+                        region.position + half_extents,
+                    ),
+                    transform,
+                    NAVMESH_REGION_COLOR,
+                ),
+                NavmeshRegionShape::Sphere { radius } => {
+                    // The circles are transformed as a whole, so they are scaled as the navmesh.
+                    let center = transform * Matrix4::new_translation(&region.position);
+                    for rotation in [
+                        Matrix4::identity(),
+                        UnitQuaternion::from_axis_angle(&Vector3::x_axis(), FRAC_PI_2)
+                            .to_homogeneous(),
+                        UnitQuaternion::from_axis_angle(&Vector3::y_axis(), FRAC_PI_2)
+                            .to_homogeneous(),
+                    ] {
+                        ctx.draw_circle(
+                            Default::default(),
+                            radius,
+                            16,
+                            center * rotation,
+                            NAVMESH_REGION_COLOR,
+                        );
+                    }
+                }
+            }
+        }
+    }
+
+    /// Draws the navigational mesh into the given drawing context, the same as [`Self::draw`], but
+    /// every connected component (island) of the navmesh has its own color. It is useful to find
+    /// the parts of the navmesh, that are not reachable from each other.
+    pub fn draw_components(&self, ctx: &mut SceneDrawingContext) {
+        draw_navmesh(&self.navmesh_ref(), &self.global_transform(), ctx, true)
+    }
+}
+
+/// Color of the internal edges of a navmesh, see [`NavigationalMesh::draw`].
+pub const NAVMESH_EDGE_COLOR: Color = Color::GREEN;
+/// Color of the border edges of a navmesh, see [`NavigationalMesh::draw`].
+pub const NAVMESH_BORDER_COLOR: Color = Color::ORANGE;
+/// Color of the off-mesh links of a navmesh, see [`NavigationalMesh::draw`].
+pub const NAVMESH_OFFMESH_LINK_COLOR: Color = Color::CYAN;
+/// Color of the region brushes of a navmesh, see [`NavigationalMesh::draw`].
+pub const NAVMESH_REGION_COLOR: Color = Color::MAGENTA;
+
+fn draw_navmesh(
+    navmesh: &Navmesh,
+    transform: &Matrix4<f32>,
+    ctx: &mut SceneDrawingContext,
+    color_components: bool,
+) {
+    let transform_point =
+        |point: Vector3<f32>| transform.transform_point(&Point3::from(point)).coords;
+
+    let vertices = navmesh.vertices();
+    for edge in navmesh.edges() {
+        let component = navmesh
+            .triangle_component(edge.triangle)
+            .filter(|_| color_components);
+        let color = if let Some(component) = component {
+            // Golden angle gives distinct hues for any number of components.
+            let hue = (component as f32 * 137.508) % 360.0;
+            Color::from(Hsv::new(hue, 90.0, 100.0))
+        } else if edge.is_border() {
+            NAVMESH_BORDER_COLOR
+        } else {
+            NAVMESH_EDGE_COLOR
+        };
+        ctx.add_line(Line {
+            begin: transform_point(vertices[edge.vertices[0] as usize]),
+            end: transform_point(vertices[edge.vertices[1] as usize]),
+            color,
+        });
+    }
+
+    let up = transform.transform_vector(&navmesh.up_axis());
+    for (_, link) in navmesh.offmesh_links() {
+        let (begin, end) = (transform_point(link.from()), transform_point(link.to()));
+        ctx.add_line(Line {
+            begin,
+            end,
+            color: NAVMESH_OFFMESH_LINK_COLOR,
+        });
+
+        if !link.is_bidirectional() {
+            let direction = end - begin;
+            let length = direction.norm();
+            if length > f32::EPSILON {
+                let direction = direction.scale(1.0 / length);
+                // The arrow is spread on the horizontal plane, vertical links use any side.
+                let side = direction
+                    .cross(&up)
+                    .try_normalize(f32::EPSILON)
+                    .or_else(|| direction.cross(&Vector3::x()).try_normalize(f32::EPSILON))
+                    .unwrap_or_else(Vector3::z);
+                let size = (length * 0.2).min(0.25);
+                for sign in [-1.0, 1.0] {
+                    ctx.add_line(Line {
+                        begin: end,
+                        end: end - direction.scale(size) + side.scale(sign * size * 0.5),
+                        color: NAVMESH_OFFMESH_LINK_COLOR,
+                    });
+                }
+            }
+        }
     }
 }
 
@@ -233,6 +629,10 @@ impl NavigationalMesh {
 pub struct NavigationalMeshBuilder {
     base_builder: BaseBuilder,
     navmesh: Navmesh,
+    regions: Vec<NavmeshRegion>,
+    weld_tolerance: f32,
+    max_step_height: f32,
+    max_drop_height: f32,
 }
 
 impl NavigationalMeshBuilder {
@@ -241,22 +641,105 @@ impl NavigationalMeshBuilder {
         Self {
             base_builder,
             navmesh: Default::default(),
+            regions: Default::default(),
+            weld_tolerance: 0.0,
+            max_step_height: 0.0,
+            max_drop_height: 0.0,
         }
     }
 
+    /// Creates new navigational mesh builder with a navmesh, that is made of the world-space
+    /// geometry of every surface of the given mesh node (see [`Navmesh::from_mesh_node`]). It
+    /// covers the most common case, when a level has a dedicated mesh of the walkable floor. The
+    /// navigational mesh node is placed at the origin, since its navmesh is already in world space.
+    /// The navmesh is empty, if the handle does not point to a mesh node. Y axis is the up axis of
+    /// the navmesh, use [`Navmesh::from_mesh_node`] directly for other up axes.
+    pub fn from_mesh_node(graph: &Graph, node: Handle<Node>) -> Self {
+        Self::new(BaseBuilder::new()).with_navmesh(Navmesh::from_mesh_node(
+            graph,
+            node,
+            Vector3::y(),
+            None,
+        ))
+    }
+
+    /// The same as [`Self::from_mesh_node`], but the triangles, which angle with the horizontal
+    /// plane is larger than `max_slope` (in radians) or which face down, are not included in the
+    /// navmesh.
+    pub fn from_mesh_node_with_max_slope(
+        graph: &Graph,
+        node: Handle<Node>,
+        max_slope: f32,
+    ) -> Self {
+        Self::new(BaseBuilder::new()).with_navmesh(Navmesh::from_mesh_node(
+            graph,
+            node,
+            Vector3::y(),
+            Some(max_slope),
+        ))
+    }
+
     /// Sets the actual navigational mesh.
     pub fn with_navmesh(mut self, navmesh: Navmesh) -> Self {
         self.navmesh = navmesh;
         self
     }
 
-    fn build_navigational_mesh(self) -> NavigationalMesh {
-        NavigationalMesh {
-            base: self.base_builder.build_base(),
-            navmesh: InheritableVariable::new_modified(Container(Arc::new(RwLock::new(
-                self.navmesh,
-            )))),
+    /// Sets the region brushes of the navigational mesh. See [`NavigationalMesh::set_regions`]
+    /// for more info.
+    pub fn with_regions(mut self, regions: Vec<NavmeshRegion>) -> Self {
+        self.regions = regions;
+        self
+    }
+
+    /// Sets the tolerance, that is used to weld the vertices of the navigational mesh on build.
+    /// The vertices, that are closer than the tolerance to each other, are merged, so the
+    /// triangles with coincident (but duplicated) vertices become connected. See
+    /// [`Navmesh::weld`] for more info. Default is zero, which means that the vertices are not
+    /// welded.
+    pub fn with_weld_tolerance(mut self, weld_tolerance: f32) -> Self {
+        self.weld_tolerance = weld_tolerance;
+        self
+    }
+
+    /// Sets the maximum height of a step, that agents could walk up and down. The parts of the
+    /// navigational mesh, that are separated by a smaller vertical gap, are connected by
+    /// bidirectional off-mesh links on build. See [`Navmesh::connect_steps`] for more info.
+    /// Default is zero, which means that the steps are not connected.
+    pub fn with_max_step_height(mut self, max_step_height: f32) -> Self {
+        self.max_step_height = max_step_height;
+        self
+    }
+
+    /// Sets the maximum height of a ledge, that agents could jump down from. The parts of the
+    /// navigational mesh, that are separated by a vertical gap higher than the maximum step
+    /// height, but lower than this height, are connected by one-way off-mesh links on build. See
+    /// [`Navmesh::connect_steps`] for more info. Default is zero, which means that the ledges are
+    /// not connected.
+    pub fn with_max_drop_height(mut self, max_drop_height: f32) -> Self {
+        self.max_drop_height = max_drop_height;
+        self
+    }
+
+    fn build_navigational_mesh(mut self) -> NavigationalMesh {
+        if self.weld_tolerance > 0.0 {
+            self.navmesh.weld(self.weld_tolerance);
         }
+        if self.max_step_height > 0.0 || self.max_drop_height > 0.0 {
+            self.navmesh.connect_steps(
+                self.max_step_height,
+                self.max_drop_height,
+                NAVMESH_STEP_GAP_TOLERANCE,
+            );
+        }
+        let mut navmesh = NavigationalMesh {
+            base: self.base_builder.build_base(),
+            navmesh: InheritableVariable::new_modified(Container::new(self.navmesh)),
+            regions: InheritableVariable::new_modified(self.regions),
+            stamped_regions: Default::default(),
+        };
+        navmesh.apply_regions();
+        navmesh
     }
 
     /// Creates new navigational mesh instance.
@@ -269,3 +752,88 @@ impl NavigationalMeshBuilder {
         graph.add_node(self.build_node())
     }
 }
+
+/// A navigational mesh, that combines the navmeshes of multiple [`NavigationalMesh`] nodes into a
+/// single surface, so the paths could go across the boundaries of the nodes. It could be used
+/// everywhere where a [`Navmesh`] is expected (including [`crate::utils::navmesh::NavmeshAgent`]),
+/// since it dereferences to the combined navmesh. Use [`Graph::collect_navmeshes`] to create an
+/// aggregated navmesh from every navigational mesh of a scene graph.
+///
+/// Adjacent navmeshes are connected by welding their vertices, that are closer than the weld
+/// tolerance, so the seam edges must match (see [`Navmesh::append`] for more info). Navmeshes that
+/// do not touch each other (for example, building floors connected by stairs) could be connected
+/// using off-mesh links - either by adding the links to the source navmeshes, or by using
+/// [`Self::add_offmesh_link`].
+#[derive(Clone, Debug, Default)]
+pub struct AggregatedNavmesh {
+    navmesh: Navmesh,
+    sources: Vec<Handle<Node>>,
+    triangle_sources: Vec<u32>,
+}
+
+impl Deref for AggregatedNavmesh {
+    type Target = Navmesh;
+
+    fn deref(&self) -> &Self::Target {
+        &self.navmesh
+    }
+}
+
+impl AggregatedNavmesh {
+    /// Creates new empty aggregated navmesh.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Appends the given navmesh to the aggregated navmesh. `source` is a handle of the node, that
+    /// owns the navmesh, it is used to identify the source of every triangle of the combined
+    /// navmesh. Vertices closer than `weld_tolerance` to the existing vertices are welded together.
+    /// Triangles, that duplicate the existing ones, are skipped and keep their initial source.
+    pub fn add(&mut self, source: Handle<Node>, navmesh: &Navmesh, weld_tolerance: f32) {
+        let source_index = self.sources.len() as u32;
+        self.sources.push(source);
+        self.navmesh.append(navmesh, weld_tolerance);
+        self.triangle_sources
+            .resize(self.navmesh.triangles().len(), source_index);
+    }
+
+    /// Adds an off-mesh link between two points of the aggregated navmesh. It could be used to
+    /// connect the navmeshes, that do not touch each other. See [`Navmesh::add_offmesh_link`]
+    /// for more info.
+    pub fn add_offmesh_link(
+        &mut self,
+        from: Vector3<f32>,
+        to: Vector3<f32>,
+        bidirectional: bool,
+    ) -> OffMeshLinkHandle {
+        self.navmesh.add_offmesh_link(from, to, bidirectional)
+    }
+
+    /// Returns a reference to the combined navmesh.
+    pub fn navmesh(&self) -> &Navmesh {
+        &self.navmesh
+    }
+
+    /// Returns handles of all the nodes, that were used to build the aggregated navmesh, in the
+    /// order they were added.
+    pub fn sources(&self) -> &[Handle<Node>] {
+        &self.sources
+    }
+
+    /// Returns a handle of the node, that owns the triangle with the given index of the combined
+    /// navmesh.
+    pub fn triangle_source(&self, triangle: usize) -> Option<Handle<Node>> {
+        self.triangle_sources
+            .get(triangle)
+            .map(|index| self.sources[*index as usize])
+    }
+
+    /// Returns a handle of the node, whose navmesh contains the point closest to the given one.
+    /// It could be used to find which of the source navmeshes the start or the end point of a
+    /// path belong to.
+    pub fn source_at(&self, point: Vector3<f32>) -> Option<Handle<Node>> {
+        self.navmesh
+            .query_closest(point)
+            .and_then(|(_, triangle)| self.triangle_source(triangle))
+    }
+}
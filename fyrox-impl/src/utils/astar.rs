@@ -23,6 +23,14 @@
 //! A* is one of fastest graph search algorithms, it is used to construct shortest
 //! possible path from vertex to vertex. In vast majority of games it is used in pair
 //! with navigation meshes (navmesh). Check navmesh module docs for more info.
+//!
+//! ## Determinism
+//!
+//! Path searches are deterministic: the same query on the same graph always produces the same
+//! path, which is required by lockstep networked simulations. The vertices with equal scores are
+//! expanded in the order of their indices, the searches do not depend on the iteration order of
+//! hash maps and use only basic arithmetic operations and square roots, that are exactly rounded
+//! by IEEE 754, so the results are the same on every platform, that follows the standard.
 
 #![warn(missing_docs)]
 
@@ -35,8 +43,9 @@ use crate::core::{
 use std::{
     cmp::Ordering,
     collections::BinaryHeap,
-    fmt::{Display, Formatter},
+    fmt::{Debug, Display, Formatter},
     ops::{Deref, DerefMut},
+    time::{Duration, Instant},
 };
 
 /// Graph vertex that contains position in world and list of indices of neighbour
@@ -160,8 +169,63 @@ pub enum PathKind {
     Partial,
 }
 
-fn heuristic(a: Vector3<f32>, b: Vector3<f32>) -> f32 {
-    (a - b).norm_squared()
+/// Heuristic is used by A* to estimate the cost of the path from a vertex to the destination.
+/// Better estimations result in less vertices being explored during the search. The heuristic
+/// must never overestimate the actual cost (it must be admissible) to produce optimal paths,
+/// otherwise the search could return a longer path.
+///
+/// The default cost of an edge of the graph is the length of the edge multiplied by the penalty
+/// of the destination vertex, so every built-in heuristic returns a distance as well. Custom
+/// heuristics must return the values in the same units as the edge costs. [`Heuristic::Euclidean`]
+/// is admissible for any graph whose vertex penalties are not less than one. Other built-in
+/// heuristics are admissible only for the graphs with the matching kind of movement, on other
+/// graphs they trade the optimality of the paths for the speed of the search.
+#[derive(Default)]
+pub enum Heuristic {
+    /// Straight-line distance between the points. It is the default heuristic, that matches the
+    /// default cost of the edges of the graph (length of the edges).
+    #[default]
+    Euclidean,
+    /// Sum of absolute differences of the coordinates of the points. Well suited for the graphs
+    /// where movement is allowed along the coordinate axes only (4-connected grids, etc.), it
+    /// overestimates the cost of diagonal edges.
+    Manhattan,
+    /// Octile distance between the points - a path that moves diagonally as much as possible and
+    /// then straight. Well suited for the graphs that allow diagonal movement (8-connected grids,
+    /// hex grids, etc.), it overestimates the cost of the edges in other directions.
+    Diagonal,
+    /// User-defined heuristic. The closure is called with the position of a vertex and the position
+    /// of the destination and must return estimated cost of the path between them.
+    Custom(Box<dyn Fn(Vector3<f32>, Vector3<f32>) -> f32 + Send + Sync>),
+}
+
+impl Debug for Heuristic {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            Heuristic::Euclidean => write!(f, "Euclidean"),
+            Heuristic::Manhattan => write!(f, "Manhattan"),
+            Heuristic::Diagonal => write!(f, "Diagonal"),
+            Heuristic::Custom(_) => write!(f, "Custom"),
+        }
+    }
+}
+
+impl Heuristic {
+    /// Calculates estimated cost of the path between the given points.
+    pub fn estimate(&self, a: Vector3<f32>, b: Vector3<f32>) -> f32 {
+        match self {
+            Heuristic::Euclidean => (a - b).norm(),
+            Heuristic::Manhattan => (a - b).abs().sum(),
+            Heuristic::Diagonal => {
+                let mut d = (a - b).abs();
+                d.as_mut_slice().sort_unstable_by(|x, y| y.total_cmp(x));
+                let sqrt2 = std::f32::consts::SQRT_2;
+                let sqrt3 = 3.0f32.sqrt();
+                d[0] + (sqrt2 - 1.0) * d[1] + (sqrt3 - sqrt2) * d[2]
+            }
+            Heuristic::Custom(func) => func(a, b),
+        }
+    }
 }
 
 impl<T: VertexDataProvider> Default for Graph<T> {
@@ -178,7 +242,7 @@ impl PositionProvider for VertexData {
 
 /// Path search can be interrupted by errors, this enum stores all possible
 /// kinds of errors.
-#[derive(Clone, Debug)]
+#[derive(Clone, Debug, PartialEq, Eq)]
 pub enum PathError {
     /// Out-of-bounds vertex index was found, it can be either the index of begin/end
     /// points, or some index of neighbour vertices in list of neighbours in vertex.
@@ -198,8 +262,18 @@ pub enum PathError {
     /// See `Graph<T>.max_search_iterations` for more
     HitMaxSearchIterations(i32),
 
+    /// Path vector is still valid and partial, but pathfinder has expanded the maximum allowed
+    /// number of vertices (see [`Graph::build_indexed_path_with_cost_limited`]) and gave up. The
+    /// path leads to the vertex, that is the closest to the desired destination among the
+    /// expanded ones.
+    SearchExhausted(usize),
+
     /// Graph was empty.
     Empty,
+
+    /// Begin and end points are in different connected components (islands), so there's no path
+    /// between them. The search is not performed at all in this case.
+    Unreachable,
 }
 
 impl Display for PathError {
@@ -217,13 +291,41 @@ impl Display for PathError {
                     "Maximum search iterations ({v}) hit, returning with partial path."
                 )
             }
+            PathError::SearchExhausted(v) => {
+                write!(
+                    f,
+                    "Maximum number of expanded vertices ({v}) hit, returning with partial path."
+                )
+            }
             PathError::Empty => {
                 write!(f, "Graph was empty")
             }
+            PathError::Unreachable => {
+                write!(f, "End point is unreachable from begin point.")
+            }
         }
     }
 }
 
+/// Diagnostic statistics of a single path search, see [`Graph::build_indexed_path_with_stats`].
+/// It could be used to compare heuristics and graph layouts.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct PathfindStats {
+    /// Number of vertices, whose neighbours were evaluated during the search (the size of the
+    /// closed set), including the reopened ones.
+    pub expanded_vertices: usize,
+    /// Number of vertices, that were expanded more than once, because they were reached by
+    /// several partial paths.
+    pub reopened_vertices: usize,
+    /// Number of vertices in the resulting path.
+    pub path_vertices: usize,
+    /// Cost (g-score) of the resulting path: the sum of the costs of its edges. The cost of a
+    /// partial path is the cost of the path to the vertex closest to the destination.
+    pub path_cost: f32,
+    /// Time spent on the search.
+    pub duration: Duration,
+}
+
 #[derive(Clone)]
 /// A partially complete path containing the indices of graph vertices and its A* scores
 pub struct PartialPath {
@@ -293,6 +395,194 @@ impl PartialPath {
     }
 }
 
+/// A vertex of a partial path, the paths are stored as linked lists that share their beginnings.
+#[derive(Copy, Clone, Debug)]
+struct SearchNode {
+    vertex: usize,
+    parent: usize,
+}
+
+/// An entry of the open set - the last node of a partial path and its scores.
+#[derive(Copy, Clone, Debug)]
+struct OpenEntry {
+    node: usize,
+    vertex: usize,
+    g_score: f32,
+    f_score: f32,
+}
+
+impl Ord for OpenEntry {
+    /// The same as for [`PartialPath`] - compares f-value and heuristic. Ties are broken by the
+    /// index of the vertex and then by the index of the node (lower is better), so the search
+    /// order does not depend on the internal order of the heap.
+    fn cmp(&self, other: &Self) -> Ordering {
+        (self.f_score.total_cmp(&other.f_score))
+            .then((self.f_score - self.g_score).total_cmp(&(other.f_score - other.g_score)))
+            .then(self.vertex.cmp(&other.vertex))
+            .then(self.node.cmp(&other.node))
+            .reverse()
+    }
+}
+
+impl PartialOrd for OpenEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for OpenEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for OpenEntry {}
+
+/// Reusable storage for intermediate data of A* search (the open set, the closed set and the
+/// partial paths), see [`Graph::build_indexed_path_with_context`]. The context could be used with
+/// any graph.
+///
+/// Bidirectional search (see [`Graph::build_indexed_path_bidirectional_with_context`]) also caches
+/// the incoming links of the graph in the context, so they are collected only once. The cache must
+/// be invalidated with [`Self::invalidate_links`] when the links of the graph are changed, or when
+/// the context is used with another graph.
+#[derive(Default, Debug)]
+pub struct PathfindContext {
+    searched_vertices: Vec<bool>,
+    // The cost of the cheapest known path to every vertex.
+    g_scores: Vec<f32>,
+    open_set: BinaryHeap<OpenEntry>,
+    nodes: Vec<SearchNode>,
+    // Incoming links of every vertex, the backward front of bidirectional search walks the links
+    // in reverse direction.
+    incoming: Vec<Vec<usize>>,
+    incoming_valid: bool,
+}
+
+impl PathfindContext {
+    /// Creates a new empty context.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Drops the cached incoming links of the graph, they will be collected again by the next
+    /// bidirectional search. Must be called after the links of the graph are changed.
+    pub fn invalidate_links(&mut self) {
+        self.incoming_valid = false;
+    }
+
+    fn update_incoming<T: VertexDataProvider>(
+        &mut self,
+        graph: &Graph<T>,
+    ) -> Result<(), PathError> {
+        if self.incoming_valid && self.incoming.len() == graph.vertices.len() {
+            return Ok(());
+        }
+
+        self.incoming_valid = false;
+        self.incoming.resize_with(graph.vertices.len(), Vec::new);
+        for links in self.incoming.iter_mut() {
+            links.clear();
+        }
+        for (index, vertex) in graph.vertices.iter().enumerate() {
+            for neighbour in vertex.neighbours.iter() {
+                let neighbour_index = *neighbour as usize;
+                if neighbour_index == index {
+                    return Err(PathError::CyclicReferenceFound(index));
+                }
+                self.incoming
+                    .get_mut(neighbour_index)
+                    .ok_or(PathError::InvalidIndex(neighbour_index))?
+                    .push(index);
+            }
+        }
+        self.incoming_valid = true;
+
+        Ok(())
+    }
+
+    fn clear(&mut self, vertex_count: usize) {
+        self.searched_vertices.clear();
+        self.searched_vertices.resize(vertex_count, false);
+        self.g_scores.clear();
+        self.g_scores.resize(vertex_count, f32::MAX);
+        self.open_set.clear();
+        self.nodes.clear();
+    }
+}
+
+#[derive(Copy, Clone)]
+struct FrontEntry {
+    vertex: usize,
+    g_score: f32,
+    f_score: f32,
+}
+
+impl Ord for FrontEntry {
+    /// Compares f-value, ties are broken by the index of the vertex (lower is better).
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.f_score
+            .total_cmp(&other.f_score)
+            .then(self.vertex.cmp(&other.vertex))
+            .reverse()
+    }
+}
+
+impl PartialOrd for FrontEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for FrontEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cmp(other) == Ordering::Equal
+    }
+}
+
+impl Eq for FrontEntry {}
+
+/// One of two search fronts of bidirectional A*.
+struct SearchFront {
+    g_scores: Vec<f32>,
+    parents: Vec<usize>,
+    heap: BinaryHeap<FrontEntry>,
+}
+
+impl SearchFront {
+    fn new(vertex_count: usize, start: usize) -> Self {
+        let mut front = Self {
+            g_scores: vec![f32::MAX; vertex_count],
+            parents: vec![usize::MAX; vertex_count],
+            heap: BinaryHeap::new(),
+        };
+        front.g_scores[start] = 0.0;
+        front.heap.push(FrontEntry {
+            vertex: start,
+            g_score: 0.0,
+            f_score: 0.0,
+        });
+        front
+    }
+
+    fn min_f_score(&self) -> f32 {
+        self.heap.peek().map_or(f32::MAX, |e| e.f_score)
+    }
+
+    fn is_reached(&self, vertex: usize) -> bool {
+        self.g_scores[vertex] != f32::MAX
+    }
+
+    /// Collects the chain of vertices from the given vertex to the start of the front (inclusive).
+    fn chain(&self, mut vertex: usize, chain: &mut Vec<usize>) {
+        chain.push(vertex);
+        while self.parents[vertex] != usize::MAX {
+            vertex = self.parents[vertex];
+            chain.push(vertex);
+        }
+    }
+}
+
 impl<T: VertexDataProvider> Graph<T> {
     /// Creates new empty graph.
     pub fn new() -> Self {
@@ -428,13 +718,195 @@ impl<T: VertexDataProvider> Graph<T> {
     /// This implementation is fast and allows for multiple searches in parallel, but does not attempt to find the optimal route
     ///
     /// **See `Graph<T>.max_search_iterations`** to change the maximum amount of search iterations
-
     pub fn build_indexed_path(
         &self,
         from: usize,
         to: usize,
         path: &mut Vec<usize>,
     ) -> Result<PathKind, PathError> {
+        self.build_indexed_path_with_cost(from, to, path, |_, _, cost| Some(cost))
+    }
+
+    /// The same as [`Self::build_indexed_path`], but allows you to override the cost of traversing
+    /// every edge of the graph. `edge_cost` closure is called with the index of the source vertex,
+    /// the index of the destination vertex, and the default cost of the edge (which includes
+    /// penalty of the destination vertex). It must return either a new cost of the edge or `None`,
+    /// if the edge cannot be traversed at all.
+    pub fn build_indexed_path_with_cost<F>(
+        &self,
+        from: usize,
+        to: usize,
+        path: &mut Vec<usize>,
+        edge_cost: F,
+    ) -> Result<PathKind, PathError>
+    where
+        F: FnMut(usize, usize, f32) -> Option<f32>,
+    {
+        self.build_indexed_path_with_cost_limited(from, to, path, None, edge_cost)
+    }
+
+    /// The same as [`Self::build_indexed_path_with_cost`], but the search gives up after expanding
+    /// `max_expanded_vertices` vertices (`None` means no limit) and returns
+    /// [`PathError::SearchExhausted`]. In this case `path` contains a partial path to the
+    /// expanded vertex, that is the closest to the destination. It is a safety valve for huge
+    /// graphs, where a search to an unreachable vertex may take too much time.
+    pub fn build_indexed_path_with_cost_limited<F>(
+        &self,
+        from: usize,
+        to: usize,
+        path: &mut Vec<usize>,
+        max_expanded_vertices: Option<usize>,
+        edge_cost: F,
+    ) -> Result<PathKind, PathError>
+    where
+        F: FnMut(usize, usize, f32) -> Option<f32>,
+    {
+        self.search(
+            from,
+            to,
+            path,
+            edge_cost,
+            &Heuristic::Euclidean,
+            1.0,
+            max_expanded_vertices,
+            None,
+            &mut PathfindContext::default(),
+        )
+    }
+
+    /// The same as [`Self::build_indexed_path_with_cost_limited`], but also collects diagnostic
+    /// statistics of the search (see [`PathfindStats`]) into `stats`. It could be used to measure
+    /// the amount of work done by the searches, for example to limit it per frame.
+    pub fn build_indexed_path_with_cost_and_stats<F>(
+        &self,
+        from: usize,
+        to: usize,
+        path: &mut Vec<usize>,
+        max_expanded_vertices: Option<usize>,
+        edge_cost: F,
+        stats: &mut PathfindStats,
+    ) -> Result<PathKind, PathError>
+    where
+        F: FnMut(usize, usize, f32) -> Option<f32>,
+    {
+        *stats = PathfindStats::default();
+        let start = Instant::now();
+        let result = self.search(
+            from,
+            to,
+            path,
+            edge_cost,
+            &Heuristic::Euclidean,
+            1.0,
+            max_expanded_vertices,
+            Some(&mut *stats),
+            &mut PathfindContext::default(),
+        );
+        stats.path_vertices = path.len();
+        stats.duration = start.elapsed();
+        result
+    }
+
+    /// The same as [`Self::build_indexed_path`], but allows you to specify a heuristic, that will
+    /// be used to estimate the cost of the path to the destination. `weight` is a multiplier for
+    /// the heuristic - values greater than `1.0` make the search faster (less vertices will be
+    /// explored), but the resulting path may be less optimal (weighted A*). Use `1.0` to get
+    /// normal A* behaviour.
+    pub fn build_indexed_path_with_heuristic(
+        &self,
+        from: usize,
+        to: usize,
+        path: &mut Vec<usize>,
+        heuristic: &Heuristic,
+        weight: f32,
+    ) -> Result<PathKind, PathError> {
+        self.search(
+            from,
+            to,
+            path,
+            |_, _, cost| Some(cost),
+            heuristic,
+            weight,
+            None,
+            None,
+            &mut PathfindContext::default(),
+        )
+    }
+
+    /// The same as [`Self::build_indexed_path_with_heuristic`], but uses the given context to
+    /// store intermediate data of the search. The context is cleared, but not deallocated, before
+    /// the search, so the memory allocated by the previous searches is reused. It is useful when
+    /// lots of paths are built every frame: reuse the same context (and the same `path`) for
+    /// every search to avoid memory allocations in steady state.
+    pub fn build_indexed_path_with_context(
+        &self,
+        from: usize,
+        to: usize,
+        path: &mut Vec<usize>,
+        heuristic: &Heuristic,
+        weight: f32,
+        context: &mut PathfindContext,
+    ) -> Result<PathKind, PathError> {
+        self.search(
+            from,
+            to,
+            path,
+            |_, _, cost| Some(cost),
+            heuristic,
+            weight,
+            None,
+            None,
+            context,
+        )
+    }
+
+    /// The same as [`Self::build_indexed_path_with_heuristic`], but also collects diagnostic
+    /// statistics of the search (see [`PathfindStats`]) into `stats`. The statistics are
+    /// collected even if the search fails. Other methods do not collect statistics, so they do
+    /// not pay for it.
+    pub fn build_indexed_path_with_stats(
+        &self,
+        from: usize,
+        to: usize,
+        path: &mut Vec<usize>,
+        heuristic: &Heuristic,
+        weight: f32,
+        stats: &mut PathfindStats,
+    ) -> Result<PathKind, PathError> {
+        *stats = PathfindStats::default();
+        let start = Instant::now();
+        let result = self.search(
+            from,
+            to,
+            path,
+            |_, _, cost| Some(cost),
+            heuristic,
+            weight,
+            None,
+            Some(&mut *stats),
+            &mut PathfindContext::default(),
+        );
+        stats.path_vertices = path.len();
+        stats.duration = start.elapsed();
+        result
+    }
+
+    #[allow(clippy::too_many_arguments)]
+    fn search<F>(
+        &self,
+        from: usize,
+        to: usize,
+        path: &mut Vec<usize>,
+        mut edge_cost: F,
+        heuristic: &Heuristic,
+        weight: f32,
+        max_expanded_vertices: Option<usize>,
+        stats: Option<&mut PathfindStats>,
+        context: &mut PathfindContext,
+    ) -> Result<PathKind, PathError>
+    where
+        F: FnMut(usize, usize, f32) -> Option<f32>,
+    {
         path.clear();
 
         if self.vertices.is_empty() {
@@ -453,39 +925,83 @@ impl<T: VertexDataProvider> Graph<T> {
             return Ok(PathKind::Full);
         }
 
-        // keeps track of which vertices we've searched
-        let mut searched_vertices = vec![false; self.vertices.len()];
-
-        // creates heap for searching
-        let mut search_heap: BinaryHeap<PartialPath> = BinaryHeap::new();
-
-        // creates first partial path and adds it to heap
-        search_heap.push(PartialPath::new(from));
+        context.clear(self.vertices.len());
+        let PathfindContext {
+            searched_vertices,
+            g_scores,
+            open_set,
+            nodes,
+            ..
+        } = context;
+
+        // creates first partial path and adds it to the open set
+        nodes.push(SearchNode {
+            vertex: from,
+            parent: usize::MAX,
+        });
+        open_set.push(OpenEntry {
+            node: 0,
+            vertex: from,
+            g_score: 0.0,
+            f_score: f32::MAX,
+        });
 
         // stores best path found
-        let mut best_path = PartialPath::default();
+        let mut best_path = OpenEntry {
+            node: usize::MAX,
+            vertex: usize::MAX,
+            g_score: f32::MAX,
+            f_score: f32::MAX,
+        };
 
-        // search loop
+        // stores the path to the vertex closest (by straight-line distance) to the end, it is
+        // used as a partial path if the end is unreachable
+        let mut closest_path = 0;
+        let mut closest_g_score = 0.0;
+        let mut closest_distance = self
+            .vertices
+            .get(from)
+            .ok_or(PathError::InvalidIndex(from))?
+            .position
+            .metric_distance(&end_pos);
+        g_scores[from] = 0.0;
+
+        // search loop, errors break the loop, so the statistics are collected for failed
+        // searches as well
         let mut search_iteration = 0i32;
-
-        while self.max_search_iterations < 0 || search_iteration < self.max_search_iterations {
-            // breakes loop if heap is empty
-            if search_heap.is_empty() {
+        let mut reopened_vertices = 0;
+        let mut exhausted = false;
+        let mut error = None;
+
+        'search: while self.max_search_iterations < 0
+            || search_iteration < self.max_search_iterations
+        {
+            if max_expanded_vertices.is_some_and(|max| search_iteration as usize >= max) {
+                exhausted = !open_set.is_empty();
                 break;
             }
 
-            // pops best partial path off the heap to use for this iteration
-            let current_path = search_heap.pop().unwrap();
+            // pops best partial path off the heap to use for this iteration, breaks loop if
+            // the heap is empty
+            let Some(current_path) = open_set.pop() else {
+                break;
+            };
+
+            let current_index = nodes[current_path.node].vertex;
 
-            let current_index = *current_path.vertices.last().unwrap();
-            let current_vertex = self
-                .vertices
-                .get(current_index)
-                .ok_or(PathError::InvalidIndex(current_index))?;
+            // skips outdated paths, the vertex was reached by a cheaper path later on
+            if current_path.g_score > g_scores[current_index] {
+                continue;
+            }
+
+            let Some(current_vertex) = self.vertices.get(current_index) else {
+                error = Some(PathError::InvalidIndex(current_index));
+                break;
+            };
 
             // updates best path
             if current_path > best_path {
-                best_path = current_path.clone();
+                best_path = current_path;
 
                 // breaks if end is found
                 if current_index == to {
@@ -493,6 +1009,14 @@ impl<T: VertexDataProvider> Graph<T> {
                 }
             }
 
+            // updates closest path
+            let distance = current_vertex.position.metric_distance(&end_pos);
+            if distance < closest_distance {
+                closest_distance = distance;
+                closest_path = current_path.node;
+                closest_g_score = current_path.g_score;
+            }
+
             // evaluates path scores one level deeper and adds the paths to the heap
             for i in current_vertex.neighbours.iter() {
                 let neighbour_index = *i as usize;
@@ -500,44 +1024,83 @@ impl<T: VertexDataProvider> Graph<T> {
                 // this error is thrown for the users sake
                 // it shouldn't actually cause an issue because the next line would skip it
                 if neighbour_index == current_index {
-                    return Err(PathError::CyclicReferenceFound(current_index));
-                }
-
-                // avoids going in circles
-                if searched_vertices[neighbour_index] {
-                    continue;
+                    error = Some(PathError::CyclicReferenceFound(current_index));
+                    break 'search;
                 }
 
-                let neighbour = self
-                    .vertices
-                    .get(neighbour_index)
-                    .ok_or(PathError::InvalidIndex(neighbour_index))?;
-
-                let neighbour_g_score = current_path.g_score
-                    + ((current_vertex.position - neighbour.position).norm_squared()
-                        * neighbour.g_penalty);
-
-                let neighbour_f_score = neighbour_g_score + heuristic(neighbour.position, end_pos);
+                let Some(neighbour) = self.vertices.get(neighbour_index) else {
+                    error = Some(PathError::InvalidIndex(neighbour_index));
+                    break 'search;
+                };
 
-                search_heap.push(current_path.clone_and_add(
+                let Some(cost) = edge_cost(
+                    current_index,
                     neighbour_index,
-                    neighbour_g_score,
-                    neighbour_f_score,
-                ));
+                    current_vertex.position.metric_distance(&neighbour.position)
+                        * neighbour.g_penalty,
+                ) else {
+                    continue;
+                };
+
+                // avoids going in circles, the vertex is reopened only if a cheaper path to it
+                // is found (it could happen with inconsistent heuristics)
+                let neighbour_g_score = current_path.g_score + cost;
+                if neighbour_g_score >= g_scores[neighbour_index] {
+                    continue;
+                }
+                g_scores[neighbour_index] = neighbour_g_score;
+
+                let neighbour_f_score =
+                    neighbour_g_score + weight * heuristic.estimate(neighbour.position, end_pos);
+
+                nodes.push(SearchNode {
+                    vertex: neighbour_index,
+                    parent: current_path.node,
+                });
+                open_set.push(OpenEntry {
+                    node: nodes.len() - 1,
+                    vertex: neighbour_index,
+                    g_score: neighbour_g_score,
+                    f_score: neighbour_f_score,
+                });
             }
 
             // marks vertex as searched
+            if searched_vertices[current_index] {
+                reopened_vertices += 1;
+            }
             searched_vertices[current_index] = true;
 
             search_iteration += 1;
         }
 
-        // sets path to the best path of indices
-        path.clone_from(&best_path.vertices);
-        path.reverse();
+        // selects the best path, or the closest path if the end wasn't found
+        let (mut node, g_score) =
+            if best_path.node != usize::MAX && nodes[best_path.node].vertex == to {
+                (best_path.node, best_path.g_score)
+            } else {
+                (closest_path, closest_g_score)
+            };
+
+        if let Some(stats) = stats {
+            stats.expanded_vertices = search_iteration as usize;
+            stats.reopened_vertices = reopened_vertices;
+            stats.path_cost = g_score;
+        }
+        if let Some(error) = error {
+            return Err(error);
+        }
+
+        // the path is collected from the end to the beginning
+        while node != usize::MAX {
+            path.push(nodes[node].vertex);
+            node = nodes[node].parent;
+        }
 
         if *path.first().unwrap() == to {
             Ok(PathKind::Full)
+        } else if exhausted {
+            Err(PathError::SearchExhausted(search_iteration as usize))
         } else if search_iteration == self.max_search_iterations - 1 {
             Err(PathError::HitMaxSearchIterations(
                 self.max_search_iterations,
@@ -583,55 +1146,572 @@ impl<T: VertexDataProvider> Graph<T> {
         Ok(path_kind)
     }
 
-    /// **Deprecated** *use **`Graph<T>.build_positional_path()`** instead*
-    ///
-    /// Tries to build path of Vector3's from beginning point to endpoint. Returns path kind:
-    ///
-    /// - Full: Path vector is a direct path from beginning to end.
-    /// - Partial: Path vector is a path that ends closest to the desired end, because pathfinder could not find a full path.
-    ///
-    /// *See `PathKind`*
+    /// The same as [`Self::build_positional_path`], but allows you to specify a heuristic and its
+    /// weight. See [`Self::build_indexed_path_with_heuristic`] for more info.
+    pub fn build_positional_path_with_heuristic(
+        &self,
+        from: usize,
+        to: usize,
+        path: &mut Vec<Vector3<f32>>,
+        heuristic: &Heuristic,
+        weight: f32,
+    ) -> Result<PathKind, PathError> {
+        path.clear();
+
+        let mut indices: Vec<usize> = Vec::new();
+        let path_kind =
+            self.build_indexed_path_with_heuristic(from, to, &mut indices, heuristic, weight)?;
+
+        for index in indices.iter() {
+            let vertex = self
+                .vertices
+                .get(*index)
+                .ok_or(PathError::InvalidIndex(*index))?;
+
+            path.push(vertex.position);
+        }
+
+        Ok(path_kind)
+    }
+
+    /// Tries to build path of vertex indices from beginning point to endpoint using bidirectional
+    /// search. Unlike [`Self::build_indexed_path`], the search is performed simultaneously from
+    /// both ends and stops when the search fronts meet, which usually explores much less vertices
+    /// on large graphs. The result has the same format as the result of
+    /// [`Self::build_indexed_path`], so the methods are interchangeable.
     ///
     /// # Notes
     ///
-    /// This implementation is fast and allows for multiple searches in parallel, but does not attempt to find the optimal route
+    /// Produces optimal paths if the heuristic never overestimates the actual cost of the path (see
+    /// [`Heuristic`]), use [`Self::build_indexed_path_bidirectional_with_heuristic`] to specify
+    /// another heuristic.
     ///
     /// **See `Graph<T>.max_search_iterations`** to change the maximum amount of search iterations
-    #[deprecated = "name is too ambiguous use build_positional_path instead"]
-    pub fn build(
+    /// (an iteration is an expansion of a vertex by any of the fronts).
+    pub fn build_indexed_path_bidirectional(
         &self,
         from: usize,
         to: usize,
-        path: &mut Vec<Vector3<f32>>,
+        path: &mut Vec<usize>,
     ) -> Result<PathKind, PathError> {
-        self.build_positional_path(from, to, path)
+        self.build_indexed_path_bidirectional_with_heuristic(from, to, path, &Heuristic::Euclidean)
     }
-}
 
-#[cfg(test)]
-mod test {
-    use crate::rand::Rng;
-    use crate::utils::astar::PathError;
-    use crate::{
-        core::{algebra::Vector3, rand},
-        utils::astar::{Graph, GraphVertex, PathKind},
-    };
-    use std::time::Instant;
+    /// The same as [`Self::build_indexed_path_bidirectional`], but allows you to specify a
+    /// heuristic, that will be used to guide both search fronts.
+    pub fn build_indexed_path_bidirectional_with_heuristic(
+        &self,
+        from: usize,
+        to: usize,
+        path: &mut Vec<usize>,
+        heuristic: &Heuristic,
+    ) -> Result<PathKind, PathError> {
+        self.build_indexed_path_bidirectional_with_context(
+            from,
+            to,
+            path,
+            heuristic,
+            |_, _, cost| Some(cost),
+            &mut PathfindContext::default(),
+        )
+    }
 
-    #[test]
-    fn astar_random_points() {
-        let mut pathfinder = Graph::<GraphVertex>::new();
+    /// The same as [`Self::build_indexed_path_bidirectional`], but allows you to override the cost
+    /// of traversing every edge of the graph, see [`Self::build_indexed_path_with_cost`] for the
+    /// meaning of `edge_cost`. The closure is called with the source and destination vertices in
+    /// the direction of the link, even when the link is walked by the backward front.
+    pub fn build_indexed_path_bidirectional_with_cost<F>(
+        &self,
+        from: usize,
+        to: usize,
+        path: &mut Vec<usize>,
+        edge_cost: F,
+    ) -> Result<PathKind, PathError>
+    where
+        F: FnMut(usize, usize, f32) -> Option<f32>,
+    {
+        self.build_indexed_path_bidirectional_with_context(
+            from,
+            to,
+            path,
+            &Heuristic::Euclidean,
+            edge_cost,
+            &mut PathfindContext::default(),
+        )
+    }
 
-        let mut path = Vec::new();
-        assert!(pathfinder
-            .build_positional_path(0, 0, &mut path)
-            .is_err_and(|e| matches!(e, PathError::Empty)));
-        assert!(path.is_empty());
+    /// The same as [`Self::build_indexed_path_bidirectional_with_cost`], but uses the given
+    /// heuristic and keeps the incoming links of the graph in the given context, so they are
+    /// collected only by the first search. Reuse the same context for every search on the same
+    /// graph, and call [`PathfindContext::invalidate_links`] after the links of the graph are
+    /// changed.
+    pub fn build_indexed_path_bidirectional_with_context<F>(
+        &self,
+        from: usize,
+        to: usize,
+        path: &mut Vec<usize>,
+        heuristic: &Heuristic,
+        mut edge_cost: F,
+        context: &mut PathfindContext,
+    ) -> Result<PathKind, PathError>
+    where
+        F: FnMut(usize, usize, f32) -> Option<f32>,
+    {
+        path.clear();
 
-        let size = 40;
+        if self.vertices.is_empty() {
+            return Err(PathError::Empty);
+        }
 
-        // Create vertices.
-        let mut vertices = Vec::new();
+        let begin_pos = self
+            .vertices
+            .get(from)
+            .ok_or(PathError::InvalidIndex(from))?
+            .position;
+        let end_pos = self
+            .vertices
+            .get(to)
+            .ok_or(PathError::InvalidIndex(to))?
+            .position;
+
+        if from == to {
+            path.push(to);
+            return Ok(PathKind::Full);
+        }
+
+        // backward front walks the links in reverse direction, so it needs a list of incoming links
+        context.update_incoming(self)?;
+        let incoming = &context.incoming;
+
+        let mut forward = SearchFront::new(self.vertices.len(), from);
+        let mut backward = SearchFront::new(self.vertices.len(), to);
+
+        // cost of the best path found so far and the vertex where the fronts met
+        let mut best_cost = f32::MAX;
+        let mut meeting_vertex = None;
+
+        let mut search_iteration = 0i32;
+        let mut hit_max_iterations = false;
+
+        loop {
+            // if the backward front runs out of vertices before the fronts met, the end is
+            // unreachable and the forward front continues the search to find the closest vertex
+            if forward.heap.is_empty() {
+                break;
+            }
+
+            // no path better than the best one could be found by any of the fronts
+            if meeting_vertex.is_some()
+                && best_cost <= forward.min_f_score().max(backward.min_f_score())
+            {
+                break;
+            }
+
+            if self.max_search_iterations >= 0 && search_iteration >= self.max_search_iterations {
+                hit_max_iterations = true;
+                break;
+            }
+
+            search_iteration += 1;
+
+            // expands the smaller front to keep them balanced
+            let is_forward = backward.heap.is_empty() || forward.heap.len() <= backward.heap.len();
+            let (front, other, target_pos) = if is_forward {
+                (&mut forward, &backward, end_pos)
+            } else {
+                (&mut backward, &forward, begin_pos)
+            };
+
+            let current = front.heap.pop().unwrap();
+            if current.g_score > front.g_scores[current.vertex] {
+                // outdated entry, the vertex was reached by a cheaper path later on
+                continue;
+            }
+
+            let current_vertex = &self.vertices[current.vertex];
+            let forward_neighbours = current_vertex.neighbours.iter().map(|n| *n as usize);
+            let backward_neighbours = incoming[current.vertex].iter().copied();
+
+            let mut relax = |neighbour_index: usize| {
+                let Some(neighbour) = self.vertices.get(neighbour_index) else {
+                    return;
+                };
+                let distance = current_vertex.position.metric_distance(&neighbour.position);
+                // cost of the edge is defined by the penalty of its destination vertex
+                let cost = if is_forward {
+                    edge_cost(
+                        current.vertex,
+                        neighbour_index,
+                        distance * neighbour.g_penalty,
+                    )
+                } else {
+                    edge_cost(
+                        neighbour_index,
+                        current.vertex,
+                        distance * current_vertex.g_penalty,
+                    )
+                };
+                let Some(cost) = cost else {
+                    return;
+                };
+
+                let g_score = current.g_score + cost;
+                if g_score < front.g_scores[neighbour_index] {
+                    front.g_scores[neighbour_index] = g_score;
+                    front.parents[neighbour_index] = current.vertex;
+                    front.heap.push(FrontEntry {
+                        vertex: neighbour_index,
+                        g_score,
+                        f_score: g_score + heuristic.estimate(neighbour.position, target_pos),
+                    });
+
+                    if other.is_reached(neighbour_index) {
+                        let cost = g_score + other.g_scores[neighbour_index];
+                        // equal paths are resolved by the index of the meeting vertex, so the
+                        // result does not depend on the order of the links
+                        if cost < best_cost
+                            || (cost == best_cost
+                                && meeting_vertex.is_some_and(|vertex| neighbour_index < vertex))
+                        {
+                            best_cost = cost;
+                            meeting_vertex = Some(neighbour_index);
+                        }
+                    }
+                }
+            };
+
+            if is_forward {
+                forward_neighbours.for_each(&mut relax);
+            } else {
+                backward_neighbours.for_each(&mut relax);
+            }
+        }
+
+        if let Some(meeting_vertex) = meeting_vertex {
+            // the path is stored in reverse order - from the end to the beginning
+            backward.chain(meeting_vertex, path);
+            path.reverse();
+            path.pop();
+            forward.chain(meeting_vertex, path);
+            return Ok(PathKind::Full);
+        }
+
+        // the fronts haven't met, build a partial path to the vertex (reached by the forward front)
+        // closest to the end
+        let closest = (0..self.vertices.len())
+            .filter(|i| forward.is_reached(*i))
+            .min_by(|a, b| {
+                let da = self.vertices[*a].position.metric_distance(&end_pos);
+                let db = self.vertices[*b].position.metric_distance(&end_pos);
+                da.total_cmp(&db)
+            })
+            .unwrap_or(from);
+        forward.chain(closest, path);
+
+        if hit_max_iterations {
+            Err(PathError::HitMaxSearchIterations(
+                self.max_search_iterations,
+            ))
+        } else {
+            Ok(PathKind::Partial)
+        }
+    }
+
+    /// The same as [`Self::build_positional_path`], but uses bidirectional search. See
+    /// [`Self::build_indexed_path_bidirectional`] for more info.
+    pub fn build_positional_path_bidirectional(
+        &self,
+        from: usize,
+        to: usize,
+        path: &mut Vec<Vector3<f32>>,
+    ) -> Result<PathKind, PathError> {
+        path.clear();
+
+        let mut indices: Vec<usize> = Vec::new();
+        let path_kind = self.build_indexed_path_bidirectional(from, to, &mut indices)?;
+
+        for index in indices.iter() {
+            let vertex = self
+                .vertices
+                .get(*index)
+                .ok_or(PathError::InvalidIndex(*index))?;
+
+            path.push(vertex.position);
+        }
+
+        Ok(path_kind)
+    }
+
+    /// Tries to build path of vertex indices from the beginning vertex to the nearest (by the path
+    /// cost) of the given goal vertices using a single A* search. The search stops at the first
+    /// goal vertex it expands, and its heuristic is the minimum of the estimations to every goal.
+    /// Returns the index (in `goals`) of the reached goal, or `None` if none of the goals is
+    /// reachable. The path has the same format as the path built by [`Self::build_indexed_path`].
+    ///
+    /// **See `Graph<T>.max_search_iterations`** to change the maximum amount of search iterations
+    pub fn build_indexed_path_to_nearest(
+        &self,
+        from: usize,
+        goals: &[usize],
+        path: &mut Vec<usize>,
+    ) -> Result<Option<usize>, PathError> {
+        self.build_indexed_path_to_nearest_with_cost(from, goals, path, |_, _, cost| Some(cost))
+    }
+
+    /// The same as [`Self::build_indexed_path_to_nearest`], but allows you to override the cost of
+    /// traversing every edge of the graph. See [`Self::build_indexed_path_with_cost`] for more
+    /// info about `edge_cost` closure.
+    pub fn build_indexed_path_to_nearest_with_cost<F>(
+        &self,
+        from: usize,
+        goals: &[usize],
+        path: &mut Vec<usize>,
+        mut edge_cost: F,
+    ) -> Result<Option<usize>, PathError>
+    where
+        F: FnMut(usize, usize, f32) -> Option<f32>,
+    {
+        path.clear();
+
+        if self.vertices.is_empty() {
+            return Err(PathError::Empty);
+        }
+        if from >= self.vertices.len() {
+            return Err(PathError::InvalidIndex(from));
+        }
+        if goals.is_empty() {
+            return Ok(None);
+        }
+
+        // index of the goal for every vertex, the first goal wins if there are duplicates
+        let mut goal_of = vec![usize::MAX; self.vertices.len()];
+        let mut goal_positions = Vec::with_capacity(goals.len());
+        for (index, &goal) in goals.iter().enumerate() {
+            let vertex = self
+                .vertices
+                .get(goal)
+                .ok_or(PathError::InvalidIndex(goal))?;
+            goal_positions.push(vertex.position);
+            if goal_of[goal] == usize::MAX {
+                goal_of[goal] = index;
+            }
+        }
+        let estimate = |position: Vector3<f32>| {
+            goal_positions
+                .iter()
+                .map(|goal| Heuristic::Euclidean.estimate(position, *goal))
+                .fold(f32::MAX, f32::min)
+        };
+
+        let mut front = SearchFront::new(self.vertices.len(), from);
+        let mut search_iteration = 0i32;
+        while let Some(current) = front.heap.pop() {
+            if current.g_score > front.g_scores[current.vertex] {
+                // outdated entry, the vertex was reached by a cheaper path later on
+                continue;
+            }
+
+            let goal = goal_of[current.vertex];
+            if goal != usize::MAX {
+                front.chain(current.vertex, path);
+                return Ok(Some(goal));
+            }
+
+            if self.max_search_iterations >= 0 && search_iteration >= self.max_search_iterations {
+                return Err(PathError::HitMaxSearchIterations(
+                    self.max_search_iterations,
+                ));
+            }
+            search_iteration += 1;
+
+            let vertex = &self.vertices[current.vertex];
+            for &neighbour_index in vertex.neighbours.iter() {
+                let neighbour_index = neighbour_index as usize;
+                if neighbour_index == current.vertex {
+                    return Err(PathError::CyclicReferenceFound(current.vertex));
+                }
+                let neighbour = self
+                    .vertices
+                    .get(neighbour_index)
+                    .ok_or(PathError::InvalidIndex(neighbour_index))?;
+
+                let Some(cost) = edge_cost(
+                    current.vertex,
+                    neighbour_index,
+                    vertex.position.metric_distance(&neighbour.position) * neighbour.g_penalty,
+                ) else {
+                    continue;
+                };
+
+                let g_score = current.g_score + cost;
+                if g_score < front.g_scores[neighbour_index] {
+                    front.g_scores[neighbour_index] = g_score;
+                    front.parents[neighbour_index] = current.vertex;
+                    front.heap.push(FrontEntry {
+                        vertex: neighbour_index,
+                        g_score,
+                        f_score: g_score + estimate(neighbour.position),
+                    });
+                }
+            }
+        }
+
+        Ok(None)
+    }
+
+    /// Finds every vertex, that could be reached from the given vertex with total path cost, that
+    /// does not exceed `max_cost`. The cost is measured the same way as by
+    /// [`Self::build_indexed_path`] (length of the edges multiplied by the penalty of the
+    /// destination vertex). Returns pairs of vertex indices and their path costs, sorted by the
+    /// cost. The starting vertex is included with zero cost. The result is empty, if the starting
+    /// vertex does not exist.
+    pub fn reachable_within(&self, from: usize, max_cost: f32) -> Vec<(usize, f32)> {
+        self.reachable_within_with_cost(from, max_cost, |_, _, cost| Some(cost))
+    }
+
+    /// The same as [`Self::reachable_within`], but allows you to override the cost of traversing
+    /// every edge of the graph. See [`Self::build_indexed_path_with_cost`] for more info about
+    /// `edge_cost` closure.
+    pub fn reachable_within_with_cost<F>(
+        &self,
+        from: usize,
+        max_cost: f32,
+        mut edge_cost: F,
+    ) -> Vec<(usize, f32)>
+    where
+        F: FnMut(usize, usize, f32) -> Option<f32>,
+    {
+        let mut reached = Vec::new();
+        if from >= self.vertices.len() {
+            return reached;
+        }
+
+        let mut costs = vec![f32::MAX; self.vertices.len()];
+        let mut visited = vec![false; self.vertices.len()];
+        let mut heap = BinaryHeap::new();
+        costs[from] = 0.0;
+        heap.push(FrontEntry {
+            vertex: from,
+            g_score: 0.0,
+            f_score: 0.0,
+        });
+
+        while let Some(entry) = heap.pop() {
+            if visited[entry.vertex] {
+                continue;
+            }
+            visited[entry.vertex] = true;
+            reached.push((entry.vertex, entry.g_score));
+
+            let vertex = &self.vertices[entry.vertex];
+            for &neighbour_index in vertex.neighbours.iter() {
+                let neighbour_index = neighbour_index as usize;
+                let Some(neighbour) = self.vertices.get(neighbour_index) else {
+                    continue;
+                };
+                if visited[neighbour_index] {
+                    continue;
+                }
+
+                let Some(cost) = edge_cost(
+                    entry.vertex,
+                    neighbour_index,
+                    vertex.position.metric_distance(&neighbour.position) * neighbour.g_penalty,
+                ) else {
+                    continue;
+                };
+
+                let g_score = entry.g_score + cost;
+                if g_score <= max_cost && g_score < costs[neighbour_index] {
+                    costs[neighbour_index] = g_score;
+                    heap.push(FrontEntry {
+                        vertex: neighbour_index,
+                        g_score,
+                        f_score: g_score,
+                    });
+                }
+            }
+        }
+
+        reached
+    }
+
+    /// **Deprecated** *use **`Graph<T>.build_positional_path()`** instead*
+    ///
+    /// Tries to build path of Vector3's from beginning point to endpoint. Returns path kind:
+    ///
+    /// - Full: Path vector is a direct path from beginning to end.
+    /// - Partial: Path vector is a path that ends closest to the desired end, because pathfinder could not find a full path.
+    ///
+    /// *See `PathKind`*
+    ///
+    /// # Notes
+    ///
+    /// This implementation is fast and allows for multiple searches in parallel, but does not attempt to find the optimal route
+    ///
+    /// **See `Graph<T>.max_search_iterations`** to change the maximum amount of search iterations
+    #[deprecated = "name is too ambiguous use build_positional_path instead"]
+    pub fn build(
+        &self,
+        from: usize,
+        to: usize,
+        path: &mut Vec<Vector3<f32>>,
+    ) -> Result<PathKind, PathError> {
+        self.build_positional_path(from, to, path)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::rand::Rng;
+    use crate::utils::astar::PathError;
+    use crate::{
+        core::{algebra::Vector3, rand},
+        utils::astar::{Graph, GraphVertex, Heuristic, PathKind, PathfindContext, PathfindStats},
+    };
+    use std::time::Instant;
+
+    const GRID_SIZE: usize = 10;
+
+    /// Creates a 4-connected grid of `GRID_SIZE` x `GRID_SIZE` vertices with unit spacing. The grid
+    /// has a wall between the columns 4 and 5, that blocks the rows below `wall_height`.
+    fn make_grid(wall_height: usize) -> Graph<GraphVertex> {
+        let size = GRID_SIZE;
+        let mut pathfinder = Graph::new();
+        for y in 0..size {
+            for x in 0..size {
+                pathfinder.add_vertex(GraphVertex::new(Vector3::new(x as f32, y as f32, 0.0)));
+            }
+        }
+        for y in 0..size {
+            for x in 0..size {
+                if x + 1 < size && (x != 4 || y >= wall_height) {
+                    pathfinder.link_bidirect(y * size + x, y * size + x + 1);
+                }
+                if y + 1 < size {
+                    pathfinder.link_bidirect(y * size + x, (y + 1) * size + x);
+                }
+            }
+        }
+        pathfinder
+    }
+
+    #[test]
+    fn astar_random_points() {
+        let mut pathfinder = Graph::<GraphVertex>::new();
+
+        let mut path = Vec::new();
+        assert!(pathfinder
+            .build_positional_path(0, 0, &mut path)
+            .is_err_and(|e| matches!(e, PathError::Empty)));
+        assert!(path.is_empty());
+
+        let size = 40;
+        // An admissible heuristic could expand every vertex of the grid.
+        pathfinder.max_search_iterations = (size * size) as i32;
+
+        // Create vertices.
+        let mut vertices = Vec::new();
         for y in 0..size {
             for x in 0..size {
                 vertices.push(GraphVertex::new(Vector3::new(x as f32, y as f32, 0.0)));
@@ -747,6 +1827,571 @@ mod test {
         assert_eq!(pathfinder.vertex(3).unwrap().neighbours, vec![2, 1]);
     }
 
+    #[test]
+    fn test_partial_path_ends_closest_to_target() {
+        let mut pathfinder = Graph::new();
+
+        // A chain that goes away from the target, and a vertex closer to the target in the middle.
+        pathfinder.add_vertex(GraphVertex::new(Vector3::new(0.0, 0.0, 0.0)));
+        pathfinder.add_vertex(GraphVertex::new(Vector3::new(1.0, 0.0, 0.0)));
+        pathfinder.add_vertex(GraphVertex::new(Vector3::new(2.0, 0.0, 0.0)));
+        pathfinder.add_vertex(GraphVertex::new(Vector3::new(1.0, 5.0, 0.0)));
+        // Isolated target.
+        pathfinder.add_vertex(GraphVertex::new(Vector3::new(3.0, 0.0, 0.0)));
+
+        pathfinder.link_bidirect(0, 1);
+        pathfinder.link_bidirect(1, 2);
+        pathfinder.link_bidirect(0, 3);
+
+        let mut path = Vec::new();
+        assert_eq!(
+            pathfinder.build_indexed_path(0, 4, &mut path),
+            Ok(PathKind::Partial)
+        );
+        assert_eq!(path, vec![2, 1, 0]);
+    }
+
+    #[test]
+    fn test_heuristics() {
+        let pathfinder = make_grid(0);
+
+        let a = Vector3::new(0.0, 0.0, 0.0);
+        let b = Vector3::new(3.0, -4.0, 1.0);
+        assert_eq!(Heuristic::default().estimate(a, b), 26.0f32.sqrt());
+        assert_eq!(Heuristic::Manhattan.estimate(a, b), 8.0);
+        let diagonal = Heuristic::Diagonal.estimate(a, b);
+        assert!(diagonal > 26.0f32.sqrt() && diagonal < 8.0);
+
+        let from = 0;
+        let to = 7 * GRID_SIZE + 6;
+        let mut reference = Vec::new();
+        assert_eq!(
+            pathfinder.build_indexed_path(from, to, &mut reference),
+            Ok(PathKind::Full)
+        );
+
+        let mut path = Vec::new();
+        for heuristic in [
+            Heuristic::Euclidean,
+            Heuristic::Manhattan,
+            Heuristic::Diagonal,
+            Heuristic::Custom(Box::new(|a, b| (a - b).abs().max())),
+        ] {
+            for weight in [1.0, 2.0] {
+                assert_eq!(
+                    pathfinder
+                        .build_indexed_path_with_heuristic(from, to, &mut path, &heuristic, weight),
+                    Ok(PathKind::Full)
+                );
+                assert_eq!(path.first(), Some(&to));
+                assert_eq!(path.last(), Some(&from));
+                // Every path on a 4-connected grid without obstacles has the same length.
+                assert_eq!(path.len(), reference.len());
+            }
+        }
+
+        let mut positions = Vec::new();
+        assert_eq!(
+            pathfinder.build_positional_path_with_heuristic(
+                from,
+                to,
+                &mut positions,
+                &Heuristic::Euclidean,
+                1.0
+            ),
+            Ok(PathKind::Full)
+        );
+        assert_eq!(positions.len(), reference.len());
+    }
+
+    #[test]
+    fn test_search_exhausted() {
+        let mut pathfinder = Graph::new();
+        for i in 0..10 {
+            pathfinder.add_vertex(GraphVertex::new(Vector3::new(i as f32, 0.0, 0.0)));
+        }
+        for i in 0..9 {
+            pathfinder.link_bidirect(i, i + 1);
+        }
+        let isolated = pathfinder.add_vertex(GraphVertex::new(Vector3::new(20.0, 0.0, 0.0)));
+
+        let mut path = Vec::new();
+        let no_cost = |_: usize, _: usize, cost: f32| Some(cost);
+        assert_eq!(
+            pathfinder.build_indexed_path_with_cost_limited(0, 9, &mut path, Some(3), no_cost),
+            Err(PathError::SearchExhausted(3))
+        );
+        // The best partial path is still valid.
+        assert_eq!(path, vec![2, 1, 0]);
+
+        assert_eq!(
+            pathfinder.build_indexed_path_with_cost_limited(0, 9, &mut path, Some(100), no_cost),
+            Ok(PathKind::Full)
+        );
+        assert_eq!(path.len(), 10);
+        assert_eq!(
+            pathfinder.build_indexed_path_with_cost_limited(0, 9, &mut path, None, no_cost),
+            Ok(PathKind::Full)
+        );
+
+        // The whole component was searched before hitting the limit.
+        assert_eq!(
+            pathfinder.build_indexed_path_with_cost_limited(
+                0,
+                isolated as usize,
+                &mut path,
+                Some(100),
+                no_cost
+            ),
+            Ok(PathKind::Partial)
+        );
+    }
+
+    #[test]
+    fn test_heuristics_with_obstacles() {
+        // The only passage through the wall is above it.
+        let mut pathfinder = make_grid(8);
+        // A penalized vertex right behind the passage makes the detour through the upper row
+        // cheaper.
+        pathfinder.vertex_mut(8 * GRID_SIZE + 5).unwrap().g_penalty = 4.0;
+
+        let from = 0;
+        let to = GRID_SIZE - 1;
+        let mut path = Vec::new();
+        let mut reference = PathfindStats::default();
+        assert_eq!(
+            pathfinder.build_indexed_path_with_stats(
+                from,
+                to,
+                &mut path,
+                &Heuristic::Custom(Box::new(|_, _| 0.0)),
+                1.0,
+                &mut reference
+            ),
+            Ok(PathKind::Full)
+        );
+        // 9 steps up, 9 steps to the right and 9 steps down.
+        assert_eq!(reference.path_cost, 27.0);
+
+        for heuristic in [
+            Heuristic::Euclidean,
+            Heuristic::Manhattan,
+            Heuristic::Diagonal,
+        ] {
+            let mut stats = PathfindStats::default();
+            assert_eq!(
+                pathfinder.build_indexed_path_with_stats(
+                    from, to, &mut path, &heuristic, 1.0, &mut stats
+                ),
+                Ok(PathKind::Full)
+            );
+            assert_eq!(path.first(), Some(&to));
+            assert_eq!(path.last(), Some(&from));
+            assert_eq!(stats.path_cost, reference.path_cost, "{heuristic:?}");
+            assert!(stats.expanded_vertices <= reference.expanded_vertices);
+        }
+    }
+
+    #[test]
+    fn test_stats() {
+        let pathfinder = make_grid(0);
+
+        let from = 0;
+        let to = 7 * GRID_SIZE + 6;
+        let mut path = Vec::new();
+        let mut stats = PathfindStats::default();
+        assert_eq!(
+            pathfinder.build_indexed_path_with_stats(
+                from,
+                to,
+                &mut path,
+                &Heuristic::Manhattan,
+                1.0,
+                &mut stats
+            ),
+            Ok(PathKind::Full)
+        );
+        assert_eq!(stats.path_vertices, path.len());
+        assert!(stats.expanded_vertices >= path.len() - 1);
+        // Every edge of the grid has unit length.
+        assert_eq!(stats.path_cost, (path.len() - 1) as f32);
+        assert!(stats.reopened_vertices <= stats.expanded_vertices);
+
+        // Weighted search explores less vertices.
+        let mut weighted_stats = PathfindStats::default();
+        assert_eq!(
+            pathfinder.build_indexed_path_with_stats(
+                from,
+                to,
+                &mut path,
+                &Heuristic::Manhattan,
+                2.0,
+                &mut weighted_stats
+            ),
+            Ok(PathKind::Full)
+        );
+        assert!(weighted_stats.expanded_vertices <= stats.expanded_vertices);
+
+        assert_eq!(
+            pathfinder.build_indexed_path_with_stats(
+                from,
+                from,
+                &mut path,
+                &Heuristic::Euclidean,
+                1.0,
+                &mut stats
+            ),
+            Ok(PathKind::Full)
+        );
+        assert_eq!(stats.expanded_vertices, 0);
+        assert_eq!(stats.path_vertices, 1);
+
+        // Statistics are collected for failed searches as well.
+        let mut broken = Graph::new();
+        for x in 0..3 {
+            broken.add_vertex(GraphVertex::new(Vector3::new(x as f32, 0.0, 0.0)));
+        }
+        broken.link_unidirect(0, 1);
+        broken.vertices[1].neighbours.push(5);
+        assert_eq!(
+            broken.build_indexed_path_with_stats(
+                0,
+                2,
+                &mut path,
+                &Heuristic::Euclidean,
+                1.0,
+                &mut stats
+            ),
+            Err(PathError::InvalidIndex(5))
+        );
+        assert_eq!(stats.expanded_vertices, 1);
+        assert_eq!(stats.path_vertices, 0);
+    }
+
+    #[test]
+    fn test_context_reuse() {
+        // A wall with a gap at the top.
+        let pathfinder = make_grid(8);
+
+        let queries = [(0, 9), (11, 77), (90, 3), (5, 5)];
+        let mut context = PathfindContext::new();
+        let mut path = Vec::new();
+        let mut reference = Vec::new();
+        for (from, to) in queries {
+            assert_eq!(
+                pathfinder.build_indexed_path_with_context(
+                    from,
+                    to,
+                    &mut path,
+                    &Heuristic::Euclidean,
+                    1.0,
+                    &mut context
+                ),
+                pathfinder.build_indexed_path(from, to, &mut reference)
+            );
+            assert_eq!(path, reference);
+        }
+
+        // The memory is reused by the subsequent searches.
+        let capacity = (context.nodes.capacity(), context.open_set.capacity());
+        for (from, to) in queries {
+            pathfinder
+                .build_indexed_path_with_context(
+                    from,
+                    to,
+                    &mut path,
+                    &Heuristic::Euclidean,
+                    1.0,
+                    &mut context,
+                )
+                .unwrap();
+        }
+        assert_eq!(
+            (context.nodes.capacity(), context.open_set.capacity()),
+            capacity
+        );
+    }
+
+    #[test]
+    fn test_bidirectional() {
+        // A grid with a wall in the middle, that has a passage at the top.
+        let pathfinder = make_grid(8);
+
+        let from = 0;
+        let to = GRID_SIZE - 1;
+
+        let mut unidirectional = Vec::new();
+        assert_eq!(
+            pathfinder.build_indexed_path_with_heuristic(
+                from,
+                to,
+                &mut unidirectional,
+                &Heuristic::Manhattan,
+                1.0
+            ),
+            Ok(PathKind::Full)
+        );
+
+        let mut bidirectional = Vec::new();
+        assert_eq!(
+            pathfinder.build_indexed_path_bidirectional_with_heuristic(
+                from,
+                to,
+                &mut bidirectional,
+                &Heuristic::Manhattan
+            ),
+            Ok(PathKind::Full)
+        );
+        assert_eq!(bidirectional.first(), Some(&to));
+        assert_eq!(bidirectional.last(), Some(&from));
+        // Shortest path goes up to the passage and then back down.
+        assert_eq!(unidirectional.len(), 26);
+        assert_eq!(bidirectional.len(), unidirectional.len());
+        for pair in bidirectional.windows(2) {
+            assert!(pathfinder
+                .vertex(pair[1])
+                .unwrap()
+                .neighbours
+                .contains(&(pair[0] as u32)));
+        }
+
+        let mut positions = Vec::new();
+        assert_eq!(
+            pathfinder.build_positional_path_bidirectional(from, from, &mut positions),
+            Ok(PathKind::Full)
+        );
+        assert_eq!(positions, vec![Vector3::new(0.0, 0.0, 0.0)]);
+    }
+
+    #[test]
+    fn test_bidirectional_with_cost() {
+        let mut pathfinder = make_grid(8);
+
+        // The passage through the wall is two rows high, only the link from the left to the right
+        // in its lower row is blocked.
+        let (left, right) = (8 * GRID_SIZE + 4, 8 * GRID_SIZE + 5);
+        let edge_cost =
+            |from: usize, to: usize, cost: f32| (from != left || to != right).then_some(cost);
+
+        let mut context = PathfindContext::new();
+        let mut path = Vec::new();
+        let mut reference = Vec::new();
+        for (from, to) in [(0, GRID_SIZE - 1), (GRID_SIZE - 1, 0)] {
+            assert_eq!(
+                pathfinder.build_indexed_path_bidirectional_with_context(
+                    from,
+                    to,
+                    &mut path,
+                    &Heuristic::Manhattan,
+                    edge_cost,
+                    &mut context
+                ),
+                Ok(PathKind::Full)
+            );
+            assert_eq!(
+                pathfinder.build_indexed_path_with_cost(from, to, &mut reference, edge_cost),
+                Ok(PathKind::Full)
+            );
+            assert_eq!(path.len(), reference.len());
+        }
+        // Backwards the path goes through the lower row, and from the left to the right through the
+        // upper one.
+        assert_eq!(path.len(), 26);
+        assert_eq!(
+            pathfinder.build_indexed_path_bidirectional_with_cost(
+                0,
+                GRID_SIZE - 1,
+                &mut path,
+                edge_cost
+            ),
+            Ok(PathKind::Full)
+        );
+        assert_eq!(path.len(), 28);
+
+        // The cached links are collected again after invalidation.
+        pathfinder.link_bidirect(4, 5);
+        context.invalidate_links();
+        assert_eq!(
+            pathfinder.build_indexed_path_bidirectional_with_context(
+                0,
+                GRID_SIZE - 1,
+                &mut path,
+                &Heuristic::Manhattan,
+                edge_cost,
+                &mut context
+            ),
+            Ok(PathKind::Full)
+        );
+        assert_eq!(path.len(), GRID_SIZE);
+    }
+
+    #[test]
+    fn test_bidirectional_one_way_links() {
+        let mut pathfinder = Graph::new();
+
+        pathfinder.add_vertex(GraphVertex::new(Vector3::new(0.0, 0.0, 0.0)));
+        pathfinder.add_vertex(GraphVertex::new(Vector3::new(1.0, 0.0, 0.0)));
+        pathfinder.add_vertex(GraphVertex::new(Vector3::new(2.0, 0.0, 0.0)));
+
+        pathfinder.link_unidirect(0, 1);
+        pathfinder.link_unidirect(1, 2);
+        pathfinder.link_unidirect(2, 0);
+
+        let mut path = Vec::new();
+        assert_eq!(
+            pathfinder.build_indexed_path_bidirectional(0, 2, &mut path),
+            Ok(PathKind::Full)
+        );
+        assert_eq!(path, vec![2, 1, 0]);
+
+        assert_eq!(
+            pathfinder.build_indexed_path_bidirectional(2, 1, &mut path),
+            Ok(PathKind::Full)
+        );
+        assert_eq!(path, vec![1, 0, 2]);
+    }
+
+    #[test]
+    fn test_bidirectional_partial_path() {
+        let mut pathfinder = Graph::new();
+
+        pathfinder.add_vertex(GraphVertex::new(Vector3::new(0.0, 0.0, 0.0)));
+        pathfinder.add_vertex(GraphVertex::new(Vector3::new(1.0, 0.0, 0.0)));
+        pathfinder.add_vertex(GraphVertex::new(Vector3::new(2.0, 0.0, 0.0)));
+        pathfinder.add_vertex(GraphVertex::new(Vector3::new(1.0, 5.0, 0.0)));
+        // Isolated target.
+        pathfinder.add_vertex(GraphVertex::new(Vector3::new(3.0, 0.0, 0.0)));
+
+        pathfinder.link_bidirect(0, 1);
+        pathfinder.link_bidirect(1, 2);
+        pathfinder.link_bidirect(0, 3);
+
+        let mut path = Vec::new();
+        assert_eq!(
+            pathfinder.build_indexed_path_bidirectional(0, 4, &mut path),
+            Ok(PathKind::Partial)
+        );
+        assert_eq!(path, vec![2, 1, 0]);
+    }
+
+    #[test]
+    fn test_reachable_within() {
+        let mut pathfinder = Graph::new();
+
+        pathfinder.add_vertex(GraphVertex::new(Vector3::new(0.0, 0.0, 0.0)));
+        pathfinder.add_vertex(GraphVertex::new(Vector3::new(1.0, 0.0, 0.0)));
+        pathfinder.add_vertex(GraphVertex::new(Vector3::new(2.0, 0.0, 0.0)));
+        pathfinder.add_vertex(GraphVertex::new(Vector3::new(3.0, 0.0, 0.0)));
+        // Isolated vertex.
+        pathfinder.add_vertex(GraphVertex::new(Vector3::new(0.0, 1.0, 0.0)));
+
+        pathfinder.link_bidirect(0, 1);
+        pathfinder.link_bidirect(1, 2);
+        pathfinder.link_bidirect(2, 3);
+        pathfinder.vertex_mut(2).unwrap().g_penalty = 3.0;
+
+        assert_eq!(
+            pathfinder.reachable_within(0, 4.0),
+            vec![(0, 0.0), (1, 1.0), (2, 4.0)]
+        );
+        assert_eq!(
+            pathfinder.reachable_within(0, 3.9),
+            vec![(0, 0.0), (1, 1.0)]
+        );
+        assert_eq!(
+            pathfinder.reachable_within_with_cost(0, 10.0, |_, dest, cost| {
+                (dest != 2).then_some(cost)
+            }),
+            vec![(0, 0.0), (1, 1.0)]
+        );
+        assert!(pathfinder.reachable_within(10, 10.0).is_empty());
+    }
+
+    #[test]
+    fn test_deterministic_tie_breaking() {
+        // Two paths of equal cost, the one through the vertex with the lower index must be
+        // chosen regardless of the order of the links.
+        for reversed in [false, true] {
+            let mut pathfinder = Graph::new();
+            pathfinder.add_vertex(GraphVertex::new(Vector3::new(0.0, 0.0, 0.0)));
+            pathfinder.add_vertex(GraphVertex::new(Vector3::new(1.0, 0.0, 1.0)));
+            pathfinder.add_vertex(GraphVertex::new(Vector3::new(1.0, 0.0, -1.0)));
+            pathfinder.add_vertex(GraphVertex::new(Vector3::new(2.0, 0.0, 0.0)));
+            let mut links = [(0, 1), (1, 3), (0, 2), (2, 3)];
+            if reversed {
+                links.reverse();
+            }
+            for (a, b) in links {
+                pathfinder.link_bidirect(a, b);
+            }
+
+            for _ in 0..10 {
+                let mut path = Vec::new();
+                assert_eq!(
+                    pathfinder.build_indexed_path(0, 3, &mut path),
+                    Ok(PathKind::Full)
+                );
+                assert_eq!(path, vec![3, 1, 0]);
+                assert_eq!(
+                    pathfinder.build_indexed_path_bidirectional(0, 3, &mut path),
+                    Ok(PathKind::Full)
+                );
+                assert_eq!(path, vec![3, 1, 0]);
+            }
+        }
+    }
+
+    #[test]
+    fn test_path_to_nearest() {
+        let mut pathfinder = Graph::new();
+
+        pathfinder.add_vertex(GraphVertex::new(Vector3::new(0.0, 0.0, 0.0)));
+        pathfinder.add_vertex(GraphVertex::new(Vector3::new(1.0, 0.0, 0.0)));
+        pathfinder.add_vertex(GraphVertex::new(Vector3::new(2.0, 0.0, 0.0)));
+        pathfinder.add_vertex(GraphVertex::new(Vector3::new(-1.0, 0.0, 0.0)));
+        pathfinder.add_vertex(GraphVertex::new(Vector3::new(-2.0, 0.0, 0.0)));
+        // Isolated vertex.
+        pathfinder.add_vertex(GraphVertex::new(Vector3::new(0.0, 1.0, 0.0)));
+
+        pathfinder.link_bidirect(0, 1);
+        pathfinder.link_bidirect(1, 2);
+        pathfinder.link_bidirect(0, 3);
+        pathfinder.link_bidirect(3, 4);
+
+        let mut path = Vec::new();
+        assert_eq!(
+            pathfinder.build_indexed_path_to_nearest(0, &[5, 4, 1], &mut path),
+            Ok(Some(2))
+        );
+        assert_eq!(path, vec![1, 0]);
+
+        // Expensive vertex makes the other goal nearer.
+        pathfinder.vertex_mut(1).unwrap().g_penalty = 10.0;
+        assert_eq!(
+            pathfinder.build_indexed_path_to_nearest(0, &[5, 4, 2], &mut path),
+            Ok(Some(1))
+        );
+        assert_eq!(path, vec![4, 3, 0]);
+
+        assert_eq!(
+            pathfinder.build_indexed_path_to_nearest(0, &[0, 4], &mut path),
+            Ok(Some(0))
+        );
+        assert_eq!(path, vec![0]);
+        assert_eq!(
+            pathfinder.build_indexed_path_to_nearest(0, &[5], &mut path),
+            Ok(None)
+        );
+        assert_eq!(
+            pathfinder.build_indexed_path_to_nearest(0, &[], &mut path),
+            Ok(None)
+        );
+        assert_eq!(
+            pathfinder.build_indexed_path_to_nearest(0, &[10], &mut path),
+            Err(PathError::InvalidIndex(10))
+        );
+    }
+
     #[ignore = "takes multiple seconds to run"]
     #[test]
     /// Tests A*'s speed when finding a direct path with no obsticles
@@ -0,0 +1,2966 @@
+// Copyright (c) 2019-present Dmitry Stepanov and Fyrox Engine contributors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Navmesh agent follows paths on a navmesh. See [`NavmeshAgent`] docs for more info.
+
+use crate::{
+    core::{
+        algebra::{Vector2, Vector3},
+        color::Color,
+        reflect::prelude::*,
+        visitor::{Visit, VisitResult, Visitor},
+    },
+    scene::{
+        self,
+        debug::{Line, SceneDrawingContext},
+    },
+    utils::{
+        astar::{PathError, PathKind},
+        navmesh::{
+            blend_directions, catmull_rom_by_length, closest_point_on_triangle, crowd,
+            time_to_collision, Navmesh, NavmeshPathError, NavmeshQueryFilter, OffMeshLinkHandle,
+            PathQuery, PathRequestId,
+        },
+    },
+};
+use std::collections::VecDeque;
+use strum_macros::{AsRefStr, EnumString, VariantNames};
+
+/// Navmesh agent is a "pathfinding unit" that performs navigation on a mesh. It is designed to
+/// cover most of simple use cases when you need to build and follow some path from point A to point B.
+///
+/// Tunable parameters of the agent (speed, radius, smoothing, etc.) could be edited using
+/// reflection, so an agent, that is stored in a script, could be configured in the inspector of
+/// the editor. The state of the agent (its position, target and path) is hidden. Such edits go
+/// through the setters, so they have the same effect as the respective method calls (for example,
+/// changing the radius recalculates the path).
+#[derive(Clone, Debug, Reflect)]
+pub struct NavmeshAgent {
+    #[reflect(hidden)]
+    pub(super) path: Vec<Vector3<f32>>,
+    #[reflect(hidden)]
+    pub(super) current: u32,
+    #[reflect(hidden)]
+    pub(super) position: Vector3<f32>,
+    #[reflect(hidden)]
+    last_warp_position: Vector3<f32>,
+    #[reflect(hidden)]
+    pub(super) target: Vector3<f32>,
+    #[reflect(hidden)]
+    last_target_position: Vector3<f32>,
+    /// Minimal distance (in meters), that the target or the agent must move to trigger path
+    /// recalculation, see [`NavmeshAgent::set_threshold`].
+    #[reflect(min_value = 0.0, setter = "set_threshold")]
+    recalculation_threshold: f32,
+    /// Movement speed of the agent (in meters per second).
+    #[reflect(min_value = 0.0, setter = "set_speed")]
+    speed: f32,
+    #[reflect(hidden)]
+    pub(super) path_dirty: bool,
+    /// Radius of the agent (in meters), the path keeps this distance from the borders of
+    /// the navmesh.
+    #[reflect(min_value = 0.0, setter = "set_radius")]
+    pub(super) radius: f32,
+    #[reflect(hidden)]
+    pub(super) interpolator: f32,
+    /// Defines how the path is smoothed, see [`NavmeshAgent::set_smoothing_mode`].
+    #[reflect(setter = "set_smoothing_mode")]
+    pub(super) smoothing: SmoothingMode,
+    #[reflect(hidden)]
+    pub(super) offmesh_segments: Vec<OffMeshLinkSegment>,
+    /// Whether the agent walks to the closest reachable point, when its target is unreachable.
+    #[reflect(setter = "set_allow_partial_path")]
+    pub(super) allow_partial_path: bool,
+    #[reflect(hidden)]
+    pub(super) partial_path: bool,
+    /// Whether the paths are calculated asynchronously, see
+    /// [`NavmeshAgent::set_async_path_calculation`].
+    #[reflect(setter = "set_async_path_calculation")]
+    async_path: bool,
+    /// Priority of the asynchronous path requests of the agent, see
+    /// [`NavmeshAgent::set_path_priority`].
+    #[reflect(setter = "set_path_priority")]
+    path_priority: i32,
+    #[reflect(hidden)]
+    path_request: Option<PathRequestId>,
+    /// Distance (in meters) from the end of the path, at which the target is considered as reached.
+    #[reflect(min_value = 0.0, setter = "set_arrival_radius")]
+    arrival_radius: f32,
+    /// Distance (in meters), that the target must move away after it was reached to make
+    /// the agent follow it again, see [`NavmeshAgent::set_reacquire_radius`].
+    #[reflect(min_value = 0.0, setter = "set_reacquire_radius")]
+    reacquire_radius: f32,
+    #[reflect(hidden)]
+    events: VecDeque<NavmeshAgentEvent>,
+    #[reflect(hidden)]
+    was_target_reached: bool,
+    #[reflect(hidden)]
+    last_offmesh_link: Option<OffMeshLinkHandle>,
+    /// Minimal time (in seconds) between path recalculations, see
+    /// [`NavmeshAgent::set_recalculation_interval`].
+    #[reflect(min_value = 0.0, setter = "set_recalculation_interval")]
+    recalculation_interval: f32,
+    #[reflect(hidden)]
+    pub(super) time_since_recalculation: f32,
+    /// Whether the agent is kept on the surface of the navmesh, see
+    /// [`NavmeshAgent::set_clamp_to_surface`].
+    #[reflect(setter = "set_clamp_to_surface")]
+    clamp_to_surface: bool,
+    #[reflect(hidden)]
+    surface_triangle: Option<usize>,
+    // Normal of the surface under the agent, see `NavmeshAgent::ground_normal`.
+    #[reflect(hidden)]
+    ground_normal: Vector3<f32>,
+    /// Distance (in meters) from the target, at which the agent starts to slow down, see
+    /// [`NavmeshAgent::set_slowdown_distance`].
+    #[reflect(min_value = 0.0, setter = "set_slowdown_distance")]
+    slowdown_distance: f32,
+    /// Filter of the triangles, that the path of the agent could go through.
+    #[reflect(setter = "set_query_filter")]
+    pub(super) query_filter: NavmeshQueryFilter,
+    /// The filter, that was used for the current path. Nested fields of the filter could be
+    /// edited via reflection without calling the setter, so the filters are compared on update.
+    #[reflect(hidden)]
+    path_query_filter: NavmeshQueryFilter,
+    /// Minimal turning radius (in meters) of the agent, see [`NavmeshAgent::set_turning_radius`].
+    #[reflect(min_value = 0.0, setter = "set_turning_radius")]
+    turning_radius: f32,
+    /// A fraction of the offset from the path, that is corrected on every update, see
+    /// [`NavmeshAgent::set_path_correction_strength`].
+    #[reflect(
+        min_value = 0.0,
+        max_value = 1.0,
+        setter = "set_path_correction_strength"
+    )]
+    path_correction_strength: f32,
+    // Remaining offset of the agent from its path, see
+    // `NavmeshAgent::set_path_correction_strength`.
+    #[reflect(hidden)]
+    pub(super) path_offset: Vector3<f32>,
+    // Offset of the agent from its path, made by the avoidance of neighbors and moving obstacles,
+    // see `NavmeshAgent::update_with_neighbors`.
+    #[reflect(hidden)]
+    avoidance_offset: Vector3<f32>,
+    /// Prediction horizon (in seconds) of the avoidance of moving obstacles, see
+    /// [`NavmeshAgent::set_avoidance_horizon`].
+    #[reflect(min_value = 0.0, setter = "set_avoidance_horizon")]
+    avoidance_horizon: f32,
+    #[reflect(hidden)]
+    heading: Vector3<f32>,
+    /// Acceleration of the agent (in meters per second squared), see
+    /// [`NavmeshAgent::set_acceleration`].
+    #[reflect(min_value = 0.0, setter = "set_acceleration")]
+    acceleration: f32,
+    /// Deceleration of the agent (in meters per second squared), see
+    /// [`NavmeshAgent::set_deceleration`].
+    #[reflect(min_value = 0.0, setter = "set_deceleration")]
+    deceleration: f32,
+    #[reflect(hidden)]
+    current_speed: f32,
+    /// Distance (in meters), that the agent keeps from its target, see
+    /// [`NavmeshAgent::set_follow_distance`].
+    #[reflect(min_value = 0.0, setter = "set_follow_distance")]
+    follow_distance: f32,
+    /// Maximum cost of the path, see [`NavmeshAgent::set_max_path_cost`].
+    #[reflect(min_value = 0.0, setter = "set_max_path_cost")]
+    max_path_cost: f32,
+    #[reflect(hidden)]
+    spent_path_cost: f32,
+    #[reflect(hidden)]
+    budget_limited: bool,
+    /// Whether the agent detects, that it stopped making progress, see
+    /// [`NavmeshAgent::set_stuck_detection`].
+    #[reflect(setter = "set_stuck_detection_enabled")]
+    stuck_detection: bool,
+    /// Time window (in seconds) of the stuck detection.
+    #[reflect(min_value = 0.0, setter = "set_stuck_time_window")]
+    stuck_time_window: f32,
+    /// Minimal progress (in meters) within the time window of the stuck detection.
+    #[reflect(min_value = 0.0, setter = "set_stuck_min_progress")]
+    stuck_min_progress: f32,
+    /// Whether the agent recalculates its path, when it is stuck.
+    #[reflect(setter = "set_replan_when_stuck")]
+    replan_when_stuck: bool,
+    #[reflect(hidden)]
+    stuck: bool,
+    #[reflect(hidden)]
+    stuck_timer: f32,
+    #[reflect(hidden)]
+    stuck_anchor: Vector3<f32>,
+    /// Initial delay (in seconds) before the next attempt to calculate a path after a
+    /// failed one, see [`NavmeshAgent::set_replan_backoff`].
+    #[reflect(min_value = 0.0, setter = "set_replan_backoff_min_interval")]
+    replan_backoff_min_interval: f32,
+    /// Maximum delay (in seconds) between the attempts to calculate a path, see
+    /// [`NavmeshAgent::set_replan_backoff`].
+    #[reflect(min_value = 0.0, setter = "set_replan_backoff_max_interval")]
+    replan_backoff_max_interval: f32,
+    // Current delay before the next attempt to calculate a path after a failed attempt.
+    #[reflect(hidden)]
+    replan_backoff: f32,
+    #[reflect(hidden)]
+    replan_backoff_timer: f32,
+    // Target of the last failed attempt.
+    #[reflect(hidden)]
+    replan_backoff_target: Vector3<f32>,
+    // Triangles, that the current path goes through.
+    #[reflect(hidden)]
+    pub(super) corridor: Vec<usize>,
+    #[reflect(hidden)]
+    target_region: Vec<usize>,
+    // Triangle of the target region, that the current path ends in.
+    #[reflect(hidden)]
+    target_region_triangle: Option<usize>,
+    // The agent was moved by `NavmeshAgent::set_position` since the last update.
+    #[reflect(hidden)]
+    pub(super) displaced: bool,
+    #[reflect(hidden)]
+    navmesh_revision: u64,
+    #[reflect(hidden)]
+    navmesh_geometry_revision: u64,
+    #[reflect(hidden)]
+    navmesh_topology_revision: u64,
+}
+
+/// Defines how a navmesh agent smooths its path. See [`NavmeshAgent::set_smoothing_mode`] for
+/// more info.
+#[derive(
+    Copy, Clone, Debug, Default, PartialEq, Eq, Visit, Reflect, AsRefStr, EnumString, VariantNames,
+)]
+pub enum SmoothingMode {
+    /// The path goes through the middle points of the edges between the triangles along the path.
+    None,
+    /// The path is built using the funnel algorithm and the agent moves along straight segments
+    /// between its waypoints.
+    #[default]
+    Funnel,
+    /// The path is built using the funnel algorithm and the agent moves along a Catmull-Rom spline
+    /// that passes through the waypoints.
+    Spline,
+}
+
+/// An event of a navmesh agent, that describes a change of its state. See
+/// [`NavmeshAgent::pop_event`] for more info.
+#[derive(Clone, Debug, PartialEq)]
+pub enum NavmeshAgentEvent {
+    /// The path of the agent was recalculated.
+    PathRecalculated,
+    /// The path of the agent could not be calculated.
+    PathFailed(NavmeshPathError),
+    /// The agent has reached its target. See [`NavmeshAgent::target_reached`] for more info.
+    TargetReached,
+    /// The agent has stopped making progress along its path. See
+    /// [`NavmeshAgent::set_stuck_detection`] for more info.
+    Stuck,
+    /// The agent has started to traverse an off-mesh link.
+    EnteredOffMeshLink(OffMeshLinkHandle),
+    /// The agent has reached its target region and entered the triangle with the given index. It
+    /// is generated together with [`Self::TargetReached`]. See [`NavmeshAgent::set_target_region`]
+    /// for more info.
+    EnteredTargetRegion(usize),
+}
+
+/// A segment of agent's path, that goes through an off-mesh link.
+#[derive(Clone, Debug, Default, PartialEq, Visit)]
+pub(super) struct OffMeshLinkSegment {
+    segment: u32,
+    link: OffMeshLinkHandle,
+}
+
+/// A part of agent's path, that lies inside a single triangle of the corridor or goes through an
+/// off-mesh link, see [`NavmeshAgent::set_max_path_cost`].
+struct PathCostPart {
+    segment: usize,
+    // Fractions of the segment, where the part begins and ends.
+    begin: f32,
+    end: f32,
+    cost: f32,
+    // Index of the triangle of the corridor, that contains the part (or the last triangle before
+    // the off-mesh link).
+    corridor_index: usize,
+}
+
+/// A snapshot of the runtime state of a navmesh agent (its position, target, path and the
+/// progress along it, etc.), see [`NavmeshAgent::snapshot`] for more info. The settings of the
+/// agent (speed, radius, etc.) are not captured.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NavmeshAgentState {
+    path: Vec<Vector3<f32>>,
+    current: u32,
+    position: Vector3<f32>,
+    last_warp_position: Vector3<f32>,
+    target: Vector3<f32>,
+    last_target_position: Vector3<f32>,
+    path_dirty: bool,
+    interpolator: f32,
+    offmesh_segments: Vec<OffMeshLinkSegment>,
+    partial_path: bool,
+    waiting_for_path: bool,
+    events: VecDeque<NavmeshAgentEvent>,
+    was_target_reached: bool,
+    last_offmesh_link: Option<OffMeshLinkHandle>,
+    time_since_recalculation: f32,
+    surface_triangle: Option<usize>,
+    ground_normal: Vector3<f32>,
+    path_offset: Vector3<f32>,
+    avoidance_offset: Vector3<f32>,
+    heading: Vector3<f32>,
+    current_speed: f32,
+    spent_path_cost: f32,
+    budget_limited: bool,
+    stuck: bool,
+    stuck_timer: f32,
+    stuck_anchor: Vector3<f32>,
+    replan_backoff: f32,
+    replan_backoff_timer: f32,
+    replan_backoff_target: Vector3<f32>,
+    corridor: Vec<usize>,
+    target_region: Vec<usize>,
+    target_region_triangle: Option<usize>,
+    displaced: bool,
+    navmesh_revision: u64,
+    navmesh_geometry_revision: u64,
+    navmesh_topology_revision: u64,
+}
+
+impl NavmeshAgentState {
+    /// Returns the position of the agent at the moment of the snapshot.
+    pub fn position(&self) -> Vector3<f32> {
+        self.position
+    }
+
+    /// Returns the target of the agent at the moment of the snapshot.
+    pub fn target(&self) -> Vector3<f32> {
+        self.target
+    }
+}
+
+/// A moving obstacle (a rolling boulder, a vehicle, etc.), that navmesh agents try to dodge. See
+/// [`NavmeshAgent::update_with_moving_obstacles`] for more info.
+#[derive(Copy, Clone, Debug, Default, PartialEq)]
+pub struct MovingObstacle {
+    /// Current position of the obstacle.
+    pub position: Vector3<f32>,
+    /// Current velocity (in meters per second) of the obstacle.
+    pub velocity: Vector3<f32>,
+    /// Radius (in meters) of the obstacle.
+    pub radius: f32,
+}
+
+impl Default for NavmeshAgent {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+/// Version of the serialized state of [`NavmeshAgent`]. The saves without the version marker are
+/// loaded with their path marked as outdated, so the path is recalculated on the next update.
+const NAVMESH_AGENT_VERSION: u8 = 1;
+
+// Manual implementation of the trait because we need to restore the transient state on load.
+impl Visit for NavmeshAgent {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        let mut region = visitor.enter_region(name)?;
+
+        let mut version = if region.is_reading() {
+            0
+        } else {
+            NAVMESH_AGENT_VERSION
+        };
+        let _ = version.visit("Version", &mut region);
+
+        // Every field is optional to be able to load the saves of any older version.
+        let _ = self.path.visit("Path", &mut region);
+        let _ = self.current.visit("Current", &mut region);
+        let _ = self.position.visit("Position", &mut region);
+        let _ = self
+            .last_warp_position
+            .visit("LastWarpPosition", &mut region);
+        let _ = self.target.visit("Target", &mut region);
+        let _ = self
+            .last_target_position
+            .visit("LastTargetPosition", &mut region);
+        let _ = self
+            .recalculation_threshold
+            .visit("RecalculationThreshold", &mut region);
+        let _ = self.speed.visit("Speed", &mut region);
+        let _ = self.path_dirty.visit("PathDirty", &mut region);
+        let _ = self.radius.visit("Radius", &mut region);
+        let _ = self.interpolator.visit("Interpolator", &mut region);
+        let _ = self.smoothing.visit("Smoothing", &mut region);
+        let _ = self.offmesh_segments.visit("OffmeshSegments", &mut region);
+        let _ = self
+            .allow_partial_path
+            .visit("AllowPartialPath", &mut region);
+        let _ = self.partial_path.visit("PartialPath", &mut region);
+        let _ = self.async_path.visit("AsyncPath", &mut region);
+        let _ = self.path_priority.visit("PathPriority", &mut region);
+        let _ = self.arrival_radius.visit("ArrivalRadius", &mut region);
+        let _ = self.reacquire_radius.visit("ReacquireRadius", &mut region);
+        let _ = self
+            .recalculation_interval
+            .visit("RecalculationInterval", &mut region);
+        let _ = self
+            .time_since_recalculation
+            .visit("TimeSinceRecalculation", &mut region);
+        let _ = self.clamp_to_surface.visit("ClampToSurface", &mut region);
+        let _ = self
+            .slowdown_distance
+            .visit("SlowdownDistance", &mut region);
+        let _ = self.query_filter.visit("QueryFilter", &mut region);
+        let _ = self.turning_radius.visit("TurningRadius", &mut region);
+        let _ = self
+            .path_correction_strength
+            .visit("PathCorrectionStrength", &mut region);
+        let _ = self.path_offset.visit("PathOffset", &mut region);
+        let _ = self.avoidance_offset.visit("AvoidanceOffset", &mut region);
+        let _ = self
+            .avoidance_horizon
+            .visit("AvoidanceHorizon", &mut region);
+        let _ = self.heading.visit("Heading", &mut region);
+        let _ = self.acceleration.visit("Acceleration", &mut region);
+        let _ = self.deceleration.visit("Deceleration", &mut region);
+        let _ = self.current_speed.visit("CurrentSpeed", &mut region);
+        let _ = self.follow_distance.visit("FollowDistance", &mut region);
+        let _ = self.max_path_cost.visit("MaxPathCost", &mut region);
+        let _ = self.spent_path_cost.visit("SpentPathCost", &mut region);
+        let _ = self.budget_limited.visit("BudgetLimited", &mut region);
+        let _ = self.stuck_detection.visit("StuckDetection", &mut region);
+        let _ = self.stuck_time_window.visit("StuckTimeWindow", &mut region);
+        let _ = self
+            .stuck_min_progress
+            .visit("StuckMinProgress", &mut region);
+        let _ = self.replan_when_stuck.visit("ReplanWhenStuck", &mut region);
+        let _ = self
+            .replan_backoff_min_interval
+            .visit("ReplanBackoffMinInterval", &mut region);
+        let _ = self
+            .replan_backoff_max_interval
+            .visit("ReplanBackoffMaxInterval", &mut region);
+        let _ = self.target_region.visit("TargetRegion", &mut region);
+        let _ = self
+            .target_region_triangle
+            .visit("TargetRegionTriangle", &mut region);
+        let _ = self.surface_triangle.visit("SurfaceTriangle", &mut region);
+        let _ = self.ground_normal.visit("GroundNormal", &mut region);
+        let _ = self.displaced.visit("Displaced", &mut region);
+        let _ = self.stuck.visit("Stuck", &mut region);
+        let _ = self.stuck_timer.visit("StuckTimer", &mut region);
+        let _ = self.stuck_anchor.visit("StuckAnchor", &mut region);
+        let _ = self.replan_backoff.visit("ReplanBackoff", &mut region);
+        let _ = self
+            .replan_backoff_timer
+            .visit("ReplanBackoffTimer", &mut region);
+        let _ = self
+            .replan_backoff_target
+            .visit("ReplanBackoffTarget", &mut region);
+        let _ = self.corridor.visit("Corridor", &mut region);
+        let _ = self.navmesh_revision.visit("NavmeshRevision", &mut region);
+        let _ = self
+            .navmesh_geometry_revision
+            .visit("NavmeshGeometryRevision", &mut region);
+        let _ = self
+            .navmesh_topology_revision
+            .visit("NavmeshTopologyRevision", &mut region);
+
+        // Path requests are not serialized, so the request must be made again after loading.
+        let mut waiting_for_path = self.path_request.is_some();
+        let _ = waiting_for_path.visit("WaitingForPath", &mut region);
+
+        if region.is_reading() {
+            if version < NAVMESH_AGENT_VERSION || waiting_for_path {
+                self.path_dirty = true;
+                self.time_since_recalculation = f32::MAX;
+            }
+            self.path_request = None;
+            self.events.clear();
+            // Prevent duplicate events for the state, that was already reported before saving.
+            self.was_target_reached = self.target_reached();
+            self.last_offmesh_link = self.current_offmesh_link();
+        }
+
+        Ok(())
+    }
+}
+
+impl NavmeshAgent {
+    /// Creates new navigation mesh agent.
+    pub fn new() -> Self {
+        Self {
+            path: vec![],
+            current: 0,
+            position: Default::default(),
+            last_warp_position: Default::default(),
+            target: Default::default(),
+            last_target_position: Default::default(),
+            recalculation_threshold: 0.25,
+            speed: 1.5,
+            path_dirty: true,
+            radius: 0.2,
+            interpolator: 0.0,
+            smoothing: SmoothingMode::Funnel,
+            offmesh_segments: Default::default(),
+            allow_partial_path: true,
+            partial_path: false,
+            async_path: false,
+            path_priority: 0,
+            path_request: None,
+            arrival_radius: 0.1,
+            reacquire_radius: 0.0,
+            events: Default::default(),
+            was_target_reached: false,
+            last_offmesh_link: None,
+            recalculation_interval: 0.0,
+            time_since_recalculation: f32::MAX,
+            clamp_to_surface: true,
+            surface_triangle: None,
+            ground_normal: Vector3::y(),
+            slowdown_distance: 1.0,
+            query_filter: Default::default(),
+            path_query_filter: Default::default(),
+            turning_radius: 0.0,
+            path_correction_strength: 1.0,
+            path_offset: Default::default(),
+            avoidance_offset: Default::default(),
+            avoidance_horizon: 2.0,
+            heading: Default::default(),
+            acceleration: 0.0,
+            deceleration: 0.0,
+            current_speed: 0.0,
+            follow_distance: 0.0,
+            max_path_cost: f32::MAX,
+            spent_path_cost: 0.0,
+            budget_limited: false,
+            stuck_detection: false,
+            stuck_time_window: 1.0,
+            stuck_min_progress: 0.1,
+            replan_when_stuck: true,
+            stuck: false,
+            stuck_timer: 0.0,
+            stuck_anchor: Default::default(),
+            replan_backoff_min_interval: 0.0,
+            replan_backoff_max_interval: 0.0,
+            replan_backoff: 0.0,
+            replan_backoff_timer: 0.0,
+            replan_backoff_target: Default::default(),
+            corridor: Default::default(),
+            navmesh_revision: 0,
+            navmesh_geometry_revision: 0,
+            navmesh_topology_revision: 0,
+            target_region: Default::default(),
+            target_region_triangle: None,
+            displaced: false,
+        }
+    }
+
+    /// Returns agent's position.
+    pub fn position(&self) -> Vector3<f32> {
+        self.position
+    }
+
+    /// Returns agent's path that will be followed. The path is smoothed, if path smoothing is
+    /// enabled (see [`Self::set_path_smoothing`]). See [`Self::current_waypoint_index`] to find
+    /// out the progress of the agent along the path.
+    pub fn path(&self) -> &[Vector3<f32>] {
+        &self.path
+    }
+
+    /// Returns an index of the waypoint of the path (see [`Self::path`]), that was passed last by
+    /// the agent. The agent moves from this waypoint towards the next one. The index is equal to
+    /// the index of the last waypoint, when the agent has reached the end of the path.
+    pub fn current_waypoint_index(&self) -> usize {
+        self.current as usize
+    }
+
+    /// Returns the corridor of the current path - an ordered list of indices of the triangles,
+    /// that the path goes through. See [`Navmesh::find_corridor`] for more info.
+    pub fn corridor(&self) -> &[usize] {
+        &self.corridor
+    }
+
+    /// Sets new speed of agent's movement.
+    pub fn set_speed(&mut self, speed: f32) -> f32 {
+        std::mem::replace(&mut self.speed, speed)
+    }
+
+    /// Returns current agent's movement speed.
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+
+    /// Sets a new acceleration (in meters per second squared) of the agent. The agent starts from
+    /// rest and its actual speed (see [`Self::current_speed`]) increases gradually up to the speed
+    /// of the agent (see [`Self::set_speed`]). Zero acceleration (default) means that the agent
+    /// reaches its speed instantly.
+    pub fn set_acceleration(&mut self, acceleration: f32) -> f32 {
+        std::mem::replace(&mut self.acceleration, acceleration.max(0.0))
+    }
+
+    /// Returns current acceleration (in meters per second squared) of the agent. See
+    /// [`Self::set_acceleration`] for more info.
+    pub fn acceleration(&self) -> f32 {
+        self.acceleration
+    }
+
+    /// Sets a new deceleration (in meters per second squared) of the agent. The agent starts
+    /// slowing down in advance, so it stops exactly at the end of its path. Zero deceleration
+    /// (default) means that the agent stops instantly.
+    pub fn set_deceleration(&mut self, deceleration: f32) -> f32 {
+        std::mem::replace(&mut self.deceleration, deceleration.max(0.0))
+    }
+
+    /// Returns current deceleration (in meters per second squared) of the agent. See
+    /// [`Self::set_deceleration`] for more info.
+    pub fn deceleration(&self) -> f32 {
+        self.deceleration
+    }
+
+    /// Returns the actual speed, that the agent has moved with during the last update. It differs
+    /// from the speed of the agent (see [`Self::speed`]), when the agent accelerates or
+    /// decelerates (see [`Self::set_acceleration`] and [`Self::set_deceleration`]).
+    pub fn current_speed(&self) -> f32 {
+        self.current_speed
+    }
+
+    /// Sets a new path recalculation threshold (in meters). The threshold is used to prevent
+    /// path recalculation in case if a target's position or the agent position haven't significantly
+    /// moved. This significance is defined by the threshold.
+    pub fn set_threshold(&mut self, threshold: f32) -> f32 {
+        std::mem::replace(&mut self.recalculation_threshold, threshold)
+    }
+
+    /// Returns the current path recalculation threshold (in meters). See [`Self::set_threshold`]
+    /// for more info.
+    pub fn threshold(&self) -> f32 {
+        self.recalculation_threshold
+    }
+
+    /// Sets a minimum time interval (in seconds) between path recalculations. It could be used to
+    /// prevent path recalculation every frame when the agent follows a fast moving target. While
+    /// the recalculation is postponed, the agent keeps following its previous path. Default is
+    /// zero, which means that the path is recalculated as soon as it is needed.
+    pub fn set_recalculation_interval(&mut self, interval: f32) -> f32 {
+        std::mem::replace(&mut self.recalculation_interval, interval)
+    }
+
+    /// Returns the current minimum time interval (in seconds) between path recalculations. See
+    /// [`Self::set_recalculation_interval`] for more info.
+    pub fn recalculation_interval(&self) -> f32 {
+        self.recalculation_interval
+    }
+
+    /// Sets the range of delays (in seconds) between the attempts to calculate a path, when the
+    /// previous attempt has failed or has produced a partial path (see
+    /// [`Self::set_allow_partial_path`]). The first retry is made after `min_interval`, every next
+    /// failed attempt doubles the delay, until it reaches `max_interval`. The delay is reset, when
+    /// a full path is found, when the target moves farther than the recalculation threshold (see
+    /// [`NavmeshAgentBuilder::with_recalculation_threshold`]) from the target of the failed
+    /// attempt, or when the agent is reset (see [`Self::reset`]). Path invalidations (see
+    /// [`Self::invalidate_path`]), including the ones caused by the changes of the navmesh, keep
+    /// the delay. It prevents the agent from searching for a path to an unreachable target every
+    /// frame. Default is zero for both intervals, which disables the backoff.
+    pub fn set_replan_backoff(&mut self, min_interval: f32, max_interval: f32) {
+        self.replan_backoff_min_interval = min_interval.max(0.0);
+        self.replan_backoff_max_interval = max_interval.max(self.replan_backoff_min_interval);
+        self.replan_backoff = self.replan_backoff.min(self.replan_backoff_max_interval);
+    }
+
+    fn set_replan_backoff_min_interval(&mut self, interval: f32) -> f32 {
+        let prev = self.replan_backoff_min_interval;
+        self.set_replan_backoff(interval, self.replan_backoff_max_interval);
+        prev
+    }
+
+    fn set_replan_backoff_max_interval(&mut self, interval: f32) -> f32 {
+        let prev = self.replan_backoff_max_interval;
+        self.set_replan_backoff(self.replan_backoff_min_interval, interval);
+        prev
+    }
+
+    /// Returns the range of delays (in seconds) between the attempts to calculate a path after a
+    /// failed attempt. See [`Self::set_replan_backoff`] for more info.
+    pub fn replan_backoff(&self) -> (f32, f32) {
+        (
+            self.replan_backoff_min_interval,
+            self.replan_backoff_max_interval,
+        )
+    }
+
+    /// Returns the current delay (in seconds) before the next attempt to calculate a path. It is
+    /// zero, if the last attempt has succeeded. See [`Self::set_replan_backoff`] for more info.
+    pub fn current_replan_backoff(&self) -> f32 {
+        self.replan_backoff
+    }
+
+    /// Sets a distance (in meters), at which the agent stops before its target. The distance is
+    /// measured in a straight line from the target: the path is cut at the point, where it comes
+    /// within the distance of the target, so the agent stops on its way to the target (approaching
+    /// it along the direction of the last segment of the path) and does not walk around it. If the
+    /// agent is already closer to the target than the distance, it stays in
+    /// place, so it settles instead of jittering when the target moves a bit. It is useful for
+    /// followers (pets, escorts, etc.), especially combined with the recalculation threshold
+    /// and interval (see [`Self::set_threshold`] and [`Self::set_recalculation_interval`]). The
+    /// target is considered reached (see [`Self::target_reached`]) when the agent stops. Default
+    /// is zero, which means that the agent walks right to the target.
+    pub fn set_follow_distance(&mut self, distance: f32) -> f32 {
+        let distance = distance.max(0.0);
+        if self.follow_distance != distance {
+            self.path_dirty = true;
+        }
+        std::mem::replace(&mut self.follow_distance, distance)
+    }
+
+    /// Returns the current follow distance (in meters). See [`Self::set_follow_distance`] for
+    /// more info.
+    pub fn follow_distance(&self) -> f32 {
+        self.follow_distance
+    }
+
+    /// Sets the maximum cost of the path, that the agent can walk. When the cost of a newly
+    /// calculated path exceeds the budget, the path is cut at the furthest point, that could be
+    /// afforded, and the agent stops there (see [`Self::reached_budget_limit`]). It could be used
+    /// for movement allowance of units in turn-based games.
+    ///
+    /// The cost of the path is its length, where every part of the path is multiplied by the
+    /// traversal cost of the triangle, that it goes through (see [`Navmesh::set_triangle_cost`]),
+    /// and by the cost multiplier of the query filter of the agent (see
+    /// [`Self::set_query_filter`]). With the default costs, the budget is the distance, that the
+    /// agent can walk, no matter how the navmesh is triangulated. Moves through off-mesh links are
+    /// multiplied by the cost of the link (see [`Navmesh::set_offmesh_link_cost`]). If the agent
+    /// can't afford the whole link, it stops at the entrance of the link.
+    ///
+    /// The budget is shared by all the paths of the agent: when the path is recalculated, the cost
+    /// of the walked part of the previous path is subtracted from the budget. Setting the budget
+    /// again (even to the same value) restores the whole allowance, so in turn-based games it
+    /// should be set at the beginning of every turn. Default is [`f32::MAX`], which means that the
+    /// path cost is not limited.
+    pub fn set_max_path_cost(&mut self, cost: f32) -> f32 {
+        self.spent_path_cost = 0.0;
+        self.path_dirty = true;
+        std::mem::replace(&mut self.max_path_cost, cost.max(0.0))
+    }
+
+    /// Returns the maximum cost of the path. See [`Self::set_max_path_cost`] for more info.
+    pub fn max_path_cost(&self) -> f32 {
+        self.max_path_cost
+    }
+
+    /// Configures stuck detection. When enabled, the agent is considered stuck (see
+    /// [`Self::is_stuck`]) if it still has some path to walk, but its position has moved less than
+    /// `min_progress` meters during the last `time_window` seconds. It could happen, for example,
+    /// when the agent is moved by a physical body (see [`Self::set_position`]), that is wedged
+    /// between other bodies. [`NavmeshAgentEvent::Stuck`] is generated when the agent gets stuck,
+    /// and the path is recalculated after every time window while the agent stays stuck (see
+    /// [`Self::set_replan_when_stuck`]). The agent is not considered stuck while it traverses an
+    /// off-mesh link. Disabled by default, default time window is 1 second and default minimal
+    /// progress is 0.1 meters.
+    pub fn set_stuck_detection(&mut self, enabled: bool, time_window: f32, min_progress: f32) {
+        self.stuck_detection = enabled;
+        self.stuck_time_window = time_window.max(0.0);
+        self.stuck_min_progress = min_progress.max(0.0);
+        self.reset_stuck_detection();
+    }
+
+    fn set_stuck_detection_enabled(&mut self, enabled: bool) -> bool {
+        let prev = self.stuck_detection;
+        self.set_stuck_detection(enabled, self.stuck_time_window, self.stuck_min_progress);
+        prev
+    }
+
+    fn set_stuck_time_window(&mut self, time_window: f32) -> f32 {
+        let prev = self.stuck_time_window;
+        self.set_stuck_detection(self.stuck_detection, time_window, self.stuck_min_progress);
+        prev
+    }
+
+    fn set_stuck_min_progress(&mut self, min_progress: f32) -> f32 {
+        let prev = self.stuck_min_progress;
+        self.set_stuck_detection(self.stuck_detection, self.stuck_time_window, min_progress);
+        prev
+    }
+
+    /// Returns `true` if stuck detection is enabled. See [`Self::set_stuck_detection`] for more
+    /// info.
+    pub fn is_stuck_detection_enabled(&self) -> bool {
+        self.stuck_detection
+    }
+
+    /// Returns the time window (in seconds) of stuck detection. See [`Self::set_stuck_detection`]
+    /// for more info.
+    pub fn stuck_time_window(&self) -> f32 {
+        self.stuck_time_window
+    }
+
+    /// Returns the minimal progress (in meters) of stuck detection. See
+    /// [`Self::set_stuck_detection`] for more info.
+    pub fn stuck_min_progress(&self) -> f32 {
+        self.stuck_min_progress
+    }
+
+    /// Defines whether the path should be recalculated when the agent is stuck. Enabled by
+    /// default. See [`Self::set_stuck_detection`] for more info.
+    pub fn set_replan_when_stuck(&mut self, enabled: bool) -> bool {
+        std::mem::replace(&mut self.replan_when_stuck, enabled)
+    }
+
+    /// Returns `true` if the path is recalculated when the agent is stuck. See
+    /// [`Self::set_replan_when_stuck`] for more info.
+    pub fn is_replan_when_stuck_enabled(&self) -> bool {
+        self.replan_when_stuck
+    }
+
+    /// Returns `true` if the agent is stuck. See [`Self::set_stuck_detection`] for more info.
+    pub fn is_stuck(&self) -> bool {
+        self.stuck
+    }
+
+    fn reset_stuck_detection(&mut self) {
+        self.stuck = false;
+        self.stuck_timer = 0.0;
+        self.stuck_anchor = self.position;
+    }
+
+    pub(super) fn update_stuck_detection(&mut self, dt: f32) {
+        if !self.stuck_detection
+            || self.current as usize + 1 >= self.path.len()
+            || self.is_traversing_offmesh_link()
+        {
+            self.reset_stuck_detection();
+            return;
+        }
+
+        if self.position.metric_distance(&self.stuck_anchor) > self.stuck_min_progress {
+            self.reset_stuck_detection();
+            return;
+        }
+
+        self.stuck_timer += dt;
+        if self.stuck_timer >= self.stuck_time_window {
+            if !self.stuck {
+                self.stuck = true;
+                self.push_event(NavmeshAgentEvent::Stuck);
+            }
+            if self.replan_when_stuck {
+                self.invalidate_path();
+            }
+            // Start the next time window.
+            self.stuck_timer = 0.0;
+            self.stuck_anchor = self.position;
+        }
+    }
+
+    /// Returns `true` if the current path was cut by the maximum path cost (see
+    /// [`Self::set_max_path_cost`]) and the agent has reached the end of it. The target is not
+    /// considered reached in this case.
+    pub fn reached_budget_limit(&self) -> bool {
+        self.budget_limited && !self.path.is_empty() && self.current as usize + 1 >= self.path.len()
+    }
+
+    /// Sets a new radius for the navmesh agent. The agent will use this radius to walk around
+    /// corners with the distance equal to the radius. This could help to prevent the agent from
+    /// being stuck in the corners. Path finding avoids the passages between the borders of the
+    /// navmesh, that are narrower than the diameter of the agent. If there's no other way to the
+    /// target, the agent goes through such passages anyway. The default value is 0.2 meters.
+    pub fn set_radius(&mut self, radius: f32) -> f32 {
+        if self.radius != radius {
+            // The clearance of the path depends on the radius.
+            self.path_dirty = true;
+        }
+        std::mem::replace(&mut self.radius, radius)
+    }
+
+    /// Returns the current radius of the navmesh agent. See [`Self::set_radius`] for more info
+    /// about radius parameter.
+    pub fn radius(&self) -> f32 {
+        self.radius
+    }
+
+    /// Sets a query filter, that defines which triangles of a navmesh the agent could walk on,
+    /// based on the flags of the triangles (see [`Navmesh::set_triangle_flags`]). It could be used
+    /// to prevent some kinds of agents from walking through some areas, for example, hazardous
+    /// ones. Default filter allows every triangle. The path will be recalculated on the next
+    /// update.
+    pub fn set_query_filter(&mut self, filter: NavmeshQueryFilter) -> NavmeshQueryFilter {
+        if self.query_filter != filter {
+            self.path_dirty = true;
+        }
+        std::mem::replace(&mut self.query_filter, filter)
+    }
+
+    /// Returns the current query filter of the agent. See [`Self::set_query_filter`] for more
+    /// info.
+    pub fn query_filter(&self) -> NavmeshQueryFilter {
+        self.query_filter
+    }
+
+    /// Sets a mask of the navmesh layers (see [`Navmesh::set_triangle_layer`]), that the agent
+    /// could walk on. `i`-th bit of the mask corresponds to `i`-th layer. The agent builds its
+    /// paths only through the triangles on these layers, and it is placed only on them, so, for
+    /// example, a ground unit on a floor of a building does not snap to the ceiling of the floor
+    /// below. It is a shortcut for [`NavmeshQueryFilter::layer_mask`] of the query filter of the
+    /// agent. Default mask contains every layer. The path will be recalculated on the next update.
+    pub fn set_layer_mask(&mut self, layer_mask: u32) -> u32 {
+        self.set_query_filter(NavmeshQueryFilter {
+            layer_mask,
+            ..self.query_filter
+        })
+        .layer_mask
+    }
+
+    /// Returns the mask of the navmesh layers, that the agent could walk on. See
+    /// [`Self::set_layer_mask`] for more info.
+    pub fn layer_mask(&self) -> u32 {
+        self.query_filter.layer_mask
+    }
+
+    /// Enables or disables path smoothing. When enabled (default), the path is built using the
+    /// funnel algorithm, which makes the path "taut" - it turns only at the corners it actually
+    /// needs to. When disabled, the path goes through the middle points of the edges between the
+    /// triangles along the path, which produces zig-zagging paths.
+    pub fn set_path_smoothing(&mut self, enabled: bool) {
+        match (enabled, self.smoothing) {
+            (true, SmoothingMode::None) => {
+                self.set_smoothing_mode(SmoothingMode::Funnel);
+            }
+            (false, _) => {
+                self.set_smoothing_mode(SmoothingMode::None);
+            }
+            _ => (),
+        }
+    }
+
+    /// Returns `true` if the path smoothing is enabled, `false` - otherwise. See
+    /// [`Self::set_path_smoothing`] for more info.
+    pub fn is_path_smoothing_enabled(&self) -> bool {
+        self.smoothing != SmoothingMode::None
+    }
+
+    /// Sets the smoothing mode of the agent. [`SmoothingMode::None`] and [`SmoothingMode::Funnel`]
+    /// are the same as disabling or enabling the path smoothing (see [`Self::set_path_smoothing`]).
+    /// [`SmoothingMode::Spline`] builds the same path as [`SmoothingMode::Funnel`], but the agent
+    /// moves along a Catmull-Rom spline that passes through the waypoints, so its position and
+    /// heading change continuously at the corners. The agent keeps its speed along the curved
+    /// parts of the spline. The spline is broken at off-mesh links. If the spline cuts a corner off
+    /// the navmesh, the position is clamped back to the closest point on the navmesh.
+    pub fn set_smoothing_mode(&mut self, mode: SmoothingMode) -> SmoothingMode {
+        if (self.smoothing == SmoothingMode::None) != (mode == SmoothingMode::None) {
+            // The waypoints are different with and without the funnel algorithm.
+            self.path_dirty = true;
+        }
+        std::mem::replace(&mut self.smoothing, mode)
+    }
+
+    /// Returns the current smoothing mode of the agent. See [`Self::set_smoothing_mode`] for more
+    /// info.
+    pub fn smoothing_mode(&self) -> SmoothingMode {
+        self.smoothing
+    }
+
+    /// Enables or disables clamping of the agent position to the surface of the navmesh. When
+    /// enabled (default), the position of the agent is projected on the closest triangle of the
+    /// navmesh after every [`Self::update`], so it always lies on a walkable surface, even if the
+    /// path cuts a corner. The height of the agent is interpolated across the triangle under it, so
+    /// the agent smoothly follows slopes between the waypoints. The closest triangle is searched
+    /// only among the triangles, that are adjacent to the triangle the agent was on, so the agent
+    /// never jumps across gaps in the navmesh. The position is not clamped while the agent
+    /// traverses an off-mesh link. Disabling the clamping saves some time on every update.
+    pub fn set_clamp_to_surface(&mut self, enabled: bool) -> bool {
+        self.surface_triangle = None;
+        std::mem::replace(&mut self.clamp_to_surface, enabled)
+    }
+
+    /// Returns `true` if the agent position is clamped to the surface of the navmesh, `false` -
+    /// otherwise. See [`Self::set_clamp_to_surface`] for more info.
+    pub fn is_clamped_to_surface(&self) -> bool {
+        self.clamp_to_surface
+    }
+
+    /// Returns a handle of an off-mesh link, that the agent is currently traversing. It could be
+    /// used to play a special animation (for example, a jump) while the agent moves along the link.
+    pub fn current_offmesh_link(&self) -> Option<OffMeshLinkHandle> {
+        self.offmesh_segments
+            .iter()
+            .find(|segment| segment.segment == self.current)
+            .map(|segment| segment.link)
+    }
+
+    /// Returns `true` if the agent is currently traversing an off-mesh link, `false` - otherwise.
+    pub fn is_traversing_offmesh_link(&self) -> bool {
+        self.current_offmesh_link().is_some()
+    }
+
+    /// Allows or disallows partial paths. When allowed (default) and the target is unreachable (for
+    /// example, it is on a disconnected "island" of the navmesh), the agent will walk to the
+    /// reachable point that is closest (by straight-line distance) to the target. When disallowed,
+    /// the agent will not move at all if the target is unreachable. If the target is on another
+    /// island, that is not connected by off-mesh links, the path calculation fails with
+    /// [`NavmeshPathError::Disconnected`] without running the search.
+    pub fn set_allow_partial_path(&mut self, allow: bool) -> bool {
+        if self.allow_partial_path != allow {
+            self.path_dirty = true;
+        }
+        std::mem::replace(&mut self.allow_partial_path, allow)
+    }
+
+    /// Returns `true` if partial paths are allowed, `false` - otherwise. See
+    /// [`Self::set_allow_partial_path`] for more info.
+    pub fn is_partial_path_allowed(&self) -> bool {
+        self.allow_partial_path
+    }
+
+    /// Returns `true` if the current path of the agent does not lead to the target, because the
+    /// target is unreachable. See [`Self::set_allow_partial_path`] for more info.
+    pub fn is_path_partial(&self) -> bool {
+        self.partial_path
+    }
+
+    /// Enables or disables asynchronous path calculation. When enabled, the agent does not
+    /// calculate its path in [`Self::update`], instead it adds a path request to the navmesh (see
+    /// [`Navmesh::request_path`]) and keeps following its old path until the new one is ready.
+    /// Keep in mind, that the requests must be processed by [`Navmesh::process_path_requests`],
+    /// otherwise the agent will never receive its new path. Disabled by default.
+    pub fn set_async_path_calculation(&mut self, enabled: bool) -> bool {
+        std::mem::replace(&mut self.async_path, enabled)
+    }
+
+    /// Returns `true` if asynchronous path calculation is enabled, `false` - otherwise. See
+    /// [`Self::set_async_path_calculation`] for more info.
+    pub fn is_async_path_calculation_enabled(&self) -> bool {
+        self.async_path
+    }
+
+    /// Sets a priority of the asynchronous path requests of the agent (see
+    /// [`Self::set_async_path_calculation`] and [`Navmesh::request_path_with_priority`]). Requests
+    /// of the agents with higher priority are calculated first, so important agents (visible to
+    /// the player, in combat, etc.) get their paths faster, when the pathfinding budget (see
+    /// [`Navmesh::set_pathfinding_budget`]) is not enough for every agent. The new priority is
+    /// used for the next request. Default is `0`.
+    pub fn set_path_priority(&mut self, priority: i32) -> i32 {
+        std::mem::replace(&mut self.path_priority, priority)
+    }
+
+    /// Returns the priority of the asynchronous path requests of the agent. See
+    /// [`Self::set_path_priority`] for more info.
+    pub fn path_priority(&self) -> i32 {
+        self.path_priority
+    }
+
+    /// Returns `true` if the agent waits for its new path to be calculated asynchronously. See
+    /// [`Self::set_async_path_calculation`] for more info.
+    pub fn is_waiting_for_path(&self) -> bool {
+        self.path_request.is_some()
+    }
+
+    /// Sets a new arrival radius (in meters). The agent is considered to be arrived at its target,
+    /// if the distance between the agent and the end of its (full) path is less or equal to the
+    /// radius. Default is 0.1 meters.
+    pub fn set_arrival_radius(&mut self, radius: f32) -> f32 {
+        std::mem::replace(&mut self.arrival_radius, radius)
+    }
+
+    /// Returns current arrival radius (in meters). See [`Self::set_arrival_radius`] for more info.
+    pub fn arrival_radius(&self) -> f32 {
+        self.arrival_radius
+    }
+
+    /// Sets a new reacquire radius (in meters). Once the agent has reached its target (see
+    /// [`Self::target_reached`]), it holds its position and does not recalculate the path, until
+    /// the target moves away from the agent farther than this radius (plus the follow distance, see
+    /// [`Self::set_follow_distance`]). It prevents the agent from vibrating in place, when its
+    /// target hovers around a single point (for example, right at the border of two triangles).
+    /// The radius should be slightly larger than the arrival radius (see
+    /// [`Self::set_arrival_radius`]), the arrival radius is used if it is smaller. Default is
+    /// zero, which disables the hold, so the path is recalculated as usual.
+    pub fn set_reacquire_radius(&mut self, radius: f32) -> f32 {
+        std::mem::replace(&mut self.reacquire_radius, radius.max(0.0))
+    }
+
+    /// Returns current reacquire radius (in meters). See [`Self::set_reacquire_radius`] for more
+    /// info.
+    pub fn reacquire_radius(&self) -> f32 {
+        self.reacquire_radius
+    }
+
+    /// Returns `true` if the agent is within the arrival radius (see [`Self::set_arrival_radius`])
+    /// from the end of its path, and the path leads to the target (i.e. it is not partial).
+    pub fn target_reached(&self) -> bool {
+        !self.partial_path
+            && !self.budget_limited
+            && self
+                .path
+                .last()
+                .is_some_and(|end| end.metric_distance(&self.position) <= self.arrival_radius)
+    }
+
+    /// Takes the oldest event from the event queue of the agent. The events are generated by
+    /// [`Self::update`] and describe the changes of the agent state, so scripts could react to
+    /// them without manual bookkeeping. The queue stores only a limited number of the most
+    /// recent events, so it should be drained every frame.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// # use fyrox_impl::utils::navmesh::{NavmeshAgent, NavmeshAgentEvent};
+    /// fn handle_events(agent: &mut NavmeshAgent) {
+    ///     while let Some(event) = agent.pop_event() {
+    ///         match event {
+    ///             NavmeshAgentEvent::TargetReached => println!("Arrived!"),
+    ///             NavmeshAgentEvent::EnteredOffMeshLink(_) => println!("Jump!"),
+    ///             _ => (),
+    ///         }
+    ///     }
+    /// }
+    /// ```
+    pub fn pop_event(&mut self) -> Option<NavmeshAgentEvent> {
+        self.events.pop_front()
+    }
+
+    fn push_event(&mut self, event: NavmeshAgentEvent) {
+        const MAX_EVENTS: usize = 32;
+        if self.events.len() >= MAX_EVENTS {
+            self.events.pop_front();
+        }
+        self.events.push_back(event);
+    }
+
+    pub(super) fn update_state_events(&mut self) {
+        let target_reached = self.target_reached();
+        if target_reached && !self.was_target_reached {
+            self.push_event(NavmeshAgentEvent::TargetReached);
+            if let Some(triangle) = self.target_region_triangle {
+                self.push_event(NavmeshAgentEvent::EnteredTargetRegion(triangle));
+            }
+        }
+        self.was_target_reached = target_reached;
+
+        let offmesh_link = self.current_offmesh_link();
+        if offmesh_link != self.last_offmesh_link {
+            if let Some(link) = offmesh_link {
+                self.push_event(NavmeshAgentEvent::EnteredOffMeshLink(link));
+            }
+        }
+        self.last_offmesh_link = offmesh_link;
+    }
+
+    /// Returns a point on the path, that is the given distance (in meters) ahead of the agent along
+    /// the path. The point is clamped to the end of the path, so the end of the path is returned
+    /// when the agent has finished its path. Returns the target of the agent, if there's no path
+    /// at all. It could be used to orient a character or to lead a camera in the direction, where
+    /// the agent is going to move.
+    pub fn look_ahead(&self, distance: f32) -> Vector3<f32> {
+        let Some(end) = self.path.last() else {
+            return self.target;
+        };
+
+        let mut remaining = distance.max(0.0);
+        let mut point = self.position;
+        for next in self.path.iter().skip(self.current as usize + 1) {
+            let length = point.metric_distance(next);
+            if length >= remaining {
+                return if length > f32::EPSILON {
+                    point.lerp(next, remaining / length)
+                } else {
+                    *next
+                };
+            }
+            remaining -= length;
+            point = *next;
+        }
+        *end
+    }
+
+    /// Returns the remaining distance (in meters) along the path from the current position of the
+    /// agent to the end of the path. Returns zero if there's no active path.
+    pub fn remaining_distance(&self) -> f32 {
+        let next = self.current as usize + 1;
+        let Some(next_point) = self.path.get(next) else {
+            return 0.0;
+        };
+        self.position.metric_distance(next_point)
+            + self.path[next..]
+                .windows(2)
+                .map(|segment| segment[0].metric_distance(&segment[1]))
+                .sum::<f32>()
+    }
+
+    /// Returns the estimated time (in seconds) that the agent needs to reach the end of its path
+    /// with its current speed. Returns zero if there's no active path, and infinity if the agent
+    /// has a path, but does not move.
+    pub fn estimated_time(&self) -> f32 {
+        let distance = self.remaining_distance();
+        if distance == 0.0 {
+            0.0
+        } else {
+            distance / self.speed
+        }
+    }
+
+    /// Sets a new distance (in meters) to the end of the path, at which the agent starts to slow
+    /// down. See [`Self::desired_velocity`] for more info. Default is 1 meter.
+    pub fn set_slowdown_distance(&mut self, distance: f32) -> f32 {
+        std::mem::replace(&mut self.slowdown_distance, distance)
+    }
+
+    /// Returns current slowdown distance (in meters). See [`Self::set_slowdown_distance`] for more
+    /// info.
+    pub fn slowdown_distance(&self) -> f32 {
+        self.slowdown_distance
+    }
+
+    /// Sets a new turning radius (in meters) of the agent. The agent starts turning towards the
+    /// next segment of its path at this distance before a waypoint and finishes the turn at the
+    /// same distance after it, which results in a smooth change of its heading (see
+    /// [`Self::heading`]). The position of the agent always stays on the path. The radius is
+    /// reduced automatically at short segments and sharp turns. Zero radius (default) means that
+    /// the heading changes instantly at every waypoint.
+    pub fn set_turning_radius(&mut self, radius: f32) -> f32 {
+        std::mem::replace(&mut self.turning_radius, radius.max(0.0))
+    }
+
+    /// Returns current turning radius (in meters). See [`Self::set_turning_radius`] for more info.
+    pub fn turning_radius(&self) -> f32 {
+        self.turning_radius
+    }
+
+    /// Sets a fraction (in `[0; 1]` range) of the offset from the path, that is corrected on every
+    /// [`Self::update`], when the agent was pushed off its path (see [`Self::set_position`]).
+    /// `1.0` (default) snaps the agent back to the path immediately. Lower values keep the rest of
+    /// the offset, so the agent continues moving forward and gently drifts back to the path over
+    /// several updates, which looks more natural for characters, that are pushed around by
+    /// physics. Zero means that the offset is never corrected, while the path stays valid. The
+    /// offset is discarded when the path is recalculated. The desired velocity (see
+    /// [`Self::desired_velocity`]) is directed from the actual position of the agent, so it
+    /// steers the agent back to the path as well. The same correction is used by
+    /// [`Self::update_with_neighbors`], [`Self::update_with_moving_obstacles`] and by the crowds
+    /// (see [`crowd::NavmeshAgentCrowd`]), where the agents are pushed off their paths by the
+    /// avoidance.
+    pub fn set_path_correction_strength(&mut self, strength: f32) -> f32 {
+        std::mem::replace(&mut self.path_correction_strength, strength.clamp(0.0, 1.0))
+    }
+
+    /// Returns current path correction strength. See [`Self::set_path_correction_strength`] for
+    /// more info.
+    pub fn path_correction_strength(&self) -> f32 {
+        self.path_correction_strength
+    }
+
+    /// Sets a new prediction horizon (in seconds) of the avoidance of moving obstacles (see
+    /// [`Self::update_with_moving_obstacles`]). The agent reacts only to the obstacles, that are
+    /// going to collide with it within this time. Larger values make the agent dodge earlier and
+    /// smoother, but it also reacts to the obstacles, that could change their direction long
+    /// before the collision. Zero disables the avoidance. Default is 2 seconds.
+    pub fn set_avoidance_horizon(&mut self, horizon: f32) -> f32 {
+        std::mem::replace(&mut self.avoidance_horizon, horizon.max(0.0))
+    }
+
+    /// Returns current prediction horizon of the avoidance of moving obstacles. See
+    /// [`Self::set_avoidance_horizon`] for more info.
+    pub fn avoidance_horizon(&self) -> f32 {
+        self.avoidance_horizon
+    }
+
+    /// Returns the normalized normal of the surface of the navmesh under the agent. On flat ground
+    /// it is the up axis of the navmesh (see [`Navmesh::set_up_axis`]), on ramps it is tilted
+    /// according to the slope. It could be used to align the character with the ground or to
+    /// drive foot IK on slopes. The normal is updated on every [`Self::update`] while the agent is
+    /// clamped to the surface (see [`Self::set_clamp_to_surface`]), and it is the up axis while
+    /// the agent traverses an off-mesh link.
+    pub fn ground_normal(&self) -> Vector3<f32> {
+        self.ground_normal
+    }
+
+    /// Returns current heading of the agent - a normalized direction, that the agent is facing
+    /// while following its path. It could be used to rotate the character, that is controlled by
+    /// the agent. Returns zero vector if the agent has never moved. See
+    /// [`Self::set_turning_radius`] for more info.
+    pub fn heading(&self) -> Vector3<f32> {
+        self.heading
+    }
+
+    /// Returns the velocity, that the agent wants to move with to follow its path. The velocity is
+    /// directed towards the next point of the path, its magnitude is equal to the speed of the
+    /// agent, but it decreases linearly when the remaining distance to the end of the path is less
+    /// than the slowdown distance (see [`Self::set_slowdown_distance`]). Returns zero vector if
+    /// there's no active path, or the agent has reached its end.
+    ///
+    /// This method could be used to move physics-based characters (for example, by applying forces
+    /// to a rigid body) instead of setting their positions directly. Keep in mind, that the agent
+    /// must be kept in sync with the actual position of the character using
+    /// [`Self::set_position`].
+    pub fn desired_velocity(&self) -> Vector3<f32> {
+        if self.target_reached() {
+            return Vector3::default();
+        }
+
+        let Some(next_point) = self.path.get(self.current as usize + 1) else {
+            return Vector3::default();
+        };
+
+        let remaining_distance = self.remaining_distance();
+        let speed = if remaining_distance < self.slowdown_distance {
+            self.speed * remaining_distance / self.slowdown_distance
+        } else {
+            self.speed
+        };
+
+        (next_point - self.position)
+            .try_normalize(f32::EPSILON)
+            .map(|direction| direction.scale(speed))
+            .unwrap_or_default()
+    }
+
+    /// Returns a steering force, that should be applied to a character moving with the given
+    /// velocity to make it move with the desired velocity (see [`Self::desired_velocity`]). The
+    /// force is not limited in any way, so it is advised to clamp it to some maximum value to get
+    /// smooth acceleration.
+    pub fn steering_force(&self, current_velocity: Vector3<f32>) -> Vector3<f32> {
+        self.desired_velocity() - current_velocity
+    }
+
+    /// Returns `true` if the agent has reached the end of its path, and the path leads to the
+    /// actual target. Returns `false` if the agent is still moving, or if it has settled at the
+    /// end of a partial path (see [`Self::set_allow_partial_path`]).
+    pub fn reached_full_target(&self) -> bool {
+        !self.partial_path
+            && !self.budget_limited
+            && !self.path.is_empty()
+            && self.current as usize + 1 >= self.path.len()
+    }
+
+    /// Draws the current path of the agent into the given drawing context. The part of the path,
+    /// that was already traversed, is drawn in [`AGENT_TRAVERSED_PATH_COLOR`], the rest of the path
+    /// is drawn in [`AGENT_PATH_COLOR`], and the segments that go through off-mesh links are drawn
+    /// in [`scene::navmesh::NAVMESH_OFFMESH_LINK_COLOR`]. Every waypoint is marked with a small
+    /// sphere. A straight line from the agent to its target is drawn in [`AGENT_TARGET_COLOR`].
+    pub fn draw_path(&self, ctx: &mut SceneDrawingContext) {
+        let current = self.current as usize;
+        for (index, segment) in self.path.windows(2).enumerate() {
+            let (begin, end) = (segment[0], segment[1]);
+            if self
+                .offmesh_segments
+                .iter()
+                .any(|s| s.segment as usize == index)
+            {
+                ctx.add_line(Line {
+                    begin,
+                    end,
+                    color: scene::navmesh::NAVMESH_OFFMESH_LINK_COLOR,
+                });
+            } else if index < current {
+                ctx.add_line(Line {
+                    begin,
+                    end,
+                    color: AGENT_TRAVERSED_PATH_COLOR,
+                });
+            } else if index == current {
+                // The agent is somewhere on this segment.
+                ctx.add_line(Line {
+                    begin,
+                    end: self.position,
+                    color: AGENT_TRAVERSED_PATH_COLOR,
+                });
+                ctx.add_line(Line {
+                    begin: self.position,
+                    end,
+                    color: AGENT_PATH_COLOR,
+                });
+            } else {
+                ctx.add_line(Line {
+                    begin,
+                    end,
+                    color: AGENT_PATH_COLOR,
+                });
+            }
+        }
+
+        for (index, point) in self.path.iter().enumerate() {
+            let color = if index <= current {
+                AGENT_TRAVERSED_PATH_COLOR
+            } else {
+                AGENT_PATH_COLOR
+            };
+            ctx.draw_sphere(*point, 6, 6, 0.05, color);
+        }
+
+        ctx.add_line(Line {
+            begin: self.position,
+            end: self.target,
+            color: AGENT_TARGET_COLOR,
+        });
+    }
+
+    /// Draws a path from the beginning of the current path of the agent to its target, that is
+    /// built without smoothing (see [`Self::set_path_smoothing`]), in [`AGENT_RAW_PATH_COLOR`].
+    /// It could be used together with [`Self::draw_path`] to compare the raw path, that goes
+    /// through the middle points of the portals, with the funneled one. Keep in mind, that the
+    /// path is calculated on every call, so this method is much slower than [`Self::draw_path`].
+    pub fn draw_raw_path(&self, navmesh: &Navmesh, ctx: &mut SceneDrawingContext) {
+        let Some(begin) = self.path.first() else {
+            return;
+        };
+
+        let mut raw = NavmeshAgent::new();
+        raw.radius = self.radius;
+        raw.allow_partial_path = self.allow_partial_path;
+        raw.smoothing = SmoothingMode::None;
+        if raw.calculate_path(navmesh, *begin, self.target).is_ok() {
+            for segment in raw.path.windows(2) {
+                ctx.add_line(Line {
+                    begin: segment[0],
+                    end: segment[1],
+                    color: AGENT_RAW_PATH_COLOR,
+                });
+            }
+        }
+    }
+}
+
+/// Color of the remaining part of the path of an agent, see [`NavmeshAgent::draw_path`].
+pub const AGENT_PATH_COLOR: Color = Color::YELLOW;
+/// Color of the traversed part of the path of an agent, see [`NavmeshAgent::draw_path`].
+pub const AGENT_TRAVERSED_PATH_COLOR: Color = Color::GRAY;
+/// Color of the straight line from an agent to its target, see [`NavmeshAgent::draw_path`].
+pub const AGENT_TARGET_COLOR: Color = Color::RED;
+/// Color of the raw (not smoothed) path of an agent, see [`NavmeshAgent::draw_raw_path`].
+pub const AGENT_RAW_PATH_COLOR: Color = Color::MAGENTA;
+
+impl NavmeshAgent {
+    /// Calculates path from point A to point B. In most cases there is no need to use this method
+    /// directly, because `update` will call it anyway if target position has moved.
+    pub fn calculate_path(
+        &mut self,
+        navmesh: &Navmesh,
+        src_point: Vector3<f32>,
+        dest_point: Vector3<f32>,
+    ) -> Result<PathKind, NavmeshPathError> {
+        self.calculate_path_counted(navmesh, src_point, dest_point, &mut 0, &mut 0.0)
+    }
+
+    /// The same as [`Self::calculate_path`], but adds the number of the triangles (or polygons),
+    /// that were expanded by the path searches, to `expanded_nodes` and writes the cost of the
+    /// found path (see [`Navmesh::find_path_with_cost`]) to `path_cost`.
+    pub(super) fn calculate_path_counted(
+        &mut self,
+        navmesh: &Navmesh,
+        src_point: Vector3<f32>,
+        dest_point: Vector3<f32>,
+        expanded_nodes: &mut usize,
+        path_cost: &mut f32,
+    ) -> Result<PathKind, NavmeshPathError> {
+        *path_cost = 0.0;
+        self.path.clear();
+        self.offmesh_segments.clear();
+        self.corridor.clear();
+
+        self.current = 0;
+        self.interpolator = 0.0;
+        self.path_offset = Default::default();
+        self.avoidance_offset = Default::default();
+        self.partial_path = false;
+        self.budget_limited = false;
+        self.path_query_filter = self.query_filter;
+
+        if let Some((src_point_on_navmesh, src_triangle)) = self.query_closest(navmesh, src_point) {
+            if let Some((mut dest_point_on_navmesh, dest_triangle)) =
+                self.query_closest(navmesh, dest_point)
+            {
+                if src_triangle == dest_triangle {
+                    self.path.push(src_point_on_navmesh);
+                    self.path.push(dest_point_on_navmesh);
+                    self.corridor.push(src_triangle);
+
+                    return Ok(PathKind::Full);
+                }
+
+                if !self.allow_partial_path {
+                    if let Err(err) =
+                        navmesh.check_full_path(src_triangle, dest_triangle, &self.query_filter)
+                    {
+                        self.partial_path = true;
+                        return Err(err);
+                    }
+                }
+
+                let mut path_triangle_indices = Vec::new();
+                let mut result = navmesh.build_triangle_path(
+                    src_triangle,
+                    dest_triangle,
+                    &mut path_triangle_indices,
+                    self.radius,
+                    &self.query_filter,
+                    expanded_nodes,
+                );
+                if self.radius > 0.0 && !matches!(result, Ok((PathKind::Full, _))) {
+                    // The agent does not fit into some passage, use a degraded path that goes
+                    // through narrow passages, if there's one.
+                    let mut degraded_path = Vec::new();
+                    let degraded = navmesh.build_triangle_path(
+                        src_triangle,
+                        dest_triangle,
+                        &mut degraded_path,
+                        0.0,
+                        &self.query_filter,
+                        expanded_nodes,
+                    );
+                    if matches!(degraded, Ok((PathKind::Full, _))) {
+                        path_triangle_indices = degraded_path;
+                        result = degraded;
+                    }
+                }
+                let path_kind =
+                    match result {
+                        Ok((path_kind, cost)) => {
+                            *path_cost = cost;
+                            path_kind
+                        }
+                        // The path is still valid, but leads to the closest found triangle.
+                        Err(
+                            PathError::HitMaxSearchIterations(_) | PathError::SearchExhausted(_),
+                        ) if self.allow_partial_path => PathKind::Partial,
+                        Err(err) => return Err(err.into()),
+                    };
+
+                if path_kind == PathKind::Partial {
+                    self.partial_path = true;
+
+                    if !self.allow_partial_path {
+                        return Ok(path_kind);
+                    }
+
+                    // Walk to the point of the closest reachable triangle, that is closest to
+                    // the target.
+                    let closest_triangle = path_triangle_indices[0];
+                    let mut closest = None;
+                    let mut closest_distance = f32::MAX;
+                    navmesh.query_closest_internal(
+                        &mut closest,
+                        &mut closest_distance,
+                        std::iter::once(closest_triangle),
+                        dest_point,
+                    );
+                    if let Some((point, _)) = closest {
+                        dest_point_on_navmesh = point;
+                    }
+                }
+
+                path_triangle_indices.reverse();
+                self.follow_corridor(
+                    navmesh,
+                    src_point_on_navmesh,
+                    dest_point_on_navmesh,
+                    path_triangle_indices,
+                );
+
+                return Ok(path_kind);
+            }
+        }
+
+        Err(NavmeshPathError::StartOffMesh)
+    }
+
+    /// Calculates a path from the current position of the agent to the cheapest triangle of its
+    /// target region and moves the target to the point, where the path enters the region. See
+    /// [`Self::set_target_region`] for more info.
+    fn calculate_region_path(&mut self, navmesh: &Navmesh) -> Result<PathKind, NavmeshPathError> {
+        self.path.clear();
+        self.offmesh_segments.clear();
+        self.corridor.clear();
+
+        self.current = 0;
+        self.interpolator = 0.0;
+        self.path_offset = Default::default();
+        self.avoidance_offset = Default::default();
+        self.partial_path = false;
+        self.budget_limited = false;
+        self.target_region_triangle = None;
+
+        let (src_point, src_triangle) = self
+            .query_closest(navmesh, self.position)
+            .ok_or(NavmeshPathError::StartOffMesh)?;
+
+        let (dest_point, corridor) = if self.target_region.contains(&src_triangle) {
+            (src_point, vec![src_triangle])
+        } else {
+            let goals = self
+                .target_region
+                .iter()
+                .copied()
+                .filter(|&triangle| {
+                    triangle < navmesh.triangles.len()
+                        && navmesh.are_triangles_connected(src_triangle, triangle)
+                })
+                .collect::<Vec<_>>();
+
+            let mut corridor = Vec::new();
+            // The agent does not fit into some passage, try to go through narrow passages then.
+            let nearest = [self.radius, 0.0].into_iter().find_map(|clearance| {
+                navmesh
+                    .graph
+                    .build_indexed_path_to_nearest_with_cost(
+                        src_triangle,
+                        &goals,
+                        &mut corridor,
+                        |src, dest, cost| {
+                            navmesh.traversal_cost(src, dest, cost, clearance, &self.query_filter)
+                        },
+                    )
+                    .ok()
+                    .flatten()
+            });
+            let Some(nearest) = nearest else {
+                self.partial_path = true;
+                if !self.allow_partial_path {
+                    return Err(NavmeshPathError::Disconnected);
+                }
+
+                // Walk as close as possible to the point of the region, that is closest to the
+                // agent.
+                let dest_point = self
+                    .target_region
+                    .iter()
+                    .filter(|&&triangle| triangle < navmesh.triangles.len())
+                    .map(|&triangle| {
+                        let [a, b, c] = navmesh.triangles[triangle]
+                            .0
+                            .map(|index| navmesh.vertices[index as usize]);
+                        closest_point_on_triangle(src_point, a, b, c)
+                    })
+                    .min_by(|a, b| {
+                        a.metric_distance(&src_point)
+                            .total_cmp(&b.metric_distance(&src_point))
+                    })
+                    .ok_or(NavmeshPathError::Disconnected)?;
+                self.target = dest_point;
+                self.last_target_position = dest_point;
+                self.calculate_path(navmesh, self.position, dest_point)?;
+                // The region is not entered, even if the point is reached.
+                self.partial_path = true;
+                return Ok(PathKind::Partial);
+            };
+            corridor.reverse();
+
+            // Stop right after entering the region, instead of walking to some point deep inside.
+            let [a, b, c] = navmesh.triangles[goals[nearest]]
+                .0
+                .map(|index| navmesh.vertices[index as usize]);
+            (closest_point_on_triangle(src_point, a, b, c), corridor)
+        };
+
+        self.target_region_triangle = corridor.last().copied();
+        self.target = dest_point;
+        self.last_target_position = dest_point;
+        self.follow_corridor(navmesh, src_point, dest_point, corridor);
+
+        Ok(PathKind::Full)
+    }
+
+    /// Builds the path through the given corridor (ordered from the source to the destination).
+    pub(super) fn follow_corridor(
+        &mut self,
+        navmesh: &Navmesh,
+        src_point: Vector3<f32>,
+        dest_point: Vector3<f32>,
+        corridor: Vec<usize>,
+    ) {
+        // Split the corridor into parts connected by off-mesh links, each part then could be
+        // walked as usual.
+        let mut part_begin = 0;
+        let mut part_src_point = src_point;
+        for i in 0..corridor.len().saturating_sub(1) {
+            // The links with a span are crossed like shared edges.
+            if let Some(traversal) = navmesh
+                .offmesh_link_between(corridor[i], corridor[i + 1])
+                .filter(|traversal| !navmesh.offmesh_links[traversal.link].has_span())
+            {
+                self.build_corridor_path(
+                    navmesh,
+                    part_src_point,
+                    traversal.enter,
+                    &corridor[part_begin..=i],
+                );
+                self.offmesh_segments.push(OffMeshLinkSegment {
+                    segment: self.path.len() as u32 - 1,
+                    link: traversal.link,
+                });
+                part_begin = i + 1;
+                part_src_point = traversal.exit;
+            }
+        }
+        self.build_corridor_path(navmesh, part_src_point, dest_point, &corridor[part_begin..]);
+        self.corridor = corridor;
+    }
+
+    fn build_corridor_path(
+        &mut self,
+        navmesh: &Navmesh,
+        src_position: Vector3<f32>,
+        dest_position: Vector3<f32>,
+        path_triangles: &[usize],
+    ) {
+        if self.smoothing != SmoothingMode::None {
+            self.straighten_path(navmesh, src_position, dest_position, path_triangles);
+        } else {
+            self.path.push(src_position);
+            for pair in path_triangles.windows(2) {
+                let [left, right] = navmesh.portal_points(pair[0], pair[1]);
+                self.path.push(left.lerp(&right, 0.5));
+            }
+            self.path.push(dest_position);
+        }
+    }
+
+    pub(super) fn straighten_path(
+        &mut self,
+        navmesh: &Navmesh,
+        src_position: Vector3<f32>,
+        dest_position: Vector3<f32>,
+        path_triangles: &[usize],
+    ) {
+        navmesh.pull_string(
+            path_triangles,
+            src_position,
+            dest_position,
+            self.radius,
+            &mut self.path,
+        );
+    }
+
+    /// Performs single update tick that moves agent to the target along the path (which is automatically
+    /// recalculated if target's position has changed).
+    pub fn update(&mut self, dt: f32, navmesh: &Navmesh) -> Result<PathKind, NavmeshPathError> {
+        self.update_path(dt, navmesh)?;
+        self.follow_path(dt, navmesh, Vector3::default());
+
+        if self.partial_path {
+            Ok(PathKind::Partial)
+        } else {
+            Ok(PathKind::Full)
+        }
+    }
+
+    /// The same as [`Self::update`], but the agent also steps around the given neighbors, that
+    /// are treated as soft obstacles. Every neighbor is defined by its position and radius, it
+    /// could be other agents, characters, props, etc. It is a lightweight alternative to
+    /// [`crowd::NavmeshAgentCrowd`] for the cases when the neighbors are mostly stationary.
+    ///
+    /// The agent follows its path exactly as in [`Self::update`] (with the acceleration, the
+    /// turning radius and the path correction), while a separation velocity pushes it sideways
+    /// from the path, away from the neighbors closer than twice the sum of their radii. The agent
+    /// returns back to the path, once the neighbors are passed. The separation never slows the
+    /// agent down along its path: the part of the push, that is directed against the path, is
+    /// converted into a sideways push. This way the agent always makes progress toward its
+    /// target and passes through a "wall" of neighbors, if there is no way around it. The agent
+    /// is kept on the navmesh and the neighbors are ignored while the agent traverses an off-mesh
+    /// link.
+    pub fn update_with_neighbors(
+        &mut self,
+        dt: f32,
+        navmesh: &Navmesh,
+        neighbors: &[(Vector3<f32>, f32)],
+    ) -> Result<PathKind, NavmeshPathError> {
+        if neighbors.is_empty() || dt <= 0.0 || self.is_traversing_offmesh_link() {
+            return self.update(dt, navmesh);
+        }
+
+        self.update_path(dt, navmesh)?;
+
+        let preferred_velocity = crowd::preferred_velocity(self, self.position, dt);
+        let separation = preferred_velocity
+            .try_normalize(f32::EPSILON)
+            .map(|forward| self.separation_velocity(navmesh, forward, neighbors))
+            .unwrap_or_default();
+        self.follow_path(dt, navmesh, separation);
+
+        if self.partial_path {
+            Ok(PathKind::Partial)
+        } else {
+            Ok(PathKind::Full)
+        }
+    }
+
+    /// The same as [`Self::update`], but the agent also dodges the given moving obstacles (rolling
+    /// boulders, vehicles, etc.), using their predicted positions. Unlike runtime obstacles (see
+    /// [`Navmesh::add_obstacle`]), moving obstacles do not modify the navmesh, they only push the
+    /// agent away from its path during this update.
+    ///
+    /// The avoidance is based on the time to collision: the agent reacts only to the obstacles,
+    /// that are going to collide with it within the prediction horizon (see
+    /// [`Self::set_avoidance_horizon`]), assuming that both keep their velocities. The sooner the
+    /// collision, the stronger the agent is pushed away from the point of the collision. Just as
+    /// in [`Self::update_with_neighbors`], the agent keeps following its path and the push never
+    /// slows it down: the part of the push, that is directed against the path, is converted into
+    /// a sideways push, so the agent dodges while still following its path. The agent is kept on
+    /// the navmesh and the obstacles are ignored while the agent traverses an off-mesh link.
+    pub fn update_with_moving_obstacles(
+        &mut self,
+        dt: f32,
+        navmesh: &Navmesh,
+        obstacles: &[MovingObstacle],
+    ) -> Result<PathKind, NavmeshPathError> {
+        if obstacles.is_empty()
+            || dt <= 0.0
+            || self.avoidance_horizon <= 0.0
+            || self.is_traversing_offmesh_link()
+        {
+            return self.update(dt, navmesh);
+        }
+
+        self.update_path(dt, navmesh)?;
+
+        let preferred_velocity = crowd::preferred_velocity(self, self.position, dt);
+        let avoidance = preferred_velocity
+            .try_normalize(f32::EPSILON)
+            .map(|forward| self.avoidance_velocity(navmesh, preferred_velocity, forward, obstacles))
+            .unwrap_or_default();
+        self.follow_path(dt, navmesh, avoidance);
+
+        if self.partial_path {
+            Ok(PathKind::Partial)
+        } else {
+            Ok(PathKind::Full)
+        }
+    }
+
+    /// Moves the agent along its path. The given avoidance velocity pushes the agent away from
+    /// the path, the accumulated push decays on its own, so the agent returns to the path once
+    /// nothing pushes it.
+    fn follow_path(&mut self, dt: f32, navmesh: &Navmesh, avoidance: Vector3<f32>) {
+        self.advance_along_path(dt, navmesh, avoidance);
+        self.update_stuck_detection(dt);
+        self.update_state_events();
+    }
+
+    /// Moves the agent along its path, see [`Self::follow_path`]. If the agent was displaced from
+    /// its path, the offset from the path is corrected according to the path correction strength
+    /// (see [`Self::set_path_correction_strength`]).
+    pub(super) fn advance_along_path(
+        &mut self,
+        dt: f32,
+        navmesh: &Navmesh,
+        avoidance: Vector3<f32>,
+    ) {
+        if std::mem::take(&mut self.displaced) && !self.path_dirty {
+            let displaced_position = self.position;
+            self.project_on_path(navmesh, displaced_position);
+            if let Some(point_on_path) = self.point_on_path() {
+                self.path_offset = displaced_position - point_on_path - self.avoidance_offset;
+            }
+        }
+
+        if let Some(source) = self.path.get(self.current as usize) {
+            if let Some(destination) = self.path.get((self.current + 1) as usize) {
+                self.position = source.lerp(destination, self.interpolator.clamp(0.0, 1.0));
+                let spline = if self.smoothing == SmoothingMode::Spline {
+                    self.sample_spline()
+                } else {
+                    None
+                };
+                // The interpolator is the fraction of the length of the spline, so the agent moves
+                // with the same speed along the curved segments.
+                let len = match spline {
+                    Some((point, _, length)) => {
+                        self.position = point;
+                        length
+                    }
+                    None => destination.metric_distance(source),
+                };
+                self.path_offset = self.path_offset.scale(1.0 - self.path_correction_strength);
+                self.update_avoidance_offset(dt, avoidance);
+                self.position += self.path_offset + self.avoidance_offset;
+                let unclamped_position = self.position;
+                if self.clamp_to_surface {
+                    self.clamp_position_to_surface(navmesh);
+                } else if (spline.is_some() || self.avoidance_offset != Vector3::default())
+                    && navmesh.triangle_at(self.position, f32::MAX).is_none()
+                {
+                    // The spline has cut a corner off the navmesh or the agent was pushed off it.
+                    if let Some((point, _)) = self.query_closest(navmesh, self.position) {
+                        self.position += navmesh.horizontal(point - self.position);
+                    }
+                }
+                if self.avoidance_offset != Vector3::default() {
+                    // Do not accumulate the push beyond the borders of the navmesh.
+                    self.avoidance_offset += navmesh.horizontal(self.position - unclamped_position);
+                }
+                self.update_heading();
+                if let Some(direction) =
+                    spline.and_then(|(_, tangent, _)| tangent.try_normalize(f32::EPSILON))
+                {
+                    self.heading = direction;
+                }
+                let speed = self.update_current_speed(dt);
+                self.interpolator += (speed * dt) / len.max(f32::EPSILON);
+                if self.interpolator >= 1.0 {
+                    self.current += 1;
+                    self.interpolator = 0.0;
+                } else if self.interpolator < 0.0 {
+                    self.current = self.current.saturating_sub(1);
+                    self.interpolator = 1.0;
+                }
+            }
+        }
+        if self.current as usize + 1 >= self.path.len() {
+            // Nothing to follow.
+            self.current_speed = 0.0;
+        }
+    }
+
+    /// Returns the point of the path, that corresponds to the current progress of the agent along
+    /// its path (without any offsets).
+    pub(super) fn point_on_path(&self) -> Option<Vector3<f32>> {
+        let source = self.path.get(self.current as usize)?;
+        Some(match self.path.get((self.current + 1) as usize) {
+            Some(destination) => source.lerp(destination, self.interpolator.clamp(0.0, 1.0)),
+            None => *source,
+        })
+    }
+
+    fn update_avoidance_offset(&mut self, dt: f32, avoidance: Vector3<f32>) {
+        // The agent returns to its path with the half of its speed, while nothing pushes it.
+        let distance = self.avoidance_offset.norm();
+        let correction = (0.5 * self.speed - avoidance.norm()).max(0.0) * dt;
+        self.avoidance_offset = if distance > correction {
+            self.avoidance_offset.scale(1.0 - correction / distance)
+        } else {
+            Vector3::default()
+        };
+        self.avoidance_offset += avoidance.scale(dt);
+    }
+
+    fn separation_velocity(
+        &self,
+        navmesh: &Navmesh,
+        forward: Vector3<f32>,
+        neighbors: &[(Vector3<f32>, f32)],
+    ) -> Vector3<f32> {
+        let mut separation = Vector3::default();
+        for (position, radius) in neighbors {
+            let offset = navmesh.horizontal(self.position - position);
+            let range = 2.0 * (self.radius + radius);
+            let distance = offset.norm();
+            if distance < range {
+                if let Some(direction) = offset.try_normalize(f32::EPSILON) {
+                    separation += direction.scale((range - distance) / range);
+                }
+            }
+        }
+
+        self.push_velocity(navmesh, separation, forward)
+    }
+
+    fn avoidance_velocity(
+        &self,
+        navmesh: &Navmesh,
+        velocity: Vector3<f32>,
+        forward: Vector3<f32>,
+        obstacles: &[MovingObstacle],
+    ) -> Vector3<f32> {
+        let mut avoidance = Vector3::default();
+        for obstacle in obstacles {
+            let offset = navmesh.horizontal(obstacle.position - self.position);
+            let relative_velocity = navmesh.horizontal(obstacle.velocity - velocity);
+            let Some(time) =
+                time_to_collision(offset, relative_velocity, self.radius + obstacle.radius)
+            else {
+                continue;
+            };
+            if time > self.avoidance_horizon {
+                continue;
+            }
+            // Move away from the point, where the agent and the obstacle would touch.
+            let contact = offset + relative_velocity.scale(time);
+            let direction = (-contact)
+                .try_normalize(f32::EPSILON)
+                .unwrap_or_else(|| forward.cross(&navmesh.up_axis));
+            avoidance += direction.scale((self.avoidance_horizon - time) / self.avoidance_horizon);
+        }
+
+        self.push_velocity(navmesh, avoidance, forward)
+    }
+
+    /// Converts the given push into a velocity, that does not slow the agent down along its path.
+    fn push_velocity(
+        &self,
+        navmesh: &Navmesh,
+        mut push: Vector3<f32>,
+        forward: Vector3<f32>,
+    ) -> Vector3<f32> {
+        let along = push.dot(&forward);
+        if along < 0.0 {
+            // Turn the push against the path into a sideways push, so the agent goes around the
+            // obstacles instead of stopping in front of them.
+            let lateral = push - forward.scale(along);
+            let side = lateral
+                .try_normalize(f32::EPSILON)
+                .unwrap_or_else(|| forward.cross(&navmesh.up_axis));
+            push = lateral + side.scale(-along);
+        }
+
+        let velocity = push.scale(self.speed);
+        if velocity.norm() > self.speed {
+            velocity.normalize().scale(self.speed)
+        } else {
+            velocity
+        }
+    }
+
+    fn update_current_speed(&mut self, dt: f32) -> f32 {
+        let mut target_speed = self.speed;
+        if self.deceleration > 0.0 {
+            // The maximum speed, from which the agent is still able to stop at the end of the path.
+            let stopping_speed = (2.0 * self.deceleration * self.remaining_distance()).sqrt();
+            target_speed = target_speed.min(stopping_speed);
+        }
+
+        self.current_speed = if self.acceleration > 0.0 && self.current_speed < target_speed {
+            (self.current_speed + self.acceleration * dt).min(target_speed)
+        } else {
+            target_speed
+        };
+        self.current_speed
+    }
+
+    fn sample_spline(&self) -> Option<(Vector3<f32>, Vector3<f32>, f32)> {
+        let current = self.current as usize;
+        let p1 = *self.path.get(current)?;
+        let p2 = *self.path.get(current + 1)?;
+        let is_offmesh_segment = |segment: usize| {
+            self.offmesh_segments
+                .iter()
+                .any(|s| s.segment as usize == segment)
+        };
+        if is_offmesh_segment(current) || p1.metric_distance(&p2) <= f32::EPSILON {
+            return None;
+        }
+        // Off-mesh links break the spline, the endpoints are duplicated at the breaks.
+        let p0 = match current.checked_sub(1) {
+            Some(previous) if !is_offmesh_segment(previous) => self.path[previous],
+            _ => p1,
+        };
+        let p3 = match self.path.get(current + 2) {
+            Some(next) if !is_offmesh_segment(current + 1) => *next,
+            _ => p2,
+        };
+        Some(catmull_rom_by_length(p0, p1, p2, p3, self.interpolator))
+    }
+
+    fn update_heading(&mut self) {
+        let current = self.current as usize;
+        let (Some(source), Some(destination)) =
+            (self.path.get(current), self.path.get(current + 1))
+        else {
+            return;
+        };
+
+        let length = destination.metric_distance(source);
+        let Some(direction) = (destination - source).try_normalize(f32::EPSILON) else {
+            return;
+        };
+
+        let traveled = length * self.interpolator.clamp(0.0, 1.0);
+        let remaining = length - traveled;
+
+        // The turn around a waypoint starts at the distance of the turning radius before it and
+        // ends at the same distance after it. The radius is limited by the half of the lengths of
+        // the adjacent segments, so the agent makes a tighter turn on short segments and sharp
+        // hairpins instead of overshooting.
+        let turn_radius =
+            |other_length: f32| self.turning_radius.min(0.5 * length.min(other_length));
+
+        self.heading = direction;
+        if self.turning_radius <= 0.0 {
+            return;
+        }
+
+        if let Some(next) = self.path.get(current + 2) {
+            let radius = turn_radius(next.metric_distance(destination));
+            if remaining < radius {
+                if let Some(next_direction) = (next - destination).try_normalize(f32::EPSILON) {
+                    let t = 0.5 * (1.0 - remaining / radius);
+                    self.heading = blend_directions(direction, next_direction, t);
+                    return;
+                }
+            }
+        }
+
+        if let Some(previous) = current.checked_sub(1).and_then(|i| self.path.get(i)) {
+            let radius = turn_radius(source.metric_distance(previous));
+            if traveled < radius {
+                if let Some(previous_direction) = (source - previous).try_normalize(f32::EPSILON) {
+                    let t = 0.5 + 0.5 * traveled / radius;
+                    self.heading = blend_directions(previous_direction, direction, t);
+                }
+            }
+        }
+    }
+
+    fn clamp_position_to_surface(&mut self, navmesh: &Navmesh) {
+        // Off-mesh links leave the surface intentionally.
+        if self.is_traversing_offmesh_link() {
+            self.surface_triangle = None;
+            self.ground_normal = navmesh.up_axis();
+            return;
+        }
+
+        let mut triangle = match self
+            .surface_triangle
+            .filter(|triangle| *triangle < navmesh.triangles.len())
+        {
+            Some(triangle) => triangle,
+            None => match self.query_closest(navmesh, self.position) {
+                Some((_, triangle)) => triangle,
+                None => return,
+            },
+        };
+
+        // Walk across adjacent triangles towards the position. The number of steps is limited,
+        // since the agent can't move too far in a single update.
+        const MAX_STEPS: usize = 16;
+        let mut closest = None;
+        for _ in 0..MAX_STEPS {
+            let mut closest_distance = f32::MAX;
+            closest = None;
+            navmesh.query_closest_internal(
+                &mut closest,
+                &mut closest_distance,
+                std::iter::once(triangle).chain(navmesh.neighbors(triangle).filter(|neighbor| {
+                    self.query_filter
+                        .passes_layer(navmesh.triangle_layers[*neighbor])
+                })),
+                self.position,
+            );
+            match closest {
+                Some((_, closest_triangle)) if closest_triangle != triangle => {
+                    triangle = closest_triangle;
+                }
+                _ => break,
+            }
+        }
+
+        // The walk gets stuck at the vertices, that are shared by non-adjacent triangles (for
+        // example, when the agent moves diagonally through a corner of a cell of a grid), step
+        // over such vertices.
+        if let Some((point, last)) = closest {
+            let offset = navmesh.to_up_space(point - self.position);
+            if Vector2::new(offset.x, offset.z).norm() > 1.0e-4 {
+                let shares_vertex = |triangle: usize| {
+                    navmesh.triangles[triangle]
+                        .0
+                        .iter()
+                        .any(|vertex| navmesh.triangles[last].0.contains(vertex))
+                };
+                if let Some(triangle) =
+                    navmesh
+                        .triangle_at(self.position, f32::MAX)
+                        .filter(|triangle| {
+                            shares_vertex(*triangle)
+                                && self
+                                    .query_filter
+                                    .passes_layer(navmesh.triangle_layers[*triangle])
+                        })
+                {
+                    let mut closest_distance = f32::MAX;
+                    navmesh.query_closest_internal(
+                        &mut closest,
+                        &mut closest_distance,
+                        std::iter::once(triangle),
+                        self.position,
+                    );
+                }
+            }
+        }
+
+        if let Some((point, triangle)) = closest {
+            // Keep the horizontal position on the path, if it is above the surface, and take the
+            // height from the surface, so the agent smoothly follows slopes.
+            let position = navmesh.to_up_space(self.position);
+            let surface = std::iter::once(triangle)
+                .chain(navmesh.neighbors(triangle))
+                .find_map(|triangle| {
+                    navmesh
+                        .height_on_triangle(triangle, position)
+                        .map(|height| (height, triangle))
+                });
+            match surface {
+                Some((height, triangle)) => {
+                    self.position =
+                        navmesh.up_space_to_world(Vector3::new(position.x, height, position.z));
+                    self.surface_triangle = Some(triangle);
+                }
+                None => {
+                    self.position = point;
+                    self.surface_triangle = Some(triangle);
+                }
+            }
+            self.update_ground_normal(navmesh);
+        }
+    }
+
+    fn update_ground_normal(&mut self, navmesh: &Navmesh) {
+        let up = navmesh.up_axis();
+        self.ground_normal = match self
+            .surface_triangle
+            .and_then(|triangle| navmesh.triangle_normal(triangle))
+            .filter(|normal| *normal != Vector3::default())
+        {
+            // The normal must point up regardless of the winding order of the triangle.
+            Some(normal) if normal.dot(&up) < 0.0 => -normal,
+            Some(normal) => normal,
+            None => up,
+        };
+    }
+
+    pub(super) fn update_path(
+        &mut self,
+        dt: f32,
+        navmesh: &Navmesh,
+    ) -> Result<(), NavmeshPathError> {
+        if self.navmesh_topology_revision != navmesh.topology_revision() {
+            self.navmesh_topology_revision = navmesh.topology_revision();
+            // The corridor and the surface triangle refer to the old indices of the triangles.
+            self.surface_triangle = None;
+            if !self.corridor.is_empty() {
+                self.corridor.clear();
+                self.invalidate_path();
+            }
+        }
+        if self.navmesh_revision != navmesh.revision() {
+            self.navmesh_revision = navmesh.revision();
+            // Partial path could become full after the change.
+            if !self.path_dirty
+                && !self.path.is_empty()
+                && (self.partial_path || !self.is_corridor_walkable(navmesh))
+            {
+                self.invalidate_path();
+            }
+        }
+        if self.navmesh_geometry_revision != navmesh.geometry_revision {
+            self.navmesh_geometry_revision = navmesh.geometry_revision;
+            // The waypoints do not lie on the moved surface anymore.
+            if !self.path.is_empty() {
+                self.invalidate_path();
+            }
+        }
+        if self.path_query_filter != self.query_filter {
+            // Same as `set_query_filter`.
+            self.path_query_filter = self.query_filter;
+            self.path_dirty = true;
+        }
+
+        self.time_since_recalculation += dt;
+        self.replan_backoff_timer += dt;
+
+        if self.reacquire_radius > 0.0 && self.path_request.is_none() && self.target_reached() {
+            let radius = self.reacquire_radius.max(self.arrival_radius) + self.follow_distance;
+            if self.target.metric_distance(&self.position) <= radius {
+                // Hold the position, until the target has moved away.
+                return Ok(());
+            }
+            self.path_dirty = true;
+        }
+
+        if self.path_dirty
+            && !self.path.is_empty()
+            && self.time_since_recalculation < self.recalculation_interval
+        {
+            // Keep following the previous path.
+            return Ok(());
+        }
+
+        if self.replan_backoff > 0.0
+            && self.target.metric_distance(&self.replan_backoff_target)
+                >= self.recalculation_threshold
+        {
+            // The target has moved significantly, the path could be found now.
+            self.replan_backoff = 0.0;
+        }
+        if self.path_dirty && self.replan_backoff_timer < self.replan_backoff {
+            // The previous attempt has failed, postpone the next one.
+            return Ok(());
+        }
+
+        if !self.async_path || !self.target_region.is_empty() {
+            if let Some(request) = self.path_request.take() {
+                navmesh.cancel_path_request(request);
+            }
+            if self.path_dirty {
+                self.time_since_recalculation = 0.0;
+                self.last_target_position = self.target;
+                self.spend_path_budget(navmesh);
+                let result = if self.target_region.is_empty() {
+                    self.calculate_path(navmesh, self.position, self.target)
+                } else {
+                    self.calculate_region_path(navmesh)
+                };
+                if let Err(err) = result {
+                    self.update_replan_backoff(false);
+                    self.push_event(NavmeshAgentEvent::PathFailed(err.clone()));
+                    return Err(err);
+                }
+                self.update_replan_backoff(!self.partial_path);
+                self.apply_follow_distance();
+                self.apply_path_budget(navmesh);
+                self.path_dirty = false;
+                self.push_event(NavmeshAgentEvent::PathRecalculated);
+            }
+            return Ok(());
+        }
+
+        if let Some(request) = self.path_request {
+            if let Some(result) = navmesh.take_path_result(request) {
+                self.path_request = None;
+                let computed = match result {
+                    Ok(computed) => computed,
+                    Err(err) => {
+                        self.update_replan_backoff(false);
+                        self.push_event(NavmeshAgentEvent::PathFailed(err.clone()));
+                        return Err(err);
+                    }
+                };
+                self.push_event(NavmeshAgentEvent::PathRecalculated);
+                self.spend_path_budget(navmesh);
+                self.path = computed.path;
+                self.offmesh_segments = computed.offmesh_segments;
+                self.corridor = computed.corridor;
+                self.partial_path = computed.partial;
+                self.update_replan_backoff(!self.partial_path);
+                self.current = 0;
+                self.interpolator = 0.0;
+                self.path_offset = Default::default();
+                self.avoidance_offset = Default::default();
+                self.apply_follow_distance();
+                self.apply_path_budget(navmesh);
+                // The agent has moved while the path was being calculated.
+                self.project_on_path(navmesh, self.position);
+            } else if self.path_dirty {
+                // The request is outdated, replace it with a new one.
+                navmesh.cancel_path_request(request);
+                self.path_request = None;
+            }
+        }
+
+        if self.path_dirty && self.path_request.is_none() {
+            self.time_since_recalculation = 0.0;
+            self.last_target_position = self.target;
+            self.path_request = Some(navmesh.add_path_request(PathQuery {
+                from: self.position,
+                to: self.target,
+                radius: self.radius,
+                smoothing: self.smoothing,
+                allow_partial_path: self.allow_partial_path,
+                filter: self.query_filter,
+                priority: self.path_priority,
+            }));
+            self.path_dirty = false;
+        }
+
+        Ok(())
+    }
+
+    /// Resets the replan backoff after a successful attempt to calculate a path, or increases it
+    /// after a failed one. See [`Self::set_replan_backoff`] for more info.
+    fn update_replan_backoff(&mut self, success: bool) {
+        if success || self.replan_backoff_max_interval <= 0.0 {
+            self.replan_backoff = 0.0;
+        } else {
+            self.replan_backoff = if self.replan_backoff > 0.0 {
+                self.replan_backoff * 2.0
+            } else {
+                self.replan_backoff_min_interval
+            }
+            .min(self.replan_backoff_max_interval);
+            self.replan_backoff_timer = 0.0;
+            self.replan_backoff_target = self.target;
+        }
+    }
+
+    /// Returns the closest point on the layers of the agent, see [`Navmesh::query_closest`].
+    fn query_closest(
+        &self,
+        navmesh: &Navmesh,
+        point: Vector3<f32>,
+    ) -> Option<(Vector3<f32>, usize)> {
+        navmesh.query_closest_in_layers(point, self.query_filter.layer_mask)
+    }
+
+    /// Checks whether the remaining part of the corridor of the path could still be walked.
+    fn is_corridor_walkable(&self, navmesh: &Navmesh) -> bool {
+        let start = self
+            .query_closest(navmesh, self.position)
+            .and_then(|(_, triangle)| self.corridor.iter().position(|t| *t == triangle))
+            .unwrap_or_default();
+        let corridor = &self.corridor[start..];
+        corridor.iter().all(|&triangle| {
+            navmesh
+                .triangle_flags
+                .get(triangle)
+                .is_some_and(|flags| self.query_filter.passes(*flags))
+                && self
+                    .query_filter
+                    .passes_layer(navmesh.triangle_layers[triangle])
+                && !navmesh.is_triangle_blocked(triangle)
+        }) && corridor.windows(2).all(|pair| {
+            navmesh.offmesh_link_between(pair[0], pair[1]).is_some()
+                || !navmesh
+                    .one_way_edges
+                    .contains(&[pair[1] as u32, pair[0] as u32])
+        })
+    }
+
+    /// Cuts the end of a full path, so the path ends at the follow distance from the target.
+    fn apply_follow_distance(&mut self) {
+        if self.follow_distance <= 0.0 || self.partial_path {
+            return;
+        }
+        let Some(&target) = self.path.last() else {
+            return;
+        };
+
+        // The segments, that are entirely within the follow distance, are removed, and the last
+        // remaining segment is cut at the point, where it enters the sphere around the target.
+        let distance = self.follow_distance;
+        while let [.., previous, end] = self.path[..] {
+            if previous.metric_distance(&target) >= distance {
+                // Solve |end + (previous - end) * t - target| = distance for t in [0; 1].
+                let direction = previous - end;
+                let offset = end - target;
+                let a = direction.norm_squared();
+                let b = 2.0 * direction.dot(&offset);
+                let c = offset.norm_squared() - distance * distance;
+                let t = (-b + (b * b - 4.0 * a * c).max(0.0).sqrt()) / (2.0 * a);
+                let last = self.path.len() - 1;
+                self.path[last] = end + direction.scale(t.clamp(0.0, 1.0));
+                break;
+            }
+            self.path.pop();
+        }
+
+        let segment_count = self.path.len().saturating_sub(1);
+        self.offmesh_segments
+            .retain(|segment| (segment.segment as usize) < segment_count);
+    }
+
+    /// Splits the path into the parts, that lie inside a single triangle of the corridor or go
+    /// through an off-mesh link, and calculates their costs. See [`Self::set_max_path_cost`] for
+    /// more info.
+    fn path_cost_parts(&self, navmesh: &Navmesh) -> Vec<PathCostPart> {
+        let mut parts = Vec::new();
+        let mut corridor_index = 0;
+        for (segment, points) in self.path.windows(2).enumerate() {
+            let (begin, end) = (points[0], points[1]);
+            let length = begin.metric_distance(&end);
+            if let Some(offmesh) = self
+                .offmesh_segments
+                .iter()
+                .find(|s| s.segment as usize == segment)
+            {
+                let link_cost = navmesh
+                    .offmesh_links
+                    .try_borrow(offmesh.link)
+                    .map_or(1.0, |link| link.cost);
+                parts.push(PathCostPart {
+                    segment,
+                    begin: 0.0,
+                    end: 1.0,
+                    cost: length * link_cost,
+                    corridor_index,
+                });
+                continue;
+            }
+
+            let mut clips = self
+                .corridor
+                .iter()
+                .enumerate()
+                .filter_map(|(index, triangle)| {
+                    let (t0, t1) = navmesh.clip_segment_by_triangle(begin, end, *triangle)?;
+                    let area_cost = navmesh.triangle_costs[*triangle]
+                        * self
+                            .query_filter
+                            .cost_multiplier(navmesh.triangle_flags[*triangle]);
+                    Some((t0, t1, area_cost, index))
+                })
+                .collect::<Vec<_>>();
+            clips.sort_by(|a, b| a.0.total_cmp(&b.0));
+
+            // The gaps between the triangles (there should be none, unless the path leaves the
+            // corridor because of numerical errors) cost the same as the previous triangle.
+            let mut cursor = 0.0;
+            let mut area_cost = clips.first().map_or(1.0, |clip| clip.2);
+            let mut add_part = |begin: f32, end: f32, area_cost: f32, corridor_index: usize| {
+                parts.push(PathCostPart {
+                    segment,
+                    begin,
+                    end,
+                    cost: length * (end - begin) * area_cost,
+                    corridor_index,
+                })
+            };
+            for (t0, t1, clip_cost, index) in clips {
+                if t1 <= cursor {
+                    continue;
+                }
+                if t0 > cursor {
+                    add_part(cursor, t0, area_cost, corridor_index);
+                }
+                add_part(t0.max(cursor), t1, clip_cost, index);
+                cursor = t1;
+                area_cost = clip_cost;
+                corridor_index = index;
+            }
+            if cursor < 1.0 {
+                add_part(cursor, 1.0, area_cost, corridor_index);
+            }
+        }
+        parts
+    }
+
+    /// Adds the cost of the walked part of the current path to the spent budget, it must be called
+    /// before the path is replaced. The walked part is defined by the progress of the agent along
+    /// the path, so it is never lost, even if the agent was moved away from its corridor.
+    fn spend_path_budget(&mut self, navmesh: &Navmesh) {
+        if self.max_path_cost == f32::MAX || self.path.len() < 2 {
+            return;
+        }
+        let (current, progress) = if self.current as usize + 1 >= self.path.len() {
+            (self.path.len() - 2, 1.0)
+        } else {
+            (self.current as usize, self.interpolator.clamp(0.0, 1.0))
+        };
+        for part in self.path_cost_parts(navmesh) {
+            if part.segment < current {
+                self.spent_path_cost += part.cost;
+            } else if part.segment == current && progress > part.begin {
+                let length = (part.end - part.begin).max(f32::EPSILON);
+                let fraction = ((progress - part.begin) / length).min(1.0);
+                self.spent_path_cost += part.cost * fraction;
+            }
+        }
+    }
+
+    /// Cuts the path at the furthest point, that could be afforded with the remaining budget.
+    fn apply_path_budget(&mut self, navmesh: &Navmesh) {
+        self.budget_limited = false;
+        if self.max_path_cost == f32::MAX {
+            return;
+        }
+
+        let remaining = (self.max_path_cost - self.spent_path_cost).max(0.0);
+        let mut total = 0.0;
+        for part in self.path_cost_parts(navmesh) {
+            if total + part.cost <= remaining {
+                total += part.cost;
+                continue;
+            }
+
+            self.budget_limited = true;
+            self.corridor.truncate(part.corridor_index + 1);
+            let segment = part.segment;
+            if self
+                .offmesh_segments
+                .iter()
+                .any(|s| s.segment as usize == segment)
+            {
+                // Off-mesh links can't be traversed partially.
+                self.path.truncate(segment + 1);
+                self.offmesh_segments
+                    .retain(|s| (s.segment as usize) < segment);
+            } else {
+                let fraction = (remaining - total) / part.cost;
+                let t = part.begin + (part.end - part.begin) * fraction;
+                let (begin, end) = (self.path[segment], self.path[segment + 1]);
+                self.path[segment + 1] = begin.lerp(&end, t);
+                self.path.truncate(segment + 2);
+                self.offmesh_segments
+                    .retain(|s| (s.segment as usize) <= segment);
+            }
+            return;
+        }
+    }
+
+    /// Moves the agent to the given position and updates its progress along the path by
+    /// projecting the position on the closest point of the remaining path. The progress never
+    /// goes back, so the agent does not return to the waypoints, that it has passed already.
+    /// The segments, that go through the triangle of the corridor under the agent, are preferred,
+    /// so the agent does not skip a part of the path, when the path passes near itself.
+    pub(super) fn project_on_path(&mut self, navmesh: &Navmesh, position: Vector3<f32>) {
+        self.position = position;
+
+        let triangle = self
+            .query_closest(navmesh, position)
+            .map(|(_, triangle)| triangle)
+            .filter(|triangle| self.corridor.contains(triangle));
+
+        let first = self.current as usize;
+        let mut closest = None;
+        for triangle in [triangle, None] {
+            let mut closest_distance = f32::MAX;
+            for (i, segment) in self.path.windows(2).enumerate().skip(first) {
+                if let Some(triangle) = triangle {
+                    if navmesh
+                        .clip_segment_by_triangle(segment[0], segment[1], triangle)
+                        .is_none()
+                    {
+                        continue;
+                    }
+                }
+                let delta = segment[1] - segment[0];
+                let sqr_len = delta.norm_squared();
+                let mut t = if sqr_len > f32::EPSILON {
+                    ((position - segment[0]).dot(&delta) / sqr_len).clamp(0.0, 1.0)
+                } else {
+                    1.0
+                };
+                if i == first {
+                    t = t.max(self.interpolator);
+                }
+                let distance = segment[0].lerp(&segment[1], t).metric_distance(&position);
+                if distance < closest_distance {
+                    closest_distance = distance;
+                    closest = Some((i, t));
+                }
+            }
+            if closest.is_some() {
+                break;
+            }
+        }
+
+        if let Some((segment, t)) = closest {
+            if t >= 1.0 {
+                self.current = segment as u32 + 1;
+                self.interpolator = 0.0;
+            } else {
+                self.current = segment as u32;
+                self.interpolator = t;
+            }
+        }
+    }
+
+    /// Returns current steering target which in most cases next path point from which
+    /// agent is close to.
+    pub fn steering_target(&self) -> Option<Vector3<f32>> {
+        self.path
+            .get(self.current as usize + 1)
+            .or_else(|| self.path.last())
+            .cloned()
+    }
+
+    /// Sets new target for the agent. It also resets the target region of the agent, see
+    /// [`Self::set_target_region`].
+    pub fn set_target(&mut self, new_target: Vector3<f32>) {
+        if !self.target_region.is_empty() {
+            self.target_region.clear();
+            self.target_region_triangle = None;
+            self.path_dirty = true;
+        }
+
+        if new_target.metric_distance(&self.last_target_position) >= self.recalculation_threshold {
+            self.path_dirty = true;
+            self.last_target_position = new_target;
+        }
+
+        self.target = new_target;
+    }
+
+    /// Returns current target of the agent.
+    pub fn target(&self) -> Vector3<f32> {
+        self.target
+    }
+
+    /// Sets a region of the navmesh as the target of the agent. The region is defined by the
+    /// indices of its triangles (they could be collected, for example, using
+    /// [`Navmesh::triangles_in_aabb`] or by the flags of the triangles). The agent walks to the
+    /// cheapest reachable triangle of the region (the search runs once for all the triangles) and
+    /// stops right after entering it, instead of walking to some specific point inside the region.
+    /// If the agent is already inside the region, it stays where it is.
+    ///
+    /// When the path is calculated, the target of the agent (see [`Self::target`]) is moved to the
+    /// point, where the path enters the region, and the entered triangle could be fetched using
+    /// [`Self::target_region_triangle`]. When the agent reaches the region,
+    /// [`NavmeshAgentEvent::EnteredTargetRegion`] event is generated. The paths to the regions are
+    /// always calculated synchronously, even if asynchronous path finding is enabled (see
+    /// [`Self::set_async_path_calculation`]). If the region is unreachable and partial paths are
+    /// allowed (see [`Self::set_allow_partial_path`]), the agent walks as close as possible to the
+    /// point of the region, that is closest to it. Setting the same region again does nothing; an
+    /// empty region or [`Self::set_target`] switch the agent back to the point target.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// # use fyrox_impl::{
+    /// #     core::{algebra::Vector3, math::aabb::AxisAlignedBoundingBox},
+    /// #     utils::navmesh::{Navmesh, NavmeshAgent},
+    /// # };
+    /// fn retreat(
+    ///     agent: &mut NavmeshAgent,
+    ///     navmesh: &Navmesh,
+    ///     safe_zone: &AxisAlignedBoundingBox,
+    /// ) {
+    ///     agent.set_target_region(&navmesh.triangles_in_aabb(safe_zone));
+    /// }
+    /// ```
+    pub fn set_target_region(&mut self, triangles: &[usize]) {
+        if self.target_region != triangles {
+            self.target_region = triangles.to_vec();
+            self.target_region_triangle = None;
+            self.path_dirty = true;
+        }
+    }
+
+    /// Returns the indices of the triangles of the target region of the agent. The slice is empty,
+    /// if the agent has a point target. See [`Self::set_target_region`] for more info.
+    pub fn target_region(&self) -> &[usize] {
+        &self.target_region
+    }
+
+    /// Returns the index of the triangle of the target region, that the current path of the agent
+    /// ends in, or `None` if the agent has no target region or the path was not found. See
+    /// [`Self::set_target_region`] for more info.
+    pub fn target_region_triangle(&self) -> Option<usize> {
+        self.target_region_triangle
+    }
+
+    /// Sets new position of the agent. If the agent was moved by some external force (for example,
+    /// knocked back by physics) and the path is still valid, the agent continues from the closest
+    /// point of the remaining part of the path on the next [`Self::update`]. The agent never goes
+    /// back to the waypoints, that it has passed already.
+    pub fn set_position(&mut self, new_position: Vector3<f32>) {
+        if new_position.metric_distance(&self.last_warp_position) >= self.recalculation_threshold {
+            self.path_dirty = true;
+            self.last_warp_position = new_position;
+        }
+
+        if new_position != self.position {
+            self.displaced = true;
+        }
+        self.position = new_position;
+        self.surface_triangle = None;
+    }
+
+    /// Teleports the agent to the closest point of the given navigation mesh to the given position
+    /// and clears its current path, so the path will be recalculated from the new position on the
+    /// next [`Self::update`]. Unlike [`Self::set_position`], the agent does not try to continue
+    /// following its old path. This method should be used when a character, that is controlled by
+    /// the agent, is spawned or teleported. It is safe to call this method every frame. A pending
+    /// asynchronous path request of the agent (see [`Self::set_async_path_calculation`]) is
+    /// cancelled and removed from the queue of the navmesh.
+    pub fn reset(&mut self, position: Vector3<f32>, navmesh: &Navmesh) {
+        if let Some(request) = self.path_request.take() {
+            navmesh.cancel_path_request(request);
+        }
+        match self.query_closest(navmesh, position) {
+            Some((point, triangle)) => {
+                self.position = point;
+                self.surface_triangle = Some(triangle);
+            }
+            None => {
+                self.position = position;
+                self.surface_triangle = None;
+            }
+        }
+        self.update_ground_normal(navmesh);
+
+        self.last_warp_position = self.position;
+        self.path.clear();
+        self.offmesh_segments.clear();
+        self.corridor.clear();
+        self.partial_path = false;
+        self.current = 0;
+        self.interpolator = 0.0;
+        self.path_dirty = true;
+        // Do not wait for the recalculation interval, the old path is gone.
+        self.time_since_recalculation = f32::MAX;
+        self.replan_backoff = 0.0;
+    }
+
+    /// Forces the agent to recalculate its path from its current position on the next
+    /// [`Self::update`], ignoring the recalculation interval (see
+    /// [`Self::set_recalculation_interval`]). The agent keeps following its old path until the new
+    /// one is ready. There's no need to call this method when runtime obstacles (see
+    /// [`Navmesh::add_obstacle`]) or the navmesh itself change - the agent tracks the revision of
+    /// the navmesh (see [`Navmesh::revision`]) and recalculates its path, if the remaining part of
+    /// the path goes through the triangles, that cannot be walked anymore. The replan backoff (see
+    /// [`Self::set_replan_backoff`]) is kept, so invalidations do not cause repeated searches for
+    /// an unreachable target.
+    pub fn invalidate_path(&mut self) {
+        self.path_dirty = true;
+        self.time_since_recalculation = f32::MAX;
+    }
+
+    /// Captures the runtime state of the agent: its position, target, path, the progress along the
+    /// path, pending events and so on. The state could be restored later using [`Self::restore`],
+    /// which makes the agent repeat the same movement, if it is updated with the same time
+    /// steps on the same navmesh. It could be used for rollback networking, where the simulation
+    /// is rewound and replayed, or to undo the last movement step of the agent.
+    ///
+    /// The settings of the agent (speed, radius, etc.) are not captured, since they usually do not
+    /// change between the updates.
+    pub fn snapshot(&self) -> NavmeshAgentState {
+        NavmeshAgentState {
+            path: self.path.clone(),
+            current: self.current,
+            position: self.position,
+            last_warp_position: self.last_warp_position,
+            target: self.target,
+            last_target_position: self.last_target_position,
+            path_dirty: self.path_dirty,
+            interpolator: self.interpolator,
+            offmesh_segments: self.offmesh_segments.clone(),
+            partial_path: self.partial_path,
+            waiting_for_path: self.path_request.is_some(),
+            events: self.events.clone(),
+            was_target_reached: self.was_target_reached,
+            last_offmesh_link: self.last_offmesh_link,
+            time_since_recalculation: self.time_since_recalculation,
+            surface_triangle: self.surface_triangle,
+            ground_normal: self.ground_normal,
+            path_offset: self.path_offset,
+            avoidance_offset: self.avoidance_offset,
+            heading: self.heading,
+            current_speed: self.current_speed,
+            spent_path_cost: self.spent_path_cost,
+            budget_limited: self.budget_limited,
+            stuck: self.stuck,
+            stuck_timer: self.stuck_timer,
+            stuck_anchor: self.stuck_anchor,
+            replan_backoff: self.replan_backoff,
+            replan_backoff_timer: self.replan_backoff_timer,
+            replan_backoff_target: self.replan_backoff_target,
+            corridor: self.corridor.clone(),
+            target_region: self.target_region.clone(),
+            target_region_triangle: self.target_region_triangle,
+            displaced: self.displaced,
+            navmesh_revision: self.navmesh_revision,
+            navmesh_geometry_revision: self.navmesh_geometry_revision,
+            navmesh_topology_revision: self.navmesh_topology_revision,
+        }
+    }
+
+    /// Restores the runtime state of the agent, that was captured by [`Self::snapshot`]. The
+    /// memory of the agent is reused, so it is cheap to restore the same state many times.
+    ///
+    /// Asynchronous path requests (see [`Self::set_async_path_calculation`]) are not captured. If
+    /// the agent was waiting for a path at the moment of the snapshot, it makes a new request on
+    /// the next update.
+    pub fn restore(&mut self, state: &NavmeshAgentState) {
+        self.path.clone_from(&state.path);
+        self.current = state.current;
+        self.position = state.position;
+        self.last_warp_position = state.last_warp_position;
+        self.target = state.target;
+        self.last_target_position = state.last_target_position;
+        self.path_dirty = state.path_dirty;
+        self.interpolator = state.interpolator;
+        self.offmesh_segments.clone_from(&state.offmesh_segments);
+        self.partial_path = state.partial_path;
+        self.path_request = None;
+        self.events.clone_from(&state.events);
+        self.was_target_reached = state.was_target_reached;
+        self.last_offmesh_link = state.last_offmesh_link;
+        self.time_since_recalculation = state.time_since_recalculation;
+        self.surface_triangle = state.surface_triangle;
+        self.ground_normal = state.ground_normal;
+        self.path_offset = state.path_offset;
+        self.avoidance_offset = state.avoidance_offset;
+        self.heading = state.heading;
+        self.current_speed = state.current_speed;
+        self.spent_path_cost = state.spent_path_cost;
+        self.budget_limited = state.budget_limited;
+        self.stuck = state.stuck;
+        self.stuck_timer = state.stuck_timer;
+        self.stuck_anchor = state.stuck_anchor;
+        self.replan_backoff = state.replan_backoff;
+        self.replan_backoff_timer = state.replan_backoff_timer;
+        self.replan_backoff_target = state.replan_backoff_target;
+        self.corridor.clone_from(&state.corridor);
+        self.target_region.clone_from(&state.target_region);
+        self.target_region_triangle = state.target_region_triangle;
+        self.displaced = state.displaced;
+        self.navmesh_revision = state.navmesh_revision;
+        self.navmesh_geometry_revision = state.navmesh_geometry_revision;
+        self.navmesh_topology_revision = state.navmesh_topology_revision;
+        if state.waiting_for_path {
+            self.invalidate_path();
+        }
+    }
+}
+
+/// Allows you to build agent in declarative manner.
+pub struct NavmeshAgentBuilder {
+    position: Vector3<f32>,
+    target: Vector3<f32>,
+    recalculation_threshold: f32,
+    speed: f32,
+}
+
+impl Default for NavmeshAgentBuilder {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NavmeshAgentBuilder {
+    /// Creates new builder instance.
+    pub fn new() -> Self {
+        Self {
+            position: Default::default(),
+            target: Default::default(),
+            recalculation_threshold: 0.25,
+            speed: 1.5,
+        }
+    }
+
+    /// Sets new desired position of the agent being built.
+    pub fn with_position(mut self, position: Vector3<f32>) -> Self {
+        self.position = position;
+        self
+    }
+
+    /// Sets new desired target of the agent being built.
+    pub fn with_target(mut self, position: Vector3<f32>) -> Self {
+        self.target = position;
+        self
+    }
+
+    /// Sets new desired recalculation threshold (in meters) of the agent being built.
+    pub fn with_recalculation_threshold(mut self, threshold: f32) -> Self {
+        self.recalculation_threshold = threshold;
+        self
+    }
+
+    /// Sets new desired movement speed of the agent being built.
+    pub fn with_speed(mut self, speed: f32) -> Self {
+        self.speed = speed;
+        self
+    }
+
+    /// Build the agent.
+    pub fn build(self) -> NavmeshAgent {
+        NavmeshAgent {
+            position: self.position,
+            last_warp_position: self.position,
+            target: self.target,
+            last_target_position: self.target,
+            recalculation_threshold: self.recalculation_threshold,
+            speed: self.speed,
+            ..Default::default()
+        }
+    }
+}
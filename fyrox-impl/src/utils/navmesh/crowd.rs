@@ -0,0 +1,607 @@
+// Copyright (c) 2019-present Dmitry Stepanov and Fyrox Engine contributors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Crowd is a set of navmesh agents, that avoid collisions with each other while following their
+//! paths. See [`NavmeshAgentCrowd`] docs for more info.
+
+use crate::{
+    core::{
+        algebra::{Vector2, Vector3},
+        pool::{Handle, Pool},
+    },
+    utils::navmesh::{Navmesh, NavmeshAgent},
+};
+
+/// An agent of a crowd. It wraps a navmesh agent and stores its actual position and velocity, that
+/// are adjusted by the crowd to avoid collisions with other agents.
+#[derive(Clone, Debug)]
+pub struct CrowdAgent {
+    agent: NavmeshAgent,
+    position: Vector3<f32>,
+    velocity: Vector3<f32>,
+}
+
+impl CrowdAgent {
+    /// Returns a reference to the inner navmesh agent.
+    pub fn agent(&self) -> &NavmeshAgent {
+        &self.agent
+    }
+
+    /// Returns a reference to the inner navmesh agent. Use it to change the target, the speed,
+    /// the radius, etc. of the agent.
+    pub fn agent_mut(&mut self) -> &mut NavmeshAgent {
+        &mut self.agent
+    }
+
+    /// Returns the position, that the agent wants to have after the last update of the crowd. It
+    /// could be used as a target point for the motion of a game character.
+    pub fn desired_position(&self) -> Vector3<f32> {
+        self.position
+    }
+
+    /// Returns the velocity of the agent, calculated by the crowd at the last update.
+    pub fn velocity(&self) -> Vector3<f32> {
+        self.velocity
+    }
+}
+
+/// A handle of an agent in a crowd.
+pub type CrowdAgentHandle = Handle<CrowdAgent>;
+
+/// Crowd is a set of navmesh agents, that avoid collisions with each other while following their
+/// paths. Every agent still follows its own path on a navmesh, but the crowd adjusts the
+/// velocities of the agents using Optimal Reciprocal Collision Avoidance (ORCA), so the agents
+/// do not interpenetrate each other (the radius of an agent is defined by
+/// [`NavmeshAgent::set_radius`]).
+///
+/// ## Example
+///
+/// ```rust
+/// # use fyrox_impl::{
+/// #     core::algebra::Vector3,
+/// #     utils::navmesh::{crowd::NavmeshAgentCrowd, Navmesh, NavmeshAgentBuilder},
+/// # };
+/// fn move_squad(crowd: &mut NavmeshAgentCrowd, navmesh: &Navmesh, target: Vector3<f32>, dt: f32) {
+///     for (_, agent) in crowd.pair_iter_mut() {
+///         agent.agent_mut().set_target(target);
+///     }
+///
+///     crowd.update(dt, navmesh);
+///
+///     for (_, agent) in crowd.pair_iter() {
+///         // Use this position as target point of your game character.
+///         println!("{}", agent.desired_position());
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct NavmeshAgentCrowd {
+    agents: Pool<CrowdAgent>,
+    time_horizon: f32,
+}
+
+impl Default for NavmeshAgentCrowd {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NavmeshAgentCrowd {
+    /// Creates a new empty crowd.
+    pub fn new() -> Self {
+        Self {
+            agents: Default::default(),
+            time_horizon: 2.0,
+        }
+    }
+
+    /// Adds a new agent to the crowd and returns its handle. Initial position of the agent in the
+    /// crowd is the current position of the agent.
+    pub fn add_agent(&mut self, agent: NavmeshAgent) -> CrowdAgentHandle {
+        self.agents.spawn(CrowdAgent {
+            position: agent.position(),
+            velocity: Default::default(),
+            agent,
+        })
+    }
+
+    /// Removes an agent from the crowd and returns it, if the handle was valid.
+    pub fn remove_agent(&mut self, handle: CrowdAgentHandle) -> Option<NavmeshAgent> {
+        self.agents.try_free(handle).map(|agent| agent.agent)
+    }
+
+    /// Returns a reference to an agent of the crowd.
+    pub fn agent(&self, handle: CrowdAgentHandle) -> Option<&CrowdAgent> {
+        self.agents.try_borrow(handle)
+    }
+
+    /// Returns a reference to an agent of the crowd.
+    pub fn agent_mut(&mut self, handle: CrowdAgentHandle) -> Option<&mut CrowdAgent> {
+        self.agents.try_borrow_mut(handle)
+    }
+
+    /// Returns an iterator over all agents of the crowd.
+    pub fn pair_iter(&self) -> impl Iterator<Item = (CrowdAgentHandle, &CrowdAgent)> {
+        self.agents.pair_iter()
+    }
+
+    /// Returns an iterator over all agents of the crowd.
+    pub fn pair_iter_mut(&mut self) -> impl Iterator<Item = (CrowdAgentHandle, &mut CrowdAgent)> {
+        self.agents.pair_iter_mut()
+    }
+
+    /// Returns the total number of agents in the crowd.
+    pub fn agent_count(&self) -> u32 {
+        self.agents.alive_count()
+    }
+
+    /// Returns handles of the agents, which desired positions are within the given radius from
+    /// the given point.
+    pub fn neighbors(&self, point: Vector3<f32>, radius: f32) -> Vec<CrowdAgentHandle> {
+        self.agents
+            .pair_iter()
+            .filter(|(_, agent)| agent.position.metric_distance(&point) <= radius)
+            .map(|(handle, _)| handle)
+            .collect()
+    }
+
+    /// Sets a new time horizon (in seconds) for collision avoidance. The agents will avoid
+    /// the collisions that could happen within this time interval. Larger values make the agents
+    /// react earlier, but restrict their motion more. Default is 2 seconds.
+    pub fn set_time_horizon(&mut self, time_horizon: f32) {
+        self.time_horizon = time_horizon.max(f32::EPSILON);
+    }
+
+    /// Returns current time horizon for collision avoidance. See [`Self::set_time_horizon`] for
+    /// more info.
+    pub fn time_horizon(&self) -> f32 {
+        self.time_horizon
+    }
+
+    /// Performs a single update tick of the crowd: recalculates the paths of the agents (if
+    /// needed), calculates velocities of the agents, that follow their paths and avoid
+    /// collisions with each other, and moves the agents.
+    pub fn update(&mut self, dt: f32, navmesh: &Navmesh) {
+        if dt <= 0.0 {
+            return;
+        }
+
+        // Calculate preferred velocities, these are velocities that the agents would have if they
+        // were alone. The avoidance works on the horizontal plane of the navmesh.
+        let mut states = Vec::with_capacity(self.agents.alive_count() as usize);
+        for (handle, crowd_agent) in self.agents.pair_iter_mut() {
+            let agent = &mut crowd_agent.agent;
+            agent.position = crowd_agent.position;
+            let position = navmesh.to_up_space(crowd_agent.position);
+            let velocity = navmesh.to_up_space(crowd_agent.velocity);
+            // The agent moves along its path exactly as a standalone agent would do (including
+            // the correction of the offset from the path), the avoidance then adjusts the motion.
+            let preferred_velocity = if agent.update_path(dt, navmesh).is_ok() {
+                // The progress along the path is restored, since the agent could be blocked by the
+                // others. It is updated using the actual position after the avoidance.
+                let progress = (agent.current, agent.interpolator);
+                agent.advance_along_path(dt, navmesh, Vector3::default());
+                let desired_position = agent
+                    .point_on_path()
+                    .map_or(crowd_agent.position, |point| point + agent.path_offset);
+                (agent.current, agent.interpolator) = progress;
+                navmesh.to_up_space((desired_position - crowd_agent.position).scale(1.0 / dt))
+            } else {
+                Vector3::default()
+            };
+            states.push(AgentState {
+                handle,
+                position: Vector2::new(position.x, position.z),
+                velocity: Vector2::new(velocity.x, velocity.z),
+                preferred_velocity,
+                radius: agent.radius(),
+                max_speed: agent.speed(),
+            });
+        }
+
+        let mut lines = Vec::new();
+        for state in states.iter() {
+            lines.clear();
+            for other in states.iter() {
+                if other.handle == state.handle {
+                    continue;
+                }
+                let relative_position = other.position - state.position;
+                let range = state.radius + other.radius + state.max_speed * self.time_horizon;
+                if relative_position.norm_squared() < range * range {
+                    lines.push(orca_line(state, other, self.time_horizon, dt));
+                }
+            }
+
+            let preferred_velocity =
+                Vector2::new(state.preferred_velocity.x, state.preferred_velocity.z);
+            let mut new_velocity = Vector2::default();
+            let line_fail = linear_program2(
+                &lines,
+                state.max_speed,
+                preferred_velocity,
+                false,
+                &mut new_velocity,
+            );
+            if line_fail < lines.len() {
+                linear_program3(&lines, line_fail, state.max_speed, &mut new_velocity);
+            }
+
+            // Keep vertical motion (slopes) proportional to the planar motion.
+            let preferred_speed = preferred_velocity.norm();
+            let vertical_velocity = if preferred_speed > f32::EPSILON {
+                state.preferred_velocity.y * (new_velocity.norm() / preferred_speed)
+            } else {
+                0.0
+            };
+
+            let crowd_agent = &mut self.agents[state.handle];
+            crowd_agent.velocity = navmesh.up_space_to_world(Vector3::new(
+                new_velocity.x,
+                vertical_velocity,
+                new_velocity.y,
+            ));
+
+            // Keep the agent on the navmesh.
+            let new_position = crowd_agent.position + crowd_agent.velocity.scale(dt);
+            crowd_agent.position = navmesh
+                .query_closest(new_position)
+                .map(|(point, _)| point)
+                .unwrap_or(new_position);
+        }
+
+        for (_, crowd_agent) in self.agents.pair_iter_mut() {
+            let agent = &mut crowd_agent.agent;
+            agent.project_on_path(navmesh, crowd_agent.position);
+            // The avoidance could push the agent away from its path, the offset is corrected on
+            // the next update.
+            agent.displaced = true;
+            agent.update_stuck_detection(dt);
+            agent.update_state_events();
+        }
+    }
+}
+
+struct AgentState {
+    handle: CrowdAgentHandle,
+    position: Vector2<f32>,
+    velocity: Vector2<f32>,
+    preferred_velocity: Vector3<f32>,
+    radius: f32,
+    max_speed: f32,
+}
+
+pub(super) fn preferred_velocity(
+    agent: &NavmeshAgent,
+    position: Vector3<f32>,
+    dt: f32,
+) -> Vector3<f32> {
+    let Some(steering_target) = agent.steering_target() else {
+        return Default::default();
+    };
+    let delta = steering_target - position;
+    let distance = delta.norm();
+    if distance <= f32::EPSILON {
+        return Default::default();
+    }
+    // Slow down near the final point, so the agent won't overshoot it.
+    let speed = if agent.path.last() == Some(&steering_target) {
+        agent.speed().min(distance / dt)
+    } else {
+        agent.speed()
+    };
+    delta.scale(speed / distance)
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Line {
+    point: Vector2<f32>,
+    direction: Vector2<f32>,
+}
+
+fn det(a: Vector2<f32>, b: Vector2<f32>) -> f32 {
+    a.x * b.y - a.y * b.x
+}
+
+/// Calculates a half-plane of velocities, that allows the agent to avoid collision with the other
+/// agent within the time horizon, assuming that the other agent takes half of the responsibility.
+fn orca_line(agent: &AgentState, other: &AgentState, time_horizon: f32, dt: f32) -> Line {
+    let relative_position = other.position - agent.position;
+    let relative_velocity = agent.velocity - other.velocity;
+    let sqr_distance = relative_position.norm_squared();
+    let combined_radius = agent.radius + other.radius;
+    let sqr_combined_radius = combined_radius * combined_radius;
+
+    let direction;
+    let u;
+    if sqr_distance > sqr_combined_radius {
+        // No collision.
+        let w = relative_velocity - relative_position.scale(1.0 / time_horizon);
+        let sqr_w_len = w.norm_squared();
+        let dot = w.dot(&relative_position);
+
+        if dot < 0.0 && dot * dot > sqr_combined_radius * sqr_w_len {
+            // Project on cut-off circle.
+            let w_len = sqr_w_len.sqrt();
+            let unit_w = w.scale(1.0 / w_len);
+            direction = Vector2::new(unit_w.y, -unit_w.x);
+            u = unit_w.scale(combined_radius / time_horizon - w_len);
+        } else {
+            // Project on legs.
+            let leg = (sqr_distance - sqr_combined_radius).sqrt();
+            direction = if det(relative_position, w) > 0.0 {
+                // Left leg.
+                Vector2::new(
+                    relative_position.x * leg - relative_position.y * combined_radius,
+                    relative_position.x * combined_radius + relative_position.y * leg,
+                )
+                .scale(1.0 / sqr_distance)
+            } else {
+                // Right leg.
+                -Vector2::new(
+                    relative_position.x * leg + relative_position.y * combined_radius,
+                    -relative_position.x * combined_radius + relative_position.y * leg,
+                )
+                .scale(1.0 / sqr_distance)
+            };
+            u = direction.scale(relative_velocity.dot(&direction)) - relative_velocity;
+        }
+    } else {
+        // The agents are already colliding, project on cut-off circle of the time step.
+        let w = relative_velocity - relative_position.scale(1.0 / dt);
+        let w_len = w.norm().max(f32::EPSILON);
+        let unit_w = w.scale(1.0 / w_len);
+        direction = Vector2::new(unit_w.y, -unit_w.x);
+        u = unit_w.scale(combined_radius / dt - w_len);
+    }
+
+    Line {
+        point: agent.velocity + u.scale(0.5),
+        direction,
+    }
+}
+
+fn linear_program1(
+    lines: &[Line],
+    line_no: usize,
+    radius: f32,
+    optimal_velocity: Vector2<f32>,
+    direction_optimal: bool,
+    result: &mut Vector2<f32>,
+) -> bool {
+    let line = &lines[line_no];
+    let dot = line.point.dot(&line.direction);
+    let discriminant = dot * dot + radius * radius - line.point.norm_squared();
+
+    if discriminant < 0.0 {
+        // Max speed circle fully invalidates the line.
+        return false;
+    }
+
+    let sqrt_discriminant = discriminant.sqrt();
+    let mut t_left = -dot - sqrt_discriminant;
+    let mut t_right = -dot + sqrt_discriminant;
+
+    for other in &lines[..line_no] {
+        let denominator = det(line.direction, other.direction);
+        let numerator = det(other.direction, line.point - other.point);
+
+        if denominator.abs() <= f32::EPSILON {
+            // The lines are parallel.
+            if numerator < 0.0 {
+                return false;
+            } else {
+                continue;
+            }
+        }
+
+        let t = numerator / denominator;
+        if denominator >= 0.0 {
+            t_right = t_right.min(t);
+        } else {
+            t_left = t_left.max(t);
+        }
+
+        if t_left > t_right {
+            return false;
+        }
+    }
+
+    let t = if direction_optimal {
+        if optimal_velocity.dot(&line.direction) > 0.0 {
+            t_right
+        } else {
+            t_left
+        }
+    } else {
+        line.direction
+            .dot(&(optimal_velocity - line.point))
+            .clamp(t_left, t_right)
+    };
+    *result = line.point + line.direction.scale(t);
+
+    true
+}
+
+fn linear_program2(
+    lines: &[Line],
+    radius: f32,
+    optimal_velocity: Vector2<f32>,
+    direction_optimal: bool,
+    result: &mut Vector2<f32>,
+) -> usize {
+    *result = if direction_optimal {
+        optimal_velocity.scale(radius)
+    } else if optimal_velocity.norm_squared() > radius * radius {
+        optimal_velocity.normalize().scale(radius)
+    } else {
+        optimal_velocity
+    };
+
+    for (i, line) in lines.iter().enumerate() {
+        if det(line.direction, line.point - *result) > 0.0 {
+            // The result does not satisfy the constraint of the line.
+            let previous = *result;
+            if !linear_program1(
+                lines,
+                i,
+                radius,
+                optimal_velocity,
+                direction_optimal,
+                result,
+            ) {
+                *result = previous;
+                return i;
+            }
+        }
+    }
+
+    lines.len()
+}
+
+fn linear_program3(lines: &[Line], begin_line: usize, radius: f32, result: &mut Vector2<f32>) {
+    let mut distance = 0.0;
+    let mut projected_lines = Vec::new();
+
+    for (i, line) in lines.iter().enumerate().skip(begin_line) {
+        if det(line.direction, line.point - *result) > distance {
+            // The result does not satisfy the constraint of the line.
+            projected_lines.clear();
+            for other in &lines[..i] {
+                let determinant = det(line.direction, other.direction);
+                let point = if determinant.abs() <= f32::EPSILON {
+                    if line.direction.dot(&other.direction) > 0.0 {
+                        // The lines are parallel and point in the same direction.
+                        continue;
+                    }
+                    (line.point + other.point).scale(0.5)
+                } else {
+                    line.point
+                        + line
+                            .direction
+                            .scale(det(other.direction, line.point - other.point) / determinant)
+                };
+                let Some(direction) =
+                    (other.direction - line.direction).try_normalize(f32::EPSILON)
+                else {
+                    continue;
+                };
+                projected_lines.push(Line { point, direction });
+            }
+
+            let previous = *result;
+            if linear_program2(
+                &projected_lines,
+                radius,
+                Vector2::new(-line.direction.y, line.direction.x),
+                true,
+                result,
+            ) < projected_lines.len()
+            {
+                // This should in principle not happen, the result is by definition already in
+                // the feasible region of this linear program. If it fails, it is due to small
+                // floating point error, and the current result is kept.
+                *result = previous;
+            }
+
+            distance = det(line.direction, line.point - *result);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::algebra::Vector3,
+        utils::navmesh::{crowd::NavmeshAgentCrowd, test::make_grid_navmesh, NavmeshAgentBuilder},
+    };
+
+    #[test]
+    fn test_crowd_avoidance() {
+        let navmesh = make_grid_navmesh(10);
+
+        // Two agents that walk towards each other along the same line.
+        let mut crowd = NavmeshAgentCrowd::new();
+        let a = crowd.add_agent(
+            NavmeshAgentBuilder::new()
+                .with_position(Vector3::new(1.0, 0.0, 5.0))
+                .with_target(Vector3::new(9.0, 0.0, 5.0))
+                .build(),
+        );
+        let b = crowd.add_agent(
+            NavmeshAgentBuilder::new()
+                .with_position(Vector3::new(9.0, 0.0, 5.0))
+                .with_target(Vector3::new(1.0, 0.0, 5.0))
+                .build(),
+        );
+
+        assert_eq!(crowd.neighbors(Vector3::new(1.0, 0.0, 5.0), 0.5), vec![a]);
+
+        let dt = 1.0 / 30.0;
+        for _ in 0..400 {
+            crowd.update(dt, &navmesh);
+
+            let pa = crowd.agent(a).unwrap().desired_position();
+            let pb = crowd.agent(b).unwrap().desired_position();
+            let radius = crowd.agent(a).unwrap().agent().radius();
+            assert!(pa.metric_distance(&pb) >= 2.0 * radius * 0.95);
+        }
+
+        // Both agents must reach their targets.
+        let pa = crowd.agent(a).unwrap().desired_position();
+        let pb = crowd.agent(b).unwrap().desired_position();
+        assert!(pa.metric_distance(&Vector3::new(9.0, 0.0, 5.0)) < 0.1);
+        assert!(pb.metric_distance(&Vector3::new(1.0, 0.0, 5.0)) < 0.1);
+
+        assert!(crowd.remove_agent(a).is_some());
+        assert_eq!(crowd.agent_count(), 1);
+    }
+
+    #[test]
+    fn test_crowd_path_correction() {
+        let navmesh = make_grid_navmesh(10);
+        let dt = 1.0 / 30.0;
+
+        // Returns the distance from the path after the agent was pushed away from it.
+        let offset_after_push = |strength: f32| {
+            let mut agent = NavmeshAgentBuilder::new()
+                .with_position(Vector3::new(1.0, 0.0, 5.0))
+                .with_target(Vector3::new(9.0, 0.0, 5.0))
+                .build();
+            agent.set_path_correction_strength(strength);
+            let mut crowd = NavmeshAgentCrowd::new();
+            let handle = crowd.add_agent(agent);
+            for _ in 0..5 {
+                crowd.update(dt, &navmesh);
+            }
+            crowd.agents[handle].position.z += 0.5;
+            for _ in 0..5 {
+                crowd.update(dt, &navmesh);
+            }
+            (crowd.agents[handle].position.z - 5.0).abs()
+        };
+
+        // The agent returns to its path gradually with lower correction strength.
+        let hard = offset_after_push(1.0);
+        let soft = offset_after_push(0.1);
+        assert!(hard < soft);
+        assert!(soft < 0.5);
+    }
+}
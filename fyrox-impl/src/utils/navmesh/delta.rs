@@ -0,0 +1,336 @@
+// Copyright (c) 2019-present Dmitry Stepanov and Fyrox Engine contributors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Runtime changes of navmeshes, that could be replicated over network. See [`NavmeshDelta`]
+//! docs for more info.
+
+use crate::utils::navmesh::{
+    format::{
+        invalid_data, read_f32, read_handle, read_u32, read_u8, read_vector3, write_handle,
+        write_vector3,
+    },
+    EdgeDirection, Navmesh, NavmeshObstacle, Obstacle, ObstacleHandle,
+};
+use std::{
+    fmt::{Display, Formatter},
+    io::{Read, Write},
+};
+
+/// Signature of the binary navmesh delta format, see [`NavmeshDelta::save_binary`].
+const NAVMESH_DELTA_BINARY_MAGIC: [u8; 4] = *b"FNDL";
+
+/// Version of the binary navmesh delta format, see [`NavmeshDelta::save_binary`].
+const NAVMESH_DELTA_BINARY_VERSION: u32 = 1;
+
+/// A set of runtime changes of a navmesh relative to a baseline navmesh with the same geometry:
+/// added, removed and changed obstacles, changed triangle flags and costs, and changed edge
+/// directions. It could be used to replicate the changes of a navmesh over network: the server
+/// computes the delta using [`Navmesh::compute_delta`] and sends it to the clients (see
+/// [`Self::save_binary`]), the clients apply it to their copies of the navmesh using
+/// [`Navmesh::apply_delta`]. Obstacles keep their handles, so the handles could be used to refer
+/// to the same obstacles on every peer.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NavmeshDelta {
+    triangle_flags: Vec<(u32, u32)>,
+    triangle_costs: Vec<(u32, f32)>,
+    edge_directions: Vec<([u32; 2], EdgeDirection)>,
+    removed_obstacles: Vec<ObstacleHandle>,
+    // Obstacles, that were added or changed.
+    obstacles: Vec<(ObstacleHandle, Obstacle)>,
+}
+
+impl NavmeshDelta {
+    /// Returns `true` if the delta contains no changes.
+    pub fn is_empty(&self) -> bool {
+        self.triangle_flags.is_empty()
+            && self.triangle_costs.is_empty()
+            && self.edge_directions.is_empty()
+            && self.removed_obstacles.is_empty()
+            && self.obstacles.is_empty()
+    }
+
+    /// Writes the delta in a compact versioned binary format to the given writer. Only the
+    /// changes are written, so the size of the data depends on the number of changes, not on the
+    /// size of the navmesh.
+    pub fn save_binary<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writer.write_all(&NAVMESH_DELTA_BINARY_MAGIC)?;
+        writer.write_all(&NAVMESH_DELTA_BINARY_VERSION.to_le_bytes())?;
+
+        writer.write_all(&(self.triangle_flags.len() as u32).to_le_bytes())?;
+        for (index, flags) in self.triangle_flags.iter() {
+            writer.write_all(&index.to_le_bytes())?;
+            writer.write_all(&flags.to_le_bytes())?;
+        }
+
+        writer.write_all(&(self.triangle_costs.len() as u32).to_le_bytes())?;
+        for (index, cost) in self.triangle_costs.iter() {
+            writer.write_all(&index.to_le_bytes())?;
+            writer.write_all(&cost.to_le_bytes())?;
+        }
+
+        writer.write_all(&(self.edge_directions.len() as u32).to_le_bytes())?;
+        for (edge, direction) in self.edge_directions.iter() {
+            for triangle in edge {
+                writer.write_all(&triangle.to_le_bytes())?;
+            }
+            let direction: u8 = match direction {
+                EdgeDirection::Bidirectional => 0,
+                EdgeDirection::Forward => 1,
+                EdgeDirection::Backward => 2,
+            };
+            writer.write_all(&[direction])?;
+        }
+
+        writer.write_all(&(self.removed_obstacles.len() as u32).to_le_bytes())?;
+        for handle in self.removed_obstacles.iter() {
+            write_handle(writer, *handle)?;
+        }
+
+        writer.write_all(&(self.obstacles.len() as u32).to_le_bytes())?;
+        for (handle, obstacle) in self.obstacles.iter() {
+            write_handle(writer, *handle)?;
+            writer.write_all(&[obstacle.enabled as u8])?;
+            match obstacle.shape {
+                NavmeshObstacle::Box {
+                    center,
+                    half_extents,
+                } => {
+                    writer.write_all(&[0])?;
+                    write_vector3(writer, center)?;
+                    write_vector3(writer, half_extents)?;
+                }
+                NavmeshObstacle::Cylinder {
+                    center,
+                    radius,
+                    height,
+                } => {
+                    writer.write_all(&[1])?;
+                    write_vector3(writer, center)?;
+                    writer.write_all(&radius.to_le_bytes())?;
+                    writer.write_all(&height.to_le_bytes())?;
+                }
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Reads a delta in the binary format, written by [`Self::save_binary`]. Returns
+    /// [`std::io::ErrorKind::InvalidData`] error if the data is not a navmesh delta, has
+    /// unsupported version or is malformed.
+    pub fn load_binary<R: Read>(reader: &mut R) -> std::io::Result<Self> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != NAVMESH_DELTA_BINARY_MAGIC {
+            return Err(invalid_data("not a navmesh delta"));
+        }
+        let version = read_u32(reader)?;
+        if version == 0 || version > NAVMESH_DELTA_BINARY_VERSION {
+            return Err(invalid_data("unsupported navmesh delta version"));
+        }
+
+        let mut delta = NavmeshDelta::default();
+
+        for _ in 0..read_u32(reader)? {
+            delta
+                .triangle_flags
+                .push((read_u32(reader)?, read_u32(reader)?));
+        }
+
+        for _ in 0..read_u32(reader)? {
+            delta
+                .triangle_costs
+                .push((read_u32(reader)?, read_f32(reader)?));
+        }
+
+        for _ in 0..read_u32(reader)? {
+            let edge = [read_u32(reader)?, read_u32(reader)?];
+            let direction = match read_u8(reader)? {
+                0 => EdgeDirection::Bidirectional,
+                1 => EdgeDirection::Forward,
+                2 => EdgeDirection::Backward,
+                _ => return Err(invalid_data("invalid edge direction")),
+            };
+            delta.edge_directions.push((edge, direction));
+        }
+
+        for _ in 0..read_u32(reader)? {
+            delta.removed_obstacles.push(read_handle(reader)?);
+        }
+
+        for _ in 0..read_u32(reader)? {
+            let handle = read_handle(reader)?;
+            let enabled = read_u8(reader)? != 0;
+            let shape = match read_u8(reader)? {
+                0 => NavmeshObstacle::Box {
+                    center: read_vector3(reader)?,
+                    half_extents: read_vector3(reader)?,
+                },
+                1 => NavmeshObstacle::Cylinder {
+                    center: read_vector3(reader)?,
+                    radius: read_f32(reader)?,
+                    height: read_f32(reader)?,
+                },
+                _ => return Err(invalid_data("invalid obstacle shape")),
+            };
+            delta.obstacles.push((handle, Obstacle { shape, enabled }));
+        }
+
+        Ok(delta)
+    }
+}
+
+/// An error that may occur when a delta is applied to a navmesh. See [`Navmesh::apply_delta`] for
+/// more info.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NavmeshDeltaError {
+    /// The obstacles could not be added, because their handles are occupied by other obstacles of
+    /// the navmesh (for example, by the obstacles, that were added locally). The rest of the delta
+    /// is applied, but the navmesh is out of sync with the source navmesh.
+    ObstacleHandlesOccupied(Vec<ObstacleHandle>),
+}
+
+impl Display for NavmeshDeltaError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NavmeshDeltaError::ObstacleHandlesOccupied(handles) => {
+                write!(f, "Obstacle handles {handles:?} are occupied.")
+            }
+        }
+    }
+}
+
+impl std::error::Error for NavmeshDeltaError {}
+
+impl Navmesh {
+    /// Computes a set of runtime changes of the navmesh relative to the given baseline navmesh. The
+    /// delta contains the triangles, whose flags or costs differ, the edges, whose directions
+    /// differ, and the obstacles, that were added, removed or changed (obstacles are matched by
+    /// their handles). Applying the delta to the baseline (see [`Self::apply_delta`]) makes it
+    /// equal to this navmesh in these aspects. The navmeshes must have the same geometry (for
+    /// example, the baseline could be a copy of the navmesh, made when a client has joined the
+    /// game), the geometry itself is not compared.
+    pub fn compute_delta(&self, baseline: &Navmesh) -> NavmeshDelta {
+        let mut delta = NavmeshDelta::default();
+
+        for (index, (flags, baseline_flags)) in self
+            .triangle_flags
+            .iter()
+            .zip(baseline.triangle_flags.iter())
+            .enumerate()
+        {
+            if flags != baseline_flags {
+                delta.triangle_flags.push((index as u32, *flags));
+            }
+        }
+
+        for (index, (cost, baseline_cost)) in self
+            .triangle_costs
+            .iter()
+            .zip(baseline.triangle_costs.iter())
+            .enumerate()
+        {
+            if cost != baseline_cost {
+                delta.triangle_costs.push((index as u32, *cost));
+            }
+        }
+
+        // Every one-way edge of either navmesh is a candidate, the pairs are sorted to keep the
+        // delta deterministic.
+        let mut edges = self
+            .one_way_edges
+            .iter()
+            .chain(baseline.one_way_edges.iter())
+            .map(|[a, b]| [*a.min(b), *a.max(b)])
+            .collect::<Vec<_>>();
+        edges.sort_unstable();
+        edges.dedup();
+        for [a, b] in edges {
+            let direction = self.edge_direction(a as usize, b as usize);
+            if direction != baseline.edge_direction(a as usize, b as usize) {
+                delta.edge_directions.push(([a, b], direction));
+            }
+        }
+
+        for (handle, _) in baseline.obstacles.pair_iter() {
+            if !self.obstacles.is_valid_handle(handle) {
+                delta.removed_obstacles.push(handle);
+            }
+        }
+        for (handle, obstacle) in self.obstacles.pair_iter() {
+            if baseline.obstacles.try_borrow(handle) != Some(obstacle) {
+                delta.obstacles.push((handle, obstacle.clone()));
+            }
+        }
+
+        delta
+    }
+
+    /// Applies a set of changes, computed by [`Self::compute_delta`], to the navmesh. Triangles
+    /// and edges, that do not exist in the navmesh, are ignored. Added obstacles get the same
+    /// handles as in the source navmesh. Navmesh agents recalculate their paths automatically, if
+    /// the changes affect their paths.
+    ///
+    /// Returns an error, if some obstacles could not be added, because their handles are occupied
+    /// by other obstacles of the navmesh. The rest of the delta is applied anyway. The navmesh is
+    /// out of sync with the source navmesh in this case, and it should be re-sent in full.
+    pub fn apply_delta(&mut self, delta: &NavmeshDelta) -> Result<(), NavmeshDeltaError> {
+        for (index, flags) in delta.triangle_flags.iter() {
+            self.set_triangle_flags(*index as usize, *flags);
+        }
+        for (index, cost) in delta.triangle_costs.iter() {
+            self.set_triangle_cost(*index as usize, *cost);
+        }
+        for ([a, b], direction) in delta.edge_directions.iter() {
+            self.set_edge_direction(*a as usize, *b as usize, *direction);
+        }
+
+        for handle in delta.removed_obstacles.iter() {
+            self.remove_obstacle(*handle);
+        }
+        let mut occupied = Vec::new();
+        for (handle, obstacle) in delta.obstacles.iter() {
+            match self.obstacles.try_borrow(*handle) {
+                Some(existing) if existing.shape == obstacle.shape => {
+                    self.set_obstacle_enabled(*handle, obstacle.enabled);
+                }
+                existing => {
+                    if existing.is_some() {
+                        self.remove_obstacle(*handle);
+                    }
+                    match self.obstacles.spawn_at_handle(*handle, obstacle.clone()) {
+                        Ok(_) => {
+                            if obstacle.enabled {
+                                self.update_blocked_triangles(&obstacle.shape, true);
+                            }
+                        }
+                        Err(_) => occupied.push(*handle),
+                    }
+                }
+            }
+        }
+
+        if occupied.is_empty() {
+            Ok(())
+        } else {
+            Err(NavmeshDeltaError::ObstacleHandlesOccupied(occupied))
+        }
+    }
+}
@@ -0,0 +1,364 @@
+// Copyright (c) 2019-present Dmitry Stepanov and Fyrox Engine contributors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Flow field is a set of directions toward a single goal for every triangle of a navmesh. See
+//! [`NavmeshFlowField`] docs for more info.
+
+use crate::{core::algebra::Vector3, utils::navmesh::Navmesh};
+use std::{cmp::Ordering, collections::BinaryHeap};
+
+#[derive(Copy, Clone, Debug)]
+struct FlowFieldCell {
+    // Index of the next triangle on the way to the goal.
+    next: Option<u32>,
+    // Total path cost from the triangle to the goal.
+    cost: f32,
+    // A segment, that must be crossed to get to the next triangle. It is either a shared edge
+    // or a degenerate segment at the entrance of an off-mesh link.
+    portal: [Vector3<f32>; 2],
+}
+
+impl Default for FlowFieldCell {
+    fn default() -> Self {
+        Self {
+            next: None,
+            cost: f32::MAX,
+            portal: Default::default(),
+        }
+    }
+}
+
+#[derive(Copy, Clone)]
+struct QueueEntry {
+    triangle: usize,
+    cost: f32,
+}
+
+impl Ord for QueueEntry {
+    fn cmp(&self, other: &Self) -> Ordering {
+        self.cost.total_cmp(&other.cost).reverse()
+    }
+}
+
+impl PartialOrd for QueueEntry {
+    fn partial_cmp(&self, other: &Self) -> Option<Ordering> {
+        Some(self.cmp(other))
+    }
+}
+
+impl PartialEq for QueueEntry {
+    fn eq(&self, other: &Self) -> bool {
+        self.cost == other.cost
+    }
+}
+
+impl Eq for QueueEntry {}
+
+/// Flow field stores a direction toward a single goal for every triangle of a navmesh. It is
+/// built by a single Dijkstra search from the goal (see [`Navmesh::build_flow_field`]), after that
+/// any number of units could query the direction to move in at their position without building
+/// their own paths. It is much faster than per-agent path finding, when a lot of units are moving
+/// to the same destination (creeps in a tower defense game, units moving to a rally point in a
+/// strategy game, etc.).
+///
+/// The path costs are the same as the ones used by [`Navmesh::build_path`], off-mesh links,
+/// one-way edges and runtime obstacles are respected. The flow field is a snapshot, it must be
+/// rebuilt when the goal or the navmesh changes, see [`Self::update`].
+///
+/// ## Example
+///
+/// ```rust
+/// # use fyrox_impl::{
+/// #     core::algebra::Vector3,
+/// #     utils::navmesh::{flow_field::NavmeshFlowField, Navmesh},
+/// # };
+/// fn move_units(
+///     flow_field: &mut NavmeshFlowField,
+///     navmesh: &Navmesh,
+///     goal: Vector3<f32>,
+///     units: &mut [Vector3<f32>],
+///     speed: f32,
+///     dt: f32,
+/// ) {
+///     // The field is rebuilt only if the goal or the navmesh has changed.
+///     flow_field.update(navmesh, goal);
+///
+///     for position in units.iter_mut() {
+///         if let Some(direction) = flow_field.direction_at(navmesh, *position) {
+///             *position += direction.scale(speed * dt);
+///         }
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct NavmeshFlowField {
+    goal: Vector3<f32>,
+    goal_triangle: Option<usize>,
+    revision: u64,
+    cells: Vec<FlowFieldCell>,
+}
+
+impl NavmeshFlowField {
+    pub(super) fn new(navmesh: &Navmesh, goal: Vector3<f32>) -> Self {
+        let mut flow_field = Self {
+            goal,
+            goal_triangle: navmesh.query_closest(goal).map(|(_, triangle)| triangle),
+            revision: navmesh.revision(),
+            cells: vec![Default::default(); navmesh.triangles.len()],
+        };
+
+        let Some(goal_triangle) = flow_field.goal_triangle else {
+            return flow_field;
+        };
+
+        // The search goes from the goal backwards, so the edges of the graph must be reversed.
+        let vertices = &navmesh.graph.vertices;
+        let mut incoming = vec![Vec::new(); vertices.len()];
+        for (index, vertex) in vertices.iter().enumerate() {
+            for &neighbour in vertex.neighbours.iter() {
+                if let Some(incoming) = incoming.get_mut(neighbour as usize) {
+                    incoming.push(index);
+                }
+            }
+        }
+
+        let cells = &mut flow_field.cells;
+        let mut visited = vec![false; cells.len()];
+        let mut queue = BinaryHeap::new();
+        cells[goal_triangle].cost = 0.0;
+        queue.push(QueueEntry {
+            triangle: goal_triangle,
+            cost: 0.0,
+        });
+
+        while let Some(entry) = queue.pop() {
+            if visited[entry.triangle] {
+                continue;
+            }
+            visited[entry.triangle] = true;
+
+            let vertex = &vertices[entry.triangle];
+            for &source in incoming[entry.triangle].iter() {
+                if visited[source] {
+                    continue;
+                }
+
+                let source_vertex = &vertices[source];
+                let Some(cost) = navmesh.traversal_cost(
+                    source,
+                    entry.triangle,
+                    source_vertex.position.metric_distance(&vertex.position) * vertex.g_penalty,
+                    0.0,
+                    &Default::default(),
+                ) else {
+                    continue;
+                };
+
+                let cost = entry.cost + cost;
+                if cost < cells[source].cost {
+                    let portal = match navmesh.offmesh_link_between(source, entry.triangle) {
+                        Some(traversal) => [traversal.enter; 2],
+                        None => match navmesh.portal_between(source, entry.triangle) {
+                            Some(portal) => [
+                                navmesh.vertices[portal.left],
+                                navmesh.vertices[portal.right],
+                            ],
+                            None => [vertex.position; 2],
+                        },
+                    };
+                    cells[source] = FlowFieldCell {
+                        next: Some(entry.triangle as u32),
+                        cost,
+                        portal,
+                    };
+                    queue.push(QueueEntry {
+                        triangle: source,
+                        cost,
+                    });
+                }
+            }
+        }
+
+        flow_field
+    }
+
+    /// Returns the goal of the flow field.
+    pub fn goal(&self) -> Vector3<f32> {
+        self.goal
+    }
+
+    /// Returns an index of the triangle, that contains the goal of the flow field, or `None` if
+    /// the navmesh was empty.
+    pub fn goal_triangle(&self) -> Option<usize> {
+        self.goal_triangle
+    }
+
+    /// Checks whether the flow field was built for another state of the given navmesh (see
+    /// [`Navmesh::revision`]) and must be rebuilt.
+    pub fn is_outdated(&self, navmesh: &Navmesh) -> bool {
+        self.revision != navmesh.revision()
+    }
+
+    /// Rebuilds the flow field, if the navmesh has changed since the flow field was built, or if
+    /// the goal has moved to another triangle. If the goal has moved within its triangle, only the
+    /// goal is updated, since the directions outside of the goal triangle do not depend on the
+    /// exact position of the goal. Returns `true` if the flow field was rebuilt.
+    pub fn update(&mut self, navmesh: &Navmesh, goal: Vector3<f32>) -> bool {
+        if !self.is_outdated(navmesh)
+            && self.goal_triangle.is_some()
+            && navmesh.query_closest(goal).map(|(_, triangle)| triangle) == self.goal_triangle
+        {
+            self.goal = goal;
+            return false;
+        }
+
+        *self = navmesh.build_flow_field(goal);
+        true
+    }
+
+    /// Returns the total path cost from the triangle with the given index to the goal, or `None`
+    /// if the goal is not reachable from the triangle. See [`Navmesh::reachable_within`] for more
+    /// info about path costs.
+    pub fn cost(&self, triangle: usize) -> Option<f32> {
+        self.cells
+            .get(triangle)
+            .map(|cell| cell.cost)
+            .filter(|cost| *cost != f32::MAX)
+    }
+
+    /// Returns an index of the next triangle on the way from the triangle with the given index to
+    /// the goal. Returns `None` for the goal triangle and for the triangles, from which the goal is
+    /// not reachable.
+    pub fn next_triangle(&self, triangle: usize) -> Option<usize> {
+        self.cells
+            .get(triangle)
+            .and_then(|cell| cell.next)
+            .map(|next| next as usize)
+    }
+
+    /// Returns a normalized direction, in which a unit at the given point inside the triangle with
+    /// the given index should move to get to the goal. Inside the goal triangle, the direction
+    /// points straight at the goal. In the rest of the triangles, the direction points at the
+    /// closest point of the edge, that leads to the next triangle on the way to the goal (or at
+    /// the entrance of an off-mesh link). Returns `None` if the goal is not reachable from the
+    /// triangle or the point is already at the goal.
+    pub fn direction_in_triangle(
+        &self,
+        triangle: usize,
+        point: Vector3<f32>,
+    ) -> Option<Vector3<f32>> {
+        let mut next = None;
+        let target = if Some(triangle) == self.goal_triangle {
+            self.goal
+        } else {
+            let cell = self.cells.get(triangle)?;
+            next = Some(cell.next? as usize);
+            let [a, b] = cell.portal;
+            let edge = b - a;
+            let length = edge.norm_squared();
+            if length <= f32::EPSILON {
+                a
+            } else {
+                // Aim slightly inside the edge to not slide along the walls.
+                let t = ((point - a).dot(&edge) / length).clamp(0.1, 0.9);
+                a + edge.scale(t)
+            }
+        };
+        // If the point is right at the portal, continue with the next triangle.
+        (target - point)
+            .try_normalize(f32::EPSILON)
+            .or_else(|| next.and_then(|next| self.direction_in_triangle(next, point)))
+    }
+
+    /// Returns a normalized direction, in which a unit at the given point should move to get to
+    /// the goal. The point is attached to the closest triangle of the given navmesh, which must be
+    /// the same navmesh, that was used to build the flow field. See
+    /// [`Self::direction_in_triangle`] for more info.
+    pub fn direction_at(&self, navmesh: &Navmesh, point: Vector3<f32>) -> Option<Vector3<f32>> {
+        let (_, triangle) = navmesh.query_closest(point)?;
+        self.direction_in_triangle(triangle, point)
+    }
+
+    /// Returns the total path cost from the given point to the goal. See [`Self::cost`] and
+    /// [`Self::direction_at`] for more info.
+    pub fn cost_at(&self, navmesh: &Navmesh, point: Vector3<f32>) -> Option<f32> {
+        let (_, triangle) = navmesh.query_closest(point)?;
+        self.cost(triangle)
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::algebra::Vector3,
+        utils::navmesh::{test::make_grid_navmesh, NavmeshObstacle},
+    };
+
+    #[test]
+    fn test_flow_field() {
+        let mut navmesh = make_grid_navmesh(10);
+        // A wall, that must be walked around.
+        navmesh.add_obstacle(NavmeshObstacle::Box {
+            center: Vector3::new(5.0, 0.0, 3.5),
+            half_extents: Vector3::new(0.4, 1.0, 3.4),
+        });
+
+        let goal = Vector3::new(8.3, 0.0, 1.3);
+        let mut flow_field = navmesh.build_flow_field(goal);
+        let goal_triangle = flow_field.goal_triangle().unwrap();
+        assert_eq!(flow_field.cost(goal_triangle), Some(0.0));
+        assert_eq!(flow_field.next_triangle(goal_triangle), None);
+        assert!(
+            flow_field
+                .cost_at(&navmesh, Vector3::new(1.5, 0.0, 1.5))
+                .unwrap()
+                > 0.0
+        );
+
+        for start in [
+            Vector3::new(1.5, 0.0, 1.5),
+            Vector3::new(0.5, 0.0, 9.5),
+            Vector3::new(9.5, 0.0, 9.5),
+        ] {
+            let mut position = start;
+            for _ in 0..800 {
+                let Some(direction) = flow_field.direction_at(&navmesh, position) else {
+                    break;
+                };
+                position += direction.scale(0.05);
+                let (_, triangle) = navmesh.query_closest(position).unwrap();
+                assert!(!navmesh.is_triangle_blocked(triangle));
+            }
+            assert!(position.metric_distance(&goal) <= 0.05);
+        }
+
+        // Moving the goal inside its triangle does not require rebuilding.
+        assert!(!flow_field.update(&navmesh, Vector3::new(8.4, 0.0, 1.2)));
+        assert_eq!(flow_field.goal(), Vector3::new(8.4, 0.0, 1.2));
+        assert!(flow_field.update(&navmesh, Vector3::new(1.5, 0.0, 1.5)));
+        assert_ne!(flow_field.goal_triangle(), Some(goal_triangle));
+
+        // Changes of the navmesh make the flow field outdated.
+        navmesh.set_triangle_cost(0, 2.0);
+        assert!(flow_field.is_outdated(&navmesh));
+        assert!(flow_field.update(&navmesh, Vector3::new(1.5, 0.0, 1.5)));
+        assert!(!flow_field.is_outdated(&navmesh));
+    }
+}
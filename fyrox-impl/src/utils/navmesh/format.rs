@@ -0,0 +1,357 @@
+// Copyright (c) 2019-present Dmitry Stepanov and Fyrox Engine contributors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Binary and Wavefront OBJ formats of navmeshes, see [`Navmesh::save_binary`] and
+//! [`Navmesh::write_obj`].
+
+use crate::{
+    core::{algebra::Vector3, math::TriangleDefinition, pool::Handle},
+    utils::{
+        astar::VertexData,
+        navmesh::{up_rotation, Navmesh, OffMeshLink, Vertex},
+    },
+};
+use std::{
+    fs::File,
+    io::{BufWriter, Read, Write},
+    path::Path,
+};
+
+/// Signature of the binary navmesh format, see [`Navmesh::save_binary`].
+const NAVMESH_BINARY_MAGIC: [u8; 4] = *b"FNAV";
+
+/// Version of the binary navmesh format, see [`Navmesh::save_binary`].
+const NAVMESH_BINARY_VERSION: u32 = 5;
+
+pub(super) fn invalid_data(message: &str) -> std::io::Error {
+    std::io::Error::new(std::io::ErrorKind::InvalidData, message)
+}
+
+pub(super) fn read_u8<R: Read>(reader: &mut R) -> std::io::Result<u8> {
+    let mut bytes = [0; 1];
+    reader.read_exact(&mut bytes)?;
+    Ok(bytes[0])
+}
+
+pub(super) fn read_u32<R: Read>(reader: &mut R) -> std::io::Result<u32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(u32::from_le_bytes(bytes))
+}
+
+pub(super) fn read_f32<R: Read>(reader: &mut R) -> std::io::Result<f32> {
+    let mut bytes = [0; 4];
+    reader.read_exact(&mut bytes)?;
+    Ok(f32::from_le_bytes(bytes))
+}
+
+pub(super) fn read_vector3<R: Read>(reader: &mut R) -> std::io::Result<Vector3<f32>> {
+    Ok(Vector3::new(
+        read_f32(reader)?,
+        read_f32(reader)?,
+        read_f32(reader)?,
+    ))
+}
+
+pub(super) fn read_handle<R: Read, T>(reader: &mut R) -> std::io::Result<Handle<T>> {
+    Ok(Handle::new(read_u32(reader)?, read_u32(reader)?))
+}
+
+pub(super) fn write_handle<W: Write, T>(writer: &mut W, handle: Handle<T>) -> std::io::Result<()> {
+    writer.write_all(&handle.index().to_le_bytes())?;
+    writer.write_all(&handle.generation().to_le_bytes())
+}
+
+pub(super) fn write_vector3<W: Write>(writer: &mut W, vector: Vector3<f32>) -> std::io::Result<()> {
+    for component in vector.iter() {
+        writer.write_all(&component.to_le_bytes())?;
+    }
+    Ok(())
+}
+
+impl Navmesh {
+    /// Writes the navmesh in Wavefront OBJ format to the given writer. Off-mesh links are written
+    /// as line elements. This method is intended for debugging purposes, the output could be
+    /// inspected in any 3D modelling software.
+    pub fn write_obj<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writeln!(writer, "# Fyrox navmesh")?;
+        writeln!(writer, "o navmesh")?;
+        for vertex in self.vertices.iter() {
+            writeln!(writer, "v {} {} {}", vertex.x, vertex.y, vertex.z)?;
+        }
+        for triangle in self.triangles.iter() {
+            // OBJ indices are one-based.
+            writeln!(
+                writer,
+                "f {} {} {}",
+                triangle[0] + 1,
+                triangle[1] + 1,
+                triangle[2] + 1
+            )?;
+        }
+
+        if self.offmesh_links.alive_count() > 0 {
+            writeln!(writer, "o offmesh_links")?;
+            let mut index = self.vertices.len();
+            for (_, link) in self.offmesh_links.pair_iter() {
+                for point in [link.from, link.to] {
+                    writeln!(writer, "v {} {} {}", point.x, point.y, point.z)?;
+                }
+                writeln!(writer, "l {} {}", index + 1, index + 2)?;
+                index += 2;
+            }
+        }
+
+        Ok(())
+    }
+
+    /// Saves the navmesh in Wavefront OBJ format to a file at the given path. See
+    /// [`Self::write_obj`] for more info.
+    pub fn save_to_obj(&self, path: &Path) -> std::io::Result<()> {
+        let mut writer = BufWriter::new(File::create(path)?);
+        self.write_obj(&mut writer)?;
+        writer.flush()
+    }
+
+    /// Writes the navmesh in a compact versioned binary format to the given writer. The format
+    /// contains vertices, triangles, their costs, flags and layers, off-mesh links, one-way edges,
+    /// the adjacency information, the up axis (see [`Self::set_up_axis`]) and the maximum number of
+    /// vertices of the convex polygons (see [`Self::build_convex_polys`]), so the navmesh could be
+    /// loaded quickly using [`Self::load_binary`]. Runtime obstacles are not saved. Unlike
+    /// [`Visit`](crate::core::visitor::Visit)-based serialization, this format is intended for standalone
+    /// navmesh assets, that are baked in advance.
+    pub fn save_binary<W: Write>(&self, writer: &mut W) -> std::io::Result<()> {
+        writer.write_all(&NAVMESH_BINARY_MAGIC)?;
+        writer.write_all(&NAVMESH_BINARY_VERSION.to_le_bytes())?;
+        writer.write_all(&self.tile_size.to_le_bytes())?;
+
+        writer.write_all(&(self.vertices.len() as u32).to_le_bytes())?;
+        for vertex in self.vertices.iter() {
+            write_vector3(writer, *vertex)?;
+        }
+
+        writer.write_all(&(self.triangles.len() as u32).to_le_bytes())?;
+        for triangle in self.triangles.iter() {
+            for index in triangle.0 {
+                writer.write_all(&index.to_le_bytes())?;
+            }
+        }
+        for cost in self.triangle_costs.iter() {
+            writer.write_all(&cost.to_le_bytes())?;
+        }
+        for flags in self.triangle_flags.iter() {
+            writer.write_all(&flags.to_le_bytes())?;
+        }
+
+        writer.write_all(&self.offmesh_links.alive_count().to_le_bytes())?;
+        for link in self.offmesh_links.iter() {
+            write_vector3(writer, link.from)?;
+            write_vector3(writer, link.to)?;
+            writer.write_all(&[link.bidirectional as u8])?;
+            writer.write_all(&link.cost.to_le_bytes())?;
+            // `u32::MAX` marks a link end, that is not attached to any triangle.
+            for triangle in [link.from_triangle, link.to_triangle] {
+                let index = triangle.map_or(u32::MAX, |index| index as u32);
+                writer.write_all(&index.to_le_bytes())?;
+            }
+        }
+
+        // The adjacency is optional, the readers must rebuild it if it is missing.
+        writer.write_all(&[1])?;
+        for vertex in self.graph.vertices.iter() {
+            writer.write_all(&(vertex.neighbours.len() as u32).to_le_bytes())?;
+            for neighbour in vertex.neighbours.iter() {
+                writer.write_all(&neighbour.to_le_bytes())?;
+            }
+        }
+
+        writer.write_all(&(self.one_way_edges.len() as u32).to_le_bytes())?;
+        for edge in self.one_way_edges.iter() {
+            for triangle in edge {
+                writer.write_all(&triangle.to_le_bytes())?;
+            }
+        }
+
+        for layer in self.triangle_layers.iter() {
+            writer.write_all(&layer.to_le_bytes())?;
+        }
+
+        write_vector3(writer, self.up_axis)?;
+        writer.write_all(&(self.max_polygon_vertices as u32).to_le_bytes())?;
+
+        for link in self.offmesh_links.iter() {
+            write_vector3(writer, link.span)?;
+        }
+
+        Ok(())
+    }
+
+    /// Reads a navmesh in the binary format, written by [`Self::save_binary`]. The adjacency
+    /// information is taken from the data as is, if it is present, otherwise it is rebuilt.
+    /// Off-mesh links get new handles. Returns [`std::io::ErrorKind::InvalidData`] error if the
+    /// data is not a navmesh, has unsupported version or is malformed.
+    pub fn load_binary<R: Read>(reader: &mut R) -> std::io::Result<Self> {
+        let mut magic = [0; 4];
+        reader.read_exact(&mut magic)?;
+        if magic != NAVMESH_BINARY_MAGIC {
+            return Err(invalid_data("not a navmesh"));
+        }
+        let version = read_u32(reader)?;
+        if version == 0 || version > NAVMESH_BINARY_VERSION {
+            return Err(invalid_data("unsupported navmesh version"));
+        }
+
+        let mut navmesh = Navmesh {
+            tile_size: read_f32(reader)?,
+            ..Default::default()
+        };
+
+        let vertex_count = read_u32(reader)?;
+        for _ in 0..vertex_count {
+            navmesh.vertices.push(read_vector3(reader)?);
+        }
+
+        let triangle_count = read_u32(reader)? as usize;
+        for _ in 0..triangle_count {
+            let mut triangle = [0; 3];
+            for index in triangle.iter_mut() {
+                *index = read_u32(reader)?;
+                if *index >= vertex_count {
+                    return Err(invalid_data("invalid vertex index"));
+                }
+            }
+            navmesh.triangles.push(TriangleDefinition(triangle));
+        }
+        for _ in 0..triangle_count {
+            navmesh.triangle_costs.push(read_f32(reader)?);
+        }
+        for _ in 0..triangle_count {
+            navmesh.triangle_flags.push(read_u32(reader)?);
+        }
+
+        let read_triangle = |reader: &mut R| -> std::io::Result<Option<usize>> {
+            match read_u32(reader)? {
+                u32::MAX => Ok(None),
+                index if (index as usize) < triangle_count => Ok(Some(index as usize)),
+                _ => Err(invalid_data("invalid triangle index")),
+            }
+        };
+        let link_count = read_u32(reader)?;
+        for _ in 0..link_count {
+            let _ = navmesh.offmesh_links.spawn(OffMeshLink {
+                from: read_vector3(reader)?,
+                to: read_vector3(reader)?,
+                bidirectional: read_u8(reader)? != 0,
+                cost: read_f32(reader)?,
+                span: Default::default(),
+                from_triangle: read_triangle(reader)?,
+                to_triangle: read_triangle(reader)?,
+            });
+        }
+
+        navmesh.rebuild_octree();
+
+        if read_u8(reader)? != 0 {
+            let mut vertices = Vec::with_capacity(triangle_count);
+            for (triangle_index, triangle) in navmesh.triangles.iter().enumerate() {
+                let center = (navmesh.vertices[triangle[0] as usize]
+                    + navmesh.vertices[triangle[1] as usize]
+                    + navmesh.vertices[triangle[2] as usize])
+                    .scale(1.0 / 3.0);
+                let mut data = VertexData::new(center);
+                data.g_penalty = navmesh.triangle_costs[triangle_index];
+                for _ in 0..read_u32(reader)? {
+                    let neighbour = read_u32(reader)?;
+                    if neighbour as usize >= triangle_count {
+                        return Err(invalid_data("invalid triangle index"));
+                    }
+                    data.neighbours.push(neighbour);
+                }
+                vertices.push(Vertex {
+                    triangle_index,
+                    data,
+                });
+            }
+            navmesh.graph.set_vertices(vertices);
+
+            let handles = navmesh
+                .offmesh_links
+                .pair_iter()
+                .map(|(handle, _)| handle)
+                .collect::<Vec<_>>();
+            for handle in handles {
+                navmesh.connect_offmesh_link(handle);
+            }
+        } else {
+            navmesh.rebuild_graph();
+        }
+
+        // One-way edges were added in the second version.
+        if version >= 2 {
+            for _ in 0..read_u32(reader)? {
+                let edge = [read_u32(reader)?, read_u32(reader)?];
+                if navmesh
+                    .portal_between(edge[0] as usize, edge[1] as usize)
+                    .is_none()
+                {
+                    return Err(invalid_data("invalid one-way edge"));
+                }
+                navmesh.one_way_edges.insert(edge);
+            }
+        }
+
+        // Layers were added in the third version.
+        if version >= 3 {
+            for _ in 0..triangle_count {
+                let layer = read_u32(reader)?;
+                if layer >= u32::BITS {
+                    return Err(invalid_data("invalid triangle layer"));
+                }
+                navmesh.triangle_layers.push(layer);
+            }
+        } else {
+            navmesh.triangle_layers = vec![0; triangle_count];
+        }
+
+        // Up axis and convex polygons were added in the fourth version.
+        if version >= 4 {
+            navmesh.up_axis = read_vector3(reader)?
+                .try_normalize(f32::EPSILON)
+                .ok_or_else(|| invalid_data("invalid up axis"))?;
+            navmesh.up_rotation = up_rotation(navmesh.up_axis);
+            navmesh.max_polygon_vertices = match read_u32(reader)? {
+                count @ (0 | 3..) => count as usize,
+                _ => return Err(invalid_data("invalid number of polygon vertices")),
+            };
+        }
+
+        // Spans of off-mesh links were added in the fifth version.
+        if version >= 5 {
+            for link in navmesh.offmesh_links.iter_mut() {
+                link.span = read_vector3(reader)?;
+            }
+        }
+
+        navmesh.rebuild_obstacles();
+        navmesh.rebuild_polygons();
+
+        Ok(navmesh)
+    }
+}
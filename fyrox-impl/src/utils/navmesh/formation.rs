@@ -0,0 +1,359 @@
+// Copyright (c) 2019-present Dmitry Stepanov and Fyrox Engine contributors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Formation is a group of navmesh agents, that move together keeping a certain shape. See
+//! [`NavmeshAgentFormation`] docs for more info.
+
+use crate::{
+    core::{
+        algebra::{Vector2, Vector3},
+        pool::{Handle, Pool},
+    },
+    utils::navmesh::{Navmesh, NavmeshAgent},
+};
+
+/// Shape of a formation. It defines the placement of the members relative to the leader of the
+/// formation.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum FormationShape {
+    /// The members stand side by side, perpendicular to the direction of motion.
+    #[default]
+    Line,
+    /// The members form a "V" shape, the first member is at the apex and the rest of the
+    /// members alternate between the left and the right wings.
+    Wedge,
+    /// The members follow each other in a single column.
+    Column,
+}
+
+impl FormationShape {
+    /// Returns an offset of the slot with the given index relative to the leader of the formation.
+    /// `x` component of the offset is directed to the right of the leader, `y` component - along
+    /// the direction of motion.
+    pub fn slot_offset(self, index: usize, count: usize, spacing: f32) -> Vector2<f32> {
+        match self {
+            FormationShape::Line => Vector2::new(
+                (index as f32 - (count.saturating_sub(1)) as f32 * 0.5) * spacing,
+                0.0,
+            ),
+            FormationShape::Wedge => {
+                let rank = ((index + 1) / 2) as f32;
+                let side = if index % 2 == 1 { -1.0 } else { 1.0 };
+                Vector2::new(side * rank * spacing, -rank * spacing)
+            }
+            FormationShape::Column => Vector2::new(0.0, -(index as f32) * spacing),
+        }
+    }
+}
+
+/// A member of a formation. It wraps a navmesh agent, which target is set by the formation.
+#[derive(Clone, Debug)]
+pub struct FormationMember {
+    agent: NavmeshAgent,
+    slot_position: Vector3<f32>,
+}
+
+impl FormationMember {
+    /// Returns a reference to the inner navmesh agent.
+    pub fn agent(&self) -> &NavmeshAgent {
+        &self.agent
+    }
+
+    /// Returns a reference to the inner navmesh agent. Use it to change the speed, the radius,
+    /// etc. of the agent. Keep in mind, that the target of the agent is overwritten by the
+    /// formation on every update.
+    pub fn agent_mut(&mut self) -> &mut NavmeshAgent {
+        &mut self.agent
+    }
+
+    /// Returns the position of the slot of the member, calculated at the last update of the
+    /// formation. The position always lies on the navmesh.
+    pub fn slot_position(&self) -> Vector3<f32> {
+        self.slot_position
+    }
+}
+
+/// A handle of a member of a formation.
+pub type FormationMemberHandle = Handle<FormationMember>;
+
+/// Formation is a group of navmesh agents, that move together keeping a certain shape (see
+/// [`FormationShape`]). Instead of calculating a path for every member, the formation calculates
+/// a single path for a virtual leader, and the members follow their slots around the leader.
+/// Slots, that are off the navmesh, are snapped to the closest point of the navmesh.
+///
+/// ## Example
+///
+/// ```rust
+/// # use fyrox_impl::{
+/// #     core::algebra::Vector3,
+/// #     utils::navmesh::{
+/// #         formation::{FormationShape, NavmeshAgentFormation},
+/// #         Navmesh, NavmeshAgentBuilder,
+/// #     },
+/// # };
+/// fn move_squad(navmesh: &Navmesh, positions: &[Vector3<f32>], dt: f32) {
+///     let mut formation = NavmeshAgentFormation::new();
+///     formation.set_shape(FormationShape::Wedge);
+///     for position in positions {
+///         formation.add_member(NavmeshAgentBuilder::new().with_position(*position).build());
+///     }
+///
+///     formation.set_target(Vector3::new(10.0, 0.0, 10.0));
+///     formation.update(dt, navmesh);
+///
+///     for (_, member) in formation.pair_iter() {
+///         // Use this position as target point of your game character.
+///         println!("{}", member.agent().position());
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug)]
+pub struct NavmeshAgentFormation {
+    leader: NavmeshAgent,
+    members: Pool<FormationMember>,
+    shape: FormationShape,
+    spacing: f32,
+    heading: Vector3<f32>,
+}
+
+impl Default for NavmeshAgentFormation {
+    fn default() -> Self {
+        Self::new()
+    }
+}
+
+impl NavmeshAgentFormation {
+    /// Creates a new empty formation.
+    pub fn new() -> Self {
+        Self {
+            leader: NavmeshAgent::new(),
+            members: Default::default(),
+            shape: Default::default(),
+            spacing: 1.0,
+            heading: Vector3::z(),
+        }
+    }
+
+    /// Adds a new member to the formation and returns its handle. Members take the slots in the
+    /// order of addition.
+    pub fn add_member(&mut self, agent: NavmeshAgent) -> FormationMemberHandle {
+        self.members.spawn(FormationMember {
+            slot_position: agent.position(),
+            agent,
+        })
+    }
+
+    /// Removes a member from the formation and returns its agent, if the handle was valid.
+    pub fn remove_member(&mut self, handle: FormationMemberHandle) -> Option<NavmeshAgent> {
+        self.members.try_free(handle).map(|member| member.agent)
+    }
+
+    /// Returns a reference to a member of the formation.
+    pub fn member(&self, handle: FormationMemberHandle) -> Option<&FormationMember> {
+        self.members.try_borrow(handle)
+    }
+
+    /// Returns a reference to a member of the formation.
+    pub fn member_mut(&mut self, handle: FormationMemberHandle) -> Option<&mut FormationMember> {
+        self.members.try_borrow_mut(handle)
+    }
+
+    /// Returns an iterator over all members of the formation.
+    pub fn pair_iter(&self) -> impl Iterator<Item = (FormationMemberHandle, &FormationMember)> {
+        self.members.pair_iter()
+    }
+
+    /// Returns an iterator over all members of the formation.
+    pub fn pair_iter_mut(
+        &mut self,
+    ) -> impl Iterator<Item = (FormationMemberHandle, &mut FormationMember)> {
+        self.members.pair_iter_mut()
+    }
+
+    /// Returns the total number of members in the formation.
+    pub fn member_count(&self) -> u32 {
+        self.members.alive_count()
+    }
+
+    /// Returns a reference to the virtual leader of the formation. Its path is the path of the
+    /// whole formation.
+    pub fn leader(&self) -> &NavmeshAgent {
+        &self.leader
+    }
+
+    /// Sets a new shape of the formation.
+    pub fn set_shape(&mut self, shape: FormationShape) {
+        self.shape = shape;
+    }
+
+    /// Returns current shape of the formation.
+    pub fn shape(&self) -> FormationShape {
+        self.shape
+    }
+
+    /// Sets a new distance (in meters) between adjacent slots of the formation. Default is 1
+    /// meter.
+    pub fn set_spacing(&mut self, spacing: f32) {
+        self.spacing = spacing;
+    }
+
+    /// Returns current distance (in meters) between adjacent slots of the formation.
+    pub fn spacing(&self) -> f32 {
+        self.spacing
+    }
+
+    /// Sets a new target for the whole formation. The leader of the formation starts its path
+    /// from the center of the formation.
+    pub fn set_target(&mut self, target: Vector3<f32>) {
+        let count = self.members.alive_count();
+        if count > 0 {
+            let center = self
+                .members
+                .iter()
+                .fold(Vector3::default(), |sum, member| {
+                    sum + member.agent.position()
+                })
+                .scale(1.0 / count as f32);
+            self.leader.set_position(center);
+        }
+        self.leader.set_target(target);
+    }
+
+    /// Returns current target of the formation.
+    pub fn target(&self) -> Vector3<f32> {
+        self.leader.target()
+    }
+
+    /// Performs a single update tick of the formation: moves the leader along its path, assigns
+    /// new targets to the members according to the shape of the formation and updates the members.
+    pub fn update(&mut self, dt: f32, navmesh: &Navmesh) {
+        // The leader must not outrun the slowest member.
+        let speed = self
+            .members
+            .iter()
+            .map(|member| member.agent.speed())
+            .reduce(f32::min);
+        if let Some(speed) = speed {
+            self.leader.set_speed(speed);
+        }
+
+        let _ = self.leader.update(dt, navmesh);
+
+        let leader_position = self.leader.position();
+        if let Some(heading) = self.leader.steering_target().and_then(|steering_target| {
+            Vector3::new(
+                steering_target.x - leader_position.x,
+                0.0,
+                steering_target.z - leader_position.z,
+            )
+            .try_normalize(f32::EPSILON)
+        }) {
+            self.heading = heading;
+        }
+        let right = Vector3::new(self.heading.z, 0.0, -self.heading.x);
+
+        let count = self.members.alive_count() as usize;
+        for (index, member) in self.members.iter_mut().enumerate() {
+            let offset = self.shape.slot_offset(index, count, self.spacing);
+            let slot = leader_position + right.scale(offset.x) + self.heading.scale(offset.y);
+            member.slot_position = navmesh
+                .query_closest(slot)
+                .map(|(point, _)| point)
+                .unwrap_or(slot);
+            member.agent.set_target(member.slot_position);
+            let _ = member.agent.update(dt, navmesh);
+        }
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::algebra::{Vector2, Vector3},
+        utils::navmesh::{
+            formation::{FormationShape, NavmeshAgentFormation},
+            test::make_grid_navmesh,
+            NavmeshAgentBuilder,
+        },
+    };
+
+    #[test]
+    fn test_slot_offsets() {
+        let line = FormationShape::Line;
+        assert_eq!(line.slot_offset(0, 3, 2.0), Vector2::new(-2.0, 0.0));
+        assert_eq!(line.slot_offset(2, 3, 2.0), Vector2::new(2.0, 0.0));
+
+        let wedge = FormationShape::Wedge;
+        assert_eq!(wedge.slot_offset(0, 3, 1.0), Vector2::new(0.0, 0.0));
+        assert_eq!(wedge.slot_offset(1, 3, 1.0), Vector2::new(-1.0, -1.0));
+        assert_eq!(wedge.slot_offset(2, 3, 1.0), Vector2::new(1.0, -1.0));
+
+        let column = FormationShape::Column;
+        assert_eq!(column.slot_offset(2, 3, 1.5), Vector2::new(0.0, -3.0));
+    }
+
+    #[test]
+    fn test_formation() {
+        let navmesh = make_grid_navmesh(10);
+
+        let mut formation = NavmeshAgentFormation::new();
+        let members = [
+            Vector3::new(4.0, 0.0, 1.0),
+            Vector3::new(5.0, 0.0, 1.5),
+            Vector3::new(6.0, 0.0, 1.0),
+        ]
+        .map(|position| {
+            formation.add_member(
+                NavmeshAgentBuilder::new()
+                    .with_position(position)
+                    .with_speed(2.0)
+                    .build(),
+            )
+        });
+        assert_eq!(formation.member_count(), 3);
+
+        formation.set_target(Vector3::new(5.0, 0.0, 8.0));
+        assert_eq!(formation.target(), Vector3::new(5.0, 0.0, 8.0));
+        for _ in 0..200 {
+            formation.update(0.05, &navmesh);
+        }
+
+        // The members must stand in a line across the direction of motion (+Z).
+        let positions = members.map(|handle| formation.member(handle).unwrap().agent().position());
+        for (position, x) in positions.iter().zip([4.0, 5.0, 6.0]) {
+            assert!(position.metric_distance(&Vector3::new(x, 0.0, 8.0)) < 0.5);
+        }
+
+        // Slots off the navmesh are snapped to the navmesh.
+        formation.set_spacing(6.0);
+        formation.set_target(Vector3::new(5.0, 0.0, 9.0));
+        formation.set_shape(FormationShape::Line);
+        for _ in 0..200 {
+            formation.update(0.05, &navmesh);
+        }
+        for (_, member) in formation.pair_iter() {
+            let slot = member.slot_position();
+            assert!((0.0..=10.0).contains(&slot.x) && (0.0..=10.0).contains(&slot.z));
+        }
+
+        assert!(formation.remove_member(members[0]).is_some());
+        assert_eq!(formation.member_count(), 2);
+    }
+}
@@ -0,0 +1,224 @@
+// Copyright (c) 2019-present Dmitry Stepanov and Fyrox Engine contributors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Uniform grid over the triangles of a navmesh, that is used to accelerate point queries.
+
+use crate::core::{
+    algebra::{Vector2, Vector3},
+    math::TriangleDefinition,
+};
+use std::ops::RangeInclusive;
+
+/// Uniform grid on XZ plane, every cell of the grid stores indices of the triangles, which
+/// bounds (projected on XZ plane) intersect the cell.
+#[derive(Clone, Debug, Default)]
+pub(super) struct TriangleGrid {
+    origin: Vector2<f32>,
+    cell_size: f32,
+    width: usize,
+    depth: usize,
+    cells: Vec<Vec<u32>>,
+}
+
+impl TriangleGrid {
+    pub(super) fn new(triangles: &[TriangleDefinition], vertices: &[Vector3<f32>]) -> Self {
+        if triangles.is_empty() {
+            return Default::default();
+        }
+
+        let mut min = Vector2::repeat(f32::MAX);
+        let mut max = Vector2::repeat(-f32::MAX);
+        for triangle in triangles {
+            for index in triangle.0 {
+                let vertex = vertices[index as usize];
+                min = min.inf(&Vector2::new(vertex.x, vertex.z));
+                max = max.sup(&Vector2::new(vertex.x, vertex.z));
+            }
+        }
+
+        // Pick the cell size so, that there's roughly one triangle per cell, while keeping the
+        // total number of cells proportional to the number of triangles.
+        let count = triangles.len() as f32;
+        let extents = (max - min).map(|e| e.max(f32::EPSILON));
+        let cell_size = (extents.x * extents.y / count)
+            .sqrt()
+            .max(extents.x.max(extents.y) / count)
+            .max(f32::EPSILON);
+        let width = ((extents.x / cell_size).ceil() as usize).max(1);
+        let depth = ((extents.y / cell_size).ceil() as usize).max(1);
+
+        let mut grid = Self {
+            origin: min,
+            cell_size,
+            width,
+            depth,
+            cells: vec![Vec::new(); width * depth],
+        };
+
+        for (triangle_index, triangle) in triangles.iter().enumerate() {
+            let (triangle_min, triangle_max) =
+                triangle_bounds(triangle.0.map(|index| vertices[index as usize]));
+
+            let (min_x, min_z) = grid.clamped_cell_coords(triangle_min);
+            let (max_x, max_z) = grid.clamped_cell_coords(triangle_max);
+            for z in min_z..=max_z {
+                for x in min_x..=max_x {
+                    grid.cells[z * width + x].push(triangle_index as u32);
+                }
+            }
+        }
+
+        grid
+    }
+
+    /// Moves a triangle with the given index from the cells, that are intersected by its old
+    /// points, to the cells, that are intersected by its new points. Returns `false` and leaves
+    /// the grid intact, if the new points are outside of the grid, so the grid must be rebuilt.
+    pub(super) fn move_triangle(
+        &mut self,
+        index: u32,
+        old_points: [Vector3<f32>; 3],
+        new_points: [Vector3<f32>; 3],
+    ) -> bool {
+        let (min, max) = triangle_bounds(new_points);
+        let grid_max =
+            self.origin + Vector2::new(self.width as f32, self.depth as f32).scale(self.cell_size);
+        if self.cells.is_empty()
+            || min.x < self.origin.x
+            || min.y < self.origin.y
+            || max.x > grid_max.x
+            || max.y > grid_max.y
+        {
+            return false;
+        }
+
+        let (old_min, old_max) = triangle_bounds(old_points);
+        let (min_x, min_z) = self.clamped_cell_coords(old_min);
+        let (max_x, max_z) = self.clamped_cell_coords(old_max);
+        for z in min_z..=max_z {
+            for x in min_x..=max_x {
+                self.cells[z * self.width + x].retain(|triangle| *triangle != index);
+            }
+        }
+
+        let (min_x, min_z) = self.clamped_cell_coords(min);
+        let (max_x, max_z) = self.clamped_cell_coords(max);
+        for z in min_z..=max_z {
+            for x in min_x..=max_x {
+                self.cells[z * self.width + x].push(index);
+            }
+        }
+        true
+    }
+
+    fn clamped_cell_coords(&self, point: Vector2<f32>) -> (usize, usize) {
+        let (x, z) = self.cell_coords(Vector3::new(point.x, 0.0, point.y));
+        (
+            x.clamp(0, self.width as i64 - 1) as usize,
+            z.clamp(0, self.depth as i64 - 1) as usize,
+        )
+    }
+
+    /// Returns coordinates of a cell, that contains the given point. The coordinates could be
+    /// outside of the grid.
+    pub(super) fn cell_coords(&self, point: Vector3<f32>) -> (i64, i64) {
+        (
+            ((point.x - self.origin.x) / self.cell_size).floor() as i64,
+            ((point.z - self.origin.y) / self.cell_size).floor() as i64,
+        )
+    }
+
+    pub(super) fn cell_size(&self) -> f32 {
+        self.cell_size
+    }
+
+    /// Returns triangles of a cell at the given coordinates or an empty slice, if the
+    /// coordinates are outside of the grid.
+    pub(super) fn cell(&self, x: i64, z: i64) -> &[u32] {
+        if x >= 0 && z >= 0 && (x as usize) < self.width && (z as usize) < self.depth {
+            &self.cells[z as usize * self.width + x as usize]
+        } else {
+            &[]
+        }
+    }
+
+    /// Calls the given closure for every cell of the grid, that intersects the given rectangle on
+    /// XZ plane.
+    pub(super) fn visit_rect<F>(&self, min: Vector3<f32>, max: Vector3<f32>, mut func: F)
+    where
+        F: FnMut(&[u32]),
+    {
+        if self.cells.is_empty() {
+            return;
+        }
+        let (min_x, min_z) = self.clamped_cell_coords(Vector2::new(min.x, min.z));
+        let (max_x, max_z) = self.clamped_cell_coords(Vector2::new(max.x, max.z));
+        for z in min_z..=max_z {
+            for x in min_x..=max_x {
+                func(&self.cells[z * self.width + x]);
+            }
+        }
+    }
+
+    /// Returns the range of rings around the cell with the given coordinates, that contain at
+    /// least one cell of the grid. A ring is a set of cells at the same Chebyshev distance from
+    /// the cell.
+    pub(super) fn ring_range(&self, x: i64, z: i64) -> RangeInclusive<i64> {
+        if self.cells.is_empty() {
+            return RangeInclusive::new(1, 0);
+        }
+        let last_x = self.width as i64 - 1;
+        let last_z = self.depth as i64 - 1;
+        let first = 0.max(-x).max(x - last_x).max(-z).max(z - last_z);
+        let last = x.max(last_x - x).max(z).max(last_z - z);
+        first..=last
+    }
+
+    /// Calls the given closure for every cell of the ring with the given radius around the cell
+    /// with the given coordinates.
+    pub(super) fn visit_ring<F>(&self, x: i64, z: i64, radius: i64, mut func: F)
+    where
+        F: FnMut(&[u32]),
+    {
+        if radius == 0 {
+            func(self.cell(x, z));
+            return;
+        }
+        for cx in (x - radius)..=(x + radius) {
+            func(self.cell(cx, z - radius));
+            func(self.cell(cx, z + radius));
+        }
+        for cz in (z - radius + 1)..=(z + radius - 1) {
+            func(self.cell(x - radius, cz));
+            func(self.cell(x + radius, cz));
+        }
+    }
+}
+
+/// Returns the bounds of the given points projected on XZ plane.
+fn triangle_bounds(points: [Vector3<f32>; 3]) -> (Vector2<f32>, Vector2<f32>) {
+    let mut min = Vector2::repeat(f32::MAX);
+    let mut max = Vector2::repeat(-f32::MAX);
+    for point in points {
+        min = min.inf(&Vector2::new(point.x, point.z));
+        max = max.sup(&Vector2::new(point.x, point.z));
+    }
+    (min, max)
+}
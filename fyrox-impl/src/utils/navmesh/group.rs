@@ -0,0 +1,371 @@
+// Copyright (c) 2019-present Dmitry Stepanov and Fyrox Engine contributors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Group is a set of navmesh agents, that share the same pace. See [`NavmeshAgentGroup`] docs
+//! for more info.
+
+use crate::{
+    core::pool::{Handle, Pool},
+    utils::navmesh::{Navmesh, NavmeshAgent, NavmeshPathError},
+};
+
+/// Defines how the speeds of the members of a group are synchronized.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum GroupSyncMode {
+    /// Every member moves with its own speed.
+    Independent,
+    /// The speed of every member is scaled down, so all the members reach their targets at
+    /// approximately the same time. The member with the longest travel time moves with its full
+    /// speed.
+    #[default]
+    Arrival,
+    /// All the members move with the speed of the slowest member, so the distances between the
+    /// members, that move in the same direction, are preserved.
+    SlowestPace,
+}
+
+/// A member of a group. It wraps a navmesh agent, which speed is set by the group.
+#[derive(Clone, Debug)]
+pub struct GroupMember {
+    agent: NavmeshAgent,
+    speed: f32,
+}
+
+impl GroupMember {
+    /// Returns a reference to the inner navmesh agent.
+    pub fn agent(&self) -> &NavmeshAgent {
+        &self.agent
+    }
+
+    /// Returns a reference to the inner navmesh agent. Use it to change the target, the radius,
+    /// etc. of the agent. Keep in mind, that the speed of the agent is overwritten by the group on
+    /// every update, use [`Self::set_speed`] instead.
+    pub fn agent_mut(&mut self) -> &mut NavmeshAgent {
+        &mut self.agent
+    }
+
+    /// Sets the maximum speed of the member. The actual speed of the agent could be lower, it
+    /// depends on the sync mode of the group (see [`GroupSyncMode`]).
+    pub fn set_speed(&mut self, speed: f32) {
+        self.speed = speed;
+    }
+
+    /// Returns the maximum speed of the member.
+    pub fn speed(&self) -> f32 {
+        self.speed
+    }
+}
+
+/// A handle of a member of a group.
+pub type GroupMemberHandle = Handle<GroupMember>;
+
+/// Group is a set of navmesh agents, that share the same pace. Every member follows its own path
+/// to its own target, but the group adjusts the speeds of the members according to the sync mode
+/// (see [`GroupSyncMode`]), so faster members do not race ahead of the rest of the group. The
+/// speeds are recalculated on every update using the remaining lengths of the paths, so the
+/// members stay in sync even if their paths are changed.
+///
+/// ## Example
+///
+/// ```rust
+/// # use fyrox_impl::{
+/// #     core::algebra::Vector3,
+/// #     utils::navmesh::{
+/// #         group::{GroupSyncMode, NavmeshAgentGroup},
+/// #         Navmesh, NavmeshAgentBuilder,
+/// #     },
+/// # };
+/// fn move_squad(navmesh: &Navmesh, units: &[(Vector3<f32>, Vector3<f32>)], dt: f32) {
+///     let mut group = NavmeshAgentGroup::new();
+///     group.set_sync_mode(GroupSyncMode::Arrival);
+///     for (position, target) in units {
+///         group.add_member(
+///             NavmeshAgentBuilder::new()
+///                 .with_position(*position)
+///                 .with_target(*target)
+///                 .build(),
+///         );
+///     }
+///
+///     for (_, error) in group.update(dt, navmesh) {
+///         println!("A member of the group could not find its path: {error}");
+///     }
+///
+///     for (_, member) in group.pair_iter() {
+///         // Use this position as target point of your game character.
+///         println!("{}", member.agent().position());
+///     }
+/// }
+/// ```
+#[derive(Clone, Debug, Default)]
+pub struct NavmeshAgentGroup {
+    members: Pool<GroupMember>,
+    sync_mode: GroupSyncMode,
+}
+
+impl NavmeshAgentGroup {
+    /// Creates a new empty group.
+    pub fn new() -> Self {
+        Self::default()
+    }
+
+    /// Adds a new member to the group and returns its handle. Current speed of the agent becomes
+    /// the maximum speed of the member.
+    pub fn add_member(&mut self, agent: NavmeshAgent) -> GroupMemberHandle {
+        self.members.spawn(GroupMember {
+            speed: agent.speed(),
+            agent,
+        })
+    }
+
+    /// Removes a member from the group and returns its agent, if the handle was valid. The speed
+    /// of the agent is restored to the maximum speed of the member.
+    pub fn remove_member(&mut self, handle: GroupMemberHandle) -> Option<NavmeshAgent> {
+        self.members.try_free(handle).map(|mut member| {
+            member.agent.set_speed(member.speed);
+            member.agent
+        })
+    }
+
+    /// Returns a reference to a member of the group.
+    pub fn member(&self, handle: GroupMemberHandle) -> Option<&GroupMember> {
+        self.members.try_borrow(handle)
+    }
+
+    /// Returns a reference to a member of the group.
+    pub fn member_mut(&mut self, handle: GroupMemberHandle) -> Option<&mut GroupMember> {
+        self.members.try_borrow_mut(handle)
+    }
+
+    /// Returns an iterator over all members of the group.
+    pub fn pair_iter(&self) -> impl Iterator<Item = (GroupMemberHandle, &GroupMember)> {
+        self.members.pair_iter()
+    }
+
+    /// Returns an iterator over all members of the group.
+    pub fn pair_iter_mut(&mut self) -> impl Iterator<Item = (GroupMemberHandle, &mut GroupMember)> {
+        self.members.pair_iter_mut()
+    }
+
+    /// Returns the total number of members in the group.
+    pub fn member_count(&self) -> u32 {
+        self.members.alive_count()
+    }
+
+    /// Sets a new sync mode of the group. Default is [`GroupSyncMode::Arrival`].
+    pub fn set_sync_mode(&mut self, sync_mode: GroupSyncMode) {
+        self.sync_mode = sync_mode;
+    }
+
+    /// Returns current sync mode of the group.
+    pub fn sync_mode(&self) -> GroupSyncMode {
+        self.sync_mode
+    }
+
+    /// Performs a single update tick of the group: recalculates the paths of the members (if
+    /// needed), calculates the speeds of the members according to the sync mode and moves the
+    /// members along their paths. Returns the handles of the members, that failed to update their
+    /// paths, along with the errors (see [`NavmeshAgent::update`]). Such members do not stop the
+    /// rest of the group.
+    pub fn update(
+        &mut self,
+        dt: f32,
+        navmesh: &Navmesh,
+    ) -> Vec<(GroupMemberHandle, NavmeshPathError)> {
+        let mut errors = Vec::new();
+
+        // The paths must be up-to-date to know the remaining distances. The time is accounted
+        // later, when the members are moved.
+        for (handle, member) in self.members.pair_iter_mut() {
+            if let Err(error) = member.agent.update_path(0.0, navmesh) {
+                errors.push((handle, error));
+            }
+        }
+
+        match self.sync_mode {
+            GroupSyncMode::Independent => {
+                for member in self.members.iter_mut() {
+                    member.agent.set_speed(member.speed);
+                }
+            }
+            GroupSyncMode::Arrival => {
+                // Members without a path (or without a speed) do not hold the group back.
+                let travel_time = self
+                    .members
+                    .iter()
+                    .filter(|member| member.speed > 0.0)
+                    .map(|member| member.agent.remaining_distance() / member.speed)
+                    .fold(0.0, f32::max);
+                for member in self.members.iter_mut() {
+                    let remaining_distance = member.agent.remaining_distance();
+                    let speed = if travel_time > 0.0 && remaining_distance > 0.0 {
+                        (remaining_distance / travel_time).min(member.speed)
+                    } else {
+                        member.speed
+                    };
+                    member.agent.set_speed(speed);
+                }
+            }
+            GroupSyncMode::SlowestPace => {
+                // Stopped members do not hold the group back, same as in the arrival mode.
+                let pace = self
+                    .members
+                    .iter()
+                    .filter(|member| member.speed > 0.0)
+                    .map(|member| member.speed)
+                    .reduce(f32::min)
+                    .unwrap_or_default();
+                for member in self.members.iter_mut() {
+                    member.agent.set_speed(pace);
+                }
+            }
+        }
+
+        for (handle, member) in self.members.pair_iter_mut() {
+            if let Err(error) = member.agent.update(dt, navmesh) {
+                // The latest error replaces the one from the preliminary path update.
+                match errors.iter_mut().find(|(other, _)| *other == handle) {
+                    Some(entry) => entry.1 = error,
+                    None => errors.push((handle, error)),
+                }
+            }
+        }
+
+        errors
+    }
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::algebra::Vector3,
+        utils::navmesh::{
+            group::{GroupSyncMode, NavmeshAgentGroup},
+            test::make_grid_navmesh,
+            NavmeshAgentBuilder, NavmeshObstacle, NavmeshPathError,
+        },
+    };
+
+    #[test]
+    fn test_group_sync() {
+        let navmesh = make_grid_navmesh(10);
+
+        let mut group = NavmeshAgentGroup::new();
+        assert_eq!(group.sync_mode(), GroupSyncMode::Arrival);
+        let far_start = Vector3::new(2.0, 0.0, 1.0);
+        let far = group.add_member(
+            NavmeshAgentBuilder::new()
+                .with_position(far_start)
+                .with_target(Vector3::new(2.0, 0.0, 9.0))
+                .with_speed(2.0)
+                .build(),
+        );
+        let near_start = Vector3::new(6.0, 0.0, 1.0);
+        let near = group.add_member(
+            NavmeshAgentBuilder::new()
+                .with_position(near_start)
+                .with_target(Vector3::new(6.0, 0.0, 5.0))
+                .with_speed(2.0)
+                .build(),
+        );
+        assert_eq!(group.member_count(), 2);
+
+        // The near member slows down to arrive together with the far one.
+        for _ in 0..20 {
+            group.update(0.05, &navmesh);
+        }
+        let near_member = group.member(near).unwrap();
+        assert!((near_member.agent().speed() - 1.0).abs() < 0.05);
+        assert_eq!(near_member.speed(), 2.0);
+        let far_progress = group.member(far).unwrap().agent().position().z - far_start.z;
+        let near_progress = near_member.agent().position().z - near_start.z;
+        assert!((far_progress / 8.0 - near_progress / 4.0).abs() < 0.05);
+
+        // The slowest member defines the pace of the whole group.
+        group.set_sync_mode(GroupSyncMode::SlowestPace);
+        group.member_mut(far).unwrap().set_speed(3.0);
+        group.member_mut(near).unwrap().set_speed(0.5);
+        group.update(0.05, &navmesh);
+        for (_, member) in group.pair_iter() {
+            assert_eq!(member.agent().speed(), 0.5);
+        }
+
+        // A stopped member does not freeze the rest of the group.
+        let stopped = group.add_member(
+            NavmeshAgentBuilder::new()
+                .with_position(Vector3::new(8.0, 0.0, 1.0))
+                .with_target(Vector3::new(8.0, 0.0, 1.0))
+                .with_speed(0.0)
+                .build(),
+        );
+        assert!(group.update(0.05, &navmesh).is_empty());
+        assert_eq!(group.member(far).unwrap().agent().speed(), 0.5);
+        assert_eq!(group.member(near).unwrap().agent().speed(), 0.5);
+        group.remove_member(stopped);
+
+        // Every member moves with its own speed.
+        group.set_sync_mode(GroupSyncMode::Independent);
+        group.update(0.05, &navmesh);
+        assert_eq!(group.member(far).unwrap().agent().speed(), 3.0);
+        assert_eq!(group.member(near).unwrap().agent().speed(), 0.5);
+
+        let agent = group.remove_member(near).unwrap();
+        assert_eq!(agent.speed(), 0.5);
+        assert_eq!(group.member_count(), 1);
+    }
+
+    #[test]
+    fn test_group_errors() {
+        let mut navmesh = make_grid_navmesh(10);
+        let target = Vector3::new(5.5, 0.0, 5.5);
+        navmesh.add_obstacle(NavmeshObstacle::Cylinder {
+            center: target,
+            radius: 0.2,
+            height: 2.0,
+        });
+
+        let mut group = NavmeshAgentGroup::new();
+        let blocked = group.add_member(
+            NavmeshAgentBuilder::new()
+                .with_position(Vector3::new(0.5, 0.0, 0.5))
+                .with_target(target)
+                .build(),
+        );
+        group
+            .member_mut(blocked)
+            .unwrap()
+            .agent_mut()
+            .set_allow_partial_path(false);
+        let free = group.add_member(
+            NavmeshAgentBuilder::new()
+                .with_position(Vector3::new(0.5, 0.0, 1.5))
+                .with_target(Vector3::new(8.5, 0.0, 1.5))
+                .build(),
+        );
+
+        // The error is reported once per member and the other members keep moving.
+        let errors = group.update(0.1, &navmesh);
+        assert_eq!(errors, vec![(blocked, NavmeshPathError::EndOffMesh)]);
+        for _ in 0..10 {
+            group.update(0.1, &navmesh);
+        }
+        assert!(group.member(free).unwrap().agent().position().x > 1.0);
+    }
+}
@@ -0,0 +1,6731 @@
+// Copyright (c) 2019-present Dmitry Stepanov and Fyrox Engine contributors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Contains all structures and methods to create and manage navigation meshes (navmesh).
+//!
+//! Navigation mesh is a set of convex polygons which is used for path finding in complex
+//! environment.
+//!
+//! Path queries are deterministic: the same query on the same navmesh always produces the same
+//! path, so the paths could be calculated independently by every peer of a lockstep networked
+//! simulation. See [`crate::utils::astar`] module docs for more info. Keep in mind, that the
+//! navmeshes with a custom up axis (see [`Navmesh::set_up_axis`]) rotate the vertices using
+//! trigonometric functions, that are not exactly rounded, so the results of such navmeshes could
+//! differ between platforms (but not between the runs on the same platform).
+
+#![warn(missing_docs)]
+
+use crate::{
+    core::{
+        algebra::{Matrix4, Point3, Unit, UnitQuaternion, Vector2, Vector3},
+        arrayvec::ArrayVec,
+        math::{
+            self, aabb::AxisAlignedBoundingBox, plane::Plane, ray::Ray, PositionProvider,
+            TriangleDefinition, Vector3Ext,
+        },
+        parking_lot::Mutex,
+        pool::{Handle, Pool},
+        rand::Rng,
+        reflect::prelude::*,
+        visitor::{Visit, VisitResult, Visitor},
+    },
+    scene::{
+        self,
+        mesh::{
+            buffer::{VertexAttributeUsage, VertexReadTrait},
+            Mesh,
+        },
+        node::Node,
+    },
+    utils::{
+        astar::{
+            Graph, GraphVertex, PathError, PathKind, PathfindStats, VertexData, VertexDataProvider,
+        },
+        raw_mesh::{RawMeshBuilder, RawVertex},
+    },
+};
+use fxhash::{FxBuildHasher, FxHashMap, FxHashSet};
+use fyrox_core::math::octree::{Octree, OctreeNode};
+use fyrox_graph::BaseSceneGraph;
+use grid::TriangleGrid;
+use polygon::{NavmeshPolygon, PolygonMesh};
+use rayon::prelude::*;
+use std::{
+    collections::{hash_map::Entry, VecDeque},
+    fmt::{Display, Formatter},
+    ops::{Deref, DerefMut},
+    sync::{
+        atomic::{AtomicUsize, Ordering},
+        OnceLock,
+    },
+};
+
+mod agent;
+pub mod crowd;
+mod delta;
+pub mod flow_field;
+mod format;
+pub mod formation;
+mod grid;
+pub mod group;
+pub mod polygon;
+#[cfg(test)]
+pub(crate) mod test;
+
+use agent::OffMeshLinkSegment;
+pub use agent::*;
+pub use delta::*;
+
+#[derive(Clone, Debug, Default, Visit)]
+struct Vertex {
+    triangle_index: usize,
+    data: VertexData,
+}
+
+impl Deref for Vertex {
+    type Target = VertexData;
+
+    fn deref(&self) -> &Self::Target {
+        &self.data
+    }
+}
+
+impl DerefMut for Vertex {
+    fn deref_mut(&mut self) -> &mut Self::Target {
+        &mut self.data
+    }
+}
+
+impl PositionProvider for Vertex {
+    fn position(&self) -> Vector3<f32> {
+        self.data.position
+    }
+}
+
+impl VertexDataProvider for Vertex {}
+
+/// Off-mesh link is a virtual connection between two points on a navmesh, that cannot be reached
+/// just by walking. For example, it could be a ledge from which an agent could jump down, but
+/// cannot walk between the ledge and the ground. Path finding treats off-mesh links as virtual
+/// edges between the triangles that contain the ends of a link, which allows paths to go between
+/// disconnected parts of a navmesh. See [`Navmesh::add_offmesh_link`] for more info.
+#[derive(Clone, Debug, PartialEq, Visit)]
+pub struct OffMeshLink {
+    from: Vector3<f32>,
+    to: Vector3<f32>,
+    bidirectional: bool,
+    cost: f32,
+    // See `OffMeshLink::span`.
+    #[visit(optional)]
+    span: Vector3<f32>,
+    #[visit(skip)]
+    from_triangle: Option<usize>,
+    #[visit(skip)]
+    to_triangle: Option<usize>,
+}
+
+impl Default for OffMeshLink {
+    fn default() -> Self {
+        Self {
+            from: Default::default(),
+            to: Default::default(),
+            bidirectional: false,
+            cost: 1.0,
+            span: Default::default(),
+            from_triangle: None,
+            to_triangle: None,
+        }
+    }
+}
+
+impl OffMeshLink {
+    /// Returns the beginning of the link.
+    pub fn from(&self) -> Vector3<f32> {
+        self.from
+    }
+
+    /// Returns the end of the link.
+    pub fn to(&self) -> Vector3<f32> {
+        self.to
+    }
+
+    /// Returns `true` if the link could be traversed in both directions, `false` - if it could be
+    /// traversed only from its beginning to its end.
+    pub fn is_bidirectional(&self) -> bool {
+        self.bidirectional
+    }
+
+    /// Returns traversal cost multiplier of the link. See [`Navmesh::set_offmesh_link_cost`] for
+    /// more info.
+    pub fn cost(&self) -> f32 {
+        self.cost
+    }
+
+    /// Returns a vector from the center of the link to the ends of its span. The links, that
+    /// connect two edges (see [`Navmesh::connect_steps`]), have non-zero span: they could be crossed
+    /// at any point of the segments `from ± span` and `to ± span`, like a shared edge of two
+    /// triangles. Path smoothing treats such links as portals, so the agents do not detour to the
+    /// center of the link and do not traverse it (see [`NavmeshAgent::current_offmesh_link`]).
+    /// Regular links have zero span.
+    pub fn span(&self) -> Vector3<f32> {
+        self.span
+    }
+
+    fn has_span(&self) -> bool {
+        self.span != Vector3::default()
+    }
+}
+
+/// A handle of an off-mesh link in a navmesh.
+pub type OffMeshLinkHandle = Handle<OffMeshLink>;
+
+/// An edge of a navmesh with its adjacency information. See [`Navmesh::edges`] for more info.
+#[derive(Copy, Clone, Debug, PartialEq, Eq)]
+pub struct NavmeshEdge {
+    /// Indices of the vertices of the edge (in the winding order of [`Self::triangle`]).
+    pub vertices: [u32; 2],
+    /// Index of a triangle that the edge belongs to.
+    pub triangle: usize,
+    /// Index of another triangle that shares the edge, or `None` if the edge lies on the border
+    /// of the navmesh.
+    pub neighbor: Option<usize>,
+}
+
+impl NavmeshEdge {
+    /// Returns `true` if the edge lies on the border of the navmesh (it belongs to a single
+    /// triangle), `false` - otherwise.
+    pub fn is_border(&self) -> bool {
+        self.neighbor.is_none()
+    }
+}
+
+/// Direction in which a shared edge between two triangles could be traversed. See
+/// [`Navmesh::set_edge_direction`] for more info.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub enum EdgeDirection {
+    /// The edge could be traversed in both directions.
+    #[default]
+    Bidirectional,
+    /// The edge could be traversed only from the first triangle to the second one.
+    Forward,
+    /// The edge could be traversed only from the second triangle to the first one.
+    Backward,
+}
+
+/// Shape of a runtime obstacle. See [`Navmesh::add_obstacle`] for more info. The shapes are
+/// oriented relative to the up axis of the navmesh (see [`Navmesh::set_up_axis`]).
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub enum NavmeshObstacle {
+    /// Axis-aligned box, its Y axis is the up axis of the navmesh.
+    Box {
+        /// Center of the box.
+        center: Vector3<f32>,
+        /// Half of the size of the box along each axis.
+        half_extents: Vector3<f32>,
+    },
+    /// Vertical cylinder, it is aligned with the up axis of the navmesh.
+    Cylinder {
+        /// Center of the cylinder.
+        center: Vector3<f32>,
+        /// Radius of the cylinder.
+        radius: f32,
+        /// Full height of the cylinder.
+        height: f32,
+    },
+}
+
+impl NavmeshObstacle {
+    /// Returns the bounding box of the obstacle in the space, where the up axis of the navmesh is
+    /// +Y.
+    fn bounds(&self, navmesh: &Navmesh) -> (Vector3<f32>, Vector3<f32>) {
+        let (center, half_extents) = match *self {
+            NavmeshObstacle::Box {
+                center,
+                half_extents,
+            } => (center, half_extents),
+            NavmeshObstacle::Cylinder {
+                center,
+                radius,
+                height,
+            } => (center, Vector3::new(radius, height * 0.5, radius)),
+        };
+        let center = navmesh.to_up_space(center);
+        (center - half_extents, center + half_extents)
+    }
+
+    /// Checks whether the obstacle overlaps the given world-space triangle. The test is performed
+    /// in the space, where the up axis of the navmesh is +Y.
+    fn overlaps_triangle(
+        &self,
+        navmesh: &Navmesh,
+        a: Vector3<f32>,
+        b: Vector3<f32>,
+        c: Vector3<f32>,
+    ) -> bool {
+        let (min, max) = self.bounds(navmesh);
+        let [a, b, c] = [a, b, c].map(|point| navmesh.to_up_space(point));
+        if a.y.max(b.y).max(c.y) < min.y || a.y.min(b.y).min(c.y) > max.y {
+            return false;
+        }
+
+        match *self {
+            NavmeshObstacle::Box { .. } => {
+                // Separating axis test on XZ plane.
+                let rect = [
+                    Vector3::new(min.x, 0.0, min.z),
+                    Vector3::new(max.x, 0.0, min.z),
+                    Vector3::new(max.x, 0.0, max.z),
+                    Vector3::new(min.x, 0.0, max.z),
+                ];
+                let triangle = [a, b, c];
+                let mut axes = vec![Vector3::x(), Vector3::z()];
+                for i in 0..3 {
+                    let edge = triangle[(i + 1) % 3] - triangle[i];
+                    axes.push(Vector3::new(-edge.z, 0.0, edge.x));
+                }
+                axes.iter().all(|axis| {
+                    let project = |points: &[Vector3<f32>]| {
+                        points.iter().fold((f32::MAX, f32::MIN), |(min, max), p| {
+                            let d = p.x * axis.x + p.z * axis.z;
+                            (min.min(d), max.max(d))
+                        })
+                    };
+                    let (rect_min, rect_max) = project(&rect);
+                    let (triangle_min, triangle_max) = project(&triangle);
+                    rect_max >= triangle_min && triangle_max >= rect_min
+                })
+            }
+            NavmeshObstacle::Cylinder { center, radius, .. } => {
+                let center = navmesh.to_up_space(center);
+                if is_point_inside_triangle_2d(center, a, b, c) {
+                    return true;
+                }
+                [(a, b), (b, c), (c, a)].iter().any(|(begin, end)| {
+                    let edge = Vector3::new(end.x - begin.x, 0.0, end.z - begin.z);
+                    let to_center = Vector3::new(center.x - begin.x, 0.0, center.z - begin.z);
+                    let t = (to_center.dot(&edge) / edge.norm_squared().max(f32::EPSILON))
+                        .clamp(0.0, 1.0);
+                    (to_center - edge.scale(t)).norm() <= radius
+                })
+            }
+        }
+    }
+}
+
+/// A runtime obstacle of a navmesh. See [`Navmesh::add_obstacle`] for more info.
+#[derive(Clone, Debug, PartialEq)]
+pub struct Obstacle {
+    shape: NavmeshObstacle,
+    enabled: bool,
+}
+
+impl Obstacle {
+    /// Returns the shape of the obstacle.
+    pub fn shape(&self) -> &NavmeshObstacle {
+        &self.shape
+    }
+
+    /// Returns `true` if the obstacle is enabled, `false` - otherwise.
+    pub fn is_enabled(&self) -> bool {
+        self.enabled
+    }
+}
+
+/// A handle of a runtime obstacle in a navmesh.
+pub type ObstacleHandle = Handle<Obstacle>;
+
+/// Border edges of a navmesh and a uniform grid over them, see [`Navmesh::border_distance`].
+#[derive(Clone, Debug, Default)]
+struct BorderEdges {
+    edges: Vec<[u32; 2]>,
+    grid: TriangleGrid,
+}
+
+/// Describes how an off-mesh link is traversed between two triangles.
+#[derive(Copy, Clone, Debug)]
+struct OffMeshLinkTraversal {
+    link: OffMeshLinkHandle,
+    enter: Vector3<f32>,
+    exit: Vector3<f32>,
+}
+
+/// See module docs.
+///
+/// Triangle costs, flags and layers of the navmesh could be edited using reflection, vertices and
+/// triangles are read-only (use [`Navmesh::modify`] to change them). Keep in mind, that such edits
+/// bypass the setters (for example, [`Navmesh::set_triangle_cost`]), so [`Navmesh::rebuild`] must
+/// be called afterwards.
+#[derive(Clone, Debug, Reflect)]
+pub struct Navmesh {
+    #[reflect(hidden)]
+    octree: OnceLock<Octree>,
+    /// Triangles of the navmesh, each triangle is defined by three indices of the vertices.
+    #[reflect(read_only)]
+    triangles: Vec<TriangleDefinition>,
+    /// Positions of the vertices of the navmesh.
+    #[reflect(read_only)]
+    vertices: Vec<Vector3<f32>>,
+    /// Traversal cost multipliers of the triangles.
+    #[reflect(immutable_collection)]
+    triangle_costs: Vec<f32>,
+    /// User-defined flags of the triangles, see [`NavmeshQueryFilter`].
+    #[reflect(immutable_collection)]
+    triangle_flags: Vec<u32>,
+    /// Layers of the triangles, see [`Navmesh::set_triangle_layer`].
+    #[reflect(immutable_collection)]
+    triangle_layers: Vec<u32>,
+    /// Size of a tile (in meters) of the navmesh, see [`Navmesh::rebuild_tile`].
+    #[reflect(min_value = 0.01)]
+    tile_size: f32,
+    // Normalized "up" direction of the navmesh, see `Navmesh::set_up_axis`.
+    #[reflect(hidden)]
+    up_axis: Vector3<f32>,
+    // Rotation, that maps the up axis to +Y, or `None` if the up axis is +Y already.
+    #[reflect(hidden)]
+    up_rotation: Option<UnitQuaternion<f32>>,
+    // Maximum number of vertices of a convex polygon, see `Navmesh::build_convex_polys`. Zero
+    // means that the path search runs over the triangles.
+    #[reflect(hidden)]
+    max_polygon_vertices: usize,
+    // Rebuilt together with the graph, see `Navmesh::rebuild_polygons`.
+    #[reflect(hidden)]
+    polygons: Option<PolygonMesh>,
+    #[reflect(hidden)]
+    offmesh_links: Pool<OffMeshLink>,
+    // Several links could connect the same pair of triangles, so every pair has a list.
+    #[reflect(hidden)]
+    offmesh_link_traversals: FxHashMap<(usize, usize), Vec<OffMeshLinkTraversal>>,
+    #[reflect(hidden)]
+    graph: Graph<Vertex>,
+    // Built lazily on first query.
+    #[reflect(hidden)]
+    grid: OnceLock<TriangleGrid>,
+    #[reflect(hidden)]
+    path_requests: PathRequestQueue,
+    #[reflect(hidden)]
+    path_cache: PathCache,
+    // Maximum number of triangles (or polygons), that could be expanded by a single path search,
+    // see `Navmesh::set_max_search_nodes`.
+    #[reflect(hidden)]
+    max_search_nodes: Option<usize>,
+    // Maximum number of triangles (or polygons), that could be expanded by a single call of
+    // `Navmesh::process_path_requests`.
+    #[reflect(hidden)]
+    pathfinding_budget: Option<usize>,
+    #[reflect(hidden)]
+    obstacles: Pool<Obstacle>,
+    // Number of enabled obstacles that overlap each triangle.
+    #[reflect(hidden)]
+    blocked_triangles: Vec<u32>,
+    // Index of the connected component of each triangle. Built lazily on first query.
+    #[reflect(hidden)]
+    islands: OnceLock<Vec<u32>>,
+    // A flag for each vertex, that tells whether the vertex belongs to a border edge. Built lazily
+    // on first query.
+    #[reflect(hidden)]
+    border_vertices: OnceLock<Vec<bool>>,
+    // Pairs of vertex indices of every border edge and a grid over them. Built lazily on first
+    // query.
+    #[reflect(hidden)]
+    border_edges: OnceLock<BorderEdges>,
+    // Distance from each vertex to the closest border edge. Built lazily on first query.
+    #[reflect(hidden)]
+    vertex_clearances: OnceLock<Vec<f32>>,
+    #[reflect(hidden)]
+    revision: u64,
+    // Incremented every time the whole navmesh is moved, see `Navmesh::transform`.
+    #[reflect(hidden)]
+    geometry_revision: u64,
+    // Incremented every time the indices of the triangles could change, see
+    // `Navmesh::topology_revision`.
+    #[reflect(hidden)]
+    topology_revision: u64,
+    // Shared edges, that could be traversed only in one direction. Each entry is a pair of
+    // indices of the triangles in the allowed direction of traversal.
+    #[reflect(hidden)]
+    one_way_edges: FxHashSet<[u32; 2]>,
+}
+
+/// Default size of a tile of a navmesh, see [`Navmesh::rebuild_tile`].
+pub const DEFAULT_NAVMESH_TILE_SIZE: f32 = 32.0;
+
+/// Vertices, that are closer than this distance to each other, are welded together when a tile
+/// of a navmesh is rebuilt, see [`Navmesh::rebuild_tile`].
+pub const NAVMESH_TILE_WELD_TOLERANCE: f32 = 0.001;
+
+/// Maximum horizontal distance between border edges, that could be connected as a step by
+/// [`crate::scene::navmesh::NavigationalMeshBuilder`], see [`Navmesh::connect_steps`].
+pub const NAVMESH_STEP_GAP_TOLERANCE: f32 = 0.05;
+
+impl Default for Navmesh {
+    fn default() -> Self {
+        Self {
+            octree: Default::default(),
+            triangles: Default::default(),
+            vertices: Default::default(),
+            triangle_costs: Default::default(),
+            triangle_flags: Default::default(),
+            triangle_layers: Default::default(),
+            tile_size: DEFAULT_NAVMESH_TILE_SIZE,
+            up_axis: Vector3::y(),
+            up_rotation: None,
+            max_polygon_vertices: 0,
+            polygons: Default::default(),
+            offmesh_links: Default::default(),
+            offmesh_link_traversals: Default::default(),
+            graph: Default::default(),
+            grid: Default::default(),
+            path_requests: Default::default(),
+            path_cache: Default::default(),
+            max_search_nodes: None,
+            pathfinding_budget: None,
+            obstacles: Default::default(),
+            blocked_triangles: Default::default(),
+            islands: Default::default(),
+            border_vertices: Default::default(),
+            border_edges: Default::default(),
+            vertex_clearances: Default::default(),
+            revision: 0,
+            geometry_revision: 0,
+            topology_revision: 0,
+            one_way_edges: Default::default(),
+        }
+    }
+}
+
+impl PartialEq for Navmesh {
+    fn eq(&self, other: &Self) -> bool {
+        self.triangles == other.triangles
+            && self.vertices == other.vertices
+            && self.triangle_costs == other.triangle_costs
+            && self.triangle_flags == other.triangle_flags
+            && self.triangle_layers == other.triangle_layers
+            && self.offmesh_links == other.offmesh_links
+            && self.one_way_edges == other.one_way_edges
+    }
+}
+
+impl Visit for Navmesh {
+    fn visit(&mut self, name: &str, visitor: &mut Visitor) -> VisitResult {
+        let mut region = visitor.enter_region(name)?;
+
+        // Backward compatibility.
+        if region.is_reading() {
+            let mut pathfinder = Graph::<GraphVertex>::new();
+            if pathfinder.visit("PathFinder", &mut region).is_ok() {
+                self.vertices = pathfinder
+                    .vertices
+                    .iter()
+                    .map(|v| v.position)
+                    .collect::<Vec<_>>();
+            } else {
+                self.vertices.visit("Vertices", &mut region)?;
+            }
+        } else {
+            self.vertices.visit("Vertices", &mut region)?;
+        }
+
+        self.triangles.visit("Triangles", &mut region)?;
+
+        // Backward compatibility.
+        if self
+            .triangle_costs
+            .visit("TriangleCosts", &mut region)
+            .is_err()
+            || self.triangle_costs.len() != self.triangles.len()
+        {
+            self.triangle_costs = vec![1.0; self.triangles.len()];
+        }
+        if self
+            .triangle_flags
+            .visit("TriangleFlags", &mut region)
+            .is_err()
+            || self.triangle_flags.len() != self.triangles.len()
+        {
+            self.triangle_flags = vec![0; self.triangles.len()];
+        }
+        if self
+            .triangle_layers
+            .visit("TriangleLayers", &mut region)
+            .is_err()
+            || self.triangle_layers.len() != self.triangles.len()
+        {
+            self.triangle_layers = vec![0; self.triangles.len()];
+        }
+        if self.tile_size.visit("TileSize", &mut region).is_err() {
+            self.tile_size = DEFAULT_NAVMESH_TILE_SIZE;
+        }
+        if self.up_axis.visit("UpAxis", &mut region).is_err() {
+            self.up_axis = Vector3::y();
+        }
+        self.up_rotation = up_rotation(self.up_axis);
+        if self
+            .max_polygon_vertices
+            .visit("MaxPolygonVertices", &mut region)
+            .is_err()
+        {
+            self.max_polygon_vertices = 0;
+        }
+
+        if self
+            .offmesh_links
+            .visit("OffMeshLinks", &mut region)
+            .is_err()
+        {
+            self.offmesh_links = Default::default();
+        }
+
+        if self
+            .one_way_edges
+            .visit("OneWayEdges", &mut region)
+            .is_err()
+        {
+            self.one_way_edges = Default::default();
+        }
+
+        drop(region);
+
+        // No need to save octree, we can restore it on load.
+        if visitor.is_reading() {
+            self.rebuild_octree();
+            self.grid = Default::default();
+            self.rebuild_obstacles();
+        }
+
+        self.rebuild_graph();
+
+        Ok(())
+    }
+}
+
+// An edge collapse found by `Navmesh::find_collapse`.
+struct Collapse {
+    target: u32,
+    // Border neighbors of the collapsed vertex, if it lies on the border.
+    border: Option<[u32; 2]>,
+}
+
+#[derive(Copy, Clone, Debug)]
+struct Portal {
+    left: usize,
+    right: usize,
+}
+
+fn triangle_area_2d(a: Vector3<f32>, b: Vector3<f32>, c: Vector3<f32>) -> f32 {
+    let abx = b[0] - a[0];
+    let abz = b[2] - a[2];
+    let acx = c[0] - a[0];
+    let acz = c[2] - a[2];
+    acx * abz - abx * acz
+}
+
+// Sine of the angle below which three consecutive polygon vertices are considered collinear.
+const COLLINEAR_TOLERANCE: f32 = 1.0e-5;
+
+fn xz_length(v: Vector3<f32>) -> f32 {
+    v.x.hypot(v.z)
+}
+
+// Checks whether the turn `a -> b -> c` is nearly straight (or has zero length) on XZ plane.
+fn is_nearly_collinear(a: Vector3<f32>, b: Vector3<f32>, c: Vector3<f32>) -> bool {
+    let scale = xz_length(b - a) * xz_length(c - b);
+    triangle_area_2d(a, b, c).abs() <= COLLINEAR_TOLERANCE * scale
+}
+
+// Signed area of the ring on XZ plane. `triangle_area_2d` gives the doubled area of a triangle.
+fn ring_area_2d(ring: &[u32], vertices: &[Vector3<f32>]) -> f32 {
+    let first = vertices[ring[0] as usize];
+    0.5 * (1..ring.len() - 1)
+        .map(|i| {
+            triangle_area_2d(
+                first,
+                vertices[ring[i] as usize],
+                vertices[ring[i + 1] as usize],
+            )
+        })
+        .sum::<f32>()
+}
+
+fn is_point_inside_ring_2d(point: Vector3<f32>, ring: &[u32], vertices: &[Vector3<f32>]) -> bool {
+    let mut inside = false;
+    for i in 0..ring.len() {
+        let a = vertices[ring[i] as usize];
+        let b = vertices[ring[(i + 1) % ring.len()] as usize];
+        if (a.z > point.z) != (b.z > point.z)
+            && point.x < a.x + (point.z - a.z) * (b.x - a.x) / (b.z - a.z)
+        {
+            inside = !inside;
+        }
+    }
+    inside
+}
+
+// Removes duplicate and nearly collinear vertices of a polygon, returns indices of the vertices
+// left.
+fn simplify_polygon(points: &[Vector3<f32>]) -> Vec<usize> {
+    let mut ring = (0..points.len()).collect::<Vec<_>>();
+    while ring.len() >= 3 {
+        let count = ring.len();
+        let collinear = (0..count).position(|i| {
+            is_nearly_collinear(
+                points[ring[(i + count - 1) % count]],
+                points[ring[i]],
+                points[ring[(i + 1) % count]],
+            )
+        });
+        match collinear {
+            Some(i) => {
+                ring.remove(i);
+            }
+            None => break,
+        }
+    }
+    ring
+}
+
+// Checks whether the point lies inside the interior angle of the boundary at the given position.
+// The interior of the region must be on the "positive" side of the boundary.
+fn is_inside_cone(
+    ring: &[u32],
+    position: usize,
+    point: Vector3<f32>,
+    vertices: &[Vector3<f32>],
+) -> bool {
+    let count = ring.len();
+    let a = vertices[ring[(position + count - 1) % count] as usize];
+    let b = vertices[ring[position] as usize];
+    let c = vertices[ring[(position + 1) % count] as usize];
+    if triangle_area_2d(a, b, c) >= 0.0 {
+        triangle_area_2d(a, b, point) > 0.0 && triangle_area_2d(b, c, point) > 0.0
+    } else {
+        triangle_area_2d(a, b, point) > 0.0 || triangle_area_2d(b, c, point) > 0.0
+    }
+}
+
+// Checks whether the segment `a - b` crosses the segment `c - d` or passes through one of its
+// ends.
+fn is_segment_blocked(a: Vector3<f32>, b: Vector3<f32>, c: Vector3<f32>, d: Vector3<f32>) -> bool {
+    let d1 = triangle_area_2d(a, b, c);
+    let d2 = triangle_area_2d(a, b, d);
+    let d3 = triangle_area_2d(c, d, a);
+    let d4 = triangle_area_2d(c, d, b);
+    if d1 * d2 < 0.0 && d3 * d4 < 0.0 {
+        return true;
+    }
+    let ab = b - a;
+    let length_squared = ab.x * ab.x + ab.z * ab.z;
+    [c, d].into_iter().any(|p| {
+        let t = ((p.x - a.x) * ab.x + (p.z - a.z) * ab.z) / length_squared;
+        t > 0.0 && t < 1.0 && is_nearly_collinear(a, p, b)
+    })
+}
+
+// Connects the hole with the outer boundary by a pair of coincident edges ("bridge"), so the
+// result is a single (weakly simple) polygon that could be ear-clipped. The bridge is made between
+// the rightmost vertex of the hole and the closest visible vertex of the boundary.
+fn merge_hole(
+    ring: &mut Vec<u32>,
+    hole: &[u32],
+    other_holes: &[&[u32]],
+    vertices: &[Vector3<f32>],
+) {
+    let point = |i: u32| vertices[i as usize];
+
+    let hole_position = (0..hole.len())
+        .max_by(|a, b| point(hole[*a]).x.total_cmp(&point(hole[*b]).x))
+        .unwrap_or_default();
+    let m = hole[hole_position];
+
+    let mut candidates = (0..ring.len()).collect::<Vec<_>>();
+    candidates.sort_by(|a, b| {
+        let da = (point(ring[*a]) - point(m)).norm_squared();
+        let db = (point(ring[*b]) - point(m)).norm_squared();
+        da.total_cmp(&db)
+    });
+
+    let is_visible = |position: usize| {
+        let p = ring[position];
+        if !is_inside_cone(ring, position, point(m), vertices)
+            || !is_inside_cone(hole, hole_position, point(p), vertices)
+        {
+            return false;
+        }
+        [ring.as_slice(), hole]
+            .into_iter()
+            .chain(other_holes.iter().copied())
+            .all(|boundary| {
+                (0..boundary.len()).all(|i| {
+                    let c = boundary[i];
+                    let d = boundary[(i + 1) % boundary.len()];
+                    c == p
+                        || c == m
+                        || d == p
+                        || d == m
+                        || !is_segment_blocked(point(p), point(m), point(c), point(d))
+                })
+            })
+    };
+
+    // Fall back to the closest vertex if there is no visible one, it may happen only with
+    // malformed input (for example, when the hole intersects the outline).
+    let ring_position = candidates
+        .iter()
+        .copied()
+        .find(|position| is_visible(*position))
+        .unwrap_or(candidates[0]);
+
+    let mut merged = Vec::with_capacity(ring.len() + hole.len() + 2);
+    merged.extend_from_slice(&ring[..=ring_position]);
+    merged.extend_from_slice(&hole[hole_position..]);
+    merged.extend_from_slice(&hole[..=hole_position]);
+    merged.extend_from_slice(&ring[ring_position..]);
+    *ring = merged;
+}
+
+// Triangulates a weakly simple polygon with "positive" winding using ear clipping. The ear with
+// the best shape is clipped first to avoid thin triangles.
+fn triangulate_ring(
+    mut ring: Vec<u32>,
+    vertices: &[Vector3<f32>],
+    triangles: &mut Vec<TriangleDefinition>,
+) {
+    let point = |i: u32| vertices[i as usize];
+
+    while ring.len() > 3 {
+        let count = ring.len();
+        let corner = |i: usize| {
+            (
+                ring[(i + count - 1) % count],
+                ring[i],
+                ring[(i + 1) % count],
+            )
+        };
+
+        let mut best_ear = None;
+        for i in 0..count {
+            let (a, b, c) = corner(i);
+            let (pa, pb, pc) = (point(a), point(b), point(c));
+            let area = triangle_area_2d(pa, pb, pc);
+            if area <= 0.0 || is_nearly_collinear(pa, pb, pc) {
+                continue;
+            }
+            let is_blocked = ring.iter().any(|&v| {
+                v != a && v != b && v != c && is_point_inside_triangle_2d(point(v), pa, pb, pc)
+            });
+            if is_blocked {
+                continue;
+            }
+            let perimeter_squared =
+                (pb - pa).norm_squared() + (pc - pb).norm_squared() + (pa - pc).norm_squared();
+            let quality = area / perimeter_squared;
+            if best_ear.map_or(true, |(_, best)| quality > best) {
+                best_ear = Some((i, quality));
+            }
+        }
+
+        let ear = match best_ear {
+            Some((i, _)) => i,
+            None => {
+                // Nearly collinear or duplicated vertices (bridges produce those) could be
+                // dropped without any triangles.
+                let collinear = (0..count).find(|i| {
+                    let (a, b, c) = corner(*i);
+                    is_nearly_collinear(point(a), point(b), point(c))
+                });
+                if let Some(i) = collinear {
+                    ring.remove(i);
+                    continue;
+                }
+                // Malformed input, clip the most convex corner as the last resort.
+                let convex = (0..count)
+                    .map(|i| {
+                        let (a, b, c) = corner(i);
+                        (i, triangle_area_2d(point(a), point(b), point(c)))
+                    })
+                    .filter(|(_, area)| *area > 0.0)
+                    .max_by(|(_, a), (_, b)| a.total_cmp(b));
+                match convex {
+                    Some((i, _)) => i,
+                    None => return,
+                }
+            }
+        };
+
+        let (a, b, c) = corner(ear);
+        triangles.push(TriangleDefinition([a, b, c]));
+        ring.remove(ear);
+    }
+
+    if let [a, b, c] = ring[..] {
+        let (pa, pb, pc) = (point(a), point(b), point(c));
+        if triangle_area_2d(pa, pb, pc) > 0.0 && !is_nearly_collinear(pa, pb, pc) {
+            triangles.push(TriangleDefinition([a, b, c]));
+        }
+    }
+}
+
+#[derive(PartialEq, Clone, Copy, Eq)]
+enum Winding {
+    Clockwise,
+    CounterClockwise,
+}
+
+/// Returns a rotation, that maps the given up axis to +Y, or `None` if the axis is +Y already.
+fn up_rotation(up_axis: Vector3<f32>) -> Option<UnitQuaternion<f32>> {
+    if up_axis == Vector3::y() {
+        return None;
+    }
+    Some(
+        UnitQuaternion::rotation_between(&up_axis, &Vector3::y()).unwrap_or_else(|| {
+            // The axis is opposite to +Y.
+            UnitQuaternion::from_axis_angle(&Vector3::x_axis(), std::f32::consts::PI)
+        }),
+    )
+}
+
+fn winding(a: Vector3<f32>, b: Vector3<f32>, c: Vector3<f32>) -> Winding {
+    if triangle_area_2d(a, b, c) > 0.0 {
+        Winding::Clockwise
+    } else {
+        Winding::CounterClockwise
+    }
+}
+
+fn cross_2d(a: Vector3<f32>, b: Vector3<f32>) -> f32 {
+    a.x * b.z - a.z * b.x
+}
+
+fn is_point_inside_triangle_2d(
+    p: Vector3<f32>,
+    a: Vector3<f32>,
+    b: Vector3<f32>,
+    c: Vector3<f32>,
+) -> bool {
+    let ab = triangle_area_2d(a, b, p);
+    let bc = triangle_area_2d(b, c, p);
+    let ca = triangle_area_2d(c, a, p);
+    (ab >= 0.0 && bc >= 0.0 && ca >= 0.0) || (ab <= 0.0 && bc <= 0.0 && ca <= 0.0)
+}
+
+fn distance_to_segment(p: Vector3<f32>, a: Vector3<f32>, b: Vector3<f32>) -> f32 {
+    let ab = b - a;
+    let length_squared = ab.norm_squared();
+    let t = if length_squared > f32::EPSILON {
+        ((p - a).dot(&ab) / length_squared).clamp(0.0, 1.0)
+    } else {
+        0.0
+    };
+    (a + ab.scale(t)).metric_distance(&p)
+}
+
+/// Returns the distance between segments `p0 -> p1` and `q0 -> q1` on XZ plane.
+fn segment_distance_2d(
+    p0: Vector3<f32>,
+    p1: Vector3<f32>,
+    q0: Vector3<f32>,
+    q1: Vector3<f32>,
+) -> f32 {
+    if segment_intersection_2d(p0, p1, q0, q1).is_some_and(|(t, _)| (0.0..=1.0).contains(&t)) {
+        return 0.0;
+    }
+    let [p0, p1, q0, q1] = [p0, p1, q0, q1].map(|point| Vector3::new(point.x, 0.0, point.z));
+    distance_to_segment(p0, q0, q1)
+        .min(distance_to_segment(p1, q0, q1))
+        .min(distance_to_segment(q0, p0, p1))
+        .min(distance_to_segment(q1, p0, p1))
+}
+
+/// Separating axis test of a triangle and an axis-aligned bounding box.
+fn triangle_intersects_aabb(triangle: [Vector3<f32>; 3], aabb: &AxisAlignedBoundingBox) -> bool {
+    let center = aabb.center();
+    let half_extents = aabb.half_extents();
+    let [a, b, c] = triangle.map(|vertex| vertex - center);
+    let edges = [b - a, c - b, a - c];
+
+    let is_separated = |axis: Vector3<f32>| {
+        let radius = half_extents.dot(&axis.abs());
+        let (a, b, c) = (a.dot(&axis), b.dot(&axis), c.dot(&axis));
+        a.min(b).min(c) > radius || a.max(b).max(c) < -radius
+    };
+
+    let box_axes = [Vector3::x(), Vector3::y(), Vector3::z()];
+    if box_axes.iter().any(|axis| is_separated(*axis)) {
+        return false;
+    }
+    if is_separated(edges[0].cross(&edges[1])) {
+        return false;
+    }
+    !box_axes
+        .iter()
+        .any(|axis| edges.iter().any(|edge| is_separated(axis.cross(edge))))
+}
+
+/// Checks whether two border edges (in the space, where the up axis is +Y) face each other on the
+/// horizontal plane and returns a pair of points on the edges in the middle of their overlapping
+/// part and a vector from the middle of the overlapping part of the first edge to its end.
+fn step_between(
+    a: [Vector3<f32>; 2],
+    b: [Vector3<f32>; 2],
+    max_horizontal_gap: f32,
+) -> Option<(Vector3<f32>, Vector3<f32>, Vector3<f32>)> {
+    const MIN_OVERLAP: f32 = 1.0e-3;
+    // Cosine of the maximum angle between the edges.
+    const MIN_COS: f32 = 0.99;
+
+    let flat = |point: Vector3<f32>| Vector2::new(point.x, point.z);
+    let origin = flat(a[0]);
+    let direction = flat(a[1]) - origin;
+    let length = direction.norm();
+    let direction = direction.try_normalize(f32::EPSILON)?;
+    let other_direction = (flat(b[1]) - flat(b[0])).try_normalize(f32::EPSILON)?;
+    // The triangles of the edges must be on the opposite sides of the edges.
+    if direction.dot(&other_direction) > -MIN_COS {
+        return None;
+    }
+
+    let normal = Vector2::new(-direction.y, direction.x);
+    let mut range = [f32::MAX, -f32::MAX];
+    for point in b {
+        let offset = flat(point) - origin;
+        if offset.dot(&normal).abs() > max_horizontal_gap {
+            return None;
+        }
+        let t = offset.dot(&direction);
+        range = [range[0].min(t), range[1].max(t)];
+    }
+    let begin = range[0].max(0.0);
+    let end = range[1].min(length);
+    if end - begin <= MIN_OVERLAP {
+        return None;
+    }
+
+    let span_begin = a[0].lerp(&a[1], begin / length);
+    let span_end = a[0].lerp(&a[1], end / length);
+    let middle = span_begin.lerp(&span_end, 0.5);
+    let other_edge = flat(b[1]) - flat(b[0]);
+    let s =
+        ((flat(middle) - flat(b[0])).dot(&other_edge) / other_edge.norm_squared()).clamp(0.0, 1.0);
+    Some((
+        middle,
+        b[0].lerp(&b[1], s),
+        (span_end - span_begin).scale(0.5),
+    ))
+}
+
+/// Spherically interpolates between two normalized directions. Opposite directions are
+/// interpolated by rotation around the up axis.
+fn blend_directions(a: Vector3<f32>, b: Vector3<f32>, t: f32) -> Vector3<f32> {
+    Unit::new_unchecked(a)
+        .try_slerp(&Unit::new_unchecked(b), t, 1.0e-6)
+        .map(|direction| direction.into_inner())
+        .unwrap_or_else(|| {
+            UnitQuaternion::from_axis_angle(&Vector3::y_axis(), std::f32::consts::PI * t) * a
+        })
+}
+
+/// Samples a centripetal Catmull-Rom spline segment between `p1` and `p2` at `t`. Returns the
+/// point on the spline and its (non-normalized) tangent. Unlike the uniform spline, the centripetal
+/// one does not form loops and cusps, when the adjacent segments have very different lengths.
+fn catmull_rom(
+    p0: Vector3<f32>,
+    p1: Vector3<f32>,
+    p2: Vector3<f32>,
+    p3: Vector3<f32>,
+    t: f32,
+) -> (Vector3<f32>, Vector3<f32>) {
+    let knot = |a: Vector3<f32>, b: Vector3<f32>| a.metric_distance(&b).sqrt().max(1.0e-4);
+    let (d0, d1, d2) = (knot(p0, p1), knot(p1, p2), knot(p2, p3));
+    let m1 = ((p1 - p0).scale(1.0 / d0) - (p2 - p0).scale(1.0 / (d0 + d1))
+        + (p2 - p1).scale(1.0 / d1))
+    .scale(d1);
+    let m2 = ((p2 - p1).scale(1.0 / d1) - (p3 - p1).scale(1.0 / (d1 + d2))
+        + (p3 - p2).scale(1.0 / d2))
+    .scale(d1);
+    // Cubic Hermite segment with the tangents above.
+    let (t2, t3) = (t * t, t * t * t);
+    let point = p1.scale(2.0 * t3 - 3.0 * t2 + 1.0)
+        + m1.scale(t3 - 2.0 * t2 + t)
+        + p2.scale(3.0 * t2 - 2.0 * t3)
+        + m2.scale(t3 - t2);
+    let tangent = (p1 - p2).scale(6.0 * t2 - 6.0 * t)
+        + m1.scale(3.0 * t2 - 4.0 * t + 1.0)
+        + m2.scale(3.0 * t2 - 2.0 * t);
+    (point, tangent)
+}
+
+/// Number of pieces of a spline segment, that are used to approximate its arc length.
+const SPLINE_LENGTH_SAMPLES: usize = 16;
+
+/// Samples a Catmull-Rom spline segment between `p1` and `p2` at the given fraction of its
+/// arc length, so that evenly spaced fractions give evenly spaced points. Returns the point on the
+/// spline, its (non-normalized) tangent and the arc length of the segment.
+fn catmull_rom_by_length(
+    p0: Vector3<f32>,
+    p1: Vector3<f32>,
+    p2: Vector3<f32>,
+    p3: Vector3<f32>,
+    fraction: f32,
+) -> (Vector3<f32>, Vector3<f32>, f32) {
+    let mut lengths = [0.0f32; SPLINE_LENGTH_SAMPLES + 1];
+    let mut previous = p1;
+    for i in 1..=SPLINE_LENGTH_SAMPLES {
+        let (point, _) = catmull_rom(p0, p1, p2, p3, i as f32 / SPLINE_LENGTH_SAMPLES as f32);
+        lengths[i] = lengths[i - 1] + point.metric_distance(&previous);
+        previous = point;
+    }
+    let length = lengths[SPLINE_LENGTH_SAMPLES];
+    let distance = fraction.clamp(0.0, 1.0) * length;
+    let piece = lengths
+        .partition_point(|l| *l < distance)
+        .clamp(1, SPLINE_LENGTH_SAMPLES);
+    let piece_length = lengths[piece] - lengths[piece - 1];
+    let local = if piece_length > f32::EPSILON {
+        (distance - lengths[piece - 1]) / piece_length
+    } else {
+        0.0
+    };
+    let t = ((piece - 1) as f32 + local) / SPLINE_LENGTH_SAMPLES as f32;
+    let (point, tangent) = catmull_rom(p0, p1, p2, p3, t);
+    (point, tangent, length)
+}
+
+/// Clips triangle `abc` by a sphere. Returns a convex polygon, that contains the part of the
+/// triangle inside the sphere, or an empty polygon if there is no such part. The disc, where the
+/// sphere intersects the plane of the triangle, is approximated with a circumscribed polygon, so
+/// the result could be slightly bigger than the real part.
+fn clip_triangle_by_sphere(
+    a: Vector3<f32>,
+    b: Vector3<f32>,
+    c: Vector3<f32>,
+    center: Vector3<f32>,
+    radius: f32,
+) -> Vec<Vector3<f32>> {
+    const SIDES: usize = 16;
+
+    let (Some(normal), Some(tangent)) = (
+        (b - a).cross(&(c - a)).try_normalize(f32::EPSILON),
+        (b - a).try_normalize(f32::EPSILON),
+    ) else {
+        return Vec::new();
+    };
+    let distance = (center - a).dot(&normal);
+    if distance.abs() >= radius {
+        return Vec::new();
+    }
+    let disc_center = center - normal.scale(distance);
+    let disc_radius = (radius * radius - distance * distance).sqrt();
+    let bitangent = normal.cross(&tangent);
+
+    let mut polygon = vec![a, b, c];
+    for side in 0..SIDES {
+        let angle = side as f32 * std::f32::consts::TAU / SIDES as f32;
+        let direction = tangent.scale(angle.cos()) + bitangent.scale(angle.sin());
+        let signed_distance = |p: &Vector3<f32>| (p - disc_center).dot(&direction) - disc_radius;
+        let mut clipped = Vec::with_capacity(polygon.len() + 1);
+        for (i, current) in polygon.iter().enumerate() {
+            let next = &polygon[(i + 1) % polygon.len()];
+            let (current_distance, next_distance) =
+                (signed_distance(current), signed_distance(next));
+            if current_distance <= 0.0 {
+                clipped.push(*current);
+            }
+            if (current_distance <= 0.0) != (next_distance <= 0.0) {
+                let t = current_distance / (current_distance - next_distance);
+                clipped.push(current.lerp(next, t));
+            }
+        }
+        polygon = clipped;
+        if polygon.len() < 3 {
+            return Vec::new();
+        }
+    }
+    polygon
+}
+
+/// Returns a uniformly distributed random point inside triangle `abc`.
+fn random_point_in_triangle(
+    a: Vector3<f32>,
+    b: Vector3<f32>,
+    c: Vector3<f32>,
+    rng: &mut impl Rng,
+) -> Vector3<f32> {
+    let u = rng.gen::<f32>().sqrt();
+    let v = rng.gen::<f32>();
+    a.scale(1.0 - u) + b.scale(u * (1.0 - v)) + c.scale(u * v)
+}
+
+/// Clips segment `p0 -> p1` by triangle `abc` on XZ plane. Returns a pair of fractions along
+/// the segment, that define the part of the segment inside the triangle.
+fn clip_segment_by_triangle_2d(
+    p0: Vector3<f32>,
+    p1: Vector3<f32>,
+    a: Vector3<f32>,
+    b: Vector3<f32>,
+    c: Vector3<f32>,
+) -> Option<(f32, f32)> {
+    let sign = cross_2d(b - a, c - a).signum();
+    let direction = p1 - p0;
+    let (mut t0, mut t1) = (0.0f32, 1.0f32);
+    for (e0, e1) in [(a, b), (b, c), (c, a)] {
+        let edge = e1 - e0;
+        // Both values are positive on the inner side of the edge.
+        let start = sign * cross_2d(edge, p0 - e0);
+        let delta = sign * cross_2d(edge, direction);
+        if delta.abs() <= f32::EPSILON {
+            if start < 0.0 {
+                return None;
+            }
+            continue;
+        }
+        let t = -start / delta;
+        if delta > 0.0 {
+            t0 = t0.max(t);
+        } else {
+            t1 = t1.min(t);
+        }
+        if t0 > t1 {
+            return None;
+        }
+    }
+    Some((t0, t1))
+}
+
+/// Intersects segment `p0 -> p1` with segment `q0 -> q1` on XZ plane. Returns a pair of
+/// fractions along each segment in case of intersection.
+fn segment_intersection_2d(
+    p0: Vector3<f32>,
+    p1: Vector3<f32>,
+    q0: Vector3<f32>,
+    q1: Vector3<f32>,
+) -> Option<(f32, f32)> {
+    let r = p1 - p0;
+    let s = q1 - q0;
+    let denominator = cross_2d(r, s);
+    if denominator.abs() <= f32::EPSILON {
+        return None;
+    }
+    let qp = q0 - p0;
+    let t = cross_2d(qp, s) / denominator;
+    let u = cross_2d(qp, r) / denominator;
+    if (0.0..=1.0).contains(&u) {
+        Some((t, u))
+    } else {
+        None
+    }
+}
+
+/// Returns the time (in seconds), after which two circles on the same plane collide, if they keep
+/// their velocities. `offset` and `relative_velocity` are the position and the velocity of the
+/// second circle relative to the first one (both must lie on the plane of the circles), `range` is
+/// the sum of their radii. Returns zero if the circles overlap already, and `None` if they never
+/// collide.
+fn time_to_collision(
+    offset: Vector3<f32>,
+    relative_velocity: Vector3<f32>,
+    range: f32,
+) -> Option<f32> {
+    let c = offset.norm_squared() - range * range;
+    if c < 0.0 {
+        return Some(0.0);
+    }
+    let a = relative_velocity.norm_squared();
+    let b = 2.0 * offset.dot(&relative_velocity);
+    let discriminant = b * b - 4.0 * a * c;
+    if a <= f32::EPSILON || discriminant < 0.0 {
+        return None;
+    }
+    let time = (-b - discriminant.sqrt()) / (2.0 * a);
+    (time >= 0.0).then_some(time)
+}
+
+/// Splits the triangle at the midpoints of its edges. `midpoints` contains an index of the
+/// midpoint vertex for every split edge (`[a, b]`, `[b, c]`, `[c, a]`). The winding order of the
+/// triangle is kept.
+fn split_triangle(
+    triangle: [u32; 3],
+    midpoints: [Option<u32>; 3],
+    vertices: &[Vector3<f32>],
+) -> Vec<[u32; 3]> {
+    let rotate = |start: usize| {
+        (
+            triangle[start],
+            triangle[(start + 1) % 3],
+            triangle[(start + 2) % 3],
+            midpoints[start],
+            midpoints[(start + 1) % 3],
+        )
+    };
+    match midpoints.iter().filter(|m| m.is_some()).count() {
+        1 => {
+            let start = midpoints.iter().position(|m| m.is_some()).unwrap();
+            let (a, b, c, m, _) = rotate(start);
+            let m = m.unwrap();
+            vec![[a, m, c], [m, b, c]]
+        }
+        2 => {
+            // The edge `[c, a]` is the one that is not split.
+            let start = (midpoints.iter().position(|m| m.is_none()).unwrap() + 1) % 3;
+            let (a, b, c, m0, m1) = rotate(start);
+            let (m0, m1) = (m0.unwrap(), m1.unwrap());
+            // Split the remaining quad using its shortest diagonal.
+            let position = |i: u32| vertices[i as usize];
+            if position(a).metric_distance(&position(m1))
+                <= position(m0).metric_distance(&position(c))
+            {
+                vec![[m0, b, m1], [a, m0, m1], [a, m1, c]]
+            } else {
+                vec![[m0, b, m1], [a, m0, c], [m0, m1, c]]
+            }
+        }
+        3 => {
+            let [a, b, c] = triangle;
+            let [m0, m1, m2] = midpoints.map(Option::unwrap);
+            vec![[a, m0, m2], [m0, b, m1], [m2, m1, c], [m0, m1, m2]]
+        }
+        _ => vec![triangle],
+    }
+}
+
+fn make_graph(triangles: &[TriangleDefinition], vertices: &[Vector3<f32>]) -> Graph<Vertex> {
+    let mut graph = Graph::new();
+
+    // Add vertices at the center of each triangle.
+    for (triangle_index, triangle) in triangles.iter().enumerate() {
+        let a = vertices[triangle[0] as usize];
+        let b = vertices[triangle[1] as usize];
+        let c = vertices[triangle[2] as usize];
+
+        let center = (a + b + c).scale(1.0 / 3.0);
+        graph.add_vertex(Vertex {
+            triangle_index,
+            data: VertexData::new(center),
+        });
+    }
+
+    // Build edge-triangle map first.
+    #[derive(Copy, Clone, PartialEq, Hash, Eq)]
+    struct Edge {
+        a: usize,
+        b: usize,
+    }
+
+    let total_edge_count = triangles.len() * 3;
+    let mut edge_triangle_map =
+        FxHashMap::with_capacity_and_hasher(total_edge_count, FxBuildHasher::default());
+
+    for (triangle_index, triangle) in triangles.iter().enumerate() {
+        for edge in triangle.edges() {
+            edge_triangle_map.insert(
+                Edge {
+                    a: edge.a as usize,
+                    b: edge.b as usize,
+                },
+                triangle_index,
+            );
+        }
+    }
+
+    // Link vertices.
+    for (triangle_index, triangle) in triangles.iter().enumerate() {
+        for edge in triangle.edges() {
+            // Adjacent edge must have opposite winding.
+            let adjacent_edge = Edge {
+                a: edge.b as usize,
+                b: edge.a as usize,
+            };
+
+            if let Some(adjacent_triangle_index) = edge_triangle_map.get(&adjacent_edge) {
+                graph.link_bidirect(triangle_index, *adjacent_triangle_index);
+            }
+        }
+    }
+
+    graph
+}
+
+/// A point, where a segment traced along the surface of a navmesh leaves the navmesh. See
+/// [`Navmesh::raycast`] for more info.
+#[derive(Copy, Clone, Debug, PartialEq)]
+pub struct NavmeshRaycastHit {
+    /// A point on the border edge of the navmesh, where the segment leaves the navmesh.
+    pub position: Vector3<f32>,
+    /// Indices of the vertices of the border edge crossed by the segment.
+    pub edge: [u32; 2],
+    /// Index of the triangle from which the segment has left the navmesh.
+    pub triangle: usize,
+}
+
+/// Returns a point of the triangle `abc` that is closest to the given point `p`.
+fn closest_point_on_triangle(
+    p: Vector3<f32>,
+    a: Vector3<f32>,
+    b: Vector3<f32>,
+    c: Vector3<f32>,
+) -> Vector3<f32> {
+    let ab = b - a;
+    let ac = c - a;
+    let ap = p - a;
+    let d1 = ab.dot(&ap);
+    let d2 = ac.dot(&ap);
+    if d1 <= 0.0 && d2 <= 0.0 {
+        return a;
+    }
+
+    let bp = p - b;
+    let d3 = ab.dot(&bp);
+    let d4 = ac.dot(&bp);
+    if d3 >= 0.0 && d4 <= d3 {
+        return b;
+    }
+
+    let vc = d1 * d4 - d3 * d2;
+    if vc <= 0.0 && d1 >= 0.0 && d3 <= 0.0 {
+        return a + ab.scale(d1 / (d1 - d3));
+    }
+
+    let cp = p - c;
+    let d5 = ab.dot(&cp);
+    let d6 = ac.dot(&cp);
+    if d6 >= 0.0 && d5 <= d6 {
+        return c;
+    }
+
+    let vb = d5 * d2 - d1 * d6;
+    if vb <= 0.0 && d2 >= 0.0 && d6 <= 0.0 {
+        return a + ac.scale(d2 / (d2 - d6));
+    }
+
+    let va = d3 * d6 - d5 * d4;
+    if va <= 0.0 && (d4 - d3) >= 0.0 && (d5 - d6) >= 0.0 {
+        return b + (c - b).scale((d4 - d3) / ((d4 - d3) + (d5 - d6)));
+    }
+
+    let denominator = va + vb + vc;
+    if denominator.abs() <= f32::EPSILON {
+        // Degenerate triangle.
+        return a;
+    }
+    a + ab.scale(vb / denominator) + ac.scale(vc / denominator)
+}
+
+/// Collects the world-space triangles of every surface of the given mesh nodes. Handles, that do
+/// not point to mesh nodes, are ignored.
+fn collect_world_triangles(
+    graph: &scene::graph::Graph,
+    nodes: &[Handle<Node>],
+) -> Vec<[Vector3<f32>; 3]> {
+    let mut triangles = Vec::new();
+    for mesh in nodes
+        .iter()
+        .filter_map(|handle| graph.try_get(*handle).and_then(|node| node.cast::<Mesh>()))
+    {
+        let global_transform = mesh.global_transform();
+        for surface in mesh.surfaces() {
+            let shared_data = surface.data();
+            let shared_data = shared_data.data_ref();
+
+            let vertex_buffer = &shared_data.vertex_buffer;
+            for triangle in shared_data.geometry_buffer.iter() {
+                let mut points = [Vector3::default(); 3];
+                for (point, index) in points.iter_mut().zip(triangle.0) {
+                    *point = global_transform
+                        .transform_point(&Point3::from(
+                            vertex_buffer
+                                .get(index as usize)
+                                .unwrap()
+                                .read_3_f32(VertexAttributeUsage::Position)
+                                .unwrap(),
+                        ))
+                        .coords;
+                }
+                triangles.push(points);
+            }
+        }
+    }
+    triangles
+}
+
+/// A set of parameters for navmesh baking. See [`Navmesh::bake_from_graph`] for more info.
+#[derive(Clone, Debug, PartialEq)]
+pub struct NavmeshBakeParams {
+    /// Maximum angle (in radians) between a surface and the horizontal plane, for the surface to
+    /// be walkable. Default is 45 degrees.
+    pub max_slope: f32,
+    /// Radius of the agents that will walk on the navmesh. Walkable triangles, which centers are
+    /// closer than this distance to an obstacle (a non-walkable surface that rises above the
+    /// triangle) will be removed. Default is 0.2 meters.
+    pub agent_radius: f32,
+    /// Height of the agents that will walk on the navmesh. Walkable triangles, which have an
+    /// obstacle above them closer than this distance will be removed. Default is 2.0 meters.
+    pub agent_height: f32,
+    /// Size of a cell that is used to weld vertices, the vertices that fall into the same cell
+    /// will be merged into one. Default is 0.01 meters.
+    pub cell_size: f32,
+    /// "Up" direction of the world, that is used to measure slopes and the space above the
+    /// surfaces. It is also set as the up axis of the baked navmesh, see
+    /// [`Navmesh::set_up_axis`]. Default is +Y.
+    pub up_axis: Vector3<f32>,
+    /// Maximum height of a step, that agents could walk up and down. Walkable surfaces, that are
+    /// separated by a vertical gap, which does not exceed this height, are connected by
+    /// bidirectional off-mesh links (see [`Navmesh::connect_steps`]). Default is zero, which means
+    /// that the steps are not connected.
+    pub max_step_height: f32,
+    /// Maximum height of a ledge, that agents could jump down from. Walkable surfaces, that are
+    /// separated by a vertical gap, which is higher than [`Self::max_step_height`], but does not
+    /// exceed this height, are connected by one-way off-mesh links from the upper surface to the
+    /// lower one (see [`Navmesh::connect_steps`]). Default is zero, which means that the ledges
+    /// are not connected.
+    pub max_drop_height: f32,
+}
+
+impl Default for NavmeshBakeParams {
+    fn default() -> Self {
+        Self {
+            max_slope: 45.0f32.to_radians(),
+            agent_radius: 0.2,
+            agent_height: 2.0,
+            cell_size: 0.01,
+            up_axis: Vector3::y(),
+            max_step_height: 0.0,
+            max_drop_height: 0.0,
+        }
+    }
+}
+
+/// A filter, that defines how a navmesh could be traversed, based on the flags of the triangles
+/// (see [`Navmesh::set_triangle_flags`]). A triangle passes the filter if it has at least one of
+/// the included flags (or if there are no included flags at all) and has none of the excluded
+/// flags. Every flag also has a traversal cost multiplier, the cost of moving into a triangle is
+/// multiplied by the multipliers of all its flags (in addition to the cost of the triangle itself,
+/// see [`Navmesh::set_triangle_cost`]). Additionally, the layer of a triangle (see
+/// [`Navmesh::set_triangle_layer`]) must be in the layer mask of the filter.
+///
+/// The filter describes how some kind of units traverses the world, so it is advised to create
+/// a filter per unit type once and reuse it for every query. Default filter has no included and
+/// excluded flags and all multipliers are `1.0`, it does not change path finding in any way.
+///
+/// ```rust
+/// # use fyrox_impl::utils::navmesh::NavmeshQueryFilter;
+/// const ROAD: u32 = 1 << 0;
+/// const GRASS: u32 = 1 << 1;
+/// const HAZARD: u32 = 1 << 2;
+///
+/// // Vehicles, that must avoid hazardous areas and prefer roads over grass.
+/// let mut filter = NavmeshQueryFilter {
+///     exclude_flags: HAZARD,
+///     ..Default::default()
+/// };
+/// filter.set_flag_cost(GRASS, 4.0);
+/// assert!(filter.passes(ROAD));
+/// assert!(!filter.passes(ROAD | HAZARD));
+/// assert_eq!(filter.cost_multiplier(ROAD), 1.0);
+/// assert_eq!(filter.cost_multiplier(GRASS), 4.0);
+/// ```
+#[derive(Copy, Clone, Debug, PartialEq, Visit, Reflect)]
+pub struct NavmeshQueryFilter {
+    /// A set of flags, at least one of which must be set for a triangle to pass the filter. Zero
+    /// means that any triangle passes (including the ones without flags).
+    pub include_flags: u32,
+    /// A set of flags, none of which must be set for a triangle to pass the filter.
+    pub exclude_flags: u32,
+    /// Traversal cost multipliers of every flag, `i`-th multiplier corresponds to `1 << i` flag.
+    pub flag_costs: [f32; 32],
+    /// A set of layers, a triangle must be on one of them to pass the filter. `i`-th bit
+    /// corresponds to `i`-th layer. Default mask contains every layer.
+    #[visit(optional)]
+    pub layer_mask: u32,
+}
+
+impl Default for NavmeshQueryFilter {
+    fn default() -> Self {
+        Self {
+            include_flags: 0,
+            exclude_flags: 0,
+            flag_costs: [1.0; 32],
+            layer_mask: u32::MAX,
+        }
+    }
+}
+
+impl NavmeshQueryFilter {
+    /// Checks whether a triangle with the given flags passes the filter.
+    pub fn passes(&self, flags: u32) -> bool {
+        (self.include_flags == 0 || flags & self.include_flags != 0)
+            && flags & self.exclude_flags == 0
+    }
+
+    /// Checks whether a triangle on the given layer passes the layer mask of the filter.
+    pub fn passes_layer(&self, layer: u32) -> bool {
+        1u32.checked_shl(layer)
+            .is_some_and(|bit| self.layer_mask & bit != 0)
+    }
+
+    /// Sets traversal cost multiplier for every given flag.
+    pub fn set_flag_cost(&mut self, flags: u32, cost: f32) {
+        for (i, flag_cost) in self.flag_costs.iter_mut().enumerate() {
+            if flags & (1 << i) != 0 {
+                *flag_cost = cost;
+            }
+        }
+    }
+
+    /// Returns traversal cost multiplier of a triangle with the given flags, it is a product of
+    /// the multipliers of every flag.
+    pub fn cost_multiplier(&self, flags: u32) -> f32 {
+        let mut multiplier = 1.0;
+        let mut bits = flags;
+        while bits != 0 {
+            multiplier *= self.flag_costs[bits.trailing_zeros() as usize];
+            // Clear the lowest set bit.
+            bits &= bits - 1;
+        }
+        multiplier
+    }
+}
+
+/// Statistics of navmesh simplification. See [`Navmesh::simplify`] for more info.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq)]
+pub struct NavmeshSimplifyStats {
+    /// Number of triangles before the simplification.
+    pub triangles_before: usize,
+    /// Number of triangles after the simplification.
+    pub triangles_after: usize,
+    /// Number of vertices before the simplification.
+    pub vertices_before: usize,
+    /// Number of vertices after the simplification.
+    pub vertices_after: usize,
+}
+
+/// A path between two points together with its cost. See [`Navmesh::find_path_with_cost`] for
+/// more info.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NavmeshPath {
+    /// Points of the path, the first one is the start point and the last one is the end point.
+    pub points: Vec<Vector3<f32>>,
+    /// Total cost of the path, see [`Navmesh::find_path_with_cost`].
+    pub cost: f32,
+}
+
+/// A reason why a path between two points could not be found. See [`Navmesh::find_path`] and
+/// [`NavmeshAgent::update`] for more info. The error could be converted to [`PathError`], so the
+/// code, that handles the errors of the path search, could be used with the navmesh queries.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NavmeshPathError {
+    /// The start point could not be located on the navmesh (the navmesh is empty).
+    StartOffMesh,
+    /// The end point is not on the walkable part of the navmesh: the closest triangle is excluded
+    /// by the query filter (see [`NavmeshQueryFilter`]) or blocked by an obstacle (see
+    /// [`Navmesh::add_obstacle`]).
+    EndOffMesh,
+    /// The start and the end points are not connected: they are on different islands of the
+    /// navmesh (see [`Navmesh::connected_components`]) or all the paths between them are blocked.
+    Disconnected,
+    /// The search has hit its limit of iterations (see [`Graph::max_search_iterations`]) before
+    /// reaching the end point.
+    SearchLimitExceeded(i32),
+    /// The search has expanded the maximum number of triangles (see
+    /// [`Navmesh::set_max_search_nodes`]) before reaching the end point.
+    SearchExhausted(usize),
+    /// The search has failed because of invalid data of the navmesh.
+    Search(PathError),
+}
+
+impl Display for NavmeshPathError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NavmeshPathError::StartOffMesh => {
+                write!(f, "Start point is not on the navmesh.")
+            }
+            NavmeshPathError::EndOffMesh => {
+                write!(f, "End point is not on the walkable part of the navmesh.")
+            }
+            NavmeshPathError::Disconnected => {
+                write!(f, "End point is unreachable from start point.")
+            }
+            NavmeshPathError::SearchLimitExceeded(iterations) => {
+                write!(f, "Maximum search iterations ({iterations}) hit.")
+            }
+            NavmeshPathError::SearchExhausted(nodes) => {
+                write!(f, "Maximum number of expanded triangles ({nodes}) hit.")
+            }
+            NavmeshPathError::Search(err) => err.fmt(f),
+        }
+    }
+}
+
+impl std::error::Error for NavmeshPathError {}
+
+impl From<PathError> for NavmeshPathError {
+    fn from(err: PathError) -> Self {
+        match err {
+            PathError::Empty => NavmeshPathError::StartOffMesh,
+            PathError::Unreachable => NavmeshPathError::Disconnected,
+            PathError::HitMaxSearchIterations(iterations) => {
+                NavmeshPathError::SearchLimitExceeded(iterations)
+            }
+            PathError::SearchExhausted(nodes) => NavmeshPathError::SearchExhausted(nodes),
+            err => NavmeshPathError::Search(err),
+        }
+    }
+}
+
+impl From<NavmeshPathError> for PathError {
+    fn from(err: NavmeshPathError) -> Self {
+        match err {
+            NavmeshPathError::StartOffMesh => PathError::Empty,
+            NavmeshPathError::EndOffMesh | NavmeshPathError::Disconnected => PathError::Unreachable,
+            NavmeshPathError::SearchLimitExceeded(iterations) => {
+                PathError::HitMaxSearchIterations(iterations)
+            }
+            NavmeshPathError::SearchExhausted(nodes) => PathError::SearchExhausted(nodes),
+            NavmeshPathError::Search(err) => err,
+        }
+    }
+}
+
+/// A unique identifier of an asynchronous path request. See [`Navmesh::request_path`] for more
+/// info.
+#[derive(Copy, Clone, Debug, Default, PartialEq, Eq, Hash, PartialOrd, Ord)]
+pub struct PathRequestId(u64);
+
+/// Status of an asynchronous path request. See [`Navmesh::request_path`] for more info.
+#[derive(Clone, Debug, PartialEq)]
+pub enum PathStatus {
+    /// The path is not calculated yet.
+    Pending,
+    /// The path is calculated.
+    Ready(Vec<Vector3<f32>>),
+    /// The path could not be calculated.
+    Failed(NavmeshPathError),
+    /// There's no such request. It could happen if the request was cancelled or its result was
+    /// already taken.
+    Unknown,
+}
+
+#[derive(Clone, Debug)]
+struct PathQuery {
+    from: Vector3<f32>,
+    to: Vector3<f32>,
+    radius: f32,
+    smoothing: SmoothingMode,
+    allow_partial_path: bool,
+    filter: NavmeshQueryFilter,
+    priority: i32,
+}
+
+#[derive(Clone, Debug)]
+struct ComputedPath {
+    path: Vec<Vector3<f32>>,
+    offmesh_segments: Vec<OffMeshLinkSegment>,
+    corridor: Vec<usize>,
+    partial: bool,
+}
+
+#[derive(Default, Debug)]
+struct PathRequestQueueState {
+    next_id: u64,
+    // Sorted by priority (from the highest to the lowest), then by the order of addition.
+    pending: VecDeque<(PathRequestId, PathQuery)>,
+    in_progress: Vec<PathRequestId>,
+    results: FxHashMap<PathRequestId, Result<ComputedPath, NavmeshPathError>>,
+}
+
+/// A queue of asynchronous path requests. It is protected by a mutex, so the requests could be
+/// added and processed using a shared reference to a navmesh (for example, from multiple
+/// threads).
+#[derive(Default, Debug)]
+struct PathRequestQueue(Mutex<PathRequestQueueState>);
+
+impl Clone for PathRequestQueue {
+    fn clone(&self) -> Self {
+        // Requests are bound to a particular navmesh instance.
+        Self::default()
+    }
+}
+
+/// A key of a cached triangle path. Floating-point parameters are stored as bits, since the
+/// cached path is valid only for the exact same parameters.
+#[derive(Copy, Clone, PartialEq, Eq, Hash, Debug)]
+struct PathCacheKey {
+    from: usize,
+    to: usize,
+    clearance: u32,
+    include_flags: u32,
+    exclude_flags: u32,
+    flag_costs: [u32; 32],
+    layer_mask: u32,
+}
+
+impl PathCacheKey {
+    fn new(from: usize, to: usize, clearance: f32, filter: &NavmeshQueryFilter) -> Self {
+        Self {
+            from,
+            to,
+            clearance: clearance.to_bits(),
+            include_flags: filter.include_flags,
+            exclude_flags: filter.exclude_flags,
+            flag_costs: filter.flag_costs.map(f32::to_bits),
+            layer_mask: filter.layer_mask,
+        }
+    }
+}
+
+#[derive(Debug)]
+struct PathCacheEntry {
+    // Reversed path of triangle indices, as returned by `Navmesh::build_triangle_path`.
+    triangles: Vec<usize>,
+    kind: PathKind,
+    cost: f32,
+    last_used: u64,
+}
+
+#[derive(Default, Debug)]
+struct PathCacheState {
+    time: u64,
+    entries: FxHashMap<PathCacheKey, PathCacheEntry>,
+}
+
+/// A cache of triangle paths with least-recently-used eviction policy. It is protected by a mutex,
+/// so it could be used with a shared reference to a navmesh.
+#[derive(Default, Debug)]
+struct PathCache {
+    // Kept outside of the mutex, so the disabled cache (the default) is never locked.
+    capacity: AtomicUsize,
+    state: Mutex<PathCacheState>,
+}
+
+impl Clone for PathCache {
+    fn clone(&self) -> Self {
+        // Cached paths are bound to a particular navmesh instance, only the capacity is copied.
+        Self {
+            capacity: AtomicUsize::new(self.capacity()),
+            state: Default::default(),
+        }
+    }
+}
+
+impl PathCache {
+    fn get(&self, key: &PathCacheKey, path: &mut Vec<usize>) -> Option<(PathKind, f32)> {
+        if self.capacity() == 0 {
+            return None;
+        }
+        let mut state = self.state.lock();
+        state.time += 1;
+        let time = state.time;
+        let entry = state.entries.get_mut(key)?;
+        entry.last_used = time;
+        path.clear();
+        path.extend_from_slice(&entry.triangles);
+        Some((entry.kind, entry.cost))
+    }
+
+    fn insert(&self, key: PathCacheKey, path: &[usize], kind: PathKind, cost: f32) {
+        let capacity = self.capacity();
+        if capacity == 0 {
+            return;
+        }
+        let mut state = self.state.lock();
+        if state.entries.len() >= capacity && !state.entries.contains_key(&key) {
+            if let Some(oldest) = state
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| *key)
+            {
+                state.entries.remove(&oldest);
+            }
+        }
+        state.time += 1;
+        let last_used = state.time;
+        state.entries.insert(
+            key,
+            PathCacheEntry {
+                triangles: path.to_vec(),
+                kind,
+                cost,
+                last_used,
+            },
+        );
+    }
+
+    fn set_capacity(&self, capacity: usize) {
+        let mut state = self.state.lock();
+        self.capacity.store(capacity, Ordering::Relaxed);
+        while state.entries.len() > capacity {
+            if let Some(oldest) = state
+                .entries
+                .iter()
+                .min_by_key(|(_, entry)| entry.last_used)
+                .map(|(key, _)| *key)
+            {
+                state.entries.remove(&oldest);
+            }
+        }
+    }
+
+    fn capacity(&self) -> usize {
+        self.capacity.load(Ordering::Relaxed)
+    }
+
+    fn len(&self) -> usize {
+        self.state.lock().entries.len()
+    }
+
+    fn clear(&self) {
+        if self.capacity() != 0 {
+            self.state.lock().entries.clear();
+        }
+    }
+}
+
+/// A closed polygon on XZ plane, used by [`Navmesh::from_polygons_with_holes`]. The last vertex
+/// is implicitly connected with the first one, the winding order could be arbitrary.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct Polygon {
+    /// Vertices of the polygon.
+    pub vertices: Vec<Vector3<f32>>,
+}
+
+impl Polygon {
+    /// Creates a new polygon from the given vertices.
+    pub fn new(vertices: Vec<Vector3<f32>>) -> Self {
+        Self { vertices }
+    }
+}
+
+/// An error that may occur during navmesh import from a polygon soup. See
+/// [`Navmesh::from_polygon_soup`] for more info.
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum PolygonSoupError {
+    /// A polygon has less than three vertices.
+    TooFewVertices {
+        /// Index of the polygon.
+        polygon: usize,
+    },
+    /// A polygon references a vertex that does not exist.
+    InvalidIndex {
+        /// Index of the polygon.
+        polygon: usize,
+        /// Index of the vertex.
+        index: u32,
+    },
+    /// A polygon has zero area (when projected on XZ plane).
+    Degenerate {
+        /// Index of the polygon.
+        polygon: usize,
+    },
+    /// A polygon has winding order that differs from the winding order of the first polygon.
+    InconsistentWinding {
+        /// Index of the polygon.
+        polygon: usize,
+    },
+    /// A polygon is not convex.
+    NonConvex {
+        /// Index of the polygon.
+        polygon: usize,
+    },
+    /// A hole does not lie inside any outline.
+    OrphanHole {
+        /// Index of the polygon.
+        polygon: usize,
+    },
+}
+
+impl Display for PolygonSoupError {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            PolygonSoupError::TooFewVertices { polygon } => {
+                write!(f, "Polygon {polygon} has less than three vertices.")
+            }
+            PolygonSoupError::InvalidIndex { polygon, index } => {
+                write!(f, "Polygon {polygon} has invalid vertex index {index}.")
+            }
+            PolygonSoupError::Degenerate { polygon } => {
+                write!(f, "Polygon {polygon} has zero area.")
+            }
+            PolygonSoupError::InconsistentWinding { polygon } => {
+                write!(
+                    f,
+                    "Polygon {polygon} has winding order different from the first polygon."
+                )
+            }
+            PolygonSoupError::NonConvex { polygon } => {
+                write!(f, "Polygon {polygon} is not convex.")
+            }
+            PolygonSoupError::OrphanHole { polygon } => {
+                write!(f, "Polygon {polygon} is a hole outside of any outline.")
+            }
+        }
+    }
+}
+
+impl std::error::Error for PolygonSoupError {}
+
+/// A problem of a navmesh, found by [`Navmesh::validate`].
+#[derive(Clone, Debug, PartialEq, Eq)]
+pub enum NavmeshValidationIssue {
+    /// A triangle references a vertex that does not exist.
+    InvalidIndex {
+        /// Index of the triangle.
+        triangle: usize,
+        /// Index of the vertex.
+        index: u32,
+    },
+    /// A triangle has zero area or references the same vertex more than once.
+    DegenerateTriangle {
+        /// Index of the triangle.
+        triangle: usize,
+    },
+    /// A triangle references the same vertices as some other triangle.
+    DuplicateTriangle {
+        /// Index of the triangle.
+        triangle: usize,
+        /// Index of the first triangle with the same vertices.
+        original: usize,
+    },
+    /// Two adjacent triangles have different winding order (the shared edge goes in the same
+    /// direction in both triangles).
+    InconsistentWinding {
+        /// Index of the triangle.
+        triangle: usize,
+        /// Index of the adjacent triangle.
+        neighbor: usize,
+    },
+    /// An edge is shared by more than two triangles.
+    NonManifoldEdge {
+        /// Indices of the vertices of the edge.
+        vertices: [u32; 2],
+        /// Indices of the triangles, that share the edge.
+        triangles: Vec<usize>,
+    },
+    /// A vertex is not referenced by any triangle.
+    UnreferencedVertex {
+        /// Index of the vertex.
+        vertex: usize,
+    },
+}
+
+impl Display for NavmeshValidationIssue {
+    fn fmt(&self, f: &mut Formatter<'_>) -> std::fmt::Result {
+        match self {
+            NavmeshValidationIssue::InvalidIndex { triangle, index } => {
+                write!(f, "Triangle {triangle} has invalid vertex index {index}.")
+            }
+            NavmeshValidationIssue::DegenerateTriangle { triangle } => {
+                write!(f, "Triangle {triangle} has zero area.")
+            }
+            NavmeshValidationIssue::DuplicateTriangle { triangle, original } => {
+                write!(
+                    f,
+                    "Triangle {triangle} is a duplicate of triangle {original}."
+                )
+            }
+            NavmeshValidationIssue::InconsistentWinding { triangle, neighbor } => {
+                write!(
+                    f,
+                    "Triangle {triangle} has winding order different from adjacent triangle \
+                    {neighbor}."
+                )
+            }
+            NavmeshValidationIssue::NonManifoldEdge {
+                vertices,
+                triangles,
+            } => {
+                write!(
+                    f,
+                    "Edge {}-{} is shared by more than two triangles: {triangles:?}.",
+                    vertices[0], vertices[1]
+                )
+            }
+            NavmeshValidationIssue::UnreferencedVertex { vertex } => {
+                write!(f, "Vertex {vertex} is not referenced by any triangle.")
+            }
+        }
+    }
+}
+
+/// A temporary modification context which allows you to modify a navmesh. When the modification
+/// context is dropped, it recalculates navigation graph automatically.
+pub struct NavmeshModificationContext<'a> {
+    navmesh: &'a mut Navmesh,
+}
+
+impl<'a> Drop for NavmeshModificationContext<'a> {
+    fn drop(&mut self) {
+        self.navmesh.rebuild();
+    }
+}
+
+impl<'a> NavmeshModificationContext<'a> {
+    /// Adds the triangle to the navigational mesh and returns its index in the internal array. Vertex indices in
+    /// the triangle must be valid!
+    pub fn add_triangle(&mut self, triangle: TriangleDefinition) -> u32 {
+        let index = self.navmesh.triangles.len();
+        self.navmesh.triangles.push(triangle);
+        self.navmesh.triangle_costs.push(1.0);
+        self.navmesh.triangle_flags.push(0);
+        self.navmesh.triangle_layers.push(0);
+        index as u32
+    }
+
+    /// Removes a triangle at the given index from the navigational mesh.
+    pub fn remove_triangle(&mut self, index: usize) -> TriangleDefinition {
+        // One-way edges refer to the triangles by their indices, shift them the same way.
+        if !self.navmesh.one_way_edges.is_empty() {
+            let index = index as u32;
+            let shift = |i: u32| if i > index { i - 1 } else { i };
+            self.navmesh.one_way_edges = std::mem::take(&mut self.navmesh.one_way_edges)
+                .into_iter()
+                .filter(|edge| !edge.contains(&index))
+                .map(|[a, b]| [shift(a), shift(b)])
+                .collect();
+        }
+        self.navmesh.triangle_costs.remove(index);
+        self.navmesh.triangle_flags.remove(index);
+        self.navmesh.triangle_layers.remove(index);
+        self.navmesh.triangles.remove(index)
+    }
+
+    /// Removes last triangle from the navigational mesh. Automatically fixes vertex links in the internal
+    /// navigational graph.
+    pub fn pop_triangle(&mut self) -> Option<TriangleDefinition> {
+        if self.navmesh.triangles.is_empty() {
+            None
+        } else {
+            Some(self.remove_triangle(self.navmesh.triangles.len() - 1))
+        }
+    }
+
+    /// Removes a vertex at the given index from the navigational mesh. All triangles that share the vertex will
+    /// be also removed.
+    pub fn remove_vertex(&mut self, index: usize) -> Vector3<f32> {
+        // Remove triangles that sharing the vertex first.
+        let mut i = 0;
+        while i < self.navmesh.triangles.len() {
+            if self.navmesh.triangles[i]
+                .indices()
+                .contains(&(index as u32))
+            {
+                self.remove_triangle(i);
+            } else {
+                i += 1;
+            }
+        }
+
+        // Shift vertex indices in triangles. Example:
+        //
+        // 0:A 1:B 2:C 3:D 4:E
+        // [A,B,C], [A,C,D], [A,D,E], [D,C,E]
+        // [0,1,2], [0,2,3], [0,3,4], [3,2,4]
+        //
+        // Remove B.
+        //
+        // 0:A 1:C 2:D 3:E
+        // [A,C,D], [A,D,E], [D,C,E]
+        // [0,1,2], [0,2,3], [2,1,3]
+        for triangle in self.navmesh.triangles.iter_mut() {
+            for other_vertex_index in triangle.indices_mut() {
+                if *other_vertex_index > index as u32 {
+                    *other_vertex_index -= 1;
+                }
+            }
+        }
+
+        self.navmesh.vertices.remove(index)
+    }
+
+    /// Returns a mutable reference to the internal array of vertices.
+    pub fn vertices_mut(&mut self) -> &mut [Vector3<f32>] {
+        &mut self.navmesh.vertices
+    }
+
+    /// Adds the vertex to the navigational mesh. The vertex will **not** be connected with any other vertex.
+    pub fn add_vertex(&mut self, vertex: Vector3<f32>) -> u32 {
+        let index = self.navmesh.vertices.len();
+        self.navmesh.vertices.push(vertex);
+        index as u32
+    }
+
+    /// Removes last vertex from the navigational mesh. All triangles that share the vertex will be also removed.
+    pub fn pop_vertex(&mut self) -> Option<Vector3<f32>> {
+        if self.navmesh.vertices.is_empty() {
+            None
+        } else {
+            Some(self.remove_vertex(self.navmesh.vertices.len() - 1))
+        }
+    }
+
+    /// Inserts the vertex at the given index. Automatically shift indices in triangles to preserve mesh structure.
+    pub fn insert_vertex(&mut self, index: u32, vertex: Vector3<f32>) {
+        self.navmesh.vertices.insert(index as usize, vertex);
+
+        // Shift vertex indices in triangles. Example:
+        //
+        // 0:A 1:C 2:D 3:E
+        // [A,C,D], [A,D,E], [D,C,E]
+        // [0,1,2], [0,2,3], [2,1,3]
+        //
+        // Insert B.
+        //
+        // 0:A 1:B 2:C 3:D 4:E
+        // [A,C,D], [A,D,E], [D,C,E]
+        // [0,2,3], [0,3,4], [3,2,4]
+        for triangle in self.navmesh.triangles.iter_mut() {
+            for other_vertex_index in triangle.indices_mut() {
+                if *other_vertex_index >= index {
+                    *other_vertex_index += 1;
+                }
+            }
+        }
+    }
+}
+
+impl Navmesh {
+    /// Creates new navigation mesh from given set of triangles and vertices. This is
+    /// low level method that allows to specify triangles and vertices directly. In
+    /// most cases you should use `from_mesh` method.
+    pub fn new(triangles: Vec<TriangleDefinition>, vertices: Vec<Vector3<f32>>) -> Self {
+        let mut navmesh = Self {
+            graph: Default::default(),
+            triangle_costs: vec![1.0; triangles.len()],
+            triangle_flags: vec![0; triangles.len()],
+            triangle_layers: vec![0; triangles.len()],
+            tile_size: DEFAULT_NAVMESH_TILE_SIZE,
+            up_axis: Vector3::y(),
+            up_rotation: None,
+            max_polygon_vertices: 0,
+            polygons: Default::default(),
+            offmesh_links: Default::default(),
+            offmesh_link_traversals: Default::default(),
+            triangles,
+            vertices,
+            octree: Default::default(),
+            grid: Default::default(),
+            path_requests: Default::default(),
+            path_cache: Default::default(),
+            max_search_nodes: None,
+            pathfinding_budget: None,
+            obstacles: Default::default(),
+            blocked_triangles: Default::default(),
+            islands: Default::default(),
+            border_vertices: Default::default(),
+            border_edges: Default::default(),
+            vertex_clearances: Default::default(),
+            revision: 0,
+            geometry_revision: 0,
+            topology_revision: 0,
+            one_way_edges: Default::default(),
+        };
+        navmesh.rebuild_octree();
+        navmesh.rebuild_graph();
+        navmesh.rebuild_obstacles();
+        navmesh
+    }
+
+    /// The same as [`Self::new`], but the vertices, that are closer than `weld_tolerance` to each
+    /// other, are merged before the adjacency information is built. It could be used to create a
+    /// navmesh from exported geometry, that has duplicated vertices along the seams (for example,
+    /// because of split normals or UVs), which otherwise would prevent the paths from going across
+    /// the seams. See [`Self::weld`] for more info.
+    pub fn new_welded(
+        triangles: Vec<TriangleDefinition>,
+        vertices: Vec<Vector3<f32>>,
+        weld_tolerance: f32,
+    ) -> Self {
+        let mut navmesh = Self {
+            triangle_costs: vec![1.0; triangles.len()],
+            triangle_flags: vec![0; triangles.len()],
+            triangle_layers: vec![0; triangles.len()],
+            triangles,
+            vertices,
+            ..Default::default()
+        };
+        navmesh.weld(weld_tolerance);
+        navmesh
+    }
+
+    /// Rebuilds the internal acceleration structure, that is used by point queries (such as
+    /// [`Self::query_closest`] or [`Self::triangle_at`]). The structure is built lazily on the
+    /// first query and rebuilt automatically when the navmesh is modified using
+    /// [`Self::modify`], so there's no need to call this method, unless you want to avoid the
+    /// (small) delay on the first query after bulk modifications.
+    pub fn rebuild_acceleration(&mut self) {
+        self.grid = Default::default();
+        self.grid();
+    }
+
+    fn grid(&self) -> &TriangleGrid {
+        self.grid.get_or_init(|| match self.up_rotation() {
+            Some(rotation) => {
+                let vertices = self
+                    .vertices
+                    .iter()
+                    .map(|vertex| rotation * *vertex)
+                    .collect::<Vec<_>>();
+                TriangleGrid::new(&self.triangles, &vertices)
+            }
+            None => TriangleGrid::new(&self.triangles, &self.vertices),
+        })
+    }
+
+    /// Sets the "up" direction of the navmesh. Default is +Y. The up axis defines the vertical
+    /// direction for point location queries: [`Self::triangle_at`] and [`Self::project_down`]
+    /// measure heights along this axis, agents that are clamped to the surface (see
+    /// [`NavmeshAgent::set_clamp_to_surface`]) take their height along this axis too, and runtime
+    /// obstacles (see [`Self::add_obstacle`]) are aligned with it. Path smoothing, raycasts, border
+    /// distances, tiles and the steering of agents work on the plane, that is perpendicular to
+    /// this axis. It should be set for the navmeshes built from the
+    /// geometry, that uses a different convention (for example, Z-up geometry exported from some
+    /// modelling software). Zero axis is ignored.
+    pub fn set_up_axis(&mut self, axis: Vector3<f32>) {
+        if let Some(axis) = axis.try_normalize(f32::EPSILON) {
+            if axis != self.up_axis {
+                self.up_axis = axis;
+                self.up_rotation = up_rotation(axis);
+                self.grid = Default::default();
+                self.border_edges = Default::default();
+                self.vertex_clearances = Default::default();
+                self.rebuild_polygons();
+                self.rebuild_obstacles();
+                self.bump_revision();
+            }
+        }
+    }
+
+    /// Returns the normalized "up" direction of the navmesh. See [`Self::set_up_axis`] for more
+    /// info.
+    pub fn up_axis(&self) -> Vector3<f32> {
+        self.up_axis
+    }
+
+    /// Returns a rotation, that maps the up axis of the navmesh to +Y, or `None` if the up axis
+    /// is +Y already.
+    fn up_rotation(&self) -> Option<UnitQuaternion<f32>> {
+        self.up_rotation
+    }
+
+    /// Removes the vertical component (along the up axis) of the given vector.
+    fn horizontal(&self, vector: Vector3<f32>) -> Vector3<f32> {
+        vector - self.up_axis.scale(vector.dot(&self.up_axis))
+    }
+
+    /// The same as [`clip_segment_by_triangle_2d`], but the segment is clipped by a triangle of
+    /// the navmesh on its horizontal plane.
+    fn clip_segment_by_triangle(
+        &self,
+        p0: Vector3<f32>,
+        p1: Vector3<f32>,
+        triangle: usize,
+    ) -> Option<(f32, f32)> {
+        let [a, b, c] = self.triangles[triangle]
+            .0
+            .map(|index| self.to_up_space(self.vertices[index as usize]));
+        clip_segment_by_triangle_2d(self.to_up_space(p0), self.to_up_space(p1), a, b, c)
+    }
+
+    /// Transforms a point to the space, where the up axis of the navmesh is +Y.
+    fn to_up_space(&self, point: Vector3<f32>) -> Vector3<f32> {
+        match self.up_rotation() {
+            Some(rotation) => rotation * point,
+            None => point,
+        }
+    }
+
+    /// Transforms a point from the space, where the up axis of the navmesh is +Y, back to the
+    /// world space.
+    fn up_space_to_world(&self, point: Vector3<f32>) -> Vector3<f32> {
+        match self.up_rotation() {
+            Some(rotation) => rotation.inverse_transform_vector(&point),
+            None => point,
+        }
+    }
+
+    /// Calls the given closure for every cell of the acceleration grid, that intersects the given
+    /// world-space bounding box.
+    fn visit_grid_rect<F>(&self, min: Vector3<f32>, max: Vector3<f32>, func: F)
+    where
+        F: FnMut(&[u32]),
+    {
+        match self.up_rotation() {
+            Some(rotation) => {
+                let mut local_min = Vector3::repeat(f32::MAX);
+                let mut local_max = Vector3::repeat(-f32::MAX);
+                for corner in 0..8 {
+                    let point = Vector3::new(
+                        if corner & 1 == 0 { min.x } else { max.x },
+                        if corner & 2 == 0 { min.y } else { max.y },
+                        if corner & 4 == 0 { min.z } else { max.z },
+                    );
+                    let point = rotation * point;
+                    local_min = local_min.inf(&point);
+                    local_max = local_max.sup(&point);
+                }
+                self.grid().visit_rect(local_min, local_max, func)
+            }
+            None => self.grid().visit_rect(min, max, func),
+        }
+    }
+
+    /// Rebuilds all the internal data, that depends on the geometry of the navmesh or on the costs
+    /// of the triangles. This is done automatically by [`Self::modify`] and the setters, so call
+    /// this method only when the navmesh was changed by other means (for example, using
+    /// reflection).
+    pub fn rebuild(&mut self) {
+        self.rebuild_octree();
+        self.grid = Default::default();
+        self.rebuild_graph();
+        self.rebuild_obstacles();
+    }
+
+    /// Transforms the vertices and the off-mesh links of the navmesh using the given matrix. It
+    /// could be used to move the navmesh from the local space of a node to the world space (see
+    /// [`scene::navmesh::NavigationalMesh::local_to_world`]). The winding order of the triangles is
+    /// flipped, if the transformation mirrors the navmesh. Runtime obstacles are not transformed.
+    ///
+    /// The topology of the navmesh stays the same, so the adjacency information and the
+    /// attachments of the off-mesh links are preserved, only the acceleration structures are
+    /// rebuilt. Navmesh agents recalculate their paths automatically after the transformation. See
+    /// also [`Self::transform_vertices`].
+    pub fn transform(&mut self, matrix: &Matrix4<f32>) {
+        let transform_point =
+            |point: Vector3<f32>| matrix.transform_point(&Point3::from(point)).coords;
+
+        for vertex in self.vertices.iter_mut() {
+            *vertex = transform_point(*vertex);
+        }
+        for link in self.offmesh_links.iter_mut() {
+            link.from = transform_point(link.from);
+            link.to = transform_point(link.to);
+            link.span = matrix.transform_vector(&link.span);
+        }
+        for ((from_triangle, _), traversals) in self.offmesh_link_traversals.iter_mut() {
+            for traversal in traversals.iter_mut() {
+                let link = &self.offmesh_links[traversal.link];
+                if link.from_triangle == Some(*from_triangle) {
+                    traversal.enter = link.from;
+                    traversal.exit = link.to;
+                } else {
+                    traversal.enter = link.to;
+                    traversal.exit = link.from;
+                }
+            }
+        }
+        if matrix.fixed_view::<3, 3>(0, 0).determinant() < 0.0 {
+            for triangle in self.triangles.iter_mut() {
+                triangle.0.swap(1, 2);
+            }
+        }
+
+        self.refresh_geometry();
+    }
+
+    /// Transforms the vertices with the given indices using the given matrix. Invalid indices are
+    /// ignored, every vertex is transformed once even if its index is repeated. It could be used to
+    /// move a part of the navmesh at runtime, for example a region that is attached to a moving
+    /// platform or an elevator, so the agents could keep walking on it.
+    ///
+    /// The topology of the navmesh stays the same, so the adjacency information is preserved (and
+    /// the region stays connected with the rest of the navmesh, if it shares vertices with it),
+    /// only the moved triangles are updated in the acceleration structures. Off-mesh links and
+    /// runtime obstacles are not transformed. The paths stay valid, so the navmesh agents do not
+    /// recalculate them (which would be too expensive for a platform, that moves every frame),
+    /// the agents, that are clamped to the surface (see [`NavmeshAgent::set_clamp_to_surface`]),
+    /// take their height from the moved triangles.
+    pub fn transform_vertices(&mut self, indices: &[usize], matrix: &Matrix4<f32>) {
+        let mut old_positions = FxHashMap::default();
+        for &index in indices {
+            if let Some(vertex) = self.vertices.get_mut(index) {
+                if let Entry::Vacant(entry) = old_positions.entry(index as u32) {
+                    entry.insert(*vertex);
+                    *vertex = matrix.transform_point(&Point3::from(*vertex)).coords;
+                }
+            }
+        }
+        if old_positions.is_empty() {
+            return;
+        }
+
+        let moved_triangles = (0..self.triangles.len())
+            .filter(|&index| {
+                self.triangles[index]
+                    .0
+                    .iter()
+                    .any(|vertex| old_positions.contains_key(vertex))
+            })
+            .collect::<Vec<_>>();
+
+        // Move the triangles in the acceleration grid, the grid is rebuilt only if some triangle
+        // has left its bounds.
+        let up_rotation = self.up_rotation;
+        let to_up_space =
+            |point: Vector3<f32>| up_rotation.map_or(point, |rotation| rotation * point);
+        if let Some(grid) = self.grid.get_mut() {
+            let is_moved = moved_triangles.iter().all(|&index| {
+                let triangle = self.triangles[index].0;
+                let old_points = triangle.map(|vertex| {
+                    to_up_space(
+                        old_positions
+                            .get(&vertex)
+                            .cloned()
+                            .unwrap_or(self.vertices[vertex as usize]),
+                    )
+                });
+                let new_points = triangle.map(|vertex| to_up_space(self.vertices[vertex as usize]));
+                grid.move_triangle(index as u32, old_points, new_points)
+            });
+            if !is_moved {
+                self.grid = Default::default();
+            }
+        }
+        // The octree can't be updated partially, it is rebuilt on the next ray cast.
+        self.octree = Default::default();
+
+        let mut is_blocking_changed = false;
+        for &index in moved_triangles.iter() {
+            let [a, b, c] = self.triangles[index]
+                .0
+                .map(|vertex| self.vertices[vertex as usize]);
+            self.graph.vertices[index].position = (a + b + c).scale(1.0 / 3.0);
+
+            let blocked = self
+                .obstacles
+                .iter()
+                .filter(|obstacle| {
+                    obstacle.enabled && obstacle.shape.overlaps_triangle(self, a, b, c)
+                })
+                .count() as u32;
+            let count = &mut self.blocked_triangles[index];
+            is_blocking_changed |= (*count > 0) != (blocked > 0);
+            *count = blocked;
+        }
+
+        // The border edges are moved only if some of their vertices was moved, otherwise only the
+        // clearances of the moved vertices change.
+        if old_positions
+            .keys()
+            .any(|&vertex| self.is_border_vertex(vertex as usize))
+        {
+            self.border_edges = Default::default();
+            self.vertex_clearances = Default::default();
+        } else if self.vertex_clearances.get().is_some() {
+            let clearances = old_positions
+                .keys()
+                .map(|&vertex| {
+                    let clearance = self.border_distance(self.vertices[vertex as usize]);
+                    (vertex as usize, clearance)
+                })
+                .collect::<Vec<_>>();
+            if let Some(vertex_clearances) = self.vertex_clearances.get_mut() {
+                for (vertex, clearance) in clearances {
+                    vertex_clearances[vertex] = clearance;
+                }
+            }
+        }
+
+        if is_blocking_changed {
+            self.bump_revision();
+        }
+        // The moved triangles could be not coplanar with the rest of their polygons anymore.
+        self.rebuild_polygons();
+    }
+
+    // Updates the data, that depends on the positions of the vertices, but not on the topology of
+    // the navmesh.
+    fn refresh_geometry(&mut self) {
+        self.rebuild_octree();
+        self.grid = Default::default();
+        for (vertex, triangle) in self.graph.vertices.iter_mut().zip(&self.triangles) {
+            let [a, b, c] = triangle.0.map(|index| self.vertices[index as usize]);
+            vertex.position = (a + b + c).scale(1.0 / 3.0);
+        }
+        self.border_edges = Default::default();
+        self.vertex_clearances = Default::default();
+        self.geometry_revision = self.geometry_revision.wrapping_add(1);
+        self.bump_revision();
+        self.rebuild_obstacles();
+        self.rebuild_polygons();
+    }
+
+    /// Appends the triangles and the vertices of the other navmesh to this navmesh. The vertices of
+    /// the other navmesh, that are closer than `weld_tolerance` to the vertices of this navmesh,
+    /// are welded together, so the shared edges at the seam become connected and the paths could
+    /// go across the seam. Triangles of the other navmesh, that duplicate the existing triangles
+    /// (i.e. use the same vertices after welding), and triangles, that collapse after welding,
+    /// are skipped. Off-mesh links, traversal costs, flags, layers, one-way edges and runtime
+    /// obstacles of the other navmesh are appended as well, except the links (with ends closer than
+    /// `weld_tolerance`) and the obstacles, that this navmesh already has.
+    ///
+    /// Keep in mind, that the seam edges must match exactly (after welding), since T-junctions
+    /// do not form adjacency.
+    pub fn append(&mut self, other: &Navmesh, weld_tolerance: f32) {
+        let remap = self.weld_vertices(&other.vertices, weld_tolerance);
+
+        let triangle_key = |triangle: &TriangleDefinition| {
+            let mut key = triangle.0;
+            key.sort_unstable();
+            key
+        };
+        let mut existing = self
+            .triangles
+            .iter()
+            .map(triangle_key)
+            .collect::<fxhash::FxHashSet<_>>();
+        let mut triangle_remap = vec![None; other.triangles.len()];
+        for (index, (((triangle, cost), flags), layer)) in other
+            .triangles
+            .iter()
+            .zip(other.triangle_costs.iter())
+            .zip(other.triangle_flags.iter())
+            .zip(other.triangle_layers.iter())
+            .enumerate()
+        {
+            let triangle = TriangleDefinition(triangle.0.map(|i| remap[i as usize]));
+            if triangle[0] == triangle[1]
+                || triangle[1] == triangle[2]
+                || triangle[2] == triangle[0]
+            {
+                continue;
+            }
+            if existing.insert(triangle_key(&triangle)) {
+                triangle_remap[index] = Some(self.triangles.len() as u32);
+                self.triangles.push(triangle);
+                self.triangle_costs.push(*cost);
+                self.triangle_flags.push(*flags);
+                self.triangle_layers.push(*layer);
+            }
+        }
+        for [a, b] in other.one_way_edges.iter() {
+            if let (Some(a), Some(b)) = (triangle_remap[*a as usize], triangle_remap[*b as usize]) {
+                self.one_way_edges.insert([a, b]);
+            }
+        }
+
+        // Links and obstacles, that already exist in this navmesh (for example, when the same
+        // navmesh is appended twice), are skipped.
+        let existing_links = self
+            .offmesh_links
+            .iter()
+            .map(|link| (link.from, link.to, link.bidirectional, link.cost))
+            .collect::<Vec<_>>();
+        for (_, link) in other.offmesh_links.pair_iter() {
+            let is_duplicate = existing_links
+                .iter()
+                .any(|(from, to, bidirectional, cost)| {
+                    link.from.metric_distance(from) <= weld_tolerance
+                        && link.to.metric_distance(to) <= weld_tolerance
+                        && link.bidirectional == *bidirectional
+                        && link.cost == *cost
+                });
+            if !is_duplicate {
+                let _ = self.offmesh_links.spawn(OffMeshLink {
+                    from: link.from,
+                    to: link.to,
+                    bidirectional: link.bidirectional,
+                    cost: link.cost,
+                    span: link.span,
+                    ..Default::default()
+                });
+            }
+        }
+        let existing_obstacles = self.obstacles.iter().cloned().collect::<Vec<_>>();
+        for (_, obstacle) in other.obstacles.pair_iter() {
+            if !existing_obstacles.contains(obstacle) {
+                let _ = self.obstacles.spawn(obstacle.clone());
+            }
+        }
+
+        self.rebuild();
+    }
+
+    /// Merges the vertices of the navmesh, that are closer than `weld_tolerance` to each other, and
+    /// remaps the triangles to use the merged vertices, so the triangles, that had separate (but
+    /// coincident) vertices along their shared edges, become adjacent. Triangles, that collapse
+    /// after welding, and duplicated triangles are removed, the traversal costs, flags, layers and
+    /// one-way edges of the rest of the triangles are preserved. Returns the number of the merged
+    /// vertices.
+    ///
+    /// Zero tolerance merges only the vertices at exactly the same position.
+    pub fn weld(&mut self, weld_tolerance: f32) -> usize {
+        let vertices = std::mem::take(&mut self.vertices);
+        let remap = self.weld_vertices(&vertices, weld_tolerance.max(0.0));
+
+        let triangles = std::mem::take(&mut self.triangles);
+        let costs = std::mem::take(&mut self.triangle_costs);
+        let flags = std::mem::take(&mut self.triangle_flags);
+        let layers = std::mem::take(&mut self.triangle_layers);
+        let mut existing = FxHashSet::default();
+        let mut triangle_remap = vec![None; triangles.len()];
+        for (index, triangle) in triangles.iter().enumerate() {
+            let triangle = TriangleDefinition(triangle.0.map(|i| remap[i as usize]));
+            if triangle[0] == triangle[1]
+                || triangle[1] == triangle[2]
+                || triangle[2] == triangle[0]
+            {
+                continue;
+            }
+            let mut key = triangle.0;
+            key.sort_unstable();
+            if existing.insert(key) {
+                triangle_remap[index] = Some(self.triangles.len() as u32);
+                self.triangles.push(triangle);
+                self.triangle_costs
+                    .push(costs.get(index).cloned().unwrap_or(1.0));
+                self.triangle_flags
+                    .push(flags.get(index).cloned().unwrap_or_default());
+                self.triangle_layers
+                    .push(layers.get(index).cloned().unwrap_or_default());
+            }
+        }
+        self.one_way_edges = std::mem::take(&mut self.one_way_edges)
+            .into_iter()
+            .filter_map(|[a, b]| {
+                Some([
+                    triangle_remap.get(a as usize).cloned().flatten()?,
+                    triangle_remap.get(b as usize).cloned().flatten()?,
+                ])
+            })
+            .collect();
+
+        self.rebuild();
+        vertices.len() - self.vertices.len()
+    }
+
+    // Adds the given vertices to the navmesh, welding them with the existing vertices, that are
+    // closer than the given tolerance. Returns new indices of the given vertices.
+    fn weld_vertices(&mut self, vertices: &[Vector3<f32>], weld_tolerance: f32) -> Vec<u32> {
+        // Spatial hash of the vertices, it is used to find the vertices to weld with.
+        let cell_size = weld_tolerance.max(f32::EPSILON);
+        let cell_of = |p: &Vector3<f32>| p.map(|c| (c / cell_size).floor() as i32);
+        let mut vertex_map = FxHashMap::<Vector3<i32>, Vec<u32>>::default();
+        for (index, vertex) in self.vertices.iter().enumerate() {
+            vertex_map
+                .entry(cell_of(vertex))
+                .or_default()
+                .push(index as u32);
+        }
+
+        let mut remap = Vec::with_capacity(vertices.len());
+        for vertex in vertices.iter() {
+            let cell = cell_of(vertex);
+            let mut weld_with = None;
+            'search: for dz in -1..=1 {
+                for dy in -1..=1 {
+                    for dx in -1..=1 {
+                        let Some(indices) = vertex_map.get(&(cell + Vector3::new(dx, dy, dz)))
+                        else {
+                            continue;
+                        };
+                        for &index in indices {
+                            if self.vertices[index as usize].metric_distance(vertex)
+                                <= weld_tolerance
+                            {
+                                weld_with = Some(index);
+                                break 'search;
+                            }
+                        }
+                    }
+                }
+            }
+            let index = weld_with.unwrap_or_else(|| {
+                let index = self.vertices.len() as u32;
+                self.vertices.push(*vertex);
+                vertex_map.entry(cell).or_default().push(index);
+                index
+            });
+            remap.push(index);
+        }
+
+        remap
+    }
+
+    /// Sets a new size of a tile of the navmesh (in meters). The navmesh is divided into a grid of
+    /// square tiles on the horizontal plane (see [`Self::set_up_axis`]), a triangle belongs to the
+    /// tile that contains its center. See [`Self::rebuild_tile`] for more info. Default size is
+    /// [`DEFAULT_NAVMESH_TILE_SIZE`].
+    pub fn set_tile_size(&mut self, size: f32) {
+        self.tile_size = size.max(0.01);
+    }
+
+    /// Returns current size of a tile of the navmesh. See [`Self::set_tile_size`] for more info.
+    pub fn tile_size(&self) -> f32 {
+        self.tile_size
+    }
+
+    /// Returns coordinates of a tile, that contains the given point.
+    pub fn tile_at(&self, point: Vector3<f32>) -> Vector2<i32> {
+        let point = self.to_up_space(point);
+        Vector2::new(
+            (point.x / self.tile_size).floor() as i32,
+            (point.z / self.tile_size).floor() as i32,
+        )
+    }
+
+    /// Returns indices of all the triangles, that belong to the given tile.
+    pub fn tile_triangles(&self, tile: Vector2<i32>) -> Vec<usize> {
+        (0..self.triangles.len())
+            .filter(|&index| self.tile_at(self.triangle_center(index)) == tile)
+            .collect()
+    }
+
+    fn triangle_center(&self, index: usize) -> Vector3<f32> {
+        let [a, b, c] = self.triangles[index].0.map(|i| self.vertices[i as usize]);
+        (a + b + c).scale(1.0 / 3.0)
+    }
+
+    /// Replaces the geometry of the given tile with the new one, leaving the rest of the navmesh
+    /// intact. It could be used to update the navmesh of a destructible (or constructible) world
+    /// incrementally: only the tile, that was changed, needs to be baked again. The new geometry
+    /// should lie within the bounds of the tile (see [`Self::set_tile_size`]).
+    ///
+    /// The new geometry is stitched with the adjacent tiles: the vertices on the tile borders are
+    /// welded with the vertices of the adjacent tiles (see [`NAVMESH_TILE_WELD_TOLERANCE`]), and
+    /// the border edges, that have vertices of the other side lying on them (T-junctions), are
+    /// split, so the adjacent tiles do not need to have the same tessellation of the shared
+    /// borders. Traversal costs and flags of the new triangles are set to their defaults. Queries
+    /// span tiles transparently, since the tiles form a single navmesh.
+    ///
+    /// Only the triangles of the tile and the split border triangles are connected again, the rest
+    /// of the navigation graph, the edge directions and the off-mesh links are kept (their indices
+    /// of the triangles are shifted, since the old triangles of the tile are removed).
+    pub fn rebuild_tile(
+        &mut self,
+        tile: Vector2<i32>,
+        triangles: Vec<TriangleDefinition>,
+        vertices: Vec<Vector3<f32>>,
+    ) {
+        // Remove the old geometry of the tile. Indices of the rest of the triangles are shifted, so
+        // the new index of every old triangle is remembered to remap the graph.
+        let mut triangle_remap = vec![None; self.triangles.len()];
+        let mut kept_count = 0;
+        for (index, new_index) in triangle_remap.iter_mut().enumerate() {
+            if self.tile_at(self.triangle_center(index)) != tile {
+                *new_index = Some(kept_count);
+                kept_count += 1;
+            }
+        }
+        let mut index = 0;
+        self.triangles.retain(|_| {
+            index += 1;
+            triangle_remap[index - 1].is_some()
+        });
+        let mut index = 0;
+        self.triangle_costs.retain(|_| {
+            index += 1;
+            triangle_remap[index - 1].is_some()
+        });
+        let mut index = 0;
+        self.triangle_flags.retain(|_| {
+            index += 1;
+            triangle_remap[index - 1].is_some()
+        });
+        let mut index = 0;
+        self.triangle_layers.retain(|_| {
+            index += 1;
+            triangle_remap[index - 1].is_some()
+        });
+        self.remove_unused_vertices();
+
+        // Add the new geometry.
+        let remap = self.weld_vertices(&vertices, NAVMESH_TILE_WELD_TOLERANCE);
+        for triangle in triangles {
+            let triangle = TriangleDefinition(triangle.0.map(|i| remap[i as usize]));
+            if triangle[0] == triangle[1]
+                || triangle[1] == triangle[2]
+                || triangle[2] == triangle[0]
+            {
+                continue;
+            }
+            self.triangles.push(triangle);
+            self.triangle_costs.push(1.0);
+            self.triangle_flags.push(0);
+            self.triangle_layers.push(0);
+        }
+
+        let splits = self.split_tile_t_junctions(tile);
+        self.update_tile_graph(&triangle_remap, kept_count, &splits);
+    }
+
+    // Updates the graph and the rest of the data, that depends on the indices of the triangles,
+    // after the geometry of a tile was replaced. `triangle_remap` maps the old indices of the
+    // triangles to the new ones (`None` for the removed triangles), the triangles starting from
+    // `first_new` are the new ones and `splits` are the pairs of triangles, that were split at the
+    // T-junctions, and their new halves. Only the new and the split triangles are connected with
+    // their neighbors again, the rest of the graph is kept as is.
+    fn update_tile_graph(
+        &mut self,
+        triangle_remap: &[Option<usize>],
+        first_new: usize,
+        splits: &[(usize, usize)],
+    ) {
+        let mut is_changed = vec![false; self.triangles.len()];
+        for flag in is_changed[first_new..].iter_mut() {
+            *flag = true;
+        }
+        for &(triangle, _) in splits {
+            is_changed[triangle] = true;
+        }
+        let remap = |index: usize| triangle_remap.get(index).copied().flatten();
+
+        // Remap the vertices of the graph of the kept triangles and drop the edges to the removed
+        // and the changed triangles, the latter are connected again below.
+        let mut vertices = Vec::with_capacity(self.triangles.len());
+        for (old_index, mut vertex) in std::mem::take(&mut self.graph.vertices)
+            .into_iter()
+            .enumerate()
+        {
+            let Some(index) = remap(old_index) else {
+                continue;
+            };
+            if is_changed[index] {
+                vertex.neighbours.clear();
+            } else {
+                vertex.neighbours.retain_mut(|neighbour| {
+                    match remap(*neighbour as usize).filter(|n| !is_changed[*n]) {
+                        Some(new_index) => {
+                            *neighbour = new_index as u32;
+                            true
+                        }
+                        None => false,
+                    }
+                });
+            }
+            vertex.triangle_index = index;
+            vertices.push(vertex);
+        }
+        for triangle_index in vertices.len()..self.triangles.len() {
+            vertices.push(Vertex {
+                triangle_index,
+                data: VertexData::new(Default::default()),
+            });
+        }
+        let changed = (0..self.triangles.len())
+            .filter(|index| is_changed[*index])
+            .collect::<Vec<_>>();
+        for &index in changed.iter() {
+            let vertex = &mut vertices[index];
+            vertex.position = self.triangle_center(index);
+            vertex.g_penalty = self.triangle_costs[index];
+        }
+        self.graph.vertices = vertices;
+
+        // Connect the changed triangles with the triangles, that share an edge with them. Only
+        // the triangles, that share a vertex with the changed triangles, could be adjacent.
+        let changed_vertices = changed
+            .iter()
+            .flat_map(|index| self.triangles[*index].0)
+            .collect::<FxHashSet<_>>();
+        let mut edge_triangle_map = FxHashMap::default();
+        for (index, triangle) in self.triangles.iter().enumerate() {
+            if triangle.0.iter().any(|v| changed_vertices.contains(v)) {
+                for edge in triangle.edges() {
+                    edge_triangle_map.insert((edge.a, edge.b), index);
+                }
+            }
+        }
+        for &index in changed.iter() {
+            for edge in self.triangles[index].edges() {
+                // Adjacent edge must have opposite winding.
+                if let Some(&adjacent) = edge_triangle_map.get(&(edge.b, edge.a)) {
+                    self.graph.link_bidirect(index, adjacent);
+                }
+            }
+        }
+
+        // Shared edges of the split triangles could belong to their new halves now, so one-way
+        // edges are checked against every part of the original triangle.
+        let mut origins = (0..self.triangles.len()).collect::<Vec<_>>();
+        for &(triangle, half) in splits {
+            origins[half] = origins[triangle];
+        }
+        let origins = &origins;
+        let parts = |index: usize| {
+            std::iter::once(index).chain(
+                (first_new..origins.len()).filter(move |i| *i != index && origins[*i] == index),
+            )
+        };
+        let mut one_way_edges = FxHashSet::default();
+        for [a, b] in std::mem::take(&mut self.one_way_edges) {
+            let (Some(a), Some(b)) = (remap(a as usize), remap(b as usize)) else {
+                continue;
+            };
+            for a in parts(a) {
+                for b in parts(b) {
+                    if self.portal_between(a, b).is_some() {
+                        one_way_edges.insert([a as u32, b as u32]);
+                    }
+                }
+            }
+        }
+        self.one_way_edges = one_way_edges;
+
+        self.rebuild_octree();
+        self.grid = Default::default();
+
+        // Off-mesh links, that were attached to the removed or the changed triangles, are attached
+        // again, the rest of the links are only remapped.
+        let mut traversals = FxHashMap::default();
+        for ((from, to), list) in std::mem::take(&mut self.offmesh_link_traversals) {
+            if let (Some(from), Some(to)) = (remap(from), remap(to)) {
+                if !is_changed[from] && !is_changed[to] {
+                    traversals.insert((from, to), list);
+                }
+            }
+        }
+        self.offmesh_link_traversals = traversals;
+        let mut detached = Vec::new();
+        for (handle, link) in self.offmesh_links.pair_iter_mut() {
+            let from = link.from_triangle.and_then(remap);
+            let to = link.to_triangle.and_then(remap);
+            match (from, to) {
+                (Some(from), Some(to)) if !is_changed[from] && !is_changed[to] => {
+                    link.from_triangle = Some(from);
+                    link.to_triangle = Some(to);
+                }
+                _ => detached.push(handle),
+            }
+        }
+        for handle in detached {
+            self.link_offmesh_link(handle);
+        }
+
+        self.islands = Default::default();
+        self.border_vertices = Default::default();
+        self.border_edges = Default::default();
+        self.vertex_clearances = Default::default();
+        self.bump_revision();
+        self.topology_revision = self.topology_revision.wrapping_add(1);
+        self.rebuild_obstacles();
+        self.rebuild_polygons();
+    }
+
+    fn remove_unused_vertices(&mut self) {
+        let mut remap = vec![u32::MAX; self.vertices.len()];
+        for triangle in self.triangles.iter() {
+            for &index in triangle.0.iter() {
+                remap[index as usize] = 0;
+            }
+        }
+        let mut vertices = Vec::with_capacity(self.vertices.len());
+        for (index, vertex) in self.vertices.iter().enumerate() {
+            if remap[index] != u32::MAX {
+                remap[index] = vertices.len() as u32;
+                vertices.push(*vertex);
+            }
+        }
+        for triangle in self.triangles.iter_mut() {
+            *triangle = TriangleDefinition(triangle.0.map(|i| remap[i as usize]));
+        }
+        self.vertices = vertices;
+    }
+
+    // Splits the border edges along the borders of the given tile, that have vertices lying on
+    // them, so the triangles of the adjacent tiles share the same edges. Returns the pairs of the
+    // split triangles and their new halves.
+    fn split_tile_t_junctions(&mut self, tile: Vector2<i32>) -> Vec<(usize, usize)> {
+        let tolerance = NAVMESH_TILE_WELD_TOLERANCE;
+        let min = Vector2::new(tile.x as f32, tile.y as f32).scale(self.tile_size);
+        let max = min.add_scalar(self.tile_size);
+        let up_rotation = self.up_rotation;
+        let is_on_tile_border = |p: &Vector3<f32>| {
+            let p = up_rotation.map_or(*p, |rotation| rotation * p);
+            p.x >= min.x - tolerance
+                && p.x <= max.x + tolerance
+                && p.z >= min.y - tolerance
+                && p.z <= max.y + tolerance
+                && ((p.x - min.x).abs() <= tolerance
+                    || (p.x - max.x).abs() <= tolerance
+                    || (p.z - min.y).abs() <= tolerance
+                    || (p.z - max.y).abs() <= tolerance)
+        };
+
+        let border_vertices = self
+            .vertices
+            .iter()
+            .enumerate()
+            .filter(|(_, v)| is_on_tile_border(v))
+            .map(|(i, _)| i as u32)
+            .collect::<Vec<_>>();
+
+        let mut split_triangles = Vec::new();
+        loop {
+            // Border edges are the edges, that are used by one triangle only.
+            let mut edges = FxHashMap::<(u32, u32), (usize, u32, u32, u32)>::default();
+            for (triangle_index, triangle) in self.triangles.iter().enumerate() {
+                for edge in triangle.edges() {
+                    let key = (edge.a.min(edge.b), edge.a.max(edge.b));
+                    edges
+                        .entry(key)
+                        .and_modify(|(_, _, _, count)| *count += 1)
+                        .or_insert((triangle_index, edge.a, edge.b, 1));
+                }
+            }
+
+            let mut splits = Vec::new();
+            for &(triangle_index, a, b, count) in edges.values() {
+                let (pa, pb) = (self.vertices[a as usize], self.vertices[b as usize]);
+                if count != 1 || !is_on_tile_border(&pa) || !is_on_tile_border(&pb) {
+                    continue;
+                }
+
+                // Find a vertex on the edge, that is closest to the beginning of the edge.
+                let ab = pb - pa;
+                let length_squared = ab.norm_squared();
+                let mut closest = None;
+                let mut closest_t = f32::MAX;
+                for &v in border_vertices.iter() {
+                    if v == a || v == b {
+                        continue;
+                    }
+                    let p = self.vertices[v as usize];
+                    let t = (p - pa).dot(&ab) / length_squared;
+                    if t > 0.0
+                        && t < 1.0
+                        && t < closest_t
+                        && distance_to_segment(p, pa, pb) <= tolerance
+                    {
+                        closest_t = t;
+                        closest = Some(v);
+                    }
+                }
+
+                if let Some(v) = closest {
+                    splits.push((triangle_index, a, b, v));
+                }
+            }
+
+            if splits.is_empty() {
+                break split_triangles;
+            }
+
+            // Only one split per triangle at a time, other splits will be found on the next
+            // iteration.
+            let mut is_split = vec![false; self.triangles.len()];
+            for (triangle_index, a, b, v) in splits {
+                if std::mem::replace(&mut is_split[triangle_index], true) {
+                    continue;
+                }
+                let triangle = self.triangles[triangle_index].0;
+                let c = *triangle.iter().find(|&&i| i != a && i != b).unwrap();
+                // Edges are in the winding order of the triangle, so the order is kept.
+                let (first, second) = ([a, v, c], [v, b, c]);
+                self.triangles[triangle_index] = TriangleDefinition(first);
+                split_triangles.push((triangle_index, self.triangles.len()));
+                self.triangles.push(TriangleDefinition(second));
+                self.triangle_costs
+                    .push(self.triangle_costs[triangle_index]);
+                self.triangle_flags
+                    .push(self.triangle_flags[triangle_index]);
+                self.triangle_layers
+                    .push(self.triangle_layers[triangle_index]);
+            }
+        }
+    }
+
+    /// Reduces the number of triangles of the navmesh by collapsing the edges of the dense parts of
+    /// the navmesh. A vertex is removed only if the removal keeps the surface and the walkable
+    /// boundary within `max_edge_error` (in meters) of their original shape: inner vertices must
+    /// lie close to the planes of the triangles, that replace them, and border vertices must lie
+    /// close to the border edge, that replaces them. The error is tracked for every removed vertex,
+    /// so it does not accumulate over a series of collapses.
+    ///
+    /// The topology of the navmesh is preserved, the islands are never split or merged. Vertices of
+    /// the triangles with different traversal costs (see [`Self::set_triangle_cost`]), flags (see
+    /// [`Self::set_triangle_flags`]) or one-way edges (see [`Self::set_edge_direction`]) are kept,
+    /// so such areas keep their shape. Unused vertices are
+    /// removed, and every internal structure is rebuilt afterwards. Keep in mind, that the indices
+    /// of the triangles and the vertices are changed by this method.
+    ///
+    /// Returns the number of triangles and vertices before and after the simplification, it could
+    /// be used to tune the tolerance.
+    pub fn simplify(&mut self, max_edge_error: f32) -> NavmeshSimplifyStats {
+        let triangles_before = self.triangles.len();
+        let vertices_before = self.vertices.len();
+
+        let mut triangles = self.triangles.iter().map(|t| Some(*t)).collect::<Vec<_>>();
+        let mut incident = vec![Vec::new(); self.vertices.len()];
+        for (index, triangle) in self.triangles.iter().enumerate() {
+            for &vertex in triangle.0.iter() {
+                incident[vertex as usize].push(index);
+            }
+        }
+        // Positions of the removed vertices, that are represented by a vertex or a border edge.
+        let mut absorbed = vec![Vec::new(); self.vertices.len()];
+        let mut absorbed_by_edges = FxHashMap::<(u32, u32), Vec<Vector3<f32>>>::default();
+        let edge_key = |a: u32, b: u32| (a.min(b), a.max(b));
+
+        let mut changed = true;
+        while changed {
+            changed = false;
+            for vertex in 0..self.vertices.len() {
+                let Some(collapse) = self.find_collapse(
+                    vertex,
+                    &triangles,
+                    &incident,
+                    &absorbed,
+                    &absorbed_by_edges,
+                    max_edge_error,
+                ) else {
+                    continue;
+                };
+
+                let (v, u) = (vertex as u32, collapse.target);
+                if let Some([a, b]) = collapse.border {
+                    let mut points = absorbed_by_edges
+                        .remove(&edge_key(a, v))
+                        .unwrap_or_default();
+                    points.extend(
+                        absorbed_by_edges
+                            .remove(&edge_key(v, b))
+                            .unwrap_or_default(),
+                    );
+                    points.push(self.vertices[vertex]);
+                    absorbed_by_edges
+                        .entry(edge_key(a, b))
+                        .or_default()
+                        .extend(points);
+                }
+                let mut points = std::mem::take(&mut absorbed[vertex]);
+                points.push(self.vertices[vertex]);
+                absorbed[u as usize].extend(points);
+
+                for triangle_index in std::mem::take(&mut incident[vertex]) {
+                    let triangle = triangles[triangle_index].unwrap();
+                    if triangle.0.contains(&u) {
+                        triangles[triangle_index] = None;
+                        for &other in triangle.0.iter() {
+                            if other != v {
+                                incident[other as usize].retain(|&t| t != triangle_index);
+                            }
+                        }
+                    } else {
+                        triangles[triangle_index] = Some(TriangleDefinition(triangle.0.map(|i| {
+                            if i == v {
+                                u
+                            } else {
+                                i
+                            }
+                        })));
+                        incident[u as usize].push(triangle_index);
+                    }
+                }
+
+                changed = true;
+            }
+        }
+
+        // Remove unused vertices.
+        let mut remap = vec![u32::MAX; self.vertices.len()];
+        let mut vertices = Vec::new();
+        for (index, vertex) in self.vertices.iter().enumerate() {
+            if !incident[index].is_empty() {
+                remap[index] = vertices.len() as u32;
+                vertices.push(*vertex);
+            }
+        }
+        let mut triangle_costs = Vec::new();
+        let mut triangle_flags = Vec::new();
+        let mut triangle_layers = Vec::new();
+        let mut triangle_remap = vec![None; triangles.len()];
+        self.triangles = triangles
+            .iter()
+            .zip(self.triangle_costs.iter())
+            .zip(self.triangle_flags.iter())
+            .zip(self.triangle_layers.iter())
+            .enumerate()
+            .filter_map(|(index, (((triangle, cost), flags), layer))| {
+                triangle.map(|triangle| {
+                    triangle_remap[index] = Some(triangle_costs.len() as u32);
+                    triangle_costs.push(*cost);
+                    triangle_flags.push(*flags);
+                    triangle_layers.push(*layer);
+                    TriangleDefinition(triangle.0.map(|i| remap[i as usize]))
+                })
+            })
+            .collect();
+        self.vertices = vertices;
+        self.triangle_costs = triangle_costs;
+        self.triangle_flags = triangle_flags;
+        self.triangle_layers = triangle_layers;
+        // The edges between the triangles, that are not adjacent anymore, are filtered out when
+        // the graph is rebuilt.
+        self.one_way_edges = std::mem::take(&mut self.one_way_edges)
+            .into_iter()
+            .filter_map(|[a, b]| Some([triangle_remap[a as usize]?, triangle_remap[b as usize]?]))
+            .collect();
+        self.rebuild();
+
+        NavmeshSimplifyStats {
+            triangles_before,
+            triangles_after: self.triangles.len(),
+            vertices_before,
+            vertices_after: self.vertices.len(),
+        }
+    }
+
+    /// Splits the edges of the triangle at the given index in halves, until every edge of the
+    /// pieces is not longer than `max_edge_length` (in meters). It could be used to paint traversal
+    /// costs (see [`Self::set_triangle_cost`]) or flags (see [`Self::set_triangle_flags`]) at
+    /// finer granularity, than the source geometry has, without re-authoring it. The new vertices
+    /// are placed at the midpoints of the split edges, so the shape of the surface is unchanged.
+    ///
+    /// Adjacent triangles, that share the split edges, are split as well, so the navmesh stays a
+    /// valid connected surface without T-junctions. The pieces keep the traversal costs, the flags
+    /// and the one-way edges of the triangles they were split from. Every split triangle keeps its
+    /// index for one of its pieces, the other pieces are added to the end of the array, so the
+    /// indices of the other triangles stay the same.
+    ///
+    /// Returns the indices of the triangles, that the given triangle was split into (it contains
+    /// the given index only, if the triangle does not need to be split). Returns an empty array,
+    /// if the index is invalid.
+    pub fn subdivide_triangle(&mut self, index: usize, max_edge_length: f32) -> Vec<usize> {
+        if index >= self.triangles.len() {
+            return Vec::new();
+        }
+        let mut selected = vec![false; self.triangles.len()];
+        selected[index] = true;
+        self.subdivide_selected(selected, max_edge_length)
+            .iter()
+            .enumerate()
+            .filter(|(_, selected)| **selected)
+            .map(|(index, _)| index)
+            .collect()
+    }
+
+    /// Splits the edges of all the triangles of the navmesh in halves, until every edge is not
+    /// longer than `max_edge_length` (in meters). See [`Self::subdivide_triangle`] for more info.
+    /// Returns the number of the added triangles.
+    pub fn subdivide(&mut self, max_edge_length: f32) -> usize {
+        let triangles_before = self.triangles.len();
+        self.subdivide_selected(vec![true; triangles_before], max_edge_length);
+        self.triangles.len() - triangles_before
+    }
+
+    // Splits the long edges of the selected triangles until there are no such edges. Returns a
+    // flag for every triangle, that tells whether the triangle is a piece of a selected triangle.
+    fn subdivide_selected(&mut self, mut selected: Vec<bool>, max_edge_length: f32) -> Vec<bool> {
+        if max_edge_length.is_nan() || max_edge_length <= 0.0 {
+            return selected;
+        }
+        let max_length_squared = max_edge_length * max_edge_length;
+        let vertices_before = self.vertices.len();
+        // Index of the source triangle of every piece.
+        let mut sources = (0..self.triangles.len() as u32).collect::<Vec<_>>();
+
+        // Every pass shortens the longest edge of the selected triangles, so the loop ends.
+        loop {
+            let mut midpoints = FxHashMap::<(u32, u32), u32>::default();
+            for (triangle, _) in self
+                .triangles
+                .iter()
+                .zip(selected.iter())
+                .filter(|(_, selected)| **selected)
+            {
+                for edge in triangle.edges() {
+                    let key = (edge.a.min(edge.b), edge.a.max(edge.b));
+                    let (a, b) = (self.vertices[key.0 as usize], self.vertices[key.1 as usize]);
+                    if !midpoints.contains_key(&key) && (b - a).norm_squared() > max_length_squared
+                    {
+                        midpoints.insert(key, self.vertices.len() as u32);
+                        self.vertices.push(a.lerp(&b, 0.5));
+                    }
+                }
+            }
+            if midpoints.is_empty() {
+                break;
+            }
+
+            for index in 0..self.triangles.len() {
+                let triangle = self.triangles[index].0;
+                let midpoint = |a: u32, b: u32| midpoints.get(&(a.min(b), a.max(b))).cloned();
+                let pieces = split_triangle(
+                    triangle,
+                    [
+                        midpoint(triangle[0], triangle[1]),
+                        midpoint(triangle[1], triangle[2]),
+                        midpoint(triangle[2], triangle[0]),
+                    ],
+                    &self.vertices,
+                );
+                self.triangles[index] = TriangleDefinition(pieces[0]);
+                for piece in pieces.into_iter().skip(1) {
+                    self.triangles.push(TriangleDefinition(piece));
+                    self.triangle_costs.push(self.triangle_costs[index]);
+                    self.triangle_flags.push(self.triangle_flags[index]);
+                    self.triangle_layers.push(self.triangle_layers[index]);
+                    selected.push(selected[index]);
+                    sources.push(sources[index]);
+                }
+            }
+        }
+
+        if self.vertices.len() == vertices_before {
+            return selected;
+        }
+
+        // Move the one-way edges to the pieces, the pieces that do not share an edge are filtered
+        // out when the graph is rebuilt.
+        if !self.one_way_edges.is_empty() {
+            let mut pieces = FxHashMap::<u32, Vec<u32>>::default();
+            for (index, source) in sources.iter().enumerate() {
+                pieces.entry(*source).or_default().push(index as u32);
+            }
+            let mut one_way_edges = FxHashSet::default();
+            for [a, b] in self.one_way_edges.iter() {
+                if let (Some(a_pieces), Some(b_pieces)) = (pieces.get(a), pieces.get(b)) {
+                    for &a in a_pieces {
+                        for &b in b_pieces {
+                            one_way_edges.insert([a, b]);
+                        }
+                    }
+                }
+            }
+            self.one_way_edges = one_way_edges;
+        }
+
+        self.rebuild();
+        selected
+    }
+
+    // Finds a vertex, that the given vertex could be collapsed into, without violating the
+    // error tolerance and the topology of the navmesh.
+    fn find_collapse(
+        &self,
+        vertex: usize,
+        triangles: &[Option<TriangleDefinition>],
+        incident: &[Vec<usize>],
+        absorbed: &[Vec<Vector3<f32>>],
+        absorbed_by_edges: &FxHashMap<(u32, u32), Vec<Vector3<f32>>>,
+        max_error: f32,
+    ) -> Option<Collapse> {
+        let v = vertex as u32;
+        let vertex_triangles = &incident[vertex];
+        let first = *vertex_triangles.first()?;
+        let (cost, flags, layer) = (
+            self.triangle_costs[first],
+            self.triangle_flags[first],
+            self.triangle_layers[first],
+        );
+        if vertex_triangles.iter().any(|&t| {
+            self.triangle_costs[t] != cost
+                || self.triangle_flags[t] != flags
+                || self.triangle_layers[t] != layer
+                || self
+                    .one_way_edges
+                    .iter()
+                    .any(|edge| edge.contains(&(t as u32)))
+        }) {
+            return None;
+        }
+
+        // Neighbor vertices with the number of triangles, that share an edge with them.
+        let mut neighbors = Vec::<(u32, u32)>::new();
+        for &triangle_index in vertex_triangles {
+            for &other in triangles[triangle_index].unwrap().0.iter() {
+                if other == v {
+                    continue;
+                }
+                match neighbors.iter_mut().find(|(n, _)| *n == other) {
+                    Some((_, count)) => *count += 1,
+                    None => neighbors.push((other, 1)),
+                }
+            }
+        }
+        if neighbors.iter().any(|(_, count)| *count > 2) {
+            // Non-manifold edge.
+            return None;
+        }
+
+        let position = self.vertices[vertex];
+        let border = neighbors
+            .iter()
+            .filter(|(_, count)| *count == 1)
+            .map(|(n, _)| *n)
+            .collect::<Vec<_>>();
+        let (candidates, border) = match border.as_slice() {
+            [] => (neighbors.iter().map(|(n, _)| *n).collect::<Vec<_>>(), None),
+            &[a, b] => {
+                // The new border edge must stay close to every removed border vertex.
+                let (pa, pb) = (self.vertices[a as usize], self.vertices[b as usize]);
+                let key = |x: u32, y: u32| (x.min(y), x.max(y));
+                let mut points = std::iter::once(&position)
+                    .chain(absorbed_by_edges.get(&key(a, v)).into_iter().flatten())
+                    .chain(absorbed_by_edges.get(&key(v, b)).into_iter().flatten());
+                if points.any(|p| distance_to_segment(*p, pa, pb) > max_error) {
+                    return None;
+                }
+                (vec![a, b], Some([a, b]))
+            }
+            // A vertex, that joins multiple parts of the border, must be kept.
+            _ => return None,
+        };
+
+        let normal = |t: [u32; 3]| {
+            let [a, b, c] = t.map(|i| self.vertices[i as usize]);
+            (b - a).cross(&(c - a))
+        };
+
+        let mut best = None;
+        let mut best_error = f32::MAX;
+        'candidates: for &u in candidates.iter() {
+            // Link condition - the only common neighbors of the vertices must be the vertices of
+            // the triangles, that share the collapsed edge. Otherwise, the collapse changes the
+            // topology of the navmesh.
+            let opposite = vertex_triangles
+                .iter()
+                .filter_map(|&t| {
+                    let triangle = triangles[t].unwrap().0;
+                    triangle
+                        .contains(&u)
+                        .then(|| *triangle.iter().find(|&&i| i != u && i != v).unwrap())
+                })
+                .collect::<Vec<_>>();
+            for &t in incident[u as usize].iter() {
+                for &other in triangles[t].unwrap().0.iter() {
+                    if other != u
+                        && other != v
+                        && !opposite.contains(&other)
+                        && neighbors.iter().any(|(n, _)| *n == other)
+                    {
+                        continue 'candidates;
+                    }
+                }
+            }
+
+            let mut error = 0.0f32;
+            let mut kept = 0;
+            for &t in vertex_triangles {
+                let triangle = triangles[t].unwrap().0;
+                if triangle.contains(&u) {
+                    continue;
+                }
+                kept += 1;
+
+                let new_triangle = triangle.map(|i| if i == v { u } else { i });
+                let new_normal = normal(new_triangle);
+                let length = new_normal.norm();
+                // The new triangle must not degenerate or flip.
+                if length <= f32::EPSILON || new_normal.dot(&normal(triangle)) <= 0.0 {
+                    continue 'candidates;
+                }
+
+                let origin = self.vertices[u as usize];
+                for point in std::iter::once(&position).chain(absorbed[vertex].iter()) {
+                    error = error.max((point - origin).dot(&new_normal).abs() / length);
+                }
+            }
+
+            // The collapse must not remove the last triangles of the vertex, since it could
+            // remove the whole island.
+            if kept > 0 && error <= max_error && error < best_error {
+                best = Some(u);
+                best_error = error;
+            }
+        }
+
+        best.map(|target| Collapse { target, border })
+    }
+
+    fn rebuild_octree(&mut self) {
+        self.octree = OnceLock::from(self.build_octree());
+    }
+
+    fn build_octree(&self) -> Octree {
+        // Build triangles for octree.
+        let raw_triangles = self
+            .triangles
+            .iter()
+            .map(|t| {
+                [
+                    self.vertices[t[0] as usize],
+                    self.vertices[t[1] as usize],
+                    self.vertices[t[2] as usize],
+                ]
+            })
+            .collect::<Vec<[Vector3<f32>; 3]>>();
+
+        Octree::new(&raw_triangles, 32)
+    }
+
+    fn rebuild_graph(&mut self) {
+        self.graph = make_graph(&self.triangles, &self.vertices);
+        self.bump_revision();
+        self.topology_revision = self.topology_revision.wrapping_add(1);
+        let stale_edges = self
+            .one_way_edges
+            .iter()
+            .filter(|[a, b]| self.portal_between(*a as usize, *b as usize).is_none())
+            .cloned()
+            .collect::<Vec<_>>();
+        for edge in stale_edges {
+            self.one_way_edges.remove(&edge);
+        }
+        self.islands = Default::default();
+        self.border_vertices = Default::default();
+        self.border_edges = Default::default();
+        self.vertex_clearances = Default::default();
+        for (vertex, cost) in self.graph.vertices.iter_mut().zip(&self.triangle_costs) {
+            vertex.g_penalty = *cost;
+        }
+
+        self.offmesh_link_traversals.clear();
+        let handles = self
+            .offmesh_links
+            .pair_iter()
+            .map(|(handle, _)| handle)
+            .collect::<Vec<_>>();
+        for handle in handles {
+            self.link_offmesh_link(handle);
+        }
+        self.rebuild_polygons();
+    }
+
+    fn link_offmesh_link(&mut self, handle: OffMeshLinkHandle) {
+        let link = &self.offmesh_links[handle];
+        let from_triangle = self.query_closest(link.from).map(|(_, index)| index);
+        let to_triangle = self.query_closest(link.to).map(|(_, index)| index);
+
+        let link = &mut self.offmesh_links[handle];
+        link.from_triangle = from_triangle;
+        link.to_triangle = to_triangle;
+
+        self.connect_offmesh_link(handle);
+    }
+
+    // Adds the edges of an off-mesh link, that is already attached to the triangles, to the graph.
+    fn connect_offmesh_link(&mut self, handle: OffMeshLinkHandle) {
+        let link = &self.offmesh_links[handle];
+        if let (Some(from_triangle), Some(to_triangle)) = (link.from_triangle, link.to_triangle) {
+            // Links inside a single triangle make no sense, since the triangle is convex.
+            if from_triangle != to_triangle {
+                self.graph.link_unidirect(from_triangle, to_triangle);
+                self.offmesh_link_traversals
+                    .entry((from_triangle, to_triangle))
+                    .or_default()
+                    .push(OffMeshLinkTraversal {
+                        link: handle,
+                        enter: link.from,
+                        exit: link.to,
+                    });
+
+                if link.bidirectional {
+                    self.graph.link_unidirect(to_triangle, from_triangle);
+                    self.offmesh_link_traversals
+                        .entry((to_triangle, from_triangle))
+                        .or_default()
+                        .push(OffMeshLinkTraversal {
+                            link: handle,
+                            enter: link.to,
+                            exit: link.from,
+                        });
+                }
+            }
+        }
+    }
+
+    /// Adds a new off-mesh link between two points on the navmesh and returns its handle. Off-mesh
+    /// link allows path finding to build paths between the parts of the navmesh, that are not
+    /// connected by shared edges (for example, a ledge and the ground below it). Ends of the link
+    /// are attached to the closest triangles of the navmesh. If `bidirectional` is `false`, the
+    /// link could be traversed only from `from` point to `to` point.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use fyrox_impl::{
+    ///     core::algebra::Vector3,
+    ///     utils::navmesh::{Navmesh, OffMeshLinkHandle},
+    /// };
+    ///
+    /// fn add_jump_down(navmesh: &mut Navmesh) -> OffMeshLinkHandle {
+    ///     // A one-way link from a ledge to the ground.
+    ///     let link = navmesh.add_offmesh_link(
+    ///         Vector3::new(0.0, 2.0, 0.0),
+    ///         Vector3::new(0.0, 0.0, 1.0),
+    ///         false,
+    ///     );
+    ///     // Make the jump a bit less preferable than walking.
+    ///     navmesh.set_offmesh_link_cost(link, 2.0);
+    ///     link
+    /// }
+    /// ```
+    pub fn add_offmesh_link(
+        &mut self,
+        from: Vector3<f32>,
+        to: Vector3<f32>,
+        bidirectional: bool,
+    ) -> OffMeshLinkHandle {
+        let handle = self.offmesh_links.spawn(OffMeshLink {
+            from,
+            to,
+            bidirectional,
+            ..Default::default()
+        });
+        self.link_offmesh_link(handle);
+        self.islands = Default::default();
+        self.bump_revision();
+        self.rebuild_polygons();
+        handle
+    }
+
+    /// Removes an off-mesh link with the given handle and returns the link, if the handle was
+    /// valid.
+    pub fn remove_offmesh_link(&mut self, handle: OffMeshLinkHandle) -> Option<OffMeshLink> {
+        let link = self.offmesh_links.try_free(handle)?;
+        self.rebuild_graph();
+        Some(link)
+    }
+
+    /// Sets a new traversal cost multiplier of an off-mesh link. The cost of traversing the link
+    /// is calculated as the cost of moving between the triangles, that the link connects,
+    /// multiplied by this value. Default cost is `1.0`.
+    pub fn set_offmesh_link_cost(&mut self, handle: OffMeshLinkHandle, cost: f32) {
+        if let Some(link) = self.offmesh_links.try_borrow_mut(handle) {
+            link.cost = cost;
+            self.bump_revision();
+        }
+    }
+
+    /// Returns a reference to an off-mesh link with the given handle.
+    pub fn offmesh_link(&self, handle: OffMeshLinkHandle) -> Option<&OffMeshLink> {
+        self.offmesh_links.try_borrow(handle)
+    }
+
+    /// Returns an iterator over all off-mesh links of the navmesh.
+    pub fn offmesh_links(&self) -> impl Iterator<Item = (OffMeshLinkHandle, &OffMeshLink)> {
+        self.offmesh_links.pair_iter()
+    }
+
+    /// Connects the parts of the navmesh, that are separated by vertical gaps (steps, curbs,
+    /// ledges), with off-mesh links and returns the handles of the new links. Two border edges are
+    /// considered as the sides of a step, if their projections on the horizontal plane face each
+    /// other (the edges are parallel, have opposite directions and overlap), and the horizontal
+    /// distance between them does not exceed `max_horizontal_gap`. The vertical gap is measured at
+    /// the middle of the overlapping part of the edges, along the up axis of the navmesh (see
+    /// [`Self::set_up_axis`]):
+    ///
+    /// - if the gap does not exceed `max_step_height`, the edges are connected by a
+    /// bidirectional link, so the agents could step up and down;
+    /// - otherwise, if the gap does not exceed `max_drop_height`, the edges are connected by a
+    /// one-way link from the upper edge to the lower one, so the agents could jump down, but could
+    /// not climb up.
+    ///
+    /// Larger gaps are left unconnected. Every link spans the overlapping parts of the edges (see
+    /// [`OffMeshLink::span`]), so the agents cross the step at any point along it, as if the edges
+    /// were shared, instead of detouring to a single crossing point. This method has `O(n^2)`
+    /// complexity in the worst case, where `n` is the number of border edges, so it is intended to
+    /// be called once after the navmesh is built.
+    pub fn connect_steps(
+        &mut self,
+        max_step_height: f32,
+        max_drop_height: f32,
+        max_horizontal_gap: f32,
+    ) -> Vec<OffMeshLinkHandle> {
+        let mut edges = self
+            .edges()
+            .filter(|edge| edge.is_border())
+            .map(|edge| {
+                let points = edge
+                    .vertices
+                    .map(|index| self.to_up_space(self.vertices[index as usize]));
+                (edge.triangle, points)
+            })
+            .collect::<Vec<_>>();
+        edges.sort_by(|(_, a), (_, b)| a[0].x.min(a[1].x).total_cmp(&b[0].x.min(b[1].x)));
+
+        let mut links = Vec::new();
+        for (i, (triangle, a)) in edges.iter().enumerate() {
+            let max_x = a[0].x.max(a[1].x) + max_horizontal_gap;
+            for (other_triangle, b) in edges[i + 1..].iter() {
+                if b[0].x.min(b[1].x) > max_x {
+                    break;
+                }
+                if triangle == other_triangle {
+                    continue;
+                }
+                let Some((a, b, span)) = step_between(*a, *b, max_horizontal_gap) else {
+                    continue;
+                };
+                let (lower, upper) = if a.y <= b.y { (a, b) } else { (b, a) };
+                let height = upper.y - lower.y;
+                if height <= max_step_height {
+                    links.push((lower, upper, span, true));
+                } else if height <= max_drop_height {
+                    links.push((upper, lower, span, false));
+                }
+            }
+        }
+
+        links
+            .into_iter()
+            .map(|(from, to, span, bidirectional)| {
+                let from = self.up_space_to_world(from);
+                let to = self.up_space_to_world(to);
+                let handle = self.add_offmesh_link(from, to, bidirectional);
+                // The span does not affect the connectivity, so the link is not relinked.
+                self.offmesh_links[handle].span = self.up_space_to_world(span);
+                handle
+            })
+            .collect()
+    }
+
+    /// Returns an iterator over all edges of the navmesh. Every edge is reported only once, even if
+    /// it is shared by two triangles. Border edges (that belong to a single triangle) could be
+    /// used to find the boundary of the navmesh.
+    pub fn edges(&self) -> impl Iterator<Item = NavmeshEdge> + '_ {
+        self.triangles
+            .iter()
+            .enumerate()
+            .flat_map(move |(triangle_index, triangle)| {
+                triangle.edges().into_iter().filter_map(move |edge| {
+                    let neighbor = self.adjacent_triangle(triangle_index, edge.a, edge.b);
+                    // Shared edges are reported by the triangle with the lowest index.
+                    if neighbor.is_some_and(|neighbor| neighbor < triangle_index) {
+                        None
+                    } else {
+                        Some(NavmeshEdge {
+                            vertices: [edge.a, edge.b],
+                            triangle: triangle_index,
+                            neighbor,
+                        })
+                    }
+                })
+            })
+    }
+
+    /// Returns an iterator over the triangles, that share an edge with the given triangle.
+    /// Off-mesh links are ignored.
+    pub fn neighbors(&self, triangle: usize) -> impl Iterator<Item = usize> + '_ {
+        self.graph
+            .vertices
+            .get(triangle)
+            .into_iter()
+            .flat_map(|vertex| vertex.neighbours.iter())
+            .map(|neighbour| *neighbour as usize)
+            .filter(move |neighbour| self.portal_between(triangle, *neighbour).is_some())
+    }
+
+    /// Splits the navmesh into groups of triangles, that are mutually reachable through shared
+    /// edges (off-mesh links are ignored). Every group is a sorted list of triangle indices. This
+    /// method could be used to find small unreachable "islands", that could appear after baking
+    /// a navmesh from level geometry.
+    ///
+    /// ## Complexity
+    ///
+    /// This method has `O(n)` complexity, where `n` is the number of triangles in the navmesh.
+    pub fn connected_components(&self) -> Vec<Vec<usize>> {
+        let mut visited = vec![false; self.triangles.len()];
+        let mut components = Vec::new();
+        let mut stack = Vec::new();
+
+        for start in 0..self.triangles.len() {
+            if visited[start] {
+                continue;
+            }
+
+            let mut component = Vec::new();
+            visited[start] = true;
+            stack.push(start);
+            while let Some(triangle) = stack.pop() {
+                component.push(triangle);
+
+                for neighbour in self.neighbors(triangle) {
+                    if !visited[neighbour] {
+                        visited[neighbour] = true;
+                        stack.push(neighbour);
+                    }
+                }
+            }
+
+            component.sort_unstable();
+            components.push(component);
+        }
+
+        components
+    }
+
+    /// Returns the largest (by the number of triangles) group of triangles, that are mutually
+    /// reachable through shared edges. See [`Self::connected_components`] for more info. Returns
+    /// an empty list if the navmesh is empty.
+    pub fn largest_component(&self) -> Vec<usize> {
+        self.connected_components()
+            .into_iter()
+            .max_by_key(|component| component.len())
+            .unwrap_or_default()
+    }
+
+    /// Returns the boundary of the navmesh as a set of loops of vertex indices. Border edges (that
+    /// belong to a single triangle, see [`Self::edges`]) are chained into loops, every loop is
+    /// closed implicitly (its last vertex is connected with the first one). It could be used to
+    /// render an outline of the walkable space or to implement wall-following behaviors.
+    ///
+    /// The loops are grouped by connected components (in the same order as
+    /// [`Self::connected_components`]). The first loop of every component traces its outer
+    /// boundary, the rest of its loops trace the holes. The edges of the loops go in the winding
+    /// order of their triangles, so the outer loop has the same winding as the triangles and the
+    /// holes have the opposite one, which means that they have signed areas of the opposite signs.
+    /// The outer loop is the one with the largest area.
+    ///
+    /// Border edges of the triangles with inconsistent winding order (see [`Self::validate`]) could
+    /// not form closed loops, such edges are chained into open polylines, that are returned as
+    /// loops.
+    pub fn boundary_loops(&self) -> Vec<Vec<usize>> {
+        let components = self.connected_components();
+        let mut triangle_components = vec![0; self.triangles.len()];
+        for (index, component) in components.iter().enumerate() {
+            for &triangle in component {
+                triangle_components[triangle] = index;
+            }
+        }
+
+        let border_edges = self
+            .edges()
+            .filter(|edge| edge.is_border())
+            .collect::<Vec<_>>();
+        let mut outgoing_edges = FxHashMap::<u32, Vec<usize>>::default();
+        for (index, edge) in border_edges.iter().enumerate() {
+            outgoing_edges
+                .entry(edge.vertices[0])
+                .or_default()
+                .push(index);
+        }
+
+        let mut used = vec![false; border_edges.len()];
+        let mut component_loops = vec![Vec::new(); components.len()];
+        for start in 0..border_edges.len() {
+            if used[start] {
+                continue;
+            }
+
+            let component = triangle_components[border_edges[start].triangle];
+            let first_vertex = border_edges[start].vertices[0];
+            let mut ring = Vec::new();
+            let mut current = start;
+            loop {
+                used[current] = true;
+                let [begin, end] = border_edges[current].vertices;
+                ring.push(begin);
+                if end == first_vertex {
+                    break;
+                }
+                // Vertices, where multiple loops touch each other, have multiple outgoing edges.
+                let next = outgoing_edges.get(&end).and_then(|edges| {
+                    edges.iter().cloned().find(|&edge| {
+                        !used[edge] && triangle_components[border_edges[edge].triangle] == component
+                    })
+                });
+                match next {
+                    Some(next) => current = next,
+                    None => {
+                        // The chain is not closed.
+                        ring.push(end);
+                        break;
+                    }
+                }
+            }
+            component_loops[component].push(ring);
+        }
+
+        component_loops
+            .into_iter()
+            .flat_map(|mut loops| {
+                loops.sort_by(|a, b| {
+                    ring_area_2d(b, &self.vertices)
+                        .abs()
+                        .total_cmp(&ring_area_2d(a, &self.vertices).abs())
+                });
+                loops
+            })
+            .map(|ring| ring.into_iter().map(|index| index as usize).collect())
+            .collect()
+    }
+
+    /// Returns the surface area (in square meters) of a triangle with the given index. Returns
+    /// `None` if the index is out of bounds. Degenerate triangles have zero area.
+    pub fn triangle_area(&self, index: usize) -> Option<f32> {
+        let triangle = self.triangles.get(index)?;
+        let [a, b, c] = triangle.0.map(|index| self.vertices[index as usize]);
+        let area = 0.5 * (b - a).cross(&(c - a)).norm();
+        Some(if area.is_finite() { area } else { 0.0 })
+    }
+
+    /// Returns the centroid (the average of the vertices) of a triangle with the given index.
+    /// Returns `None` if the index is out of bounds.
+    pub fn triangle_centroid(&self, index: usize) -> Option<Vector3<f32>> {
+        let triangle = self.triangles.get(index)?;
+        let [a, b, c] = triangle.0.map(|index| self.vertices[index as usize]);
+        Some((a + b + c).scale(1.0 / 3.0))
+    }
+
+    /// Returns the normalized normal of a triangle with the given index. The direction of the
+    /// normal depends on the winding order of the triangle, it points up for the triangles that
+    /// have the winding order produced by [`Self::fix_winding`]. Degenerate triangles have zero
+    /// normal. Returns `None` if the index is out of bounds. It could be used for slope-based
+    /// gameplay or to align objects with the surface.
+    pub fn triangle_normal(&self, index: usize) -> Option<Vector3<f32>> {
+        let triangle = self.triangles.get(index)?;
+        let [a, b, c] = triangle.0.map(|index| self.vertices[index as usize]);
+        Some(
+            (b - a)
+                .cross(&(c - a))
+                .try_normalize(f32::EPSILON)
+                .unwrap_or_default(),
+        )
+    }
+
+    /// Returns an iterator over the normals of every triangle of the navmesh, in the same order as
+    /// the triangles. See [`Self::triangle_normal`] for more info.
+    pub fn triangle_normals(&self) -> impl Iterator<Item = Vector3<f32>> + '_ {
+        (0..self.triangles.len()).filter_map(|index| self.triangle_normal(index))
+    }
+
+    /// Returns the distance (in meters, measured on the horizontal plane, see
+    /// [`Self::set_up_axis`]) from the given point to the closest border edge of the navmesh (an
+    /// edge, that belongs to a single triangle, see [`Self::edges`]). Returns [`f32::MAX`] if the
+    /// navmesh has no border edges. It could be used to keep agents away from walls and ledges,
+    /// for example by penalizing the points, that are closer to the border than some threshold.
+    /// The border edges are stored in a uniform grid, so only the edges near the point are
+    /// checked.
+    pub fn border_distance(&self, point: Vector3<f32>) -> f32 {
+        let flatten = |point: Vector3<f32>| {
+            let point = self.to_up_space(point);
+            Vector3::new(point.x, 0.0, point.z)
+        };
+        let point = flatten(point);
+        let border_edges = self.border_edges();
+        let mut closest_distance = f32::MAX;
+
+        // Check the cells of the grid in rings around the point, until the rest of the rings are
+        // guaranteed to be farther than the closest edge found so far.
+        let grid = &border_edges.grid;
+        let (x, z) = grid.cell_coords(point);
+        for radius in grid.ring_range(x, z) {
+            let ring_distance = (radius - 1).max(0) as f32 * grid.cell_size();
+            if ring_distance > closest_distance {
+                break;
+            }
+
+            grid.visit_ring(x, z, radius, |edges| {
+                for &edge in edges {
+                    let [a, b] = border_edges.edges[edge as usize]
+                        .map(|index| flatten(self.vertices[index as usize]));
+                    closest_distance = closest_distance.min(distance_to_segment(point, a, b));
+                }
+            });
+        }
+
+        closest_distance
+    }
+
+    /// Returns the distance (in meters, measured on the horizontal plane) from a vertex with the
+    /// given index to the closest border edge of the navmesh, see [`Self::border_distance`]. The
+    /// values are computed once for every vertex and cached until the navmesh is modified, which
+    /// makes this method faster than [`Self::border_distance`]. Border vertices have zero
+    /// clearance. Returns `None` if the index is out of bounds.
+    pub fn vertex_clearance(&self, index: usize) -> Option<f32> {
+        self.vertex_clearances
+            .get_or_init(|| {
+                self.vertices
+                    .iter()
+                    .map(|vertex| self.border_distance(*vertex))
+                    .collect()
+            })
+            .get(index)
+            .cloned()
+    }
+
+    /// Returns the total surface area (in square meters) of every triangle of the navmesh. It
+    /// could be used to estimate the amount of walkable space of a level.
+    pub fn total_area(&self) -> f32 {
+        (0..self.triangles.len())
+            .filter_map(|index| self.triangle_area(index))
+            .sum()
+    }
+
+    /// Returns the surface area (in square meters) of every connected component of the navmesh.
+    /// The areas are in the same order as the components returned by
+    /// [`Self::connected_components`]. It could be used to find tiny unreachable patches of the
+    /// navmesh.
+    pub fn component_areas(&self) -> Vec<f32> {
+        self.connected_components()
+            .into_iter()
+            .map(|component| {
+                component
+                    .into_iter()
+                    .filter_map(|index| self.triangle_area(index))
+                    .sum()
+            })
+            .collect()
+    }
+
+    /// Returns a random point on the surface of the navmesh. The points are distributed uniformly
+    /// over the surface: a triangle is picked with the probability proportional to its area, and
+    /// then a uniformly distributed point inside the triangle is picked. It could be used to find
+    /// spawn points or wandering targets for NPCs. Returns `None` if the navmesh has no triangles
+    /// (or all of them are degenerate).
+    pub fn random_point(&self, rng: &mut impl Rng) -> Option<Vector3<f32>> {
+        self.random_point_among(0..self.triangles.len(), rng)
+    }
+
+    /// The same as [`Self::random_point`], but returns a point, that lies not further than the
+    /// given radius from the given center. The points are distributed uniformly over the part of
+    /// the surface inside the sphere: a triangle is picked with the probability proportional to the
+    /// area of its part inside the sphere, so big triangles, that only touch the sphere, are picked
+    /// rarely. Returns `None` if there are no triangles in the sphere, or the point could not be
+    /// found after a limited number of attempts (when the triangles only touch the sphere).
+    pub fn random_point_in_radius(
+        &self,
+        center: Vector3<f32>,
+        radius: f32,
+        rng: &mut impl Rng,
+    ) -> Option<Vector3<f32>> {
+        let offset = Vector3::repeat(radius);
+        let mut candidates = Vec::new();
+        self.visit_grid_rect(center - offset, center + offset, |triangles| {
+            for &index in triangles {
+                let [a, b, c] = self.triangles[index as usize]
+                    .0
+                    .map(|index| self.vertices[index as usize]);
+                if closest_point_on_triangle(center, a, b, c).metric_distance(&center) <= radius {
+                    candidates.push(index as usize);
+                }
+            }
+        });
+        // A triangle could be stored in multiple cells.
+        candidates.sort_unstable();
+        candidates.dedup();
+
+        // Only the parts of the triangles inside the sphere are sampled, each part is split into
+        // a fan of triangles.
+        let mut pieces = Vec::new();
+        for index in candidates {
+            let [a, b, c] = self.triangles[index]
+                .0
+                .map(|index| self.vertices[index as usize]);
+            let polygon = clip_triangle_by_sphere(a, b, c, center, radius);
+            for i in 1..polygon.len().saturating_sub(1) {
+                let piece = [polygon[0], polygon[i], polygon[i + 1]];
+                let area = 0.5 * (piece[1] - piece[0]).cross(&(piece[2] - piece[0])).norm();
+                if area > 0.0 && area.is_finite() {
+                    pieces.push((piece, area));
+                }
+            }
+        }
+        let total_area = pieces.iter().map(|(_, area)| area).sum::<f32>();
+        if total_area <= 0.0 {
+            return None;
+        }
+
+        // The parts are slightly bigger than the real ones, so the points outside of the sphere
+        // are rejected. This keeps the distribution uniform.
+        const MAX_ATTEMPTS: usize = 32;
+        (0..MAX_ATTEMPTS)
+            .map(|_| {
+                let mut threshold = rng.gen_range(0.0..total_area);
+                let [a, b, c] = pieces
+                    .iter()
+                    .find(|(_, area)| {
+                        let found = threshold < *area;
+                        threshold -= area;
+                        found
+                    })
+                    .unwrap_or(&pieces[pieces.len() - 1])
+                    .0;
+                random_point_in_triangle(a, b, c, rng)
+            })
+            .find(|point| point.metric_distance(&center) <= radius)
+    }
+
+    fn random_point_among(
+        &self,
+        triangles: impl Iterator<Item = usize> + Clone,
+        rng: &mut impl Rng,
+    ) -> Option<Vector3<f32>> {
+        let total_area = triangles
+            .clone()
+            .filter_map(|index| self.triangle_area(index))
+            .sum::<f32>();
+        if total_area <= 0.0 {
+            return None;
+        }
+
+        let mut threshold = rng.gen_range(0.0..total_area);
+        let mut selected = None;
+        for index in triangles {
+            let area = self.triangle_area(index).unwrap_or_default();
+            if area <= 0.0 {
+                continue;
+            }
+            selected = Some(index);
+            if threshold < area {
+                break;
+            }
+            threshold -= area;
+        }
+
+        // Uniform distribution inside the triangle.
+        let [a, b, c] = self.triangles[selected?]
+            .0
+            .map(|index| self.vertices[index as usize]);
+        Some(random_point_in_triangle(a, b, c, rng))
+    }
+
+    /// Returns the index of the connected component (see [`Self::connected_components`]), that the
+    /// triangle with the given index belongs to, or `None` if the index is out of bounds. Unlike
+    /// [`Self::connected_components`], the components are calculated once and cached until the
+    /// topology of the navmesh changes, so the method is cheap enough to be called every frame.
+    pub fn triangle_component(&self, index: usize) -> Option<usize> {
+        self.islands().get(index).map(|island| *island as usize)
+    }
+
+    fn islands(&self) -> &[u32] {
+        self.islands.get_or_init(|| {
+            let mut islands = vec![0; self.triangles.len()];
+            for (island, component) in self.connected_components().into_iter().enumerate() {
+                for triangle in component {
+                    islands[triangle] = island as u32;
+                }
+            }
+            islands
+        })
+    }
+
+    /// Checks whether there's a path between the given points without building the actual path.
+    /// The points are attached to the closest triangles of the navmesh, and the check is done using
+    /// the connected components (see [`Self::connected_components`]) of the navmesh and the
+    /// off-mesh links between them. It could be used to cheaply reject unreachable targets before
+    /// doing the actual path finding.
+    ///
+    /// Keep in mind, that runtime obstacles are ignored by this method, so the path could still be
+    /// blocked by an obstacle.
+    ///
+    /// ## Complexity
+    ///
+    /// The components are calculated once on the first call (and after every modification of the
+    /// navmesh), the rest of the calls are `O(1)` (if there's no off-mesh links between the
+    /// components) in the average case.
+    pub fn can_reach(&self, from: Vector3<f32>, to: Vector3<f32>) -> bool {
+        let (Some((_, from_triangle)), Some((_, to_triangle))) =
+            (self.query_closest(from), self.query_closest(to))
+        else {
+            return false;
+        };
+
+        self.are_triangles_connected(from_triangle, to_triangle)
+    }
+
+    // Checks whether the islands of the given triangles are the same or connected by off-mesh
+    // links.
+    fn are_triangles_connected(&self, from_triangle: usize, to_triangle: usize) -> bool {
+        let islands = self.islands();
+        let from_island = islands[from_triangle];
+        let to_island = islands[to_triangle];
+        if from_island == to_island {
+            return true;
+        }
+
+        // Off-mesh links are the only way to get from one island to another.
+        let mut visited = FxHashSet::default();
+        let mut stack = vec![from_island];
+        visited.insert(from_island);
+        while let Some(island) = stack.pop() {
+            for &(src, dest) in self.offmesh_link_traversals.keys() {
+                if islands[src] != island {
+                    continue;
+                }
+                let dest_island = islands[dest];
+                if dest_island == to_island {
+                    return true;
+                }
+                if visited.insert(dest_island) {
+                    stack.push(dest_island);
+                }
+            }
+        }
+
+        false
+    }
+
+    /// Adds a new runtime obstacle to the navmesh and returns its handle. Path finding treats the
+    /// triangles, that overlap an enabled obstacle, as impassable. Obstacles do not change the
+    /// geometry of the navmesh, so the overlapped triangles are blocked entirely. It could be used
+    /// to block some areas of a level at runtime (closed gates, collapsed buildings, etc.) without
+    /// rebaking the navmesh. Obstacles are not serialized.
+    ///
+    /// Navmesh agents recalculate their paths automatically, if their paths go through the
+    /// triangles, that become blocked. See [`NavmeshAgent::invalidate_path`] for more info.
+    pub fn add_obstacle(&mut self, shape: NavmeshObstacle) -> ObstacleHandle {
+        let handle = self.obstacles.spawn(Obstacle {
+            shape,
+            enabled: true,
+        });
+        self.update_blocked_triangles(&shape, true);
+        handle
+    }
+
+    /// Removes a runtime obstacle with the given handle and returns its shape, if the handle was
+    /// valid.
+    pub fn remove_obstacle(&mut self, handle: ObstacleHandle) -> Option<NavmeshObstacle> {
+        let obstacle = self.obstacles.try_free(handle)?;
+        if obstacle.enabled {
+            self.update_blocked_triangles(&obstacle.shape, false);
+        }
+        Some(obstacle.shape)
+    }
+
+    /// Enables or disables a runtime obstacle. Disabled obstacles do not block triangles.
+    pub fn set_obstacle_enabled(&mut self, handle: ObstacleHandle, enabled: bool) {
+        let Some(obstacle) = self.obstacles.try_borrow_mut(handle) else {
+            return;
+        };
+        if obstacle.enabled != enabled {
+            obstacle.enabled = enabled;
+            let shape = obstacle.shape;
+            self.update_blocked_triangles(&shape, enabled);
+        }
+    }
+
+    /// Returns a reference to a runtime obstacle with the given handle.
+    pub fn obstacle(&self, handle: ObstacleHandle) -> Option<&Obstacle> {
+        self.obstacles.try_borrow(handle)
+    }
+
+    /// Returns an iterator over all runtime obstacles of the navmesh.
+    pub fn obstacles(&self) -> impl Iterator<Item = (ObstacleHandle, &Obstacle)> {
+        self.obstacles.pair_iter()
+    }
+
+    /// Returns `true` if the triangle with the given index is overlapped by at least one enabled
+    /// obstacle.
+    pub fn is_triangle_blocked(&self, index: usize) -> bool {
+        self.blocked_triangles
+            .get(index)
+            .is_some_and(|count| *count > 0)
+    }
+
+    fn update_blocked_triangles(&mut self, shape: &NavmeshObstacle, block: bool) {
+        // The bounds are in the space of the grid already.
+        let (min, max) = shape.bounds(self);
+        let mut overlapped = Vec::new();
+        self.grid().visit_rect(min, max, |triangles| {
+            for &index in triangles {
+                let triangle = &self.triangles[index as usize];
+                if shape.overlaps_triangle(
+                    self,
+                    self.vertices[triangle[0] as usize],
+                    self.vertices[triangle[1] as usize],
+                    self.vertices[triangle[2] as usize],
+                ) {
+                    overlapped.push(index as usize);
+                }
+            }
+        });
+        // A triangle could be stored in multiple cells.
+        overlapped.sort_unstable();
+        overlapped.dedup();
+
+        if !overlapped.is_empty() {
+            self.bump_revision();
+        }
+        for index in overlapped {
+            let count = &mut self.blocked_triangles[index];
+            if block {
+                *count += 1;
+            } else {
+                *count = count.saturating_sub(1);
+            }
+        }
+    }
+
+    fn rebuild_obstacles(&mut self) {
+        self.blocked_triangles = vec![0; self.triangles.len()];
+        let shapes = self
+            .obstacles
+            .iter()
+            .filter(|obstacle| obstacle.enabled)
+            .map(|obstacle| obstacle.shape)
+            .collect::<Vec<_>>();
+        for shape in shapes {
+            self.update_blocked_triangles(&shape, true);
+        }
+    }
+
+    // Returns the cheapest off-mesh link traversal between two triangles. The first added link
+    // wins among the links with the same cost.
+    fn offmesh_link_between(
+        &self,
+        src_triangle: usize,
+        dest_triangle: usize,
+    ) -> Option<&OffMeshLinkTraversal> {
+        self.offmesh_link_traversals
+            .get(&(src_triangle, dest_triangle))?
+            .iter()
+            .min_by(|a, b| {
+                self.offmesh_links[a.link]
+                    .cost
+                    .total_cmp(&self.offmesh_links[b.link].cost)
+            })
+    }
+
+    fn border_edges(&self) -> &BorderEdges {
+        self.border_edges.get_or_init(|| {
+            let edges = self
+                .edges()
+                .filter(|edge| edge.is_border())
+                .map(|edge| edge.vertices)
+                .collect::<Vec<_>>();
+            // Every edge is stored in the grid as a degenerate triangle.
+            let segments = edges
+                .iter()
+                .map(|[a, b]| TriangleDefinition([*a, *b, *b]))
+                .collect::<Vec<_>>();
+            let vertices = self
+                .vertices
+                .iter()
+                .map(|vertex| self.to_up_space(*vertex))
+                .collect::<Vec<_>>();
+            BorderEdges {
+                grid: TriangleGrid::new(&segments, &vertices),
+                edges,
+            }
+        })
+    }
+
+    fn is_border_vertex(&self, index: usize) -> bool {
+        self.border_vertices
+            .get_or_init(|| {
+                let mut border_vertices = vec![false; self.vertices.len()];
+                for edge in self.edges().filter(|edge| edge.is_border()) {
+                    for vertex in edge.vertices {
+                        border_vertices[vertex as usize] = true;
+                    }
+                }
+                border_vertices
+            })
+            .get(index)
+            .cloned()
+            .unwrap_or_default()
+    }
+
+    /// Builds a path of triangle indices from `from` triangle to `to` triangle. The path is
+    /// reversed, the first index is the destination triangle. Portals between the border vertices,
+    /// that are narrower than `2 * clearance`, are considered impassable. Returns the kind of the
+    /// path and its cost, as it was calculated by the search.
+    fn build_triangle_path(
+        &self,
+        from: usize,
+        to: usize,
+        path: &mut Vec<usize>,
+        clearance: f32,
+        filter: &NavmeshQueryFilter,
+        expanded_nodes: &mut usize,
+    ) -> Result<(PathKind, f32), PathError> {
+        let key = PathCacheKey::new(from, to, clearance, filter);
+        if let Some(cached) = self.path_cache.get(&key, path) {
+            return Ok(cached);
+        }
+
+        let (kind, cost) = match self.polygon_mesh() {
+            Some(polygons) if polygons.is_usable(clearance) => polygons.build_triangle_path(
+                self,
+                from,
+                to,
+                path,
+                clearance,
+                filter,
+                expanded_nodes,
+            )?,
+            _ => {
+                let mut stats = PathfindStats::default();
+                let result = self.graph.build_indexed_path_with_cost_and_stats(
+                    from,
+                    to,
+                    path,
+                    self.max_search_nodes,
+                    |src, dest, cost| self.traversal_cost(src, dest, cost, clearance, filter),
+                    &mut stats,
+                );
+                *expanded_nodes += stats.expanded_vertices;
+                (result?, stats.path_cost)
+            }
+        };
+        self.path_cache.insert(key, path, kind, cost);
+        Ok((kind, cost))
+    }
+
+    /// Sets the maximum number of triangle paths, that could be stored in the path cache of the
+    /// navmesh. The cache stores the results of path searches between pairs of triangles, so
+    /// repeated searches (for example, when multiple agents move to the same rally point) do not
+    /// run A* again. When the cache is full, the least recently used path is evicted. The cache is
+    /// cleared automatically when the navmesh, its costs, flags, off-mesh links or obstacles are
+    /// changed. Zero capacity (default) disables the cache.
+    pub fn set_path_cache_capacity(&mut self, capacity: usize) {
+        self.path_cache.set_capacity(capacity);
+    }
+
+    /// Returns the maximum number of paths in the path cache. See
+    /// [`Self::set_path_cache_capacity`] for more info.
+    pub fn path_cache_capacity(&self) -> usize {
+        self.path_cache.capacity()
+    }
+
+    /// Returns the number of paths, that are currently stored in the path cache. See
+    /// [`Self::set_path_cache_capacity`] for more info.
+    pub fn path_cache_len(&self) -> usize {
+        self.path_cache.len()
+    }
+
+    /// Removes every path from the path cache. See [`Self::set_path_cache_capacity`] for more
+    /// info.
+    pub fn clear_path_cache(&self) {
+        self.path_cache.clear();
+    }
+
+    /// Sets the maximum number of triangles (or polygons, see [`Self::build_convex_polys`]), that
+    /// could be expanded by a single path search. When the limit is hit, the search gives up and
+    /// fails with [`NavmeshPathError::SearchExhausted`]. Agents, that allow partial paths (see
+    /// [`NavmeshAgent::set_allow_partial_path`]), walk along the best partial path found so far
+    /// instead. It is a safety valve for huge navmeshes, where a single search to an unreachable
+    /// (but connected) area could stall the whole frame. `None` (default) means no limit.
+    pub fn set_max_search_nodes(&mut self, max_search_nodes: Option<usize>) {
+        self.max_search_nodes = max_search_nodes;
+        // Cached paths may be longer than the new limit allows.
+        self.path_cache.clear();
+    }
+
+    /// Returns the maximum number of triangles, that could be expanded by a single path search.
+    /// See [`Self::set_max_search_nodes`] for more info.
+    pub fn max_search_nodes(&self) -> Option<usize> {
+        self.max_search_nodes
+    }
+
+    /// Merges adjacent coplanar triangles into convex polygons with at most `max_verts_per_poly`
+    /// vertices and makes the path search run over the polygons instead of the triangles. The
+    /// triangles are merged only if they have the same traversal cost and flags (see
+    /// [`Self::set_triangle_cost`] and [`Self::set_triangle_flags`]) and are not separated by a
+    /// one-way edge (see [`Self::set_edge_direction`]). Off-mesh links connect the polygons, that
+    /// contain the triangles at the ends of the links.
+    ///
+    /// Open areas are covered by a few large polygons, so the search explores much less nodes,
+    /// than it does with the triangles. The paths are still returned as corridors of triangles
+    /// (the triangles of a convex polygon are crossed by a straight line in a unique order), so
+    /// path smoothing produces the same result as it would do with the polygons. However, the
+    /// cost of a path is measured between the centers of the polygons, so the found path could
+    /// differ from the one found over the triangles.
+    ///
+    /// The triangles are kept intact, so rendering and point location queries work as usual. The
+    /// polygons are rebuilt immediately when the navmesh, its costs, flags, layers, one-way edges
+    /// or off-mesh links are changed, so it is better to make such changes in batches. Runtime
+    /// obstacles (see [`Self::add_obstacle`]) do not rebuild the polygons, the search crosses the
+    /// polygons with blocked triangles over their triangles instead. The search falls back to the
+    /// triangles when the clearance of the search is larger than the narrowest passage inside some
+    /// polygon. Zero disables the polygons, values less than 3 are clamped to 3.
+    pub fn build_convex_polys(&mut self, max_verts_per_poly: usize) {
+        self.max_polygon_vertices = if max_verts_per_poly == 0 {
+            0
+        } else {
+            max_verts_per_poly.max(3)
+        };
+        self.bump_revision();
+        self.rebuild_polygons();
+    }
+
+    /// Returns the maximum number of vertices of a convex polygon or zero, if the path search runs
+    /// over the triangles. See [`Self::build_convex_polys`] for more info.
+    pub fn max_polygon_vertices(&self) -> usize {
+        self.max_polygon_vertices
+    }
+
+    /// Returns the convex polygons of the navmesh or an empty slice, if the polygons were not
+    /// built. See [`Self::build_convex_polys`] for more info.
+    pub fn polygons(&self) -> &[NavmeshPolygon] {
+        self.polygon_mesh()
+            .map(|polygons| polygons.polygons.as_slice())
+            .unwrap_or_default()
+    }
+
+    /// Returns the index of a convex polygon, that contains the triangle with the given index.
+    /// Returns `None` if the index is out of bounds or the polygons were not built. See
+    /// [`Self::build_convex_polys`] for more info.
+    pub fn triangle_polygon(&self, triangle: usize) -> Option<usize> {
+        self.polygon_mesh()?
+            .triangle_polygons
+            .get(triangle)
+            .map(|polygon| *polygon as usize)
+    }
+
+    fn polygon_mesh(&self) -> Option<&PolygonMesh> {
+        self.polygons.as_ref()
+    }
+
+    // Merges the triangles into convex polygons again. It must be called after every change, that
+    // affects merging of the triangles or the connections between them: geometry, costs, flags,
+    // layers, one-way edges and off-mesh links. Runtime obstacles are handled by the search.
+    fn rebuild_polygons(&mut self) {
+        self.polygons = (self.max_polygon_vertices > 0)
+            .then(|| PolygonMesh::new(self, self.max_polygon_vertices));
+    }
+
+    /// Returns the revision of the navmesh, that is incremented every time the navmesh changes in
+    /// a way, that could affect path finding: its geometry, off-mesh links, traversal costs,
+    /// flags or runtime obstacles. It could be used to find out whether the paths, that were
+    /// calculated earlier, are outdated. Navmesh agents use it to replan their paths
+    /// automatically.
+    pub fn revision(&self) -> u64 {
+        self.revision
+    }
+
+    /// Returns the revision of the topology of the navmesh, that is incremented every time the
+    /// triangles of the navmesh could get new indices (for example, when the navmesh is rebuilt,
+    /// modified or a tile is replaced). Triangle indices, that were obtained before the change
+    /// (for example, a corridor, see [`NavmeshAgent::corridor`]), are not valid anymore. Navmesh
+    /// agents use it to replan their paths unconditionally.
+    pub fn topology_revision(&self) -> u64 {
+        self.topology_revision
+    }
+
+    fn bump_revision(&mut self) {
+        self.revision = self.revision.wrapping_add(1);
+        self.path_cache.clear();
+    }
+
+    // Checks whether the triangle is not blocked by obstacles and passes the filter.
+    fn is_triangle_passable(&self, index: usize, filter: &NavmeshQueryFilter) -> bool {
+        !self.is_triangle_blocked(index)
+            && filter.passes(self.triangle_flags[index])
+            && filter.passes_layer(self.triangle_layers[index])
+    }
+
+    // Returns the cost of moving from one triangle to another, or `None` if the move is not
+    // possible. `cost` is the default cost of the edge of the graph.
+    fn traversal_cost(
+        &self,
+        src: usize,
+        dest: usize,
+        cost: f32,
+        clearance: f32,
+        filter: &NavmeshQueryFilter,
+    ) -> Option<f32> {
+        if !self.is_triangle_passable(dest, filter) {
+            return None;
+        }
+        let cost = cost * filter.cost_multiplier(self.triangle_flags[dest]);
+        match self.offmesh_link_between(src, dest) {
+            Some(traversal) => Some(cost * self.offmesh_links[traversal.link].cost),
+            None => {
+                if self.one_way_edges.contains(&[dest as u32, src as u32]) {
+                    return None;
+                }
+                if clearance > 0.0 {
+                    if let Some(portal) = self.portal_between(src, dest) {
+                        if self.is_border_vertex(portal.left)
+                            && self.is_border_vertex(portal.right)
+                            && self.vertices[portal.left]
+                                .metric_distance(&self.vertices[portal.right])
+                                < 2.0 * clearance
+                        {
+                            return None;
+                        }
+                    }
+                }
+                Some(cost)
+            }
+        }
+    }
+
+    /// Returns indices of every triangle, that could be reached from the given point with total
+    /// path cost, that does not exceed `max_cost`. It could be used to find movement range of a
+    /// unit or the area of effect of some ability. The starting triangle is the one closest to the
+    /// origin, it is always included. The triangles are sorted by their path cost.
+    ///
+    /// The path cost is the same as the one used by [`Self::build_path`]: it is the sum of the
+    /// distances between the centers of the triangles along the path, each multiplied by
+    /// the traversal cost of the triangle (see [`Self::set_triangle_cost`]). Off-mesh links are
+    /// respected, and the triangles blocked by obstacles are excluded.
+    pub fn reachable_within(&self, origin: Vector3<f32>, max_cost: f32) -> Vec<usize> {
+        let Some((_, origin_triangle)) = self.query_closest(origin) else {
+            return Vec::new();
+        };
+        self.graph
+            .reachable_within_with_cost(origin_triangle, max_cost, |src, dest, cost| {
+                self.traversal_cost(src, dest, cost, 0.0, &Default::default())
+            })
+            .into_iter()
+            .map(|(triangle, _)| triangle)
+            .collect()
+    }
+
+    /// Builds a flow field toward the given goal. The flow field stores a direction toward the goal
+    /// for every triangle of the navmesh, so any number of units could move to the same goal
+    /// without building their own paths. The field is built using a single Dijkstra search from
+    /// the triangle closest to the goal, it has `O(n log n)` complexity, where `n` is the number of
+    /// triangles. See [`flow_field::NavmeshFlowField`] docs for more info.
+    pub fn build_flow_field(&self, goal: Vector3<f32>) -> flow_field::NavmeshFlowField {
+        flow_field::NavmeshFlowField::new(self, goal)
+    }
+
+    /// Sets a new traversal cost multiplier of a triangle at the given index. Path finding
+    /// multiplies the cost of moving into the triangle by this value, so the triangles with high
+    /// cost will be avoided, if there's a cheaper path around them. It could be used to make some
+    /// areas (water, mud, etc.) traversable, but less preferable. Default cost is `1.0`. Costs are
+    /// preserved on serialization.
+    pub fn set_triangle_cost(&mut self, index: usize, cost: f32) {
+        if let Some(triangle_cost) = self.triangle_costs.get_mut(index) {
+            *triangle_cost = cost;
+            if let Some(vertex) = self.graph.vertex_mut(index) {
+                vertex.g_penalty = cost;
+            }
+            self.bump_revision();
+            self.rebuild_polygons();
+        }
+    }
+
+    /// Returns traversal cost multiplier of a triangle at the given index. See
+    /// [`Self::set_triangle_cost`] for more info.
+    pub fn triangle_cost(&self, index: usize) -> Option<f32> {
+        self.triangle_costs.get(index).cloned()
+    }
+
+    /// Sets user-defined flags of a triangle at the given index. The flags could be used to define
+    /// area types (road, grass, hazard, etc.) and to restrict path finding to some of them using
+    /// [`NavmeshQueryFilter`]. Default flags are `0`. Flags are preserved on serialization.
+    pub fn set_triangle_flags(&mut self, index: usize, flags: u32) {
+        if let Some(triangle_flags) = self.triangle_flags.get_mut(index) {
+            *triangle_flags = flags;
+            self.bump_revision();
+            self.rebuild_polygons();
+        }
+    }
+
+    // Sets the flags and the costs of many triangles at once. Unlike the per-triangle setters, the
+    // revision is bumped and the polygons are rebuilt only once. Returns `true` if anything has
+    // changed.
+    pub(crate) fn set_triangle_areas(
+        &mut self,
+        areas: impl IntoIterator<Item = (usize, u32, f32)>,
+    ) -> bool {
+        let mut changed = false;
+        for (index, flags, cost) in areas {
+            let (Some(triangle_flags), Some(triangle_cost)) = (
+                self.triangle_flags.get_mut(index),
+                self.triangle_costs.get_mut(index),
+            ) else {
+                continue;
+            };
+            changed |= std::mem::replace(triangle_flags, flags) != flags;
+            if std::mem::replace(triangle_cost, cost) != cost {
+                if let Some(vertex) = self.graph.vertex_mut(index) {
+                    vertex.g_penalty = cost;
+                }
+                changed = true;
+            }
+        }
+        if changed {
+            self.bump_revision();
+            self.rebuild_polygons();
+        }
+        changed
+    }
+
+    /// Returns user-defined flags of a triangle at the given index. See
+    /// [`Self::set_triangle_flags`] for more info.
+    pub fn triangle_flags(&self, index: usize) -> Option<u32> {
+        self.triangle_flags.get(index).cloned()
+    }
+
+    /// Sets the layer of a triangle at the given index. Layers could be used to separate the
+    /// surfaces, that overlap vertically (for example, the floors of a multi-story building, or
+    /// the ground and the "air" surface for flying units), so an agent walks only on the surfaces
+    /// of its layers (see [`NavmeshQueryFilter::layer_mask`]) and never snaps to a floor above or
+    /// below it. Layer must be in `0..32` range, other values are ignored. Default layer is `0`.
+    /// Layers are preserved on serialization.
+    pub fn set_triangle_layer(&mut self, index: usize, layer: u32) {
+        if layer >= u32::BITS {
+            return;
+        }
+        if let Some(triangle_layer) = self.triangle_layers.get_mut(index) {
+            *triangle_layer = layer;
+            self.bump_revision();
+            self.rebuild_polygons();
+        }
+    }
+
+    /// Returns the layer of a triangle at the given index. See [`Self::set_triangle_layer`] for
+    /// more info.
+    pub fn triangle_layer(&self, index: usize) -> Option<u32> {
+        self.triangle_layers.get(index).cloned()
+    }
+
+    /// Sets the flags (see [`Self::set_triangle_flags`]) of the triangles, which centroids are
+    /// inside the given regions. It is the glue between human-readable regions, that are placed by
+    /// designers ("lava", "road", etc.), and the flags, that are used by [`NavmeshQueryFilter`].
+    /// The regions must be sorted by their priority, the first region has the highest priority: a
+    /// triangle inside several regions gets the flags of the first one.
+    ///
+    /// The regions own the flag bits, that are used by at least one of them, the rest of the bits
+    /// of the triangles stay untouched. The triangles outside of all the regions get the owned bits
+    /// cleared, so the method could be called again after the regions were moved, and the result
+    /// is the same as if it was called only with the moved regions. Returns the number of
+    /// triangles inside at least one region.
+    pub fn apply_area_mapping(&mut self, regions: &[(AxisAlignedBoundingBox, u32)]) -> usize {
+        let flag_mask = regions.iter().fold(0, |mask, (_, flags)| mask | flags);
+        let (changes, count) = self.stamp_areas(flag_mask, None, |centroid| {
+            regions
+                .iter()
+                .find(|(aabb, _)| aabb.is_contains_point(centroid))
+                .map(|(_, flags)| (*flags, None))
+        });
+        self.set_triangle_areas(changes);
+        count
+    }
+
+    // Calculates the flags and the costs, that the areas stamp onto the triangles. `area` is
+    // called with the centroid of every triangle and returns the flags and the (optional) cost of
+    // the area, that contains the centroid, or `None` if the centroid is outside of all the
+    // areas. Only the bits of `flag_mask` are replaced, the triangles outside of the areas get
+    // these bits cleared and `outside_cost`, if any. Returns the triangles, that must be changed
+    // (see `Self::set_triangle_areas`), and the number of triangles inside the areas. It is used
+    // by both `Self::apply_area_mapping` and the regions of `NavigationalMesh`.
+    pub(crate) fn stamp_areas(
+        &self,
+        flag_mask: u32,
+        outside_cost: Option<f32>,
+        area: impl Fn(Vector3<f32>) -> Option<(u32, Option<f32>)>,
+    ) -> (Vec<(usize, u32, f32)>, usize) {
+        let mut changes = Vec::new();
+        let mut inside = 0;
+        for (index, TriangleDefinition([a, b, c])) in self.triangles.iter().enumerate() {
+            let centroid = (self.vertices[*a as usize]
+                + self.vertices[*b as usize]
+                + self.vertices[*c as usize])
+                .scale(1.0 / 3.0);
+            let (area_flags, area_cost) = match area(centroid) {
+                Some(stamp) => {
+                    inside += 1;
+                    stamp
+                }
+                None => (0, outside_cost),
+            };
+            let old_flags = self.triangle_flags[index];
+            let old_cost = self.triangle_costs[index];
+            let flags = (old_flags & !flag_mask) | (area_flags & flag_mask);
+            let cost = area_cost.unwrap_or(old_cost);
+            if flags != old_flags || cost != old_cost {
+                changes.push((index, flags, cost));
+            }
+        }
+        (changes, inside)
+    }
+
+    /// Sets a direction in which the shared edge between the two given triangles could be
+    /// traversed. It could be used for conveyor belts, slides, drop-down ledges and so on. Path
+    /// finding does not go through one-way edges in the forbidden direction, so the paths will go
+    /// around them, if possible. [`EdgeDirection::Forward`] allows to move only from `triangle_a`
+    /// to `triangle_b`, [`EdgeDirection::Backward`] - only from `triangle_b` to `triangle_a`.
+    /// Returns `false` if the triangles do not share an edge, the navmesh is not modified in this
+    /// case.
+    ///
+    /// Off-mesh links between the same triangles are not affected. Edge directions are preserved
+    /// on serialization, but they are discarded when the triangles stop sharing an edge after a
+    /// modification of the navmesh.
+    pub fn set_edge_direction(
+        &mut self,
+        triangle_a: usize,
+        triangle_b: usize,
+        direction: EdgeDirection,
+    ) -> bool {
+        if triangle_a == triangle_b || self.portal_between(triangle_a, triangle_b).is_none() {
+            return false;
+        }
+        let (a, b) = (triangle_a as u32, triangle_b as u32);
+        self.one_way_edges.remove(&[a, b]);
+        self.one_way_edges.remove(&[b, a]);
+        match direction {
+            EdgeDirection::Bidirectional => (),
+            EdgeDirection::Forward => {
+                self.one_way_edges.insert([a, b]);
+            }
+            EdgeDirection::Backward => {
+                self.one_way_edges.insert([b, a]);
+            }
+        }
+        self.bump_revision();
+        self.rebuild_polygons();
+        true
+    }
+
+    /// Returns a direction in which the shared edge between the two given triangles could be
+    /// traversed. The triangles, that do not share an edge, are reported as bidirectional. See
+    /// [`Self::set_edge_direction`] for more info.
+    pub fn edge_direction(&self, triangle_a: usize, triangle_b: usize) -> EdgeDirection {
+        let (a, b) = (triangle_a as u32, triangle_b as u32);
+        if self.one_way_edges.contains(&[a, b]) {
+            EdgeDirection::Forward
+        } else if self.one_way_edges.contains(&[b, a]) {
+            EdgeDirection::Backward
+        } else {
+            EdgeDirection::Bidirectional
+        }
+    }
+
+    /// Creates new navigation mesh (navmesh) from given mesh. It is most simple way to create complex
+    /// navigation mesh, it should be used in pair with model loading functionality - you can
+    /// load model from file and turn it into navigation mesh, or even build navigation mesh
+    /// from a model in existing scene. This method "eats" any kind of meshes with any amount
+    /// of surfaces - it joins all surfaces into single mesh and creates navmesh from it.
+    ///
+    /// Example:
+    /// ```
+    /// # use fyrox_impl::scene::Scene;
+    /// # use fyrox_impl::utils::navmesh::Navmesh;
+    /// # use fyrox_graph::SceneGraph;
+    /// #
+    /// fn make_navmesh(scene: &Scene, navmesh_name: &str) -> Navmesh {
+    ///     // Find mesh node in existing scene and create navigation mesh from it.
+    ///     let navmesh_node_handle = scene.graph.find_by_name_from_root(navmesh_name).unwrap().0;
+    ///     Navmesh::from_mesh(scene.graph[navmesh_node_handle].as_mesh())
+    /// }
+    /// ```
+    pub fn from_mesh(mesh: &Mesh) -> Self {
+        // Join surfaces into one simple mesh.
+        let mut builder = RawMeshBuilder::<RawVertex>::default();
+        let global_transform = mesh.global_transform();
+        for surface in mesh.surfaces() {
+            let shared_data = surface.data();
+            let shared_data = shared_data.data_ref();
+
+            let vertex_buffer = &shared_data.vertex_buffer;
+            for triangle in shared_data.geometry_buffer.iter() {
+                builder.insert(RawVertex::from(
+                    global_transform
+                        .transform_point(&Point3::from(
+                            vertex_buffer
+                                .get(triangle[0] as usize)
+                                .unwrap()
+                                .read_3_f32(VertexAttributeUsage::Position)
+                                .unwrap(),
+                        ))
+                        .coords,
+                ));
+                builder.insert(RawVertex::from(
+                    global_transform
+                        .transform_point(&Point3::from(
+                            vertex_buffer
+                                .get(triangle[1] as usize)
+                                .unwrap()
+                                .read_3_f32(VertexAttributeUsage::Position)
+                                .unwrap(),
+                        ))
+                        .coords,
+                ));
+                builder.insert(RawVertex::from(
+                    global_transform
+                        .transform_point(&Point3::from(
+                            vertex_buffer
+                                .get(triangle[2] as usize)
+                                .unwrap()
+                                .read_3_f32(VertexAttributeUsage::Position)
+                                .unwrap(),
+                        ))
+                        .coords,
+                ));
+            }
+        }
+
+        let mesh = builder.build();
+
+        Navmesh::new(
+            mesh.triangles,
+            mesh.vertices
+                .into_iter()
+                .map(|v| Vector3::new(v.x, v.y, v.z))
+                .collect::<Vec<_>>(),
+        )
+    }
+
+    /// Bakes a navmesh from the world-space geometry of the given mesh nodes. The baker does not
+    /// voxelize the geometry, instead it filters the triangles of the meshes:
+    ///
+    /// - every triangle, which slope is steeper than [`NavmeshBakeParams::max_slope`] or which
+    /// faces away from [`NavmeshBakeParams::up_axis`] is considered as an obstacle;
+    /// - every walkable triangle, that has an obstacle above it closer than
+    /// [`NavmeshBakeParams::agent_height`] is removed;
+    /// - every walkable triangle, which center is closer than [`NavmeshBakeParams::agent_radius`]
+    /// to an obstacle is removed.
+    ///
+    /// Then the vertices of the remaining triangles are welded using
+    /// [`NavmeshBakeParams::cell_size`], so the triangles become connected. Handles, that do not
+    /// point to mesh nodes, are ignored.
+    ///
+    /// Example:
+    /// ```
+    /// # use fyrox_impl::scene::Scene;
+    /// # use fyrox_impl::utils::navmesh::{Navmesh, NavmeshBakeParams};
+    /// # use fyrox_graph::SceneGraph;
+    /// #
+    /// fn bake_navmesh(scene: &Scene, level_name: &str) -> Navmesh {
+    ///     let level = scene.graph.find_by_name_from_root(level_name).unwrap().0;
+    ///     let meshes = scene
+    ///         .graph
+    ///         .traverse_handle_iter(level)
+    ///         .filter(|handle| scene.graph[*handle].is_mesh())
+    ///         .collect::<Vec<_>>();
+    ///     Navmesh::bake_from_graph(&scene.graph, &meshes, NavmeshBakeParams::default())
+    /// }
+    /// ```
+    pub fn bake_from_graph(
+        graph: &scene::graph::Graph,
+        nodes: &[Handle<Node>],
+        params: NavmeshBakeParams,
+    ) -> Self {
+        let triangles = collect_world_triangles(graph, nodes);
+
+        // Split the triangles into walkable surfaces and obstacles.
+        let up = params
+            .up_axis
+            .try_normalize(f32::EPSILON)
+            .unwrap_or_else(Vector3::y);
+        let min_slope_cos = params.max_slope.cos();
+        let mut walkable = Vec::new();
+        let mut obstacles = Vec::new();
+        for (index, [a, b, c]) in triangles.iter().cloned().enumerate() {
+            let Some(normal) = (b - a).cross(&(c - a)).try_normalize(f32::EPSILON) else {
+                // Skip degenerate triangles.
+                continue;
+            };
+            // Triangles, that face down (ceilings, bottoms of platforms), are not walkable.
+            if normal.dot(&up) >= min_slope_cos {
+                walkable.push([a, b, c]);
+            } else {
+                obstacles.push(index);
+            }
+        }
+
+        // Remove walkable triangles that have not enough space above them or that are too close
+        // to obstacles.
+        walkable.retain(|[a, b, c]| {
+            let center = (a + b + c).scale(1.0 / 3.0);
+            let offset = up.scale(params.cell_size.max(f32::EPSILON));
+            let ray = Ray::from_two_points(center + offset, center + up.scale(params.agent_height));
+            let has_clearance = triangles
+                .iter()
+                .all(|triangle| ray.triangle_intersection(triangle).is_none());
+
+            let is_far_from_obstacles = obstacles.iter().all(|index| {
+                let [oa, ob, oc] = triangles[*index];
+                let rises_above =
+                    oa.dot(&up).max(ob.dot(&up)).max(oc.dot(&up)) > (center + offset).dot(&up);
+                !rises_above
+                    || closest_point_on_triangle(center, oa, ob, oc).metric_distance(&center)
+                        >= params.agent_radius
+            });
+
+            has_clearance && is_far_from_obstacles
+        });
+
+        // Weld vertices.
+        let cell_size = params.cell_size.max(f32::EPSILON);
+        let mut vertices = Vec::new();
+        let mut vertex_map = FxHashMap::default();
+        let mut navmesh_triangles = Vec::new();
+        for triangle in walkable {
+            let mut indices = [0; 3];
+            for (index, point) in indices.iter_mut().zip(triangle) {
+                let key = point.map(|c| (c / cell_size).round() as i32);
+                *index = *vertex_map.entry(key).or_insert_with(|| {
+                    vertices.push(point);
+                    vertices.len() as u32 - 1
+                });
+            }
+            // Welding could collapse small triangles.
+            if indices[0] != indices[1] && indices[1] != indices[2] && indices[2] != indices[0] {
+                navmesh_triangles.push(TriangleDefinition(indices));
+            }
+        }
+
+        let mut navmesh = Navmesh::new(navmesh_triangles, vertices);
+        navmesh.set_up_axis(up);
+        if params.max_step_height > 0.0 || params.max_drop_height > 0.0 {
+            // Walkable triangles are removed near the obstacles (including the risers of the
+            // steps), so the edges of the steps could be apart horizontally.
+            navmesh.connect_steps(
+                params.max_step_height,
+                params.max_drop_height,
+                params.agent_radius + cell_size,
+            );
+        }
+        navmesh
+    }
+
+    /// Creates a navmesh from the world-space geometry of a single mesh node. It is the simplest
+    /// way to create a navmesh, when the level has a dedicated mesh of the walkable floor: every
+    /// surface of the mesh is used and the geometry of the surfaces is concatenated, the
+    /// coincident vertices are merged, so the triangles of different surfaces become connected.
+    /// Unlike [`Self::bake_from_graph`], the geometry is not checked for obstacles.
+    ///
+    /// Optional `max_slope` (in radians) filters out the triangles, which angle with the plane
+    /// perpendicular to `up_axis` is larger than the given one, so walls, that were modelled as a
+    /// part of the floor, do not become walkable. The triangles, that face away from `up_axis`
+    /// (ceilings, bottoms of platforms), are filtered out as well. `up_axis` becomes the up axis of
+    /// the navmesh (see [`Self::set_up_axis`]), zero vector means Y axis. Returns an empty
+    /// navmesh, if the handle does not point to a mesh node.
+    ///
+    /// Example:
+    /// ```
+    /// # use fyrox_impl::{core::algebra::Vector3, scene::Scene, utils::navmesh::Navmesh};
+    /// # use fyrox_graph::SceneGraph;
+    /// #
+    /// fn make_navmesh(scene: &Scene, floor_name: &str) -> Navmesh {
+    ///     let floor = scene.graph.find_by_name_from_root(floor_name).unwrap().0;
+    ///     Navmesh::from_mesh_node(
+    ///         &scene.graph,
+    ///         floor,
+    ///         Vector3::y(),
+    ///         Some(45.0f32.to_radians()),
+    ///     )
+    /// }
+    /// ```
+    pub fn from_mesh_node(
+        graph: &scene::graph::Graph,
+        node: Handle<Node>,
+        up_axis: Vector3<f32>,
+        max_slope: Option<f32>,
+    ) -> Self {
+        let up = up_axis
+            .try_normalize(f32::EPSILON)
+            .unwrap_or_else(Vector3::y);
+        let mut vertices = Vec::new();
+        let mut triangles = Vec::new();
+        for [a, b, c] in collect_world_triangles(graph, &[node]) {
+            if let Some(max_slope) = max_slope {
+                let Some(normal) = (b - a).cross(&(c - a)).try_normalize(f32::EPSILON) else {
+                    continue;
+                };
+                // Triangles, that face down (ceilings, bottoms of platforms), are not walkable.
+                if normal.dot(&up) < max_slope.cos() {
+                    continue;
+                }
+            }
+            let index = vertices.len() as u32;
+            vertices.extend([a, b, c]);
+            triangles.push(TriangleDefinition([index, index + 1, index + 2]));
+        }
+        let mut navmesh = Navmesh::new_welded(triangles, vertices, 0.0);
+        navmesh.set_up_axis(up);
+        navmesh
+    }
+
+    /// Checks the navmesh for common authoring problems: invalid vertex indices, degenerate and
+    /// duplicate triangles, adjacent triangles with inconsistent winding order, non-manifold edges
+    /// and unreferenced vertices. Returns every found problem, an empty list means that the navmesh
+    /// is valid. The navmesh is not modified. It is useful to check the navmesh, that was created
+    /// manually using [`Self::new`] or [`Self::modify`], since most of the problems lead to
+    /// incorrect paths instead of explicit errors.
+    pub fn validate(&self) -> Vec<NavmeshValidationIssue> {
+        let mut issues = Vec::new();
+        let mut referenced = vec![false; self.vertices.len()];
+        let mut unique_triangles = FxHashMap::default();
+        // Triangles of each edge along with a flag, that tells whether the edge goes from the
+        // vertex with the lower index in the triangle.
+        let mut edges = FxHashMap::<[u32; 2], Vec<(usize, bool)>>::default();
+
+        for (triangle_index, triangle) in self.triangles.iter().enumerate() {
+            let mut valid = true;
+            for &index in triangle.indices() {
+                match referenced.get_mut(index as usize) {
+                    Some(referenced) => *referenced = true,
+                    None => {
+                        issues.push(NavmeshValidationIssue::InvalidIndex {
+                            triangle: triangle_index,
+                            index,
+                        });
+                        valid = false;
+                    }
+                }
+            }
+            if !valid {
+                continue;
+            }
+
+            let [a, b, c] = triangle.0.map(|index| self.vertices[index as usize]);
+            if triangle.0[0] == triangle.0[1]
+                || triangle.0[1] == triangle.0[2]
+                || triangle.0[2] == triangle.0[0]
+                || (b - a).cross(&(c - a)).norm() <= f32::EPSILON
+            {
+                issues.push(NavmeshValidationIssue::DegenerateTriangle {
+                    triangle: triangle_index,
+                });
+                continue;
+            }
+
+            let mut key = triangle.0;
+            key.sort_unstable();
+            if let Some(original) = unique_triangles.get(&key) {
+                issues.push(NavmeshValidationIssue::DuplicateTriangle {
+                    triangle: triangle_index,
+                    original: *original,
+                });
+                continue;
+            }
+            unique_triangles.insert(key, triangle_index);
+
+            for edge in triangle.edges() {
+                edges
+                    .entry([edge.a.min(edge.b), edge.a.max(edge.b)])
+                    .or_default()
+                    .push((triangle_index, edge.a < edge.b));
+            }
+        }
+
+        let mut edges = edges.into_iter().collect::<Vec<_>>();
+        edges.sort_unstable_by_key(|(vertices, _)| *vertices);
+        for (vertices, triangles) in edges {
+            match triangles.as_slice() {
+                [_, _, _, ..] => issues.push(NavmeshValidationIssue::NonManifoldEdge {
+                    vertices,
+                    triangles: triangles.iter().map(|(triangle, _)| *triangle).collect(),
+                }),
+                [(neighbor, a), (triangle, b)] if a == b => {
+                    issues.push(NavmeshValidationIssue::InconsistentWinding {
+                        triangle: *triangle,
+                        neighbor: *neighbor,
+                    })
+                }
+                _ => (),
+            }
+        }
+
+        issues.extend(
+            referenced
+                .iter()
+                .enumerate()
+                .filter(|(_, referenced)| !**referenced)
+                .map(|(vertex, _)| NavmeshValidationIssue::UnreferencedVertex { vertex }),
+        );
+
+        issues
+    }
+
+    /// Returns `true` if the navmesh has no problems, that could be found by [`Self::validate`].
+    pub fn is_valid(&self) -> bool {
+        self.validate().is_empty()
+    }
+
+    /// Makes the winding order of the triangles consistent and returns the number of flipped
+    /// triangles. Every connected component (triangles, that share edges) is flood-filled from
+    /// an arbitrary triangle, so the adjacent triangles get the same winding order. Then the whole
+    /// component is flipped, if most of its area faces down, so the normals of the triangles
+    /// point along the up axis (see [`Self::set_up_axis`]). Components, that have surfaces facing multiple directions (for example, a
+    /// sphere), are kept internally consistent. Non-manifold edges (see [`Self::validate`]) do not
+    /// connect triangles. All vertex indices must be valid.
+    pub fn fix_winding(&mut self) -> usize {
+        let mut edges = FxHashMap::<[u32; 2], Vec<usize>>::default();
+        for (triangle_index, triangle) in self.triangles.iter().enumerate() {
+            for edge in triangle.edges() {
+                edges
+                    .entry([edge.a.min(edge.b), edge.a.max(edge.b)])
+                    .or_default()
+                    .push(triangle_index);
+            }
+        }
+
+        let mut flipped = vec![false; self.triangles.len()];
+        let mut visited = vec![false; self.triangles.len()];
+        let mut component = Vec::new();
+        let mut stack = Vec::new();
+        for start in 0..self.triangles.len() {
+            if visited[start] {
+                continue;
+            }
+
+            visited[start] = true;
+            component.clear();
+            stack.push(start);
+            while let Some(triangle_index) = stack.pop() {
+                component.push(triangle_index);
+                for edge in self.triangles[triangle_index].edges() {
+                    let Some(&[first, second]) = edges
+                        .get(&[edge.a.min(edge.b), edge.a.max(edge.b)])
+                        .map(|triangles| triangles.as_slice())
+                    else {
+                        continue;
+                    };
+                    let neighbor = if first == triangle_index {
+                        second
+                    } else {
+                        first
+                    };
+                    if visited[neighbor] {
+                        continue;
+                    }
+                    visited[neighbor] = true;
+                    // Adjacent triangles with the same winding order have the shared edge going
+                    // in opposite directions.
+                    let same_direction = self.triangles[neighbor]
+                        .edges()
+                        .iter()
+                        .any(|other| other.a == edge.a && other.b == edge.b);
+                    if same_direction {
+                        let triangle = &mut self.triangles[neighbor];
+                        triangle.0.swap(1, 2);
+                        flipped[neighbor] = !flipped[neighbor];
+                    }
+                    stack.push(neighbor);
+                }
+            }
+
+            let up_area = component
+                .iter()
+                .map(|&triangle_index| {
+                    let [a, b, c] = self.triangles[triangle_index]
+                        .0
+                        .map(|index| self.vertices[index as usize]);
+                    (b - a).cross(&(c - a)).dot(&self.up_axis)
+                })
+                .sum::<f32>();
+            if up_area < 0.0 {
+                for &triangle_index in component.iter() {
+                    self.triangles[triangle_index].0.swap(1, 2);
+                    flipped[triangle_index] = !flipped[triangle_index];
+                }
+            }
+        }
+
+        let count = flipped.iter().filter(|flipped| **flipped).count();
+        if count > 0 {
+            self.rebuild();
+        }
+        count
+    }
+
+    /// Creates a navmesh from a set of convex polygons (for example, navigation polygons produced
+    /// by Recast). Every polygon is a list of vertex indices, polygons could have an arbitrary
+    /// number of vertices. The polygons are triangulated and the adjacency information is rebuilt
+    /// using shared edges.
+    ///
+    /// All polygons must have the same winding order (either clockwise or counter-clockwise,
+    /// when viewed from above), the winding order will be converted to the one used by the
+    /// navmesh automatically. Non-convex and degenerate (zero area) polygons are rejected.
+    pub fn from_polygon_soup(
+        polygons: &[Vec<u32>],
+        vertices: &[Vector3<f32>],
+    ) -> Result<Self, PolygonSoupError> {
+        let mut triangles = Vec::new();
+        let mut reference_winding = None;
+
+        for (polygon_index, polygon) in polygons.iter().enumerate() {
+            if polygon.len() < 3 {
+                return Err(PolygonSoupError::TooFewVertices {
+                    polygon: polygon_index,
+                });
+            }
+
+            let mut points = Vec::with_capacity(polygon.len());
+            for &index in polygon {
+                let point = vertices
+                    .get(index as usize)
+                    .ok_or(PolygonSoupError::InvalidIndex {
+                        polygon: polygon_index,
+                        index,
+                    })?;
+                points.push(*point);
+            }
+
+            let area = (1..points.len() - 1)
+                .map(|i| triangle_area_2d(points[0], points[i], points[i + 1]))
+                .sum::<f32>();
+            if area.abs() <= f32::EPSILON {
+                return Err(PolygonSoupError::Degenerate {
+                    polygon: polygon_index,
+                });
+            }
+
+            // The navmesh uses "positive" winding, so the soup with the opposite winding must
+            // be flipped.
+            let is_positive = area > 0.0;
+            if *reference_winding.get_or_insert(is_positive) != is_positive {
+                return Err(PolygonSoupError::InconsistentWinding {
+                    polygon: polygon_index,
+                });
+            }
+
+            let count = points.len();
+            for i in 0..count {
+                let turn = triangle_area_2d(
+                    points[(i + count - 1) % count],
+                    points[i],
+                    points[(i + 1) % count],
+                );
+                // Collinear vertices are allowed.
+                if turn.abs() > f32::EPSILON && (turn > 0.0) != is_positive {
+                    return Err(PolygonSoupError::NonConvex {
+                        polygon: polygon_index,
+                    });
+                }
+            }
+
+            for i in 1..count - 1 {
+                // Skip zero-area triangles, produced by collinear vertices.
+                if triangle_area_2d(points[0], points[i], points[i + 1]).abs() <= f32::EPSILON {
+                    continue;
+                }
+                triangles.push(if is_positive {
+                    TriangleDefinition([polygon[0], polygon[i], polygon[i + 1]])
+                } else {
+                    TriangleDefinition([polygon[0], polygon[i + 1], polygon[i]])
+                });
+            }
+        }
+
+        Ok(Navmesh::new(triangles, vertices.to_vec()))
+    }
+
+    /// Creates a navmesh from a set of polygon outlines (for example, room outlines) and holes
+    /// in them (for example, pillars), all polygons are defined on XZ plane and can be concave.
+    /// Every hole is assigned to the smallest outline that contains it. The polygons are
+    /// triangulated using ear clipping, holes are connected to their outlines by bridges, so
+    /// every outline produces a single connected component.
+    ///
+    /// Duplicate and nearly collinear vertices are removed before triangulation. Polygons are
+    /// indexed in the returned errors in the order they were passed, holes follow outlines.
+    pub fn from_polygons_with_holes(
+        outlines: &[Polygon],
+        holes: &[Polygon],
+    ) -> Result<Self, PolygonSoupError> {
+        let mut vertices = Vec::new();
+        let mut rings = Vec::with_capacity(outlines.len() + holes.len());
+
+        for (polygon_index, polygon) in outlines.iter().chain(holes).enumerate() {
+            if polygon.vertices.len() < 3 {
+                return Err(PolygonSoupError::TooFewVertices {
+                    polygon: polygon_index,
+                });
+            }
+
+            let simplified = simplify_polygon(&polygon.vertices);
+            if simplified.len() < 3 {
+                return Err(PolygonSoupError::Degenerate {
+                    polygon: polygon_index,
+                });
+            }
+
+            let mut ring = simplified
+                .into_iter()
+                .map(|i| {
+                    vertices.push(polygon.vertices[i]);
+                    (vertices.len() - 1) as u32
+                })
+                .collect::<Vec<_>>();
+
+            let area = ring_area_2d(&ring, &vertices);
+            if area.abs() <= f32::EPSILON {
+                return Err(PolygonSoupError::Degenerate {
+                    polygon: polygon_index,
+                });
+            }
+
+            // Outlines must have "positive" winding and holes - the opposite one, this way the
+            // interior of the region is always on the same side of its boundary.
+            let is_hole = polygon_index >= outlines.len();
+            if (area > 0.0) == is_hole {
+                ring.reverse();
+            }
+
+            rings.push(ring);
+        }
+
+        let (outline_rings, hole_rings) = rings.split_at(outlines.len());
+
+        let mut outline_holes = vec![Vec::new(); outline_rings.len()];
+        for (hole_index, hole) in hole_rings.iter().enumerate() {
+            let point = vertices[hole[0] as usize];
+            let outline = outline_rings
+                .iter()
+                .enumerate()
+                .filter(|(_, outline)| is_point_inside_ring_2d(point, outline, &vertices))
+                .map(|(i, outline)| (i, ring_area_2d(outline, &vertices)))
+                .min_by(|(_, a), (_, b)| a.total_cmp(b))
+                .ok_or(PolygonSoupError::OrphanHole {
+                    polygon: outlines.len() + hole_index,
+                })?
+                .0;
+            outline_holes[outline].push(hole.as_slice());
+        }
+
+        let mut triangles = Vec::new();
+        for (outline, holes) in outline_rings.iter().zip(outline_holes) {
+            let mut ring = outline.clone();
+            for (i, hole) in holes.iter().enumerate() {
+                merge_hole(&mut ring, hole, &holes[i + 1..], &vertices);
+            }
+            triangulate_ring(ring, &vertices, &mut triangles);
+        }
+
+        Ok(Navmesh::new(triangles, vertices))
+    }
+
+    /// Tries to get a projected point on the navmesh, that is closest to the given query point.
+    /// Returns a tuple with the projection point and the triangle index, that contains this
+    /// projection point.
+    ///
+    /// ## Complexity
+    ///
+    /// This method has `O(1)` complexity in the average case (when the query point lies inside or
+    /// near the navmesh bounds) and `O(n)` complexity in the worst case. `n` here is the number of
+    /// triangles in the navmesh.
+    pub fn query_closest(&self, query_point: Vector3<f32>) -> Option<(Vector3<f32>, usize)> {
+        self.query_closest_within(query_point, f32::MAX)
+    }
+
+    /// The same as [`Self::query_closest`], but only the triangles on the layers of the given
+    /// mask (see [`Self::set_triangle_layer`]) are considered. It could be used to find a point on
+    /// a specific floor, when the floors overlap vertically.
+    pub fn query_closest_in_layers(
+        &self,
+        query_point: Vector3<f32>,
+        layer_mask: u32,
+    ) -> Option<(Vector3<f32>, usize)> {
+        let filter = NavmeshQueryFilter {
+            layer_mask,
+            ..Default::default()
+        };
+        self.query_closest_within_filtered(query_point, f32::MAX, |triangle| {
+            filter.passes_layer(self.triangle_layers[triangle])
+        })
+    }
+
+    fn query_closest_within(
+        &self,
+        query_point: Vector3<f32>,
+        max_distance: f32,
+    ) -> Option<(Vector3<f32>, usize)> {
+        self.query_closest_within_filtered(query_point, max_distance, |_| true)
+    }
+
+    fn query_closest_within_filtered(
+        &self,
+        query_point: Vector3<f32>,
+        max_distance: f32,
+        filter: impl Fn(usize) -> bool,
+    ) -> Option<(Vector3<f32>, usize)> {
+        let mut closest = None;
+        let mut closest_distance = f32::MAX;
+
+        // Check the cells of the grid in rings around the query point, until the rest of the rings
+        // are guaranteed to be farther than the closest point found so far.
+        let grid = self.grid();
+        let (x, z) = grid.cell_coords(self.to_up_space(query_point));
+        for radius in grid.ring_range(x, z) {
+            let ring_distance = (radius - 1).max(0) as f32 * grid.cell_size();
+            if ring_distance > max_distance || ring_distance * ring_distance > closest_distance {
+                break;
+            }
+
+            grid.visit_ring(x, z, radius, |triangles| {
+                self.query_closest_internal(
+                    &mut closest,
+                    &mut closest_distance,
+                    triangles.iter().map(|i| *i as usize).filter(|i| filter(*i)),
+                    query_point,
+                )
+            });
+        }
+
+        closest
+    }
+
+    /// Returns the height (along the up axis, in the space of [`Self::to_up_space`]) of the surface
+    /// of a triangle at the given point, using the barycentric coordinates of the point projected
+    /// on the horizontal plane. The point must be in the same space. Returns `None` if the
+    /// projection of the point lies outside of the triangle, or the triangle is vertical.
+    fn height_on_triangle(&self, triangle: usize, point: Vector3<f32>) -> Option<f32> {
+        let [a, b, c] = self
+            .triangles
+            .get(triangle)?
+            .0
+            .map(|index| self.to_up_space(self.vertices[index as usize]));
+        let area = triangle_area_2d(a, b, c);
+        if area.abs() <= f32::EPSILON {
+            return None;
+        }
+        let u = triangle_area_2d(point, b, c) / area;
+        let v = triangle_area_2d(a, point, c) / area;
+        let w = 1.0 - u - v;
+        const TOLERANCE: f32 = 1.0e-5;
+        if u < -TOLERANCE || v < -TOLERANCE || w < -TOLERANCE {
+            return None;
+        }
+        Some(a.y + (b.y - a.y) * v + (c.y - a.y) * w)
+    }
+
+    /// Returns the axis-aligned bounding box of all the vertices of the navmesh. The box is
+    /// invalid (see [`AxisAlignedBoundingBox::is_valid`]) if the navmesh has no vertices.
+    pub fn bounding_box(&self) -> AxisAlignedBoundingBox {
+        AxisAlignedBoundingBox::from_points(&self.vertices)
+    }
+
+    /// Returns the indices of all triangles, that intersect the given axis-aligned bounding box.
+    /// The indices are sorted in ascending order. The query uses the internal acceleration
+    /// structure (see [`Self::rebuild_acceleration`]), so only the triangles near the box are
+    /// checked.
+    pub fn triangles_in_aabb(&self, aabb: &AxisAlignedBoundingBox) -> Vec<usize> {
+        let mut triangles = Vec::new();
+        if !aabb.is_valid() {
+            return triangles;
+        }
+        self.visit_grid_rect(aabb.min, aabb.max, |cell| {
+            for &index in cell {
+                let triangle = &self.triangles[index as usize];
+                if triangle_intersects_aabb(
+                    triangle.0.map(|vertex| self.vertices[vertex as usize]),
+                    aabb,
+                ) {
+                    triangles.push(index as usize);
+                }
+            }
+        });
+        // A triangle could be stored in multiple cells.
+        triangles.sort_unstable();
+        triangles.dedup();
+        triangles
+    }
+
+    /// Returns the index of a triangle, which projection on the horizontal plane (see
+    /// [`Self::set_up_axis`]) contains the given point, and which surface is within the given
+    /// vertical distance from the point. If there are several such triangles (for example, the
+    /// point is above stacked triangles of a bridge and the floor below it), the triangle that is
+    /// vertically closest to the point is returned.
+    pub fn triangle_at(&self, point: Vector3<f32>, vertical_tolerance: f32) -> Option<usize> {
+        let point = self.to_up_space(point);
+        let grid = self.grid();
+        let (x, z) = grid.cell_coords(point);
+
+        let mut closest = None;
+        let mut closest_distance = f32::MAX;
+        for &triangle_index in grid.cell(x, z) {
+            let triangle_index = triangle_index as usize;
+            let [a, b, c] = self.triangles[triangle_index]
+                .0
+                .map(|index| self.to_up_space(self.vertices[index as usize]));
+
+            if !is_point_inside_triangle_2d(point, a, b, c) {
+                continue;
+            }
+
+            // Vertical triangles cannot contain points.
+            let Some(plane) = Plane::from_triangle(&a, &b, &c) else {
+                continue;
+            };
+            if plane.normal.y.abs() <= f32::EPSILON {
+                continue;
+            }
+            let height =
+                -(plane.normal.x * point.x + plane.normal.z * point.z + plane.d) / plane.normal.y;
+            let distance = (height - point.y).abs();
+            if distance <= vertical_tolerance && distance < closest_distance {
+                closest_distance = distance;
+                closest = Some(triangle_index);
+            }
+        }
+
+        closest
+    }
+
+    /// Casts a vertical ray down from the given point and returns the point, where the ray meets
+    /// the surface of the navmesh first (the highest triangle below the point), if the surface is
+    /// not further than `max_drop` below the point. Unlike [`Self::nearest_point`], the returned
+    /// point always has the same horizontal coordinates as the given one. When there are multiple
+    /// floors below the point, the one right below it is used. It could be used to snap objects to
+    /// the floor.
+    pub fn project_down(&self, point: Vector3<f32>, max_drop: f32) -> Option<Vector3<f32>> {
+        // Allow the point to be slightly below the surface, to compensate for precision errors.
+        const TOLERANCE: f32 = 1.0e-4;
+
+        let point = self.to_up_space(point);
+        let grid = self.grid();
+        let (x, z) = grid.cell_coords(point);
+        grid.cell(x, z)
+            .iter()
+            .filter_map(|&triangle| self.height_on_triangle(triangle as usize, point))
+            .filter(|height| *height <= point.y + TOLERANCE && point.y - *height <= max_drop)
+            .max_by(|a, b| a.total_cmp(b))
+            .map(|height| self.up_space_to_world(Vector3::new(point.x, height, point.z)))
+    }
+
+    /// Projects the given point on the closest triangle of the navmesh, that is within the given
+    /// search radius. If the projection of the point lies outside of the triangle, it will be
+    /// clamped to the closest edge (or vertex) of the triangle. Returns a tuple with the projected
+    /// point and the index of the triangle, or `None` if there's no triangles within the radius.
+    ///
+    /// This method could be used to "snap" points that are slightly off the navmesh (for example,
+    /// a target point that is a few centimeters above the floor) to the navmesh.
+    pub fn nearest_point(
+        &self,
+        point: Vector3<f32>,
+        search_radius: f32,
+    ) -> Option<(Vector3<f32>, usize)> {
+        self.query_closest_within(point, search_radius)
+            .filter(|(projection, _)| projection.metric_distance(&point) <= search_radius)
+    }
+
+    /// The same as [`Self::nearest_point`], but only the triangles accepted by the given filter are
+    /// considered. The filter receives the index of a candidate triangle and must return `true`
+    /// if the triangle is acceptable. The returned point is the closest point among all the
+    /// accepted triangles, not just the first one found. It could be used to find the closest
+    /// point with some gameplay property, for example the closest "safe" point, that does not lie
+    /// in an expensive area:
+    ///
+    /// ```rust
+    /// # use fyrox_impl::{core::algebra::Vector3, utils::navmesh::Navmesh};
+    /// fn closest_safe_point(navmesh: &Navmesh, position: Vector3<f32>) -> Option<Vector3<f32>> {
+    ///     navmesh
+    ///         .nearest_point_filtered(position, 10.0, |triangle| {
+    ///             navmesh.triangle_cost(triangle) == Some(1.0)
+    ///         })
+    ///         .map(|(point, _)| point)
+    /// }
+    /// ```
+    pub fn nearest_point_filtered(
+        &self,
+        point: Vector3<f32>,
+        search_radius: f32,
+        filter: impl Fn(usize) -> bool,
+    ) -> Option<(Vector3<f32>, usize)> {
+        self.query_closest_within_filtered(point, search_radius, filter)
+            .filter(|(projection, _)| projection.metric_distance(&point) <= search_radius)
+    }
+
+    fn query_closest_internal(
+        &self,
+        closest: &mut Option<(Vector3<f32>, usize)>,
+        closest_distance: &mut f32,
+        triangles: impl Iterator<Item = usize>,
+        query_point: Vector3<f32>,
+    ) {
+        for triangle_index in triangles {
+            let triangle = &self.triangles[triangle_index];
+            let a = self.vertices[triangle[0] as usize];
+            let b = self.vertices[triangle[1] as usize];
+            let c = self.vertices[triangle[2] as usize];
+
+            let Some(plane) = Plane::from_triangle(&a, &b, &c) else {
+                continue;
+            };
+            let projection = plane.project(&query_point);
+            if math::is_point_inside_triangle(&projection, &[a, b, c]) {
+                let sqr_distance = query_point.sqr_distance(&projection);
+                if sqr_distance < *closest_distance {
+                    *closest_distance = sqr_distance;
+                    *closest = Some((projection, triangle_index));
+                }
+            }
+
+            // Check each edge by projecting the query point onto it and checking where the
+            // projection lies.
+            for edge in self.triangles[triangle_index].edges() {
+                let a = self.vertices[edge.a as usize];
+                let b = self.vertices[edge.b as usize];
+                let ray = Ray::from_two_points(a, b);
+                let t = ray.project_point(&query_point);
+                if (0.0..=1.0).contains(&t) {
+                    let edge_pt_projection = ray.get_point(t);
+                    let sqr_distance = query_point.sqr_distance(&edge_pt_projection);
+                    if sqr_distance < *closest_distance {
+                        *closest_distance = sqr_distance;
+                        *closest = Some((ray.get_point(t), triangle_index));
+                    }
+                }
+            }
+
+            // Also check vertices.
+            for pt in [a, b, c] {
+                let sqr_distance = query_point.sqr_distance(&pt);
+                if sqr_distance < *closest_distance {
+                    *closest_distance = sqr_distance;
+                    *closest = Some((pt, triangle_index));
+                }
+            }
+        }
+    }
+
+    /// Creates a temporary modification context which allows you to modify the navmesh. When the
+    /// modification context is dropped, it recalculates navigation graph automatically.
+    pub fn modify(&mut self) -> NavmeshModificationContext {
+        NavmeshModificationContext { navmesh: self }
+    }
+
+    /// Returns reference to array of triangles.
+    pub fn triangles(&self) -> &[TriangleDefinition] {
+        &self.triangles
+    }
+
+    /// Returns reference to the internal array of vertices.
+    pub fn vertices(&self) -> &[Vector3<f32>] {
+        &self.vertices
+    }
+
+    /// Returns shared reference to inner octree.
+    pub fn octree(&self) -> &Octree {
+        self.octree.get_or_init(|| self.build_octree())
+    }
+
+    /// Tries to build path using indices of begin and end points.
+    ///
+    /// Example:
+    ///
+    /// ```
+    /// use fyrox_impl::utils::navmesh::Navmesh;
+    /// use fyrox_impl::core::algebra::Vector3;
+    /// use fyrox_impl::utils::astar::{PathKind, PathError};
+    ///
+    /// fn find_path(navmesh: &Navmesh, begin: Vector3<f32>, end: Vector3<f32>, path: &mut Vec<Vector3<f32>>) -> Result<PathKind, PathError> {
+    ///     if let Some((_, begin_index)) = navmesh.query_closest(begin) {
+    ///         if let Some((_, end_index)) = navmesh.query_closest(end) {
+    ///             return navmesh.build_path(begin_index, end_index, path);
+    ///         }
+    ///     }
+    ///     Ok(PathKind::Partial)
+    /// }
+    /// ```
+    pub fn build_path(
+        &self,
+        from: usize,
+        to: usize,
+        path: &mut Vec<Vector3<f32>>,
+    ) -> Result<PathKind, PathError> {
+        self.build_path_filtered(from, to, path, &Default::default())
+    }
+
+    /// The same as [`Self::build_path`], but the path goes only through the triangles, that pass
+    /// the given filter, and the traversal costs are modified by the filter (see
+    /// [`NavmeshQueryFilter`] for more info). The starting triangle is always allowed.
+    ///
+    /// If the triangles are on different islands of the navmesh (see
+    /// [`Self::connected_components`]), that are not connected by off-mesh links, the method
+    /// returns [`PathError::Unreachable`] immediately without running the search. If one of the
+    /// indices is out of bounds, [`PathError::InvalidIndex`] is returned.
+    pub fn build_path_filtered(
+        &self,
+        from: usize,
+        to: usize,
+        path: &mut Vec<Vector3<f32>>,
+        filter: &NavmeshQueryFilter,
+    ) -> Result<PathKind, PathError> {
+        path.clear();
+
+        for index in [from, to] {
+            if index >= self.triangles.len() {
+                return Err(PathError::InvalidIndex(index));
+            }
+        }
+        if !self.are_triangles_connected(from, to) {
+            return Err(PathError::Unreachable);
+        }
+
+        let mut indices = Vec::new();
+        let (path_kind, _) =
+            self.build_triangle_path(from, to, &mut indices, 0.0, filter, &mut 0)?;
+        path.extend(indices.into_iter().map(|i| self.graph.vertices[i].position));
+
+        Ok(path_kind)
+    }
+
+    /// Builds a path between two points, using the given filter (see [`NavmeshQueryFilter`] for
+    /// more info). The points are projected on the navmesh first, then the path is built the same
+    /// way as [`NavmeshAgent::calculate_path`] does with default agent settings, except that
+    /// partial paths are disallowed: the path is smoothed and goes through off-mesh links. The
+    /// given path buffer is cleared and reused, so the points of the path are not reallocated if
+    /// the buffer has enough capacity (the search itself still uses temporary memory).
+    ///
+    /// ```rust
+    /// # use fyrox_impl::{
+    /// #     core::algebra::Vector3,
+    /// #     utils::navmesh::{Navmesh, NavmeshQueryFilter},
+    /// # };
+    /// struct Unit {
+    ///     filter: NavmeshQueryFilter,
+    ///     path: Vec<Vector3<f32>>,
+    /// }
+    ///
+    /// fn move_unit(unit: &mut Unit, navmesh: &Navmesh, from: Vector3<f32>, to: Vector3<f32>) {
+    ///     let _ = navmesh.find_path_filtered(from, to, &mut unit.path, &unit.filter);
+    /// }
+    /// ```
+    pub fn find_path_filtered(
+        &self,
+        from: Vector3<f32>,
+        to: Vector3<f32>,
+        path: &mut Vec<Vector3<f32>>,
+        filter: &NavmeshQueryFilter,
+    ) -> Result<PathKind, NavmeshPathError> {
+        let mut agent = NavmeshAgent::new();
+        agent.allow_partial_path = false;
+        agent.query_filter = *filter;
+        agent.path = std::mem::take(path);
+        let result = agent.calculate_path(self, from, to);
+        *path = std::mem::take(&mut agent.path);
+        result
+    }
+
+    /// Finds a corridor between two points - an ordered list of indices of the triangles, that the
+    /// path from one point to another goes through. The first triangle contains the projection of
+    /// the start point on the navmesh and the last one - the projection of the end point. The
+    /// search is the same, that is used by navmesh agents with zero radius and the default filter
+    /// to build their paths, so the path could be built from the corridor using
+    /// [`Self::string_pull`]. Adjacent triangles of the corridor share an edge, unless they are
+    /// connected by an off-mesh link. Returns `None` if there's no full path between the points.
+    ///
+    /// It could be used by custom movement systems, for example to constrain movement of a
+    /// physical body to the allowed triangles.
+    pub fn find_corridor(&self, from: Vector3<f32>, to: Vector3<f32>) -> Option<Vec<usize>> {
+        self.try_find_corridor(from, to, 0.0, &Default::default())
+            .ok()
+    }
+
+    /// The same as [`Self::find_corridor`], but uses the given clearance and filter and returns
+    /// the reason, why the corridor could not be found. The corridor does not go through the
+    /// portals, that are narrower than `2 * clearance` (see [`NavmeshAgent::set_radius`]).
+    pub fn try_find_corridor(
+        &self,
+        from: Vector3<f32>,
+        to: Vector3<f32>,
+        clearance: f32,
+        filter: &NavmeshQueryFilter,
+    ) -> Result<Vec<usize>, NavmeshPathError> {
+        let (_, src_triangle) = self
+            .query_closest_in_layers(from, filter.layer_mask)
+            .ok_or(NavmeshPathError::StartOffMesh)?;
+        let (_, dest_triangle) = self
+            .query_closest_in_layers(to, filter.layer_mask)
+            .ok_or(NavmeshPathError::EndOffMesh)?;
+        if src_triangle == dest_triangle {
+            return Ok(vec![src_triangle]);
+        }
+        self.check_full_path(src_triangle, dest_triangle, filter)?;
+
+        let mut corridor = Vec::new();
+        match self.build_triangle_path(
+            src_triangle,
+            dest_triangle,
+            &mut corridor,
+            clearance,
+            filter,
+            &mut 0,
+        )? {
+            (PathKind::Full, _) => {
+                corridor.reverse();
+                Ok(corridor)
+            }
+            (PathKind::Partial, _) => Err(NavmeshPathError::Disconnected),
+        }
+    }
+
+    /// Checks whether a full path between the given triangles could exist at all, without running
+    /// the path search.
+    fn check_full_path(
+        &self,
+        src_triangle: usize,
+        dest_triangle: usize,
+        filter: &NavmeshQueryFilter,
+    ) -> Result<(), NavmeshPathError> {
+        if !self.is_triangle_passable(dest_triangle, filter) {
+            return Err(NavmeshPathError::EndOffMesh);
+        }
+        if !self.are_triangles_connected(src_triangle, dest_triangle) {
+            return Err(NavmeshPathError::Disconnected);
+        }
+        Ok(())
+    }
+
+    /// Builds a path between two points the same way as [`Self::find_path_filtered`] does with the
+    /// default filter and returns the path, or the reason why the path could not be found. Unlike
+    /// [`Self::find_path_filtered`], only full paths are returned: if the end point could not be
+    /// reached, [`NavmeshPathError::Disconnected`] is returned. It could be used to give a player
+    /// meaningful feedback, when the unit could not reach the selected point.
+    ///
+    /// ```rust
+    /// # use fyrox_impl::{
+    /// #     core::algebra::Vector3,
+    /// #     utils::navmesh::{Navmesh, NavmeshPathError},
+    /// # };
+    /// fn order_move(navmesh: &Navmesh, from: Vector3<f32>, to: Vector3<f32>) {
+    ///     match navmesh.find_path(from, to) {
+    ///         Ok(path) => println!("Moving along {path:?}"),
+    ///         Err(NavmeshPathError::Disconnected | NavmeshPathError::EndOffMesh) => {
+    ///             println!("Can't reach that area!")
+    ///         }
+    ///         Err(err) => println!("{err}"),
+    ///     }
+    /// }
+    /// ```
+    pub fn find_path(
+        &self,
+        from: Vector3<f32>,
+        to: Vector3<f32>,
+    ) -> Result<Vec<Vector3<f32>>, NavmeshPathError> {
+        let mut path = Vec::new();
+        match self.find_path_filtered(from, to, &mut path, &Default::default())? {
+            PathKind::Full => Ok(path),
+            PathKind::Partial => Err(NavmeshPathError::Disconnected),
+        }
+    }
+
+    /// The same as [`Self::find_path`], but returns `None` instead of the reason, why the path
+    /// could not be found. It is a convenience wrapper for the code, that does not care about the
+    /// reason.
+    pub fn find_path_opt(&self, from: Vector3<f32>, to: Vector3<f32>) -> Option<Vec<Vector3<f32>>> {
+        self.find_path(from, to).ok()
+    }
+
+    /// The same as [`Self::find_path`], but uses the given filter and returns the total cost of the
+    /// path together with its points. The cost is the one, that is minimized by the path search:
+    /// the sum of the distances between the centers of the triangles along the path, each
+    /// multiplied by the traversal cost of the triangle (see [`Self::set_triangle_cost`]) and by
+    /// the cost multiplier of the filter (see [`NavmeshQueryFilter::set_flag_cost`]). Moves through
+    /// off-mesh links are multiplied by the cost of the link. When the search runs over convex
+    /// polygons (see [`Self::build_convex_polys`]), the distances are measured between the centers
+    /// of the polygons instead. The cost is not affected by path smoothing.
+    ///
+    /// It could be used to compare routes or to feed the cost of a route into utility functions of
+    /// AI.
+    ///
+    /// ```rust
+    /// # use fyrox_impl::{core::algebra::Vector3, utils::navmesh::Navmesh};
+    /// fn is_worth_going(
+    ///     navmesh: &Navmesh,
+    ///     position: Vector3<f32>,
+    ///     loot: Vector3<f32>,
+    ///     value: f32,
+    /// ) -> bool {
+    ///     navmesh
+    ///         .find_path_with_cost(position, loot, &Default::default())
+    ///         .is_ok_and(|path| path.cost < value)
+    /// }
+    /// ```
+    pub fn find_path_with_cost(
+        &self,
+        from: Vector3<f32>,
+        to: Vector3<f32>,
+        filter: &NavmeshQueryFilter,
+    ) -> Result<NavmeshPath, NavmeshPathError> {
+        let mut agent = NavmeshAgent::new();
+        agent.allow_partial_path = false;
+        agent.query_filter = *filter;
+        let mut cost = 0.0;
+        match agent.calculate_path_counted(self, from, to, &mut 0, &mut cost)? {
+            PathKind::Full => Ok(NavmeshPath {
+                cost,
+                points: std::mem::take(&mut agent.path),
+            }),
+            PathKind::Partial => Err(NavmeshPathError::Disconnected),
+        }
+    }
+
+    /// Builds a path from the given point to the nearest (by the path cost) of the given goals
+    /// using a single search, which is much cheaper than building a path to every goal and picking
+    /// the shortest one. The search accepts any of the goal triangles and its heuristic is the
+    /// minimum of the estimations to every goal. The path is built the same way as
+    /// [`Self::find_path_filtered`] does with the default filter. Returns the index of the chosen
+    /// goal and the path to it, or `None` if none of the goals is reachable. Goals on the islands,
+    /// that are not connected with the start point (see [`Self::can_reach`]), are skipped without
+    /// the search.
+    ///
+    /// ```rust
+    /// # use fyrox_impl::{core::algebra::Vector3, utils::navmesh::Navmesh};
+    /// fn go_to_closest_objective(
+    ///     navmesh: &Navmesh,
+    ///     position: Vector3<f32>,
+    ///     objectives: &[Vector3<f32>],
+    /// ) -> Option<Vec<Vector3<f32>>> {
+    ///     let (index, path) = navmesh.find_path_to_nearest(position, objectives)?;
+    ///     println!("Going to objective {index}");
+    ///     Some(path)
+    /// }
+    /// ```
+    pub fn find_path_to_nearest(
+        &self,
+        from: Vector3<f32>,
+        goals: &[Vector3<f32>],
+    ) -> Option<(usize, Vec<Vector3<f32>>)> {
+        let (src_point, src_triangle) = self.query_closest(from)?;
+
+        let mut goal_indices = Vec::new();
+        let mut goal_points = Vec::new();
+        let mut goal_triangles = Vec::new();
+        for (index, goal) in goals.iter().enumerate() {
+            if let Some((point, triangle)) = self.query_closest(*goal) {
+                if self.are_triangles_connected(src_triangle, triangle) {
+                    goal_indices.push(index);
+                    goal_points.push(point);
+                    goal_triangles.push(triangle);
+                }
+            }
+        }
+        if goal_triangles.is_empty() {
+            return None;
+        }
+
+        let mut agent = NavmeshAgent::new();
+        let filter = NavmeshQueryFilter::default();
+        let mut corridor = Vec::new();
+        // The agent does not fit into some passage, try to go through narrow passages then.
+        let nearest = [agent.radius, 0.0].into_iter().find_map(|clearance| {
+            self.graph
+                .build_indexed_path_to_nearest_with_cost(
+                    src_triangle,
+                    &goal_triangles,
+                    &mut corridor,
+                    |src, dest, cost| self.traversal_cost(src, dest, cost, clearance, &filter),
+                )
+                .ok()
+                .flatten()
+        })?;
+        corridor.reverse();
+        agent.follow_corridor(self, src_point, goal_points[nearest], corridor);
+
+        Some((goal_indices[nearest], agent.path))
+    }
+
+    /// Builds paths for every pair of points (`(from, to)`) in the given slice in parallel. Every
+    /// path is built the same way as [`Self::find_path_filtered`] does with the default filter.
+    /// Returns a path for each request in the same order, `None` means that the path could not be
+    /// built. Path queries do not modify the navmesh, so it could be safely shared between worker
+    /// threads; lazily built internal structures are initialized once before the paths are
+    /// calculated.
+    pub fn find_paths_batch(
+        &self,
+        requests: &[(Vector3<f32>, Vector3<f32>)],
+    ) -> Vec<Option<Vec<Vector3<f32>>>> {
+        // Initialize the acceleration structure on the current thread, instead of blocking the
+        // worker threads on the first query.
+        self.grid();
+
+        requests
+            .par_iter()
+            .map(|(from, to)| {
+                let mut path = Vec::new();
+                self.find_path_filtered(*from, *to, &mut path, &Default::default())
+                    .ok()
+                    .map(|_| path)
+            })
+            .collect()
+    }
+
+    /// Adds a new asynchronous path request and returns its id. The path is not calculated
+    /// immediately, instead the requests are calculated in [`Self::process_path_requests`], that
+    /// could be called on the main thread with a small budget every frame (to spread the
+    /// calculations over several frames) or on a worker thread. The path is calculated the same
+    /// way as [`NavmeshAgent::calculate_path`] does with default agent settings. Use
+    /// [`Self::poll_path`] to fetch the result.
+    ///
+    /// ## Thread safety
+    ///
+    /// The requests are stored in the navmesh behind a mutex, so both methods require only a
+    /// shared reference to the navmesh. When a navmesh is shared between threads as
+    /// `Arc<RwLock<Navmesh>>` (see [`crate::scene::navmesh::NavigationalMesh::navmesh`]), a
+    /// worker thread could hold a read lock while calculating the paths, and the navmesh can't be
+    /// modified while any path is being calculated. The requests are calculated using the current
+    /// state of the navmesh at the time of processing.
+    ///
+    /// ## Example
+    ///
+    /// ```rust
+    /// # use fyrox_impl::{
+    /// #     core::algebra::Vector3,
+    /// #     utils::navmesh::{Navmesh, PathStatus},
+    /// # };
+    /// fn find_path_async(navmesh: &Navmesh, from: Vector3<f32>, to: Vector3<f32>) {
+    ///     let id = navmesh.request_path(from, to);
+    ///
+    ///     // This could be done on another thread, or every frame with a small budget.
+    ///     navmesh.process_path_requests(1);
+    ///
+    ///     match navmesh.poll_path(id) {
+    ///         PathStatus::Ready(path) => println!("{path:?}"),
+    ///         PathStatus::Failed(err) => println!("{err}"),
+    ///         PathStatus::Pending | PathStatus::Unknown => (),
+    ///     }
+    /// }
+    /// ```
+    pub fn request_path(&self, from: Vector3<f32>, to: Vector3<f32>) -> PathRequestId {
+        self.request_path_with_priority(from, to, 0)
+    }
+
+    /// The same as [`Self::request_path`], but with the given priority. The requests with higher
+    /// priority are calculated first by [`Self::process_path_requests`], the requests with the
+    /// same priority are calculated in the order they were added. It could be used to calculate
+    /// the paths of important units (visible to the player, in combat, etc.) first, while the
+    /// paths of other units wait for the next frames, when there are more requests than the
+    /// per-frame budget allows (see [`Self::set_pathfinding_budget`]). Default priority is `0`.
+    pub fn request_path_with_priority(
+        &self,
+        from: Vector3<f32>,
+        to: Vector3<f32>,
+        priority: i32,
+    ) -> PathRequestId {
+        let agent = NavmeshAgent::default();
+        self.add_path_request(PathQuery {
+            from,
+            to,
+            radius: agent.radius,
+            smoothing: agent.smoothing,
+            allow_partial_path: agent.allow_partial_path,
+            filter: agent.query_filter,
+            priority,
+        })
+    }
+
+    fn add_path_request(&self, query: PathQuery) -> PathRequestId {
+        let mut state = self.path_requests.0.lock();
+        let id = PathRequestId(state.next_id);
+        state.next_id += 1;
+        let index = state
+            .pending
+            .partition_point(|(_, pending)| pending.priority >= query.priority);
+        state.pending.insert(index, (id, query));
+        id
+    }
+
+    /// Calculates at most `max_count` pending path requests (the requests with higher priority
+    /// first, see [`Self::request_path_with_priority`]) and returns the number of calculated
+    /// requests. The processing stops earlier, if the pathfinding budget (see
+    /// [`Self::set_pathfinding_budget`]) is spent. See [`Self::request_path`] for more info.
+    pub fn process_path_requests(&self, max_count: usize) -> usize {
+        let mut count = 0;
+        let mut expanded_nodes = 0;
+        while count < max_count {
+            if self
+                .pathfinding_budget
+                .is_some_and(|budget| expanded_nodes >= budget)
+            {
+                break;
+            }
+
+            let Some((id, query)) = ({
+                let mut state = self.path_requests.0.lock();
+                let request = state.pending.pop_front();
+                if let Some((id, _)) = request.as_ref() {
+                    state.in_progress.push(*id);
+                }
+                request
+            }) else {
+                break;
+            };
+
+            // The lock is released while the path is being calculated, so other threads could
+            // process other requests.
+            let mut agent = NavmeshAgent::new();
+            agent.radius = query.radius;
+            agent.smoothing = query.smoothing;
+            agent.allow_partial_path = query.allow_partial_path;
+            agent.query_filter = query.filter;
+            let result = agent
+                .calculate_path_counted(self, query.from, query.to, &mut expanded_nodes, &mut 0.0)
+                .map(|_| ComputedPath {
+                    path: agent.path,
+                    offmesh_segments: agent.offmesh_segments,
+                    corridor: agent.corridor,
+                    partial: agent.partial_path,
+                });
+
+            let mut state = self.path_requests.0.lock();
+            if let Some(position) = state.in_progress.iter().position(|r| *r == id) {
+                state.in_progress.swap_remove(position);
+                state.results.insert(id, result);
+            }
+
+            count += 1;
+        }
+        count
+    }
+
+    /// Sets the maximum number of triangles (or polygons, see [`Self::build_convex_polys`]), that
+    /// could be expanded by the path searches during a single call of
+    /// [`Self::process_path_requests`]. It caps the total amount of pathfinding work per frame,
+    /// which makes the frame time predictable in games with lots of agents. A search can't be
+    /// interrupted, so the budget is checked before every request and the last request may exceed
+    /// it. The remaining requests wait for the next call. Paths, that are taken from the path
+    /// cache (see [`Self::set_path_cache_capacity`]), cost nothing. `None` (default) means no
+    /// limit, `Some(0)` pauses the processing.
+    pub fn set_pathfinding_budget(&mut self, max_nodes_per_frame: Option<usize>) {
+        self.pathfinding_budget = max_nodes_per_frame;
+    }
+
+    /// Returns the maximum number of triangles, that could be expanded by the path searches during
+    /// a single call of [`Self::process_path_requests`]. See [`Self::set_pathfinding_budget`] for
+    /// more info.
+    pub fn pathfinding_budget(&self) -> Option<usize> {
+        self.pathfinding_budget
+    }
+
+    fn take_path_result(
+        &self,
+        id: PathRequestId,
+    ) -> Option<Result<ComputedPath, NavmeshPathError>> {
+        self.path_requests.0.lock().results.remove(&id)
+    }
+
+    /// Returns the status of a path request. If the path is calculated (or failed), the result is
+    /// returned and removed from the navmesh, so the following calls will return
+    /// [`PathStatus::Unknown`]. See [`Self::request_path`] for more info.
+    pub fn poll_path(&self, id: PathRequestId) -> PathStatus {
+        let mut state = self.path_requests.0.lock();
+        match state.results.remove(&id) {
+            Some(Ok(computed)) => PathStatus::Ready(computed.path),
+            Some(Err(err)) => PathStatus::Failed(err),
+            None => {
+                if state.in_progress.contains(&id)
+                    || state.pending.iter().any(|(pending, _)| *pending == id)
+                {
+                    PathStatus::Pending
+                } else {
+                    PathStatus::Unknown
+                }
+            }
+        }
+    }
+
+    /// Cancels a path request. If the request is being calculated at the moment, its result will
+    /// be discarded.
+    pub fn cancel_path_request(&self, id: PathRequestId) {
+        let mut state = self.path_requests.0.lock();
+        state.pending.retain(|(pending, _)| *pending != id);
+        state.in_progress.retain(|in_progress| *in_progress != id);
+        state.results.remove(&id);
+    }
+
+    /// Tries to pick a triangle by given ray. Returns closest result.
+    pub fn ray_cast(&self, ray: Ray) -> Option<(Vector3<f32>, usize)> {
+        let mut buffer = ArrayVec::<usize, 128>::new();
+
+        let octree = self.octree();
+        octree.ray_query_static(&ray, &mut buffer);
+
+        let mut closest_distance = f32::MAX;
+        let mut result = None;
+        for node in buffer.into_iter() {
+            if let OctreeNode::Leaf { indices, .. } = octree.node(node) {
+                for &index in indices {
+                    let triangle = self.triangles[index as usize];
+                    let a = self.vertices()[triangle[0] as usize];
+                    let b = self.vertices()[triangle[1] as usize];
+                    let c = self.vertices()[triangle[2] as usize];
+
+                    if let Some(intersection) = ray.triangle_intersection_point(&[a, b, c]) {
+                        let distance = intersection.metric_distance(&ray.origin);
+                        if distance < closest_distance {
+                            closest_distance = distance;
+                            result = Some((intersection, index as usize));
+                        }
+                    }
+                }
+            } else {
+                unreachable!()
+            }
+        }
+
+        result
+    }
+
+    /// Traces a straight segment from `from` to `to` along the surface of the navmesh and returns
+    /// the first point where the segment leaves the navmesh. Returns `None` if the whole segment
+    /// stays on the navmesh (or if the navmesh is empty). The segment is traced on the horizontal
+    /// plane, by walking from the triangle closest to `from` to its neighbours, so only the
+    /// triangles along the segment are checked.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use fyrox_impl::{core::algebra::Vector3, utils::navmesh::Navmesh};
+    ///
+    /// fn is_straight_walkable(navmesh: &Navmesh, from: Vector3<f32>, to: Vector3<f32>) -> bool {
+    ///     navmesh.raycast(from, to).is_none()
+    /// }
+    /// ```
+    pub fn raycast(&self, from: Vector3<f32>, to: Vector3<f32>) -> Option<NavmeshRaycastHit> {
+        self.raycast_internal(from, to, |_, _| true)
+    }
+
+    // Same as `raycast`, but the segment also stops at the edges between the triangles, that
+    // could not be traversed (`can_enter` takes the indices of the current and the next triangle).
+    fn raycast_internal(
+        &self,
+        from: Vector3<f32>,
+        to: Vector3<f32>,
+        can_enter: impl Fn(usize, usize) -> bool,
+    ) -> Option<NavmeshRaycastHit> {
+        let (_, mut current) = self.query_closest(from)?;
+        let mut previous = None;
+        let (local_from, local_to) = (self.to_up_space(from), self.to_up_space(to));
+
+        // Every triangle can be visited at most once, this also protects from infinite loops
+        // caused by numerical errors.
+        for _ in 0..self.triangles.len() {
+            let triangle = self.triangles[current];
+            let [a, b, c] = triangle
+                .0
+                .map(|index| self.to_up_space(self.vertices[index as usize]));
+
+            if is_point_inside_triangle_2d(local_to, a, b, c) {
+                return None;
+            }
+
+            // Find an edge through which the segment leaves the triangle. It is the farthest
+            // intersection along the segment.
+            let mut exit = None;
+            let mut exit_t = -f32::MAX;
+            for edge in triangle.edges() {
+                if let Some((t, u)) = segment_intersection_2d(
+                    local_from,
+                    local_to,
+                    self.to_up_space(self.vertices[edge.a as usize]),
+                    self.to_up_space(self.vertices[edge.b as usize]),
+                ) {
+                    if t > exit_t {
+                        exit_t = t;
+                        exit = Some((edge, u));
+                    }
+                }
+            }
+
+            let (edge, u) = exit?;
+
+            let hit = NavmeshRaycastHit {
+                position: self.vertices[edge.a as usize].lerp(&self.vertices[edge.b as usize], u),
+                edge: [edge.a, edge.b],
+                triangle: current,
+            };
+
+            match self.adjacent_triangle(current, edge.a, edge.b) {
+                Some(next) if Some(next) != previous && can_enter(current, next) => {
+                    previous = Some(current);
+                    current = next;
+                }
+                _ => return Some(hit),
+            }
+        }
+
+        None
+    }
+
+    /// Checks whether a disc with the given radius could move straight from `from` to `to` without
+    /// leaving the navmesh. The segment must stay on the navmesh (see [`Self::raycast`]) and must
+    /// be at least `radius` away from every border edge of the navmesh (on the horizontal plane).
+    /// The triangles along the segment must not be blocked by obstacles and the segment must not
+    /// cross one-way edges in the forbidden direction. It could be used to skip the waypoints of a
+    /// path, when there is a shortcut, that is wide enough for an agent. See
+    /// [`Self::can_walk_straight_filtered`] for a version that respects a query filter.
+    ///
+    /// The test is conservative: it never reports a blocked segment as walkable, but it could
+    /// report a walkable segment as blocked, when the segment passes near a border edge of another
+    /// floor above or below it. Only the border edges near the segment are checked (using the
+    /// acceleration structure, see [`Self::rebuild_acceleration`]), so the test is cheap enough to
+    /// be called multiple times per frame.
+    ///
+    /// ## Example
+    ///
+    /// ```
+    /// use fyrox_impl::{core::algebra::Vector3, utils::navmesh::Navmesh};
+    ///
+    /// // Skips the waypoints, that could be bypassed by a straight segment.
+    /// fn next_waypoint(
+    ///     navmesh: &Navmesh,
+    ///     position: Vector3<f32>,
+    ///     path: &[Vector3<f32>],
+    ///     radius: f32,
+    /// ) -> Option<Vector3<f32>> {
+    ///     path.iter()
+    ///         .rev()
+    ///         .find(|waypoint| navmesh.can_walk_straight(position, **waypoint, radius))
+    ///         .or(path.first())
+    ///         .cloned()
+    /// }
+    /// ```
+    pub fn can_walk_straight(&self, from: Vector3<f32>, to: Vector3<f32>, radius: f32) -> bool {
+        self.can_walk_straight_filtered(from, to, radius, &Default::default())
+    }
+
+    /// Same as [`Self::can_walk_straight`], but every triangle along the segment must also pass
+    /// the given filter.
+    pub fn can_walk_straight_filtered(
+        &self,
+        from: Vector3<f32>,
+        to: Vector3<f32>,
+        radius: f32,
+        filter: &NavmeshQueryFilter,
+    ) -> bool {
+        let Some((_, start)) = self.query_closest(from) else {
+            return false;
+        };
+        if !self.is_triangle_passable(start, filter)
+            || self
+                .raycast_internal(from, to, |src, dest| {
+                    self.is_triangle_passable(dest, filter)
+                        && !self.one_way_edges.contains(&[dest as u32, src as u32])
+                })
+                .is_some()
+        {
+            return false;
+        }
+        if radius <= 0.0 {
+            return true;
+        }
+
+        let margin = Vector3::repeat(radius);
+        let mut walkable = true;
+        self.visit_grid_rect(from.inf(&to) - margin, from.sup(&to) + margin, |cell| {
+            if !walkable {
+                return;
+            }
+            for &index in cell {
+                for edge in self.triangles[index as usize].edges() {
+                    if self
+                        .adjacent_triangle(index as usize, edge.a, edge.b)
+                        .is_none()
+                        && segment_distance_2d(
+                            self.to_up_space(from),
+                            self.to_up_space(to),
+                            self.to_up_space(self.vertices[edge.a as usize]),
+                            self.to_up_space(self.vertices[edge.b as usize]),
+                        ) < radius
+                    {
+                        walkable = false;
+                        return;
+                    }
+                }
+            }
+        });
+        walkable
+    }
+
+    /// Returns an index of a triangle that shares the given edge with the given triangle.
+    fn adjacent_triangle(&self, triangle: usize, a: u32, b: u32) -> Option<usize> {
+        self.graph
+            .vertices
+            .get(triangle)?
+            .neighbours
+            .iter()
+            .map(|n| *n as usize)
+            .find(|n| {
+                let indices = self.triangles[*n].indices();
+                indices.contains(&a) && indices.contains(&b)
+            })
+    }
+
+    /// Builds the shortest path through the given corridor (a list of indices of adjacent
+    /// triangles, that connect the start and the end points) using the funnel algorithm (also
+    /// known as "string pulling"). The start point must lie in the first triangle of the corridor
+    /// and the end point - in the last one. The resulting path begins at the start point, goes
+    /// through the vertices of the corridor, where the path turns, and ends at the end point.
+    /// Consecutive duplicate points (that could appear when the corridor turns around the same
+    /// vertex multiple times) are removed. It could be used to smooth a path of triangles, built
+    /// by custom path finding code; the path of an agent is built the same way.
+    pub fn string_pull(
+        &self,
+        corridor: &[usize],
+        start: Vector3<f32>,
+        end: Vector3<f32>,
+    ) -> Vec<Vector3<f32>> {
+        let mut path = Vec::new();
+        self.pull_string(corridor, start, end, 0.0, &mut path);
+        path.dedup();
+        path
+    }
+
+    // Funnel algorithm. Portals are narrowed by the clearance at the border vertices of the
+    // navmesh. The points are appended to the given path.
+    fn pull_string(
+        &self,
+        path_triangles: &[usize],
+        src_position: Vector3<f32>,
+        dest_position: Vector3<f32>,
+        clearance: f32,
+        path: &mut Vec<Vector3<f32>>,
+    ) {
+        path.push(src_position);
+
+        // The turns of the funnel are checked on the horizontal plane.
+        let area = |a: Vector3<f32>, b: Vector3<f32>, c: Vector3<f32>| {
+            triangle_area_2d(
+                self.to_up_space(a),
+                self.to_up_space(b),
+                self.to_up_space(c),
+            )
+        };
+        if path_triangles.len() > 1 {
+            let mut funnel_apex = src_position;
+            let mut funnel_vertices = [funnel_apex; 2];
+            let mut side_indices = [0; 2];
+            let side_signs = [1.0, -1.0];
+
+            let mut i = 0;
+            while i < path_triangles.len() {
+                let portal_vertices = if i + 1 < path_triangles.len() {
+                    let (src_triangle, dest_triangle) = (path_triangles[i], path_triangles[i + 1]);
+                    let [mut left, mut right] = self.portal_points(src_triangle, dest_triangle);
+
+                    let delta = right - left;
+                    let len = delta.norm();
+                    // Portal could collapse into a point on very thin triangles.
+                    if clearance > 0.0 && len > f32::EPSILON {
+                        // Only the ends of a portal, that lie on the border of the navmesh, should
+                        // keep the clearance, the agent could safely pass by the inner vertices.
+                        let (inset_left, inset_right) = self
+                            .portal_between(src_triangle, dest_triangle)
+                            .map(|portal| {
+                                (
+                                    self.is_border_vertex(portal.left),
+                                    self.is_border_vertex(portal.right),
+                                )
+                            })
+                            .unwrap_or((true, true));
+                        let max_inset = if inset_left && inset_right {
+                            len * 0.5
+                        } else {
+                            len
+                        };
+                        let offset = delta.scale(clearance.min(max_inset) / len);
+
+                        if inset_left {
+                            left += offset;
+                        }
+                        if inset_right {
+                            right -= offset;
+                        }
+                    }
+
+                    [left, right]
+                } else {
+                    [dest_position, dest_position]
+                };
+
+                for current in 0..2 {
+                    let opposite = 1 - current;
+                    let side_sign = side_signs[current];
+                    if side_sign
+                        * area(
+                            funnel_apex,
+                            funnel_vertices[current],
+                            portal_vertices[current],
+                        )
+                        >= 0.0
+                    {
+                        if funnel_apex == funnel_vertices[current]
+                            || side_sign
+                                * area(
+                                    funnel_apex,
+                                    funnel_vertices[opposite],
+                                    portal_vertices[current],
+                                )
+                                < 0.0
+                        {
+                            funnel_vertices[current] = portal_vertices[current];
+                            side_indices[current] = i;
+                        } else {
+                            funnel_apex = funnel_vertices[opposite];
+                            funnel_vertices = [funnel_apex; 2];
+
+                            path.push(funnel_apex);
+
+                            i = side_indices[opposite];
+                            side_indices[current] = i;
+
+                            break;
+                        }
+                    }
+                }
+
+                i += 1;
+            }
+        }
+
+        path.push(dest_position);
+    }
+
+    /// Returns left and right points of a portal between two triangles. The triangles, that are
+    /// connected by an off-mesh link with a span (see [`OffMeshLink::span`]), have the span as the
+    /// portal. If the triangles do not share an edge (for example, when they share just a single
+    /// vertex), the portal collapses into a point - either the shared vertex or the center of the
+    /// destination triangle.
+    fn portal_points(&self, src_triangle: usize, dest_triangle: usize) -> [Vector3<f32>; 2] {
+        if let Some(portal) = self.portal_between(src_triangle, dest_triangle) {
+            return [self.vertices[portal.left], self.vertices[portal.right]];
+        }
+
+        if let Some(traversal) = self.offmesh_link_between(src_triangle, dest_triangle) {
+            let link = &self.offmesh_links[traversal.link];
+            if link.has_span() {
+                // The link is crossed like a shared edge, its span on the source side is the
+                // portal. The left end is the one, that makes a clockwise turn with the source
+                // triangle, just like it is for the shared edges.
+                let a = traversal.enter - link.span;
+                let b = traversal.enter + link.span;
+                let center = self.graph.vertices[src_triangle].position;
+                return if triangle_area_2d(
+                    self.to_up_space(a),
+                    self.to_up_space(b),
+                    self.to_up_space(center),
+                ) > 0.0
+                {
+                    [a, b]
+                } else {
+                    [b, a]
+                };
+            }
+        }
+
+        let src_indices = self.triangles[src_triangle].indices();
+        let point = self.triangles[dest_triangle]
+            .indices()
+            .iter()
+            .find(|i| src_indices.contains(i))
+            .map(|i| self.vertices[*i as usize])
+            .unwrap_or(self.graph.vertices[dest_triangle].position);
+
+        [point, point]
+    }
+
+    fn portal_between(&self, src_triangle: usize, dest_triangle: usize) -> Option<Portal> {
+        let src_triangle = self.triangles.get(src_triangle)?;
+        let dest_triangle = self.triangles.get(dest_triangle)?;
+        for src_triangle_edge in src_triangle.edges() {
+            for dest_triangle_edge in dest_triangle.edges() {
+                if src_triangle_edge == dest_triangle_edge {
+                    let [a, b, c] = src_triangle
+                        .0
+                        .map(|index| self.to_up_space(self.vertices[index as usize]));
+
+                    return if winding(a, b, c) == Winding::Clockwise {
+                        Some(Portal {
+                            left: src_triangle_edge.a as usize,
+                            right: src_triangle_edge.b as usize,
+                        })
+                    } else {
+                        Some(Portal {
+                            left: src_triangle_edge.b as usize,
+                            right: src_triangle_edge.a as usize,
+                        })
+                    };
+                }
+            }
+        }
+        None
+    }
+}
@@ -0,0 +1,675 @@
+// Copyright (c) 2019-present Dmitry Stepanov and Fyrox Engine contributors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+//! Convex polygons, that are made of adjacent coplanar triangles of a navmesh. Path finding could
+//! run over the polygons instead of the triangles, see [`Navmesh::build_convex_polys`] for more
+//! info.
+
+use crate::{
+    core::algebra::Vector3,
+    utils::{
+        astar::{Graph, PathError, PathKind, PathfindStats, VertexData},
+        navmesh::{triangle_area_2d, Navmesh, NavmeshQueryFilter, Vertex},
+    },
+};
+use fxhash::FxHashMap;
+use std::cell::RefCell;
+
+/// Cosine of the maximum angle between the normals of two triangles, that could be merged.
+const COPLANAR_COS: f32 = 0.9999;
+
+/// A convex polygon, that is made of adjacent coplanar triangles of a navmesh. See
+/// [`Navmesh::build_convex_polys`] for more info.
+#[derive(Clone, Debug, Default, PartialEq)]
+pub struct NavmeshPolygon {
+    /// Indices of the vertices of the polygon, in counterclockwise order when looking down along
+    /// the up axis, regardless of the winding order of its triangles.
+    pub vertices: Vec<u32>,
+    /// Indices of the triangles, that form the polygon.
+    pub triangles: Vec<usize>,
+}
+
+#[derive(Clone, Debug)]
+pub(super) struct PolygonMesh {
+    pub(super) polygons: Vec<NavmeshPolygon>,
+    pub(super) triangle_polygons: Vec<u32>,
+    // The polygons go first, then every triangle has its own node. A polygon, that contains a
+    // triangle blocked by a runtime obstacle, is searched over its triangle nodes instead.
+    graph: Graph<Vertex>,
+    // Pairs of triangles (in the direction of traversal), that connect two nodes of the graph, at
+    // least one of which is a polygon. It is either a shared edge or an off-mesh link.
+    connections: FxHashMap<(u32, u32), Vec<(usize, usize)>>,
+    // Width of the narrowest portal inside the polygons, that lies between two border vertices.
+    narrowest_portal: f32,
+}
+
+impl PolygonMesh {
+    pub(super) fn new(navmesh: &Navmesh, max_vertices: usize) -> Self {
+        let max_vertices = max_vertices.max(3);
+        let triangle_count = navmesh.triangles.len();
+        let normals = navmesh.triangle_normals().collect::<Vec<_>>();
+        let points = navmesh
+            .vertices
+            .iter()
+            .map(|vertex| navmesh.to_up_space(*vertex))
+            .collect::<Vec<_>>();
+
+        // Triangles with different winding order could share an edge (they are not connected in
+        // the graph though), so the edges are undirected.
+        let mut edge_triangles = FxHashMap::<(u32, u32), Vec<usize>>::default();
+        for (index, triangle) in navmesh.triangles.iter().enumerate() {
+            for edge in triangle.edges() {
+                edge_triangles
+                    .entry((edge.a.min(edge.b), edge.a.max(edge.b)))
+                    .or_default()
+                    .push(index);
+            }
+        }
+
+        // Every triangle is a polygon at first, its vertices are reordered to be counterclockwise,
+        // so the shared edge of two adjacent polygons always goes in the opposite directions.
+        // Polygons are merged greedily, every polygon grows across its longest edges, until it
+        // cannot be merged with any of its neighbors.
+        let mut polygons = navmesh
+            .triangles
+            .iter()
+            .enumerate()
+            .map(|(index, triangle)| {
+                let [a, b, c] = triangle.0;
+                let area =
+                    triangle_area_2d(points[a as usize], points[b as usize], points[c as usize]);
+                Some(NavmeshPolygon {
+                    vertices: if area < 0.0 {
+                        vec![a, c, b]
+                    } else {
+                        vec![a, b, c]
+                    },
+                    triangles: vec![index],
+                })
+            })
+            .collect::<Vec<_>>();
+        let mut owners = (0..triangle_count).collect::<Vec<_>>();
+        for index in 0..triangle_count {
+            let Some(first_triangle) = polygons[index].as_ref().map(|polygon| polygon.triangles[0])
+            else {
+                continue;
+            };
+            let [a, b, c] = navmesh.triangles[first_triangle]
+                .0
+                .map(|vertex| points[vertex as usize]);
+            if triangle_area_2d(a, b, c).abs() <= f32::EPSILON {
+                // Vertical or degenerate triangle.
+                continue;
+            }
+
+            loop {
+                let polygon = polygons[index].as_ref().unwrap();
+                let mut best: Option<(f32, usize, Vec<u32>)> = None;
+                for (i, &begin) in polygon.vertices.iter().enumerate() {
+                    let end = polygon.vertices[(i + 1) % polygon.vertices.len()];
+                    let Some(&[first, second]) = edge_triangles
+                        .get(&(begin.min(end), begin.max(end)))
+                        .map(Vec::as_slice)
+                    else {
+                        // Border or non-manifold edge.
+                        continue;
+                    };
+                    let (own, neighbor) = if owners[first] == index {
+                        (first, second)
+                    } else {
+                        (second, first)
+                    };
+                    let other_index = owners[neighbor];
+                    if other_index == index
+                        || !navmesh.graph.vertices[own]
+                            .neighbours
+                            .contains(&(neighbor as u32))
+                        || navmesh.triangle_costs[own] != navmesh.triangle_costs[neighbor]
+                        || navmesh.triangle_flags[own] != navmesh.triangle_flags[neighbor]
+                        || navmesh.triangle_layers[own] != navmesh.triangle_layers[neighbor]
+                        // Normals of the triangles with different winding are opposite.
+                        || normals[own].dot(&normals[neighbor]).abs() < COPLANAR_COS
+                        || navmesh
+                            .one_way_edges
+                            .contains(&[own as u32, neighbor as u32])
+                        || navmesh
+                            .one_way_edges
+                            .contains(&[neighbor as u32, own as u32])
+                    {
+                        continue;
+                    }
+                    let other = polygons[other_index].as_ref().unwrap();
+                    if polygon.vertices.len() + other.vertices.len() - 2 > max_vertices {
+                        continue;
+                    }
+                    let Some(merged) = merge_loops(&polygon.vertices, &other.vertices, begin, end)
+                    else {
+                        continue;
+                    };
+                    if !is_convex(&merged, &points) {
+                        continue;
+                    }
+                    let length = points[begin as usize].metric_distance(&points[end as usize]);
+                    if best.as_ref().map_or(true, |(best, ..)| length > *best) {
+                        best = Some((length, other_index, merged));
+                    }
+                }
+
+                let Some((_, other_index, merged)) = best else {
+                    break;
+                };
+                let other = polygons[other_index].take().unwrap();
+                for &triangle in other.triangles.iter() {
+                    owners[triangle] = index;
+                }
+                let polygon = polygons[index].as_mut().unwrap();
+                polygon.vertices = merged;
+                polygon.triangles.extend(other.triangles);
+            }
+        }
+
+        let mut slots = vec![0; triangle_count];
+        let mut compact = Vec::new();
+        for (slot, polygon) in polygons.into_iter().enumerate() {
+            if let Some(mut polygon) = polygon {
+                slots[slot] = compact.len() as u32;
+                polygon.triangles.sort_unstable();
+                compact.push(polygon);
+            }
+        }
+        let triangle_polygons = owners.iter().map(|slot| slots[*slot]).collect::<Vec<_>>();
+
+        let polygon_count = compact.len();
+        let mut graph = Graph::new();
+        for (index, polygon) in compact.iter().enumerate() {
+            let center = polygon
+                .vertices
+                .iter()
+                .map(|vertex| navmesh.vertices[*vertex as usize])
+                .sum::<Vector3<f32>>()
+                .scale(1.0 / polygon.vertices.len() as f32);
+            let mut data = VertexData::new(center);
+            data.g_penalty = navmesh.triangle_costs[polygon.triangles[0]];
+            graph.add_vertex(Vertex {
+                triangle_index: index,
+                data,
+            });
+        }
+        for (index, vertex) in navmesh.graph.vertices.iter().enumerate() {
+            let mut data = VertexData::new(vertex.position);
+            data.g_penalty = navmesh.triangle_costs[index];
+            graph.add_vertex(Vertex {
+                triangle_index: index,
+                data,
+            });
+        }
+
+        let mut connections = FxHashMap::<(u32, u32), Vec<(usize, usize)>>::default();
+        let mut narrowest_portal = f32::MAX;
+        for (triangle, vertex) in navmesh.graph.vertices.iter().enumerate() {
+            let polygon = triangle_polygons[triangle];
+            let triangle_node = (polygon_count + triangle) as u32;
+            for &neighbor in vertex.neighbours.iter() {
+                let other = triangle_polygons[neighbor as usize];
+                let neighbor_node = polygon_count as u32 + neighbor;
+                graph.link_unidirect(triangle_node as usize, neighbor_node as usize);
+                if polygon != other {
+                    for (src, dest) in [
+                        (polygon, other),
+                        (polygon, neighbor_node),
+                        (triangle_node, other),
+                    ] {
+                        connections
+                            .entry((src, dest))
+                            .or_default()
+                            .push((triangle, neighbor as usize));
+                        graph.link_unidirect(src as usize, dest as usize);
+                    }
+                } else if let Some(portal) = navmesh.portal_between(triangle, neighbor as usize) {
+                    if navmesh.is_border_vertex(portal.left)
+                        && navmesh.is_border_vertex(portal.right)
+                    {
+                        narrowest_portal = narrowest_portal.min(
+                            navmesh.vertices[portal.left]
+                                .metric_distance(&navmesh.vertices[portal.right]),
+                        );
+                    }
+                }
+            }
+        }
+
+        Self {
+            polygons: compact,
+            triangle_polygons,
+            graph,
+            connections,
+            narrowest_portal,
+        }
+    }
+
+    /// Returns `true` if the polygons could be used to search for a path for an agent with the
+    /// given clearance. Polygons do not account for narrow portals inside of them, so the search
+    /// must be done over the triangles in such cases.
+    pub(super) fn is_usable(&self, clearance: f32) -> bool {
+        clearance <= 0.0 || self.narrowest_portal >= 2.0 * clearance
+    }
+
+    /// Returns the triangle of a triangle node of the graph or `None` for a polygon node.
+    fn node_triangle(&self, node: usize) -> Option<usize> {
+        node.checked_sub(self.polygons.len())
+    }
+
+    /// Returns the cheapest pair of triangles, that connects two nodes of the graph, and the cost
+    /// of moving between the nodes.
+    fn connection(
+        &self,
+        navmesh: &Navmesh,
+        src: usize,
+        dest: usize,
+        cost: f32,
+        clearance: f32,
+        filter: &NavmeshQueryFilter,
+    ) -> Option<(f32, (usize, usize))> {
+        let single;
+        let pairs = match (self.node_triangle(src), self.node_triangle(dest)) {
+            (Some(from), Some(to)) => {
+                single = [(from, to)];
+                &single[..]
+            }
+            _ => self.connections.get(&(src as u32, dest as u32))?,
+        };
+        pairs
+            .iter()
+            .filter_map(|&(from, to)| {
+                navmesh
+                    .traversal_cost(from, to, cost, clearance, filter)
+                    .map(|cost| (cost, (from, to)))
+            })
+            .min_by(|a, b| a.0.total_cmp(&b.0))
+    }
+
+    /// Searches for a path over the polygons and converts it to a path of triangle indices, that
+    /// has the same format as the paths built over the triangles: the path is reversed, the first
+    /// index is the destination triangle. The polygons, that contain triangles blocked by runtime
+    /// obstacles, are split into their triangles for the search. The number of the expanded nodes
+    /// is added to `expanded_nodes`, the cost of the path is returned together with its kind.
+    #[allow(clippy::too_many_arguments)]
+    pub(super) fn build_triangle_path(
+        &self,
+        navmesh: &Navmesh,
+        from: usize,
+        to: usize,
+        path: &mut Vec<usize>,
+        clearance: f32,
+        filter: &NavmeshQueryFilter,
+        expanded_nodes: &mut usize,
+    ) -> Result<(PathKind, f32), PathError> {
+        path.clear();
+        if from >= self.triangle_polygons.len() {
+            return Err(PathError::InvalidIndex(from));
+        }
+        if to >= self.triangle_polygons.len() {
+            return Err(PathError::InvalidIndex(to));
+        }
+
+        let has_obstacles = navmesh.obstacles.iter().any(|obstacle| obstacle.enabled);
+        let split = RefCell::new(FxHashMap::default());
+        let is_split = |polygon: usize| {
+            has_obstacles
+                && *split.borrow_mut().entry(polygon).or_insert_with(|| {
+                    self.polygons[polygon]
+                        .triangles
+                        .iter()
+                        .any(|triangle| navmesh.is_triangle_blocked(*triangle))
+                })
+        };
+        let is_active = |node: usize| match self.node_triangle(node) {
+            Some(triangle) => is_split(self.triangle_polygons[triangle] as usize),
+            None => !is_split(node),
+        };
+        // The node of the given triangle: either its polygon or the triangle itself.
+        let triangle_node = |triangle: usize| {
+            let polygon = self.triangle_polygons[triangle] as usize;
+            if is_split(polygon) {
+                self.polygons.len() + triangle
+            } else {
+                polygon
+            }
+        };
+        let from_node = triangle_node(from);
+        let to_node = triangle_node(to);
+
+        let mut node_path = Vec::new();
+        let mut stats = PathfindStats::default();
+        let result = self.graph.build_indexed_path_with_cost_and_stats(
+            from_node,
+            to_node,
+            &mut node_path,
+            navmesh.max_search_nodes(),
+            |src, dest, cost| {
+                if !is_active(dest) {
+                    return None;
+                }
+                self.connection(navmesh, src, dest, cost, clearance, filter)
+                    .map(|(cost, _)| cost)
+            },
+            &mut stats,
+        );
+        *expanded_nodes += stats.expanded_vertices;
+        let kind = match result {
+            Ok(kind) => kind,
+            // The partial path is still valid and converted as usual.
+            Err(PathError::SearchExhausted(_)) => PathKind::Partial,
+            Err(err) => return Err(err),
+        };
+
+        let mut corridor = Vec::new();
+        let mut entry = from;
+        for pair in node_path.windows(2).rev() {
+            let (src, dest) = (pair[1], pair[0]);
+            let Some((_, (exit, next))) =
+                self.connection(navmesh, src, dest, 1.0, clearance, filter)
+            else {
+                return Err(PathError::InvalidIndex(dest));
+            };
+            self.inner_path(navmesh, src, entry, exit, &mut corridor);
+            entry = next;
+        }
+        let last = node_path.first().cloned().unwrap_or(from_node);
+        let exit = if kind == PathKind::Full {
+            to
+        } else if let Some(triangle) = self.node_triangle(last) {
+            triangle
+        } else {
+            // The destination is unreachable, go to the triangle that is closest to it.
+            let target = navmesh.graph.vertices[to].position;
+            self.polygons[last]
+                .triangles
+                .iter()
+                .cloned()
+                .min_by(|a, b| {
+                    let a = navmesh.graph.vertices[*a].position.metric_distance(&target);
+                    let b = navmesh.graph.vertices[*b].position.metric_distance(&target);
+                    a.total_cmp(&b)
+                })
+                .unwrap_or(entry)
+        };
+        self.inner_path(navmesh, last, entry, exit, &mut corridor);
+
+        path.extend(corridor.into_iter().rev());
+        result.map(|kind| (kind, stats.path_cost))
+    }
+
+    /// Appends the triangles of a node of the graph, that lie between the given triangles, to the
+    /// corridor. The triangles of a convex polygon form a tree, so the path is unique.
+    fn inner_path(
+        &self,
+        navmesh: &Navmesh,
+        node: usize,
+        from: usize,
+        to: usize,
+        corridor: &mut Vec<usize>,
+    ) {
+        if self.node_triangle(node).is_some() {
+            corridor.push(from);
+            return;
+        }
+        let polygon_index = node as u32;
+        let mut parents = FxHashMap::default();
+        parents.insert(from, from);
+        let mut queue = vec![from];
+        while let Some(triangle) = queue.pop() {
+            if triangle == to {
+                break;
+            }
+            for neighbor in navmesh.neighbors(triangle) {
+                if self.triangle_polygons[neighbor] == polygon_index
+                    && !parents.contains_key(&neighbor)
+                {
+                    parents.insert(neighbor, triangle);
+                    queue.push(neighbor);
+                }
+            }
+        }
+
+        let begin = corridor.len();
+        let mut current = to;
+        while let Some(&parent) = parents.get(&current) {
+            corridor.push(current);
+            if parent == current {
+                break;
+            }
+            current = parent;
+        }
+        corridor[begin..].reverse();
+    }
+}
+
+/// Merges two vertex loops, that share the edge from `begin` to `end` (in the winding order of
+/// the first loop). Returns `None`, if the loops share more than one edge.
+fn merge_loops(first: &[u32], second: &[u32], begin: u32, end: u32) -> Option<Vec<u32>> {
+    let i = first.iter().position(|v| *v == begin)?;
+    let j = second.iter().position(|v| *v == end)?;
+    if second[(j + 1) % second.len()] != begin {
+        return None;
+    }
+
+    // Walk the first loop from the end of the shared edge to its beginning, then walk the second
+    // loop from the vertex after the shared edge to the vertex before it.
+    let mut merged = Vec::with_capacity(first.len() + second.len() - 2);
+    for k in 1..=first.len() {
+        merged.push(first[(i + k) % first.len()]);
+    }
+    for k in 2..second.len() {
+        merged.push(second[(j + k) % second.len()]);
+    }
+
+    let mut sorted = merged.clone();
+    sorted.sort_unstable();
+    if sorted.windows(2).any(|pair| pair[0] == pair[1]) {
+        return None;
+    }
+    Some(merged)
+}
+
+/// Checks whether a vertex loop is convex (on the horizontal plane) and counterclockwise.
+/// Collinear vertices are allowed.
+fn is_convex(vertices: &[u32], points: &[Vector3<f32>]) -> bool {
+    const TOLERANCE: f32 = 1.0e-6;
+    (0..vertices.len()).all(|i| {
+        let a = points[vertices[i] as usize];
+        let b = points[vertices[(i + 1) % vertices.len()] as usize];
+        let c = points[vertices[(i + 2) % vertices.len()] as usize];
+        triangle_area_2d(a, b, c) >= -TOLERANCE
+    })
+}
+
+#[cfg(test)]
+mod test {
+    use crate::{
+        core::algebra::Vector3,
+        utils::{
+            astar::PathKind,
+            navmesh::{
+                polygon::{is_convex, NavmeshPolygon},
+                test::{make_grid_navmesh, make_strip_navmesh, make_two_squares_navmesh},
+                Navmesh, NavmeshAgent, NavmeshObstacle, NavmeshPathError,
+            },
+        },
+    };
+
+    fn find_path(navmesh: &Navmesh, from: Vector3<f32>, to: Vector3<f32>) -> Vec<Vector3<f32>> {
+        let mut path = Vec::new();
+        assert_eq!(
+            navmesh.find_path_filtered(from, to, &mut path, &Default::default()),
+            Ok(PathKind::Full)
+        );
+        path
+    }
+
+    #[test]
+    fn test_convex_polys_strip() {
+        let plain = make_strip_navmesh();
+        let mut navmesh = make_strip_navmesh();
+        assert!(navmesh.polygons().is_empty());
+        assert_eq!(navmesh.triangle_polygon(0), None);
+
+        navmesh.build_convex_polys(4);
+        assert_eq!(navmesh.max_polygon_vertices(), 4);
+        assert_eq!(navmesh.polygons().len(), 3);
+        assert_eq!(
+            navmesh.polygons()[0],
+            NavmeshPolygon {
+                vertices: vec![3, 0, 1, 2],
+                triangles: vec![0, 1],
+            }
+        );
+        assert_eq!(navmesh.triangle_polygon(5), Some(2));
+        assert_eq!(navmesh.triangle_polygon(6), None);
+
+        navmesh.build_convex_polys(6);
+        assert_eq!(navmesh.polygons().len(), 2);
+
+        navmesh.build_convex_polys(8);
+        assert_eq!(navmesh.polygons().len(), 1);
+        assert_eq!(navmesh.polygons()[0].triangles, vec![0, 1, 2, 3, 4, 5]);
+
+        // The corridor and the smoothed path are the same as the ones built over the triangles.
+        let mut expected = Vec::new();
+        let mut path = Vec::new();
+        assert_eq!(plain.build_path(0, 5, &mut expected), Ok(PathKind::Full));
+        assert_eq!(navmesh.build_path(0, 5, &mut path), Ok(PathKind::Full));
+        assert_eq!(path, expected);
+        let from = Vector3::new(0.1, 0.0, 0.9);
+        let to = Vector3::new(2.9, 0.0, 0.1);
+        assert_eq!(find_path(&navmesh, from, to), find_path(&plain, from, to));
+
+        // Triangles with different costs are not merged.
+        navmesh.set_triangle_cost(2, 2.0);
+        assert_eq!(navmesh.polygons().len(), 3);
+        assert_ne!(navmesh.triangle_polygon(2), navmesh.triangle_polygon(1));
+        assert_ne!(navmesh.triangle_polygon(2), navmesh.triangle_polygon(3));
+        assert_eq!(navmesh.build_path(0, 5, &mut path), Ok(PathKind::Full));
+        assert_eq!(path, expected);
+
+        navmesh.build_convex_polys(0);
+        assert!(navmesh.polygons().is_empty());
+    }
+
+    #[test]
+    fn test_convex_polys_grid() {
+        let mut navmesh = make_grid_navmesh(6);
+        navmesh.build_convex_polys(12);
+
+        let polygons = navmesh.polygons();
+        assert!(polygons.len() < navmesh.triangles().len() / 2);
+        let mut covered = vec![0; navmesh.triangles().len()];
+        for (index, polygon) in polygons.iter().enumerate() {
+            assert!(polygon.vertices.len() <= 12);
+            assert!(is_convex(&polygon.vertices, navmesh.vertices()));
+            for &triangle in polygon.triangles.iter() {
+                covered[triangle] += 1;
+                assert_eq!(navmesh.triangle_polygon(triangle), Some(index));
+            }
+        }
+        assert!(covered.iter().all(|count| *count == 1));
+
+        let from = Vector3::new(0.1, 0.0, 0.1);
+        let to = Vector3::new(5.9, 0.0, 5.7);
+        let path = find_path(&navmesh, from, to);
+        assert!(path[0].metric_distance(&from) < 1.0e-5);
+        assert!(path[path.len() - 1].metric_distance(&to) < 1.0e-5);
+        let mut path = Vec::new();
+        assert_eq!(navmesh.build_path(0, 71, &mut path), Ok(PathKind::Full));
+
+        // Obstacles do not rebuild the polygons, the blocked polygons are split into triangles.
+        let polygons = navmesh.polygons().to_vec();
+        navmesh.add_obstacle(NavmeshObstacle::Box {
+            center: Vector3::new(3.0, 0.0, 2.5),
+            half_extents: Vector3::new(0.4, 1.0, 2.4),
+        });
+        assert_eq!(navmesh.polygons(), polygons.as_slice());
+        let path = find_path(
+            &navmesh,
+            Vector3::new(1.5, 0.0, 1.5),
+            Vector3::new(4.5, 0.0, 1.5),
+        );
+        assert!(path.len() > 2);
+        // The path goes around the end of the obstacle.
+        assert!(path.iter().any(|point| point.z > 4.8));
+    }
+
+    #[test]
+    fn test_convex_polys_mixed_winding() {
+        // The first quad has the opposite winding, it is not connected to the other quads.
+        let mut triangles = make_strip_navmesh().triangles().to_vec();
+        for triangle in triangles.iter_mut().take(2) {
+            triangle.0.swap(1, 2);
+        }
+        let mut navmesh = Navmesh::new(triangles, make_strip_navmesh().vertices().to_vec());
+        navmesh.build_convex_polys(8);
+        assert_eq!(navmesh.polygons().len(), 2);
+        assert_eq!(navmesh.polygons()[0].triangles, vec![0, 1]);
+        assert_eq!(navmesh.polygons()[1].triangles, vec![2, 3, 4, 5]);
+        for polygon in navmesh.polygons() {
+            assert!(is_convex(&polygon.vertices, navmesh.vertices()));
+        }
+
+        let from = Vector3::new(1.1, 0.0, 0.9);
+        let to = Vector3::new(2.9, 0.0, 0.1);
+        assert_eq!(
+            find_path(&navmesh, from, to),
+            find_path(&make_strip_navmesh(), from, to)
+        );
+        let mut path = Vec::new();
+        assert_eq!(
+            navmesh.find_path_filtered(
+                Vector3::new(0.1, 0.0, 0.9),
+                to,
+                &mut path,
+                &Default::default()
+            ),
+            Err(NavmeshPathError::Disconnected)
+        );
+    }
+
+    #[test]
+    fn test_convex_polys_offmesh_link() {
+        let mut navmesh = make_two_squares_navmesh();
+        navmesh.add_offmesh_link(
+            Vector3::new(0.9, 1.0, 0.5),
+            Vector3::new(2.1, 0.0, 0.5),
+            false,
+        );
+        navmesh.build_convex_polys(4);
+        assert_eq!(navmesh.polygons().len(), 2);
+
+        let mut path = Vec::new();
+        assert_eq!(navmesh.build_path(0, 3, &mut path), Ok(PathKind::Full));
+        assert_eq!(path.len(), 4);
+
+        let mut agent = NavmeshAgent::new();
+        agent.set_position(Vector3::new(0.25, 1.0, 0.5));
+        agent.set_target(Vector3::new(2.75, 0.0, 0.5));
+        agent.update(0.0, &navmesh).unwrap();
+        assert!(agent.path().contains(&Vector3::new(0.9, 1.0, 0.5)));
+        assert!(agent.path().contains(&Vector3::new(2.1, 0.0, 0.5)));
+    }
+}
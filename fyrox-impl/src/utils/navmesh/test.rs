@@ -0,0 +1,4311 @@
+// Copyright (c) 2019-present Dmitry Stepanov and Fyrox Engine contributors.
+//
+// Permission is hereby granted, free of charge, to any person obtaining a copy
+// of this software and associated documentation files (the "Software"), to deal
+// in the Software without restriction, including without limitation the rights
+// to use, copy, modify, merge, publish, distribute, sublicense, and/or sell
+// copies of the Software, and to permit persons to whom the Software is
+// furnished to do so, subject to the following conditions:
+//
+// The above copyright notice and this permission notice shall be included in all
+// copies or substantial portions of the Software.
+//
+// THE SOFTWARE IS PROVIDED "AS IS", WITHOUT WARRANTY OF ANY KIND, EXPRESS OR
+// IMPLIED, INCLUDING BUT NOT LIMITED TO THE WARRANTIES OF MERCHANTABILITY,
+// FITNESS FOR A PARTICULAR PURPOSE AND NONINFRINGEMENT. IN NO EVENT SHALL THE
+// AUTHORS OR COPYRIGHT HOLDERS BE LIABLE FOR ANY CLAIM, DAMAGES OR OTHER
+// LIABILITY, WHETHER IN AN ACTION OF CONTRACT, TORT OR OTHERWISE, ARISING FROM,
+// OUT OF OR IN CONNECTION WITH THE SOFTWARE OR THE USE OR OTHER DEALINGS IN THE
+// SOFTWARE.
+
+use crate::{
+    asset::untyped::ResourceKind,
+    core::{
+        algebra::{Matrix4, UnitQuaternion, Vector2, Vector3},
+        math::{aabb::AxisAlignedBoundingBox, TriangleDefinition},
+        pool::Handle,
+        rand::{rngs::StdRng, SeedableRng},
+        reflect::prelude::*,
+        visitor::{Visit, Visitor},
+    },
+    scene::{
+        base::BaseBuilder,
+        debug::SceneDrawingContext,
+        graph::Graph,
+        mesh::{
+            surface::{SurfaceBuilder, SurfaceData, SurfaceResource},
+            MeshBuilder,
+        },
+        transform::TransformBuilder,
+    },
+    utils::{
+        astar::{PathError, PathKind},
+        navmesh::{
+            ring_area_2d, EdgeDirection, MovingObstacle, Navmesh, NavmeshAgent, NavmeshAgentEvent,
+            NavmeshBakeParams, NavmeshDelta, NavmeshDeltaError, NavmeshEdge, NavmeshObstacle,
+            NavmeshPathError, NavmeshQueryFilter, NavmeshSimplifyStats, NavmeshValidationIssue,
+            PathCacheKey, PathStatus, Polygon, PolygonSoupError, SmoothingMode, AGENT_PATH_COLOR,
+            AGENT_RAW_PATH_COLOR, AGENT_TARGET_COLOR, AGENT_TRAVERSED_PATH_COLOR,
+        },
+    },
+};
+
+// A flat strip of 3x1 meters, made of six triangles.
+pub(crate) fn make_strip_navmesh() -> Navmesh {
+    Navmesh::new(
+        vec![
+            TriangleDefinition([0, 1, 3]),
+            TriangleDefinition([1, 2, 3]),
+            TriangleDefinition([2, 5, 3]),
+            TriangleDefinition([2, 4, 5]),
+            TriangleDefinition([4, 7, 5]),
+            TriangleDefinition([4, 6, 7]),
+        ],
+        vec![
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 1.0),
+            Vector3::new(1.0, 0.0, 1.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(2.0, 0.0, 1.0),
+            Vector3::new(2.0, 0.0, 0.0),
+            Vector3::new(3.0, 0.0, 1.0),
+            Vector3::new(3.0, 0.0, 0.0),
+        ],
+    )
+}
+
+// A flat grid of `size` x `size` quads, each quad is made of two triangles. Triangles of a
+// quad at `(x, z)` have indices `2 * (z * size + x)` and `2 * (z * size + x) + 1`.
+pub(crate) fn make_grid_navmesh(size: u32) -> Navmesh {
+    let mut vertices = Vec::new();
+    for z in 0..=size {
+        for x in 0..=size {
+            vertices.push(Vector3::new(x as f32, 0.0, z as f32));
+        }
+    }
+    let mut triangles = Vec::new();
+    for z in 0..size {
+        for x in 0..size {
+            let v00 = z * (size + 1) + x;
+            let v10 = v00 + 1;
+            let v01 = v00 + size + 1;
+            let v11 = v01 + 1;
+            triangles.push(TriangleDefinition([v00, v01, v10]));
+            triangles.push(TriangleDefinition([v01, v11, v10]));
+        }
+    }
+    Navmesh::new(triangles, vertices)
+}
+
+#[test]
+fn test_navmesh() {
+    let navmesh = make_strip_navmesh();
+
+    let mut agent = NavmeshAgent::new();
+
+    agent.set_target(Vector3::new(3.0, 0.0, 1.0));
+    agent.update(1.0 / 60.0, &navmesh).unwrap();
+
+    let graph = &navmesh.graph;
+
+    assert_eq!(graph.vertices.len(), 6);
+    assert_eq!(graph.vertices[0].neighbours[0], 1);
+
+    assert_eq!(graph.vertices[1].neighbours[0], 0);
+    assert_eq!(graph.vertices[1].neighbours[1], 2);
+
+    assert_eq!(graph.vertices[2].neighbours[0], 1);
+    assert_eq!(graph.vertices[2].neighbours[1], 3);
+
+    assert_eq!(graph.vertices[3].neighbours[0], 2);
+    assert_eq!(graph.vertices[3].neighbours[1], 4);
+
+    assert_eq!(graph.vertices[4].neighbours[0], 3);
+    assert_eq!(graph.vertices[4].neighbours[1], 5);
+
+    assert_eq!(graph.vertices[5].neighbours[0], 4);
+
+    assert_eq!(
+        agent.path,
+        vec![
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(3.0, 0.0, 1.0),
+            Vector3::new(3.0, 0.0, 1.0)
+        ]
+    );
+}
+
+#[test]
+fn test_raycast() {
+    let navmesh = make_strip_navmesh();
+
+    // Whole segment lies on the navmesh.
+    assert_eq!(
+        navmesh.raycast(Vector3::new(0.25, 0.0, 0.5), Vector3::new(2.75, 0.0, 0.5)),
+        None
+    );
+
+    // Segment leaves the navmesh through the border edge of the first triangle.
+    let hit = navmesh
+        .raycast(Vector3::new(0.5, 0.0, 0.25), Vector3::new(0.5, 0.0, 2.0))
+        .unwrap();
+    assert_eq!(hit.triangle, 1);
+    assert_eq!(hit.edge, [1, 2]);
+    assert!(hit.position.metric_distance(&Vector3::new(0.5, 0.0, 1.0)) < 1.0e-5);
+
+    // Segment walks through multiple triangles before leaving the navmesh.
+    let hit = navmesh
+        .raycast(Vector3::new(0.5, 0.0, 0.5), Vector3::new(4.0, 0.0, 0.5))
+        .unwrap();
+    assert_eq!(hit.triangle, 5);
+    assert!(hit.position.metric_distance(&Vector3::new(3.0, 0.0, 0.5)) < 1.0e-5);
+}
+
+#[test]
+fn test_nearest_point() {
+    let navmesh = make_strip_navmesh();
+
+    // Slightly above the surface.
+    let (point, _) = navmesh
+        .nearest_point(Vector3::new(1.5, 0.1, 0.5), 0.5)
+        .unwrap();
+    assert!(point.metric_distance(&Vector3::new(1.5, 0.0, 0.5)) < 1.0e-5);
+
+    // Slightly outside of the border edge.
+    let (point, triangle) = navmesh
+        .nearest_point(Vector3::new(0.5, 0.0, 1.2), 0.5)
+        .unwrap();
+    assert!(point.metric_distance(&Vector3::new(0.5, 0.0, 1.0)) < 1.0e-5);
+    assert_eq!(triangle, 1);
+
+    // Too far from the navmesh.
+    assert_eq!(
+        navmesh.nearest_point(Vector3::new(0.5, 0.0, 5.0), 0.5),
+        None
+    );
+}
+
+#[test]
+fn test_nearest_point_filtered() {
+    let mut navmesh = make_strip_navmesh();
+    for triangle in 0..4 {
+        navmesh.set_triangle_cost(triangle, 10.0);
+    }
+    let is_safe = |triangle| navmesh.triangle_cost(triangle) == Some(1.0);
+
+    // The closest safe point is the edge of the closest safe triangle.
+    let (point, triangle) = navmesh
+        .nearest_point_filtered(Vector3::new(0.5, 0.0, 0.5), 5.0, is_safe)
+        .unwrap();
+    assert!(point.metric_distance(&Vector3::new(2.0, 0.0, 0.5)) < 1.0e-5);
+    assert_eq!(triangle, 4);
+
+    // Inside the safe area.
+    let (point, triangle) = navmesh
+        .nearest_point_filtered(Vector3::new(2.9, 0.0, 0.5), 5.0, is_safe)
+        .unwrap();
+    assert!(point.metric_distance(&Vector3::new(2.9, 0.0, 0.5)) < 1.0e-5);
+    assert_eq!(triangle, 5);
+
+    // Safe area is too far.
+    assert_eq!(
+        navmesh.nearest_point_filtered(Vector3::new(0.5, 0.0, 0.5), 1.0, is_safe),
+        None
+    );
+}
+
+#[test]
+fn test_path_smoothing() {
+    let navmesh = make_strip_navmesh();
+
+    let mut agent = NavmeshAgent::new();
+    agent.set_radius(0.0);
+    agent.set_position(Vector3::new(0.25, 0.0, 0.5));
+    agent.set_target(Vector3::new(2.75, 0.0, 0.5));
+    agent.update(1.0 / 60.0, &navmesh).unwrap();
+
+    // Straight corridor must produce a straight path.
+    assert!(agent
+        .path()
+        .iter()
+        .all(|p| (p.z - 0.5).abs() < 1.0e-5 && p.y == 0.0));
+
+    agent.set_path_smoothing(false);
+    agent.update(1.0 / 60.0, &navmesh).unwrap();
+
+    // Unsmoothed path goes through the middle points of the portals.
+    assert_eq!(agent.path().len(), 7);
+    assert!(agent.path().iter().all(|p| p.y == 0.0));
+}
+
+#[test]
+fn test_draw_path() {
+    let navmesh = make_strip_navmesh();
+
+    let mut agent = NavmeshAgent::new();
+    agent.set_radius(0.0);
+    agent.set_speed(1.0);
+    agent.set_position(Vector3::new(0.25, 0.0, 0.5));
+    agent.set_target(Vector3::new(2.75, 0.0, 0.5));
+    agent.update(0.5, &navmesh).unwrap();
+
+    let mut ctx = SceneDrawingContext::default();
+    agent.draw_path(&mut ctx);
+    let line_count = |ctx: &SceneDrawingContext, color| {
+        ctx.lines.iter().filter(|line| line.color == color).count()
+    };
+    assert!(line_count(&ctx, AGENT_PATH_COLOR) > 0);
+    assert!(line_count(&ctx, AGENT_TRAVERSED_PATH_COLOR) > 0);
+    assert_eq!(line_count(&ctx, AGENT_TARGET_COLOR), 1);
+    assert_eq!(line_count(&ctx, AGENT_RAW_PATH_COLOR), 0);
+
+    // Raw path goes through the middle points of the portals.
+    let mut ctx = SceneDrawingContext::default();
+    agent.draw_raw_path(&navmesh, &mut ctx);
+    assert_eq!(line_count(&ctx, AGENT_RAW_PATH_COLOR), 6);
+}
+
+#[test]
+fn test_stuck_detection() {
+    let navmesh = make_strip_navmesh();
+
+    let mut agent = NavmeshAgent::new();
+    agent.set_radius(0.0);
+    agent.set_speed(1.0);
+    agent.set_stuck_detection(true, 0.5, 0.1);
+    assert!(agent.is_stuck_detection_enabled());
+    agent.set_position(Vector3::new(0.25, 0.0, 0.5));
+    agent.set_target(Vector3::new(2.75, 0.0, 0.5));
+    for _ in 0..10 {
+        agent.update(0.1, &navmesh).unwrap();
+    }
+    assert!(!agent.is_stuck());
+
+    // The agent does not move, but still has some path to walk.
+    agent.set_speed(0.0);
+    for _ in 0..10 {
+        agent.update(0.1, &navmesh).unwrap();
+    }
+    assert!(agent.is_stuck());
+    let events = std::iter::from_fn(|| agent.pop_event()).collect::<Vec<_>>();
+    // The path must be recalculated after the agent got stuck.
+    assert!(events
+        .iter()
+        .skip_while(|event| **event != NavmeshAgentEvent::Stuck)
+        .any(|event| *event == NavmeshAgentEvent::PathRecalculated));
+
+    // The agent is free to go.
+    agent.set_speed(1.0);
+    for _ in 0..5 {
+        agent.update(0.1, &navmesh).unwrap();
+    }
+    assert!(!agent.is_stuck());
+    for _ in 0..50 {
+        agent.update(0.1, &navmesh).unwrap();
+    }
+    assert!(agent.target_reached());
+    assert!(!agent.is_stuck());
+
+    // Disabled detection never flags the agent.
+    agent.set_stuck_detection(false, 0.5, 0.1);
+    agent.set_speed(0.0);
+    agent.set_target(Vector3::new(0.25, 0.0, 0.5));
+    for _ in 0..20 {
+        agent.update(0.1, &navmesh).unwrap();
+    }
+    assert!(!agent.is_stuck());
+}
+
+#[test]
+fn test_transform_vertices() {
+    let mut navmesh = make_strip_navmesh();
+    let neighbours = |navmesh: &Navmesh| {
+        navmesh
+            .graph
+            .vertices
+            .iter()
+            .map(|vertex| vertex.neighbours.clone())
+            .collect::<Vec<_>>()
+    };
+    let adjacency = neighbours(&navmesh);
+    let revision = navmesh.revision();
+    let topology_revision = navmesh.topology_revision();
+    assert_eq!(
+        navmesh.triangle_at(Vector3::new(2.9, 0.0, 0.5), 0.05),
+        Some(5)
+    );
+
+    // Lift the far end of the strip, repeated and invalid indices are ignored.
+    navmesh.transform_vertices(
+        &[6, 7, 7, 100],
+        &Matrix4::new_translation(&Vector3::new(0.0, 1.0, 0.0)),
+    );
+    assert_eq!(navmesh.vertices()[6], Vector3::new(3.0, 1.0, 1.0));
+    assert_eq!(navmesh.vertices()[7], Vector3::new(3.0, 1.0, 0.0));
+    assert_eq!(navmesh.vertices()[5], Vector3::new(2.0, 0.0, 0.0));
+    assert_eq!(neighbours(&navmesh), adjacency);
+    // The paths stay valid.
+    assert_eq!(navmesh.revision(), revision);
+    assert_eq!(navmesh.topology_revision(), topology_revision);
+    assert_eq!(
+        navmesh.graph.vertices[5].position,
+        navmesh.triangle_centroid(5).unwrap()
+    );
+    assert_eq!(
+        navmesh.triangle_at(Vector3::new(2.9, 0.9, 0.5), 0.05),
+        Some(5)
+    );
+    let (point, triangle) = navmesh
+        .ray_cast(crate::core::math::ray::Ray::from_two_points(
+            Vector3::new(2.9, 5.0, 0.5),
+            Vector3::new(2.9, -5.0, 0.5),
+        ))
+        .unwrap();
+    assert_eq!(triangle, 5);
+    assert!(point.metric_distance(&Vector3::new(2.9, 0.9, 0.5)) < 1.0e-4);
+
+    // Stretch the strip beyond the bounds of the acceleration grid.
+    navmesh.transform_vertices(
+        &[6, 7],
+        &Matrix4::new_translation(&Vector3::new(2.0, 0.0, 0.0)),
+    );
+    assert_eq!(
+        navmesh.triangle_at(Vector3::new(4.5, 0.85, 0.5), 0.05),
+        Some(5)
+    );
+    assert_eq!(
+        navmesh.triangle_at(Vector3::new(2.9, 0.3, 0.9), 0.05),
+        Some(5)
+    );
+
+    // Whole navmesh with an off-mesh link.
+    navmesh.add_offmesh_link(
+        Vector3::new(0.5, 0.0, 0.5),
+        Vector3::new(2.5, 0.5, 0.5),
+        true,
+    );
+    let adjacency = neighbours(&navmesh);
+    navmesh.transform(&Matrix4::new_translation(&Vector3::new(10.0, 0.0, 0.0)));
+    assert_eq!(neighbours(&navmesh), adjacency);
+    assert_eq!(navmesh.offmesh_link_traversals.len(), 2);
+    for ((from, _), traversals) in navmesh.offmesh_link_traversals.iter() {
+        let expected = if *from == 0 || *from == 1 { 10.5 } else { 12.5 };
+        assert_eq!(traversals[0].enter.x, expected);
+    }
+    assert_eq!(
+        navmesh.triangle_at(Vector3::new(12.9, 0.3, 0.9), 0.05),
+        Some(5)
+    );
+}
+
+#[test]
+fn test_agent_on_moving_navmesh() {
+    let mut navmesh = make_strip_navmesh();
+
+    let mut agent = NavmeshAgent::new();
+    agent.set_radius(0.0);
+    agent.set_speed(1.0);
+    agent.set_position(Vector3::new(0.25, 0.0, 0.5));
+    agent.set_target(Vector3::new(2.75, 0.0, 0.5));
+    for _ in 0..10 {
+        agent.update(0.1, &navmesh).unwrap();
+    }
+
+    // Raise the whole strip like an elevator, the agent must follow the new surface without
+    // recalculating its path.
+    agent.set_clamp_to_surface(true);
+    let path = agent.path().to_vec();
+    let all = (0..navmesh.vertices().len()).collect::<Vec<_>>();
+    navmesh.transform_vertices(
+        &all,
+        &Matrix4::new_translation(&Vector3::new(0.0, 1.0, 0.0)),
+    );
+    for _ in 0..100 {
+        agent.update(0.1, &navmesh).unwrap();
+    }
+    assert_eq!(agent.path(), path.as_slice());
+    assert!(
+        agent
+            .position()
+            .metric_distance(&Vector3::new(2.75, 1.0, 0.5))
+            < 1.0e-4
+    );
+}
+
+#[test]
+fn test_look_ahead() {
+    let navmesh = make_strip_navmesh();
+
+    let mut agent = NavmeshAgent::new();
+    agent.set_radius(0.0);
+    agent.set_speed(1.0);
+    agent.set_position(Vector3::new(0.25, 0.0, 0.5));
+    agent.set_target(Vector3::new(2.75, 0.0, 0.5));
+    assert_eq!(agent.look_ahead(1.0), agent.target());
+
+    agent.update(0.0, &navmesh).unwrap();
+    assert!(
+        agent
+            .look_ahead(1.0)
+            .metric_distance(&Vector3::new(1.25, 0.0, 0.5))
+            < 1.0e-5
+    );
+    assert_eq!(agent.look_ahead(0.0), agent.position());
+    assert_eq!(agent.look_ahead(100.0), *agent.path().last().unwrap());
+
+    for _ in 0..100 {
+        agent.update(0.1, &navmesh).unwrap();
+    }
+    assert!(agent.target_reached());
+    assert_eq!(agent.look_ahead(1.0), *agent.path().last().unwrap());
+}
+
+#[test]
+fn test_current_waypoint_index() {
+    let navmesh = make_strip_navmesh();
+
+    let mut agent = NavmeshAgent::new();
+    agent.set_radius(0.0);
+    agent.set_speed(1.0);
+    agent.set_path_smoothing(false);
+    agent.set_position(Vector3::new(0.25, 0.0, 0.5));
+    agent.set_target(Vector3::new(2.75, 0.0, 0.5));
+    assert_eq!(agent.current_waypoint_index(), 0);
+
+    agent.update(0.1, &navmesh).unwrap();
+    assert!(agent.path().len() > 2);
+    assert_eq!(agent.current_waypoint_index(), 0);
+
+    for _ in 0..100 {
+        agent.update(0.1, &navmesh).unwrap();
+    }
+    assert_eq!(agent.current_waypoint_index(), agent.path().len() - 1);
+}
+
+#[test]
+fn test_find_path_to_nearest() {
+    let mut navmesh = make_grid_navmesh(4);
+    let from = Vector3::new(0.4, 0.0, 0.3);
+    assert_eq!(navmesh.find_path_to_nearest(from, &[]), None);
+
+    let goals = [
+        Vector3::new(3.5, 0.0, 3.5),
+        Vector3::new(0.5, 0.0, 2.5),
+        Vector3::new(3.5, 0.0, 0.5),
+    ];
+    let (index, path) = navmesh.find_path_to_nearest(from, &goals).unwrap();
+    assert_eq!(index, 1);
+    assert_eq!(path.first(), Some(&from));
+    assert_eq!(path.last(), Some(&goals[1]));
+
+    // The goal in the same triangle.
+    let (index, path) = navmesh
+        .find_path_to_nearest(from, &[goals[0], Vector3::new(0.3, 0.0, 0.4)])
+        .unwrap();
+    assert_eq!(index, 1);
+    assert_eq!(path.len(), 2);
+
+    // The nearest goal is on an unreachable island.
+    let mut ctx = navmesh.modify();
+    let a = ctx.add_vertex(Vector3::new(0.0, 0.0, -2.0));
+    let b = ctx.add_vertex(Vector3::new(0.0, 0.0, -1.0));
+    let c = ctx.add_vertex(Vector3::new(1.0, 0.0, -2.0));
+    ctx.add_triangle(TriangleDefinition([a, b, c]));
+    drop(ctx);
+    let (index, _) = navmesh
+        .find_path_to_nearest(from, &[Vector3::new(0.2, 0.0, -1.8), goals[2]])
+        .unwrap();
+    assert_eq!(index, 1);
+    assert_eq!(
+        navmesh.find_path_to_nearest(from, &[Vector3::new(0.2, 0.0, -1.8)]),
+        None
+    );
+}
+
+#[test]
+fn test_find_corridor() {
+    let navmesh = make_grid_navmesh(3);
+    let from = Vector3::new(0.2, 0.0, 0.3);
+    let to = Vector3::new(2.7, 0.0, 2.2);
+
+    let corridor = navmesh.find_corridor(from, to).unwrap();
+    assert_eq!(corridor[0], navmesh.triangle_at(from, 0.1).unwrap());
+    assert_eq!(
+        *corridor.last().unwrap(),
+        navmesh.triangle_at(to, 0.1).unwrap()
+    );
+    for pair in corridor.windows(2) {
+        assert!(navmesh.graph.vertices[pair[0]]
+            .neighbours
+            .contains(&(pair[1] as u32)));
+    }
+
+    let path = navmesh.string_pull(&corridor, from, to);
+    assert_eq!(path.first(), Some(&from));
+    assert_eq!(path.last(), Some(&to));
+
+    let mut agent = NavmeshAgent::new();
+    agent.set_position(from);
+    agent.set_target(to);
+    agent.update(0.0, &navmesh).unwrap();
+    assert_eq!(agent.corridor(), corridor.as_slice());
+
+    // Same triangle.
+    assert_eq!(
+        navmesh.find_corridor(from, Vector3::new(0.3, 0.0, 0.2)),
+        Some(vec![corridor[0]])
+    );
+
+    // The filter excludes the end triangle.
+    let mut navmesh = navmesh;
+    let end = *corridor.last().unwrap();
+    navmesh.set_triangle_flags(end, 1);
+    let filter = NavmeshQueryFilter {
+        exclude_flags: 1,
+        ..Default::default()
+    };
+    assert_eq!(
+        navmesh.try_find_corridor(from, to, 0.0, &filter),
+        Err(NavmeshPathError::EndOffMesh)
+    );
+    navmesh.set_triangle_flags(end, 0);
+
+    // Unreachable island.
+    let mut ctx = navmesh.modify();
+    let a = ctx.add_vertex(Vector3::new(5.0, 0.0, 5.0));
+    let b = ctx.add_vertex(Vector3::new(5.0, 0.0, 6.0));
+    let c = ctx.add_vertex(Vector3::new(6.0, 0.0, 5.0));
+    ctx.add_triangle(TriangleDefinition([a, b, c]));
+    drop(ctx);
+    assert_eq!(
+        navmesh.find_corridor(from, Vector3::new(5.2, 0.0, 5.2)),
+        None
+    );
+}
+
+#[test]
+fn test_weld() {
+    // Two quads with duplicated vertices along the seam at x = 1.
+    let triangles = vec![
+        TriangleDefinition([0, 1, 3]),
+        TriangleDefinition([1, 2, 3]),
+        TriangleDefinition([4, 5, 7]),
+        TriangleDefinition([5, 6, 7]),
+        // Collapses after welding.
+        TriangleDefinition([3, 4, 2]),
+    ];
+    let vertices = vec![
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.0, 0.0, 1.0),
+        Vector3::new(1.0, 0.0, 1.0),
+        Vector3::new(1.0, 0.0, 0.0),
+        Vector3::new(1.0001, 0.0, 0.0),
+        Vector3::new(1.0001, 0.0, 1.0),
+        Vector3::new(2.0, 0.0, 1.0),
+        Vector3::new(2.0, 0.0, 0.0),
+    ];
+
+    let navmesh = Navmesh::new(triangles[..4].to_vec(), vertices.clone());
+    assert_eq!(navmesh.connected_components().len(), 2);
+
+    let mut navmesh = Navmesh::new_welded(triangles.clone(), vertices.clone(), 0.001);
+    assert_eq!(navmesh.vertices().len(), 6);
+    assert_eq!(navmesh.triangles().len(), 4);
+    assert_eq!(navmesh.connected_components().len(), 1);
+    assert_eq!(navmesh.weld(0.001), 0);
+
+    // The tolerance is too small.
+    let navmesh = Navmesh::new_welded(triangles[..4].to_vec(), vertices.clone(), 0.0);
+    assert_eq!(navmesh.vertices().len(), 8);
+    assert_eq!(navmesh.connected_components().len(), 2);
+
+    // Welding keeps the attributes of the triangles.
+    let mut navmesh = Navmesh::new(triangles, vertices);
+    navmesh.set_triangle_cost(2, 5.0);
+    navmesh.set_triangle_flags(3, 7);
+    assert_eq!(navmesh.weld(0.001), 2);
+    assert_eq!(navmesh.triangle_cost(2), Some(5.0));
+    assert_eq!(navmesh.triangle_flags(3), Some(7));
+    let mut path = Vec::new();
+    assert_eq!(navmesh.build_path(0, 3, &mut path), Ok(PathKind::Full));
+}
+
+#[test]
+fn test_border_distance() {
+    let mut navmesh = make_grid_navmesh(3);
+    assert_eq!(navmesh.border_distance(Vector3::new(1.5, 0.0, 1.5)), 1.5);
+    // Height is ignored.
+    assert_eq!(navmesh.border_distance(Vector3::new(0.25, 5.0, 1.5)), 0.25);
+    // Points outside of the navmesh are measured to the closest border edge.
+    assert_eq!(navmesh.border_distance(Vector3::new(-2.0, 0.0, 1.5)), 2.0);
+    assert_eq!(navmesh.vertex_clearance(0), Some(0.0));
+    assert_eq!(navmesh.vertex_clearance(5), Some(1.0));
+    assert_eq!(navmesh.vertex_clearance(100), None);
+
+    // Cut a hole in the middle, the clearance must be updated.
+    {
+        let mut ctx = navmesh.modify();
+        ctx.remove_triangle(9);
+        ctx.remove_triangle(8);
+    }
+    assert_eq!(navmesh.border_distance(Vector3::new(1.5, 0.0, 1.5)), 0.5);
+    assert_eq!(navmesh.vertex_clearance(5), Some(0.0));
+
+    assert_eq!(
+        Navmesh::default().border_distance(Vector3::default()),
+        f32::MAX
+    );
+}
+
+#[test]
+fn test_can_walk_straight() {
+    let navmesh = make_strip_navmesh();
+    let from = Vector3::new(0.5, 0.0, 0.5);
+    assert!(navmesh.can_walk_straight(from, Vector3::new(2.5, 0.0, 0.5), 0.0));
+    assert!(navmesh.can_walk_straight(from, Vector3::new(2.5, 0.0, 0.5), 0.4));
+    // The disc does not fit between the borders.
+    assert!(!navmesh.can_walk_straight(from, Vector3::new(2.5, 0.0, 0.5), 0.6));
+    // The end point is too close to the border.
+    assert!(!navmesh.can_walk_straight(from, Vector3::new(2.9, 0.0, 0.5), 0.2));
+    // The segment leaves the navmesh.
+    assert!(!navmesh.can_walk_straight(from, Vector3::new(3.5, 0.0, 0.5), 0.0));
+    assert!(!Navmesh::default().can_walk_straight(from, from, 0.0));
+
+    // A hole in the middle of the grid.
+    let mut navmesh = make_grid_navmesh(3);
+    {
+        let mut ctx = navmesh.modify();
+        ctx.remove_triangle(9);
+        ctx.remove_triangle(8);
+    }
+    let (from, to) = (Vector3::new(0.5, 0.0, 0.9), Vector3::new(2.5, 0.0, 0.9));
+    assert!(navmesh.can_walk_straight(from, to, 0.05));
+    assert!(!navmesh.can_walk_straight(from, to, 0.2));
+    let (from, to) = (Vector3::new(0.5, 0.0, 1.5), Vector3::new(2.5, 0.0, 1.5));
+    assert!(!navmesh.can_walk_straight(from, to, 0.0));
+
+    // Blocked triangles, filtered triangles and one-way edges stop the segment.
+    let mut navmesh = make_strip_navmesh();
+    let (from, to) = (Vector3::new(0.5, 0.0, 0.5), Vector3::new(2.5, 0.0, 0.5));
+    let obstacle = navmesh.add_obstacle(NavmeshObstacle::Cylinder {
+        center: Vector3::new(1.5, 0.0, 0.5),
+        radius: 0.1,
+        height: 1.0,
+    });
+    assert!(navmesh.raycast(from, to).is_none());
+    assert!(!navmesh.can_walk_straight(from, to, 0.0));
+    navmesh.remove_obstacle(obstacle);
+    assert!(navmesh.can_walk_straight(from, to, 0.0));
+
+    navmesh.set_triangle_flags(2, 0b1);
+    navmesh.set_triangle_flags(3, 0b1);
+    let filter = NavmeshQueryFilter {
+        exclude_flags: 0b1,
+        ..Default::default()
+    };
+    assert!(navmesh.can_walk_straight(from, to, 0.0));
+    assert!(!navmesh.can_walk_straight_filtered(from, to, 0.0, &filter));
+    assert!(!navmesh.can_walk_straight_filtered(to, from, 0.0, &filter));
+    navmesh.set_triangle_flags(2, 0);
+    navmesh.set_triangle_flags(3, 0);
+
+    assert!(navmesh.set_edge_direction(3, 4, EdgeDirection::Backward));
+    assert!(!navmesh.can_walk_straight(from, to, 0.0));
+    assert!(navmesh.can_walk_straight(to, from, 0.0));
+}
+
+#[test]
+fn test_area_mapping() {
+    const LAVA: u32 = 4;
+    const ROAD: u32 = 1;
+
+    let mut navmesh = make_grid_navmesh(4);
+    navmesh.set_triangle_flags(31, 8);
+    let regions = [
+        (
+            AxisAlignedBoundingBox::from_min_max(
+                Vector3::new(0.1, -1.0, 0.1),
+                Vector3::new(1.9, 1.0, 1.9),
+            ),
+            LAVA,
+        ),
+        (
+            AxisAlignedBoundingBox::from_min_max(
+                Vector3::new(0.1, -1.0, 0.1),
+                Vector3::new(3.9, 1.0, 2.9),
+            ),
+            ROAD,
+        ),
+    ];
+    assert_eq!(navmesh.apply_area_mapping(&regions), 24);
+    fn count(navmesh: &Navmesh, flags: u32) -> usize {
+        (0..navmesh.triangles().len())
+            .filter(|i| navmesh.triangle_flags(*i) == Some(flags))
+            .count()
+    }
+    // The first region has the highest priority.
+    assert_eq!(count(&navmesh, LAVA), 8);
+    assert_eq!(count(&navmesh, ROAD), 16);
+    assert_eq!(navmesh.triangle_flags(31), Some(8));
+
+    // Applying the same regions again does not change anything.
+    let revision = navmesh.revision();
+    assert_eq!(navmesh.apply_area_mapping(&regions), 24);
+    assert_eq!(navmesh.revision(), revision);
+    assert_eq!(count(&navmesh, LAVA), 8);
+    assert_eq!(count(&navmesh, ROAD), 16);
+
+    // The bits, that are not used by the regions, are kept.
+    navmesh.set_triangle_flags(0, LAVA | 8);
+    assert_eq!(navmesh.apply_area_mapping(&regions), 24);
+    assert_eq!(navmesh.triangle_flags(0), Some(LAVA | 8));
+
+    // The triangles, that left the moved region, do not keep stale flags.
+    let mut moved = regions;
+    moved[0].0 = AxisAlignedBoundingBox::from_min_max(
+        Vector3::new(2.1, -1.0, 0.1),
+        Vector3::new(3.9, 1.0, 1.9),
+    );
+    assert_eq!(navmesh.apply_area_mapping(&moved), 24);
+    assert_eq!(navmesh.triangle_flags(0), Some(ROAD | 8));
+    assert_eq!(navmesh.triangle_flags(4), Some(LAVA));
+    assert_eq!(count(&navmesh, LAVA), 8);
+    assert_eq!(count(&navmesh, ROAD), 15);
+    assert_eq!(navmesh.triangle_flags(31), Some(8));
+}
+
+#[test]
+fn test_bounding_box_and_aabb_query() {
+    let navmesh = make_grid_navmesh(3);
+    let bounding_box = navmesh.bounding_box();
+    assert_eq!(bounding_box.min, Vector3::new(0.0, 0.0, 0.0));
+    assert_eq!(bounding_box.max, Vector3::new(3.0, 0.0, 3.0));
+    assert!(!Navmesh::default().bounding_box().is_valid());
+
+    let query = |min: Vector3<f32>, max: Vector3<f32>| {
+        navmesh.triangles_in_aabb(&AxisAlignedBoundingBox::from_min_max(min, max))
+    };
+    // Only the first triangle of the first quad touches the corner of the navmesh.
+    assert_eq!(
+        query(Vector3::new(0.05, -1.0, 0.05), Vector3::new(0.2, 1.0, 0.2)),
+        vec![0]
+    );
+    assert_eq!(
+        query(Vector3::new(1.4, -1.0, 1.4), Vector3::new(1.6, 1.0, 1.6)),
+        vec![8, 9]
+    );
+    assert_eq!(
+        query(Vector3::new(-1.0, -1.0, -1.0), Vector3::new(4.0, 1.0, 4.0)),
+        (0..18).collect::<Vec<_>>()
+    );
+    // The box is above the navmesh.
+    assert!(query(Vector3::new(0.0, 1.0, 0.0), Vector3::new(3.0, 2.0, 3.0)).is_empty());
+}
+
+#[test]
+fn test_triangle_centroid_and_normal() {
+    let mut navmesh = make_grid_navmesh(2);
+    assert_eq!(
+        navmesh.triangle_centroid(0),
+        Some(Vector3::new(1.0 / 3.0, 0.0, 1.0 / 3.0))
+    );
+    assert_eq!(navmesh.triangle_centroid(100), None);
+    assert_eq!(navmesh.triangle_normal(100), None);
+    assert_eq!(navmesh.triangle_normals().count(), 8);
+    assert!(navmesh.triangle_normals().all(|n| n == Vector3::y()));
+
+    // Flipped triangle has the opposite normal.
+    navmesh.triangles[0] = TriangleDefinition([0, 1, 3]);
+    assert_eq!(navmesh.triangle_normal(0), Some(-Vector3::y()));
+
+    // A slope rising along X.
+    navmesh.vertices[1].y = 1.0;
+    navmesh.vertices[4].y = 1.0;
+    navmesh.vertices[7].y = 1.0;
+    let normal = navmesh.triangle_normal(1).unwrap();
+    assert!((normal.norm() - 1.0).abs() < 1.0e-5);
+    assert!(normal.x < 0.0 && normal.y > 0.0);
+}
+
+#[test]
+fn test_area() {
+    let mut navmesh = make_grid_navmesh(4);
+    assert_eq!(navmesh.triangle_area(0), Some(0.5));
+    assert_eq!(navmesh.triangle_area(100), None);
+    assert_eq!(navmesh.total_area(), 16.0);
+    assert_eq!(navmesh.component_areas(), vec![16.0]);
+
+    // A separate island with a degenerate triangle.
+    let island = Navmesh::new(
+        vec![
+            TriangleDefinition([0, 1, 2]),
+            TriangleDefinition([0, 2, 3]),
+            TriangleDefinition([1, 4, 2]),
+        ],
+        vec![
+            Vector3::new(10.0, 0.0, 10.0),
+            Vector3::new(10.0, 0.0, 12.0),
+            Vector3::new(11.0, 0.0, 10.0),
+            Vector3::new(11.0, 0.0, 9.0),
+            Vector3::new(10.5, 0.0, 11.0),
+        ],
+    );
+    assert_eq!(island.triangle_area(2), Some(0.0));
+    navmesh.append(&island, 0.0);
+    assert_eq!(navmesh.total_area(), 17.5);
+    let components = navmesh.connected_components();
+    let areas = navmesh.component_areas();
+    assert_eq!(areas.len(), components.len());
+    for (component, area) in components.iter().zip(areas) {
+        if component.contains(&0) {
+            assert_eq!(area, 16.0);
+        } else {
+            assert!(area <= 1.5);
+        }
+    }
+}
+
+#[test]
+fn test_project_down() {
+    // Two floors, the upper one covers only half of the lower one.
+    let mut navmesh = make_grid_navmesh(2);
+    let mut upper = make_grid_navmesh(1);
+    upper.transform(&Matrix4::new_translation(&Vector3::new(0.0, 3.0, 0.0)));
+    navmesh.append(&upper, 0.0);
+
+    assert_eq!(
+        navmesh.project_down(Vector3::new(0.5, 5.0, 0.5), 10.0),
+        Some(Vector3::new(0.5, 3.0, 0.5))
+    );
+    // Below the upper floor.
+    assert_eq!(
+        navmesh.project_down(Vector3::new(0.5, 2.0, 0.5), 10.0),
+        Some(Vector3::new(0.5, 0.0, 0.5))
+    );
+    // Exactly on the surface.
+    assert_eq!(
+        navmesh.project_down(Vector3::new(0.5, 3.0, 0.5), 0.0),
+        Some(Vector3::new(0.5, 3.0, 0.5))
+    );
+    // Only the lower floor is there.
+    assert_eq!(
+        navmesh.project_down(Vector3::new(1.5, 5.0, 1.5), 10.0),
+        Some(Vector3::new(1.5, 0.0, 1.5))
+    );
+    // Too far.
+    assert_eq!(navmesh.project_down(Vector3::new(1.5, 5.0, 1.5), 1.0), None);
+    // Outside of the navmesh or under it.
+    assert_eq!(
+        navmesh.project_down(Vector3::new(5.0, 5.0, 5.0), 10.0),
+        None
+    );
+    assert_eq!(
+        navmesh.project_down(Vector3::new(1.5, -1.0, 1.5), 10.0),
+        None
+    );
+}
+
+#[test]
+fn test_random_point() {
+    let mut rng = StdRng::seed_from_u64(123);
+
+    assert_eq!(Navmesh::default().random_point(&mut rng), None);
+
+    let navmesh = make_grid_navmesh(4);
+    let mut histogram = [0; 4];
+    for _ in 0..4000 {
+        let point = navmesh.random_point(&mut rng).unwrap();
+        assert!((0.0..=4.0).contains(&point.x) && (0.0..=4.0).contains(&point.z));
+        let quadrant = (point.x >= 2.0) as usize + 2 * (point.z >= 2.0) as usize;
+        histogram[quadrant] += 1;
+    }
+    // Roughly uniform distribution.
+    assert!(histogram.iter().all(|count| (800..1200).contains(count)));
+
+    let center = Vector3::new(1.0, 0.0, 3.0);
+    for _ in 0..100 {
+        let point = navmesh
+            .random_point_in_radius(center, 0.5, &mut rng)
+            .unwrap();
+        assert!(point.metric_distance(&center) <= 0.5);
+    }
+    assert_eq!(
+        navmesh.random_point_in_radius(Vector3::new(10.0, 0.0, 10.0), 1.0, &mut rng),
+        None
+    );
+
+    // A small sphere inside of a huge triangle.
+    let navmesh = Navmesh::new(
+        vec![TriangleDefinition([0, 1, 2])],
+        vec![
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 1000.0),
+            Vector3::new(1000.0, 0.0, 0.0),
+        ],
+    );
+    let center = Vector3::new(100.0, 0.2, 100.0);
+    let mut histogram = [0; 2];
+    for _ in 0..1000 {
+        let point = navmesh
+            .random_point_in_radius(center, 0.5, &mut rng)
+            .unwrap();
+        assert!(point.metric_distance(&center) <= 0.5);
+        histogram[(point.x >= center.x) as usize] += 1;
+    }
+    assert!(histogram.iter().all(|count| (400..600).contains(count)));
+}
+
+#[test]
+fn test_validate() {
+    let navmesh = make_grid_navmesh(2);
+    assert!(navmesh.is_valid());
+
+    let mut navmesh = make_strip_navmesh();
+    assert!(navmesh.is_valid());
+
+    // Bypass the modification context to avoid rebuilding the internal data from invalid
+    // triangles.
+    navmesh.vertices.push(Vector3::new(10.0, 0.0, 10.0));
+    navmesh.vertices.push(Vector3::new(0.0, 0.0, 0.5));
+    let vertex_count = navmesh.vertices.len() as u32;
+    let first = navmesh.triangles[0];
+    let flipped = TriangleDefinition([first[0], first[2], first[1]]);
+    navmesh.triangles.extend([
+        TriangleDefinition([0, 1, 100]),
+        TriangleDefinition([0, 1, vertex_count - 1]),
+        first,
+        flipped,
+    ]);
+
+    let issues = navmesh.validate();
+    let triangle_count = navmesh.triangles.len();
+    assert!(issues.contains(&NavmeshValidationIssue::InvalidIndex {
+        triangle: triangle_count - 4,
+        index: 100,
+    }));
+    assert!(
+        issues.contains(&NavmeshValidationIssue::DegenerateTriangle {
+            triangle: triangle_count - 3,
+        })
+    );
+    assert!(issues.contains(&NavmeshValidationIssue::DuplicateTriangle {
+        triangle: triangle_count - 2,
+        original: 0,
+    }));
+    assert!(issues.contains(&NavmeshValidationIssue::DuplicateTriangle {
+        triangle: triangle_count - 1,
+        original: 0,
+    }));
+    assert!(
+        issues.contains(&NavmeshValidationIssue::UnreferencedVertex {
+            vertex: vertex_count as usize - 2,
+        })
+    );
+    assert!(!navmesh.is_valid());
+    assert!(issues.iter().all(|issue| !issue.to_string().is_empty()));
+
+    // An edge shared by three triangles, one of them has inconsistent winding.
+    let navmesh = Navmesh::new(
+        vec![
+            TriangleDefinition([0, 1, 2]),
+            TriangleDefinition([1, 0, 3]),
+            TriangleDefinition([0, 1, 4]),
+            TriangleDefinition([1, 2, 5]),
+        ],
+        vec![
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(0.5, 0.0, 1.0),
+            Vector3::new(0.5, 0.0, -1.0),
+            Vector3::new(0.5, 1.0, -1.0),
+            Vector3::new(1.5, 1.0, 1.0),
+        ],
+    );
+    assert_eq!(
+        navmesh.validate(),
+        vec![
+            NavmeshValidationIssue::NonManifoldEdge {
+                vertices: [0, 1],
+                triangles: vec![0, 1, 2],
+            },
+            NavmeshValidationIssue::InconsistentWinding {
+                triangle: 3,
+                neighbor: 0,
+            },
+        ]
+    );
+}
+
+#[test]
+fn test_fix_winding() {
+    let original = make_grid_navmesh(4);
+    let mut navmesh = original.clone();
+    assert_eq!(navmesh.fix_winding(), 0);
+
+    for index in [0, 5, 6, 17] {
+        navmesh.triangles[index].0.swap(1, 2);
+    }
+    assert!(!navmesh.is_valid());
+    assert_eq!(navmesh.fix_winding(), 4);
+    assert!(navmesh.is_valid());
+    assert_eq!(navmesh.triangles, original.triangles);
+
+    // The whole component faces down.
+    for triangle in navmesh.triangles.iter_mut() {
+        triangle.0.swap(1, 2);
+    }
+    assert!(navmesh.is_valid());
+    assert_eq!(navmesh.fix_winding(), 32);
+    assert_eq!(navmesh.triangles, original.triangles);
+
+    // The triangles are turned to face the up axis of the navmesh.
+    let rotation = UnitQuaternion::from_axis_angle(&Vector3::x_axis(), 2.0);
+    for vertex in navmesh.modify().vertices_mut() {
+        *vertex = rotation * *vertex;
+    }
+    navmesh.set_up_axis(rotation * Vector3::y());
+    assert_eq!(navmesh.fix_winding(), 0);
+    for triangle in navmesh.triangles.iter_mut() {
+        triangle.0.swap(1, 2);
+    }
+    assert_eq!(navmesh.fix_winding(), 32);
+    assert_eq!(navmesh.triangles, original.triangles);
+
+    // Sides of a pyramid face different directions, but their winding must be consistent.
+    let mut navmesh = Navmesh::new(
+        vec![
+            TriangleDefinition([0, 3, 1]),
+            TriangleDefinition([1, 3, 2]),
+            TriangleDefinition([0, 3, 2]),
+        ],
+        vec![
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 1.0),
+            Vector3::new(1.0, 0.0, 0.0),
+            Vector3::new(0.2, 1.0, 0.2),
+        ],
+    );
+    assert!(!navmesh.is_valid());
+    assert_eq!(navmesh.fix_winding(), 2);
+    assert!(navmesh.is_valid());
+    for triangle in navmesh.triangles.iter() {
+        let [a, b, c] = triangle.0.map(|index| navmesh.vertices[index as usize]);
+        assert!((b - a).cross(&(c - a)).y > 0.0);
+    }
+}
+
+#[test]
+fn test_find_paths_batch() {
+    fn assert_sync<T: Sync>() {}
+    assert_sync::<Navmesh>();
+
+    let navmesh = make_grid_navmesh(10);
+    let requests = (0..50)
+        .map(|i| {
+            let x = (i % 10) as f32 + 0.5;
+            (Vector3::new(x, 0.0, 0.5), Vector3::new(9.0 - x, 0.0, 8.5))
+        })
+        .collect::<Vec<_>>();
+
+    let paths = navmesh.find_paths_batch(&requests);
+    assert_eq!(paths.len(), requests.len());
+    for ((from, to), path) in requests.iter().zip(&paths) {
+        let mut expected = Vec::new();
+        navmesh
+            .find_path_filtered(*from, *to, &mut expected, &Default::default())
+            .unwrap();
+        assert_eq!(path.as_ref(), Some(&expected));
+    }
+
+    // Nothing could be found on an empty navmesh.
+    let paths = Navmesh::default().find_paths_batch(&requests[..2]);
+    assert_eq!(paths, vec![None, None]);
+}
+
+#[test]
+fn test_path_cache() {
+    let mut navmesh = make_grid_navmesh(10);
+    let (from, to) = (0, 176);
+
+    // Disabled by default.
+    let mut path = Vec::new();
+    navmesh.build_path(from, to, &mut path).unwrap();
+    assert_eq!(navmesh.path_cache_len(), 0);
+
+    navmesh.set_path_cache_capacity(2);
+    assert_eq!(navmesh.path_cache_capacity(), 2);
+    let mut cold = Vec::new();
+    navmesh.build_path(from, to, &mut cold).unwrap();
+    assert_eq!(navmesh.path_cache_len(), 1);
+    let mut warm = Vec::new();
+    navmesh.build_path(from, to, &mut warm).unwrap();
+    assert_eq!(navmesh.path_cache_len(), 1);
+    assert_eq!(cold, warm);
+
+    // Different filters are cached separately.
+    let filter = NavmeshQueryFilter {
+        exclude_flags: 1,
+        ..Default::default()
+    };
+    navmesh
+        .build_path_filtered(from, to, &mut path, &filter)
+        .unwrap();
+    assert_eq!(navmesh.path_cache_len(), 2);
+
+    // The least recently used path is evicted.
+    navmesh.build_path(from, to, &mut path).unwrap();
+    navmesh.build_path(from, 10, &mut path).unwrap();
+    assert_eq!(navmesh.path_cache_len(), 2);
+    {
+        let state = navmesh.path_cache.state.lock();
+        let key = |filter: &NavmeshQueryFilter| PathCacheKey::new(from, to, 0.0, filter);
+        assert!(state.entries.contains_key(&key(&Default::default())));
+        assert!(!state.entries.contains_key(&key(&filter)));
+    }
+
+    // Changes of the navmesh invalidate the cache.
+    let obstacle = navmesh.add_obstacle(NavmeshObstacle::Cylinder {
+        center: Vector3::new(4.5, 0.0, 4.5),
+        radius: 0.25,
+        height: 2.0,
+    });
+    assert_eq!(navmesh.path_cache_len(), 0);
+    navmesh.build_path(from, to, &mut path).unwrap();
+    assert_eq!(navmesh.path_cache_len(), 1);
+    navmesh.remove_obstacle(obstacle);
+    assert_eq!(navmesh.path_cache_len(), 0);
+
+    navmesh.build_path(from, to, &mut path).unwrap();
+    navmesh.set_triangle_cost(0, 2.0);
+    assert_eq!(navmesh.path_cache_len(), 0);
+
+    navmesh.build_path(from, to, &mut path).unwrap();
+    navmesh.clear_path_cache();
+    assert_eq!(navmesh.path_cache_len(), 0);
+
+    navmesh.build_path(from, to, &mut path).unwrap();
+    navmesh.set_path_cache_capacity(0);
+    assert_eq!(navmesh.path_cache_len(), 0);
+    navmesh.build_path(from, to, &mut path).unwrap();
+    assert_eq!(navmesh.path_cache_len(), 0);
+}
+
+#[test]
+fn test_agent_reset() {
+    let navmesh = make_grid_navmesh(10);
+
+    let mut agent = NavmeshAgent::new();
+    agent.set_position(Vector3::new(0.5, 0.0, 0.5));
+    agent.set_target(Vector3::new(8.5, 0.0, 8.5));
+    for _ in 0..5 {
+        agent.update(0.1, &navmesh).unwrap();
+    }
+    assert!(!agent.path().is_empty());
+
+    // The position outside of the navmesh is snapped to its surface.
+    agent.reset(Vector3::new(5.5, 2.0, -1.0), &navmesh);
+    let expected = Vector3::new(5.5, 0.0, 0.0);
+    assert!(agent.position().metric_distance(&expected) < 1.0e-4);
+    assert!(agent.path().is_empty());
+    assert_eq!(agent.current_waypoint_index(), 0);
+
+    // The path starts from the new position.
+    agent.update(0.0, &navmesh).unwrap();
+    assert!(agent.path()[0].metric_distance(&expected) < 1.0e-4);
+    assert!(agent.position().metric_distance(&expected) < 1.0e-4);
+
+    // Repeated resets do not move the agent.
+    for _ in 0..3 {
+        agent.reset(expected, &navmesh);
+        agent.update(0.0, &navmesh).unwrap();
+        assert!(agent.position().metric_distance(&expected) < 1.0e-4);
+    }
+
+    // A pending asynchronous request is cancelled.
+    agent.set_async_path_calculation(true);
+    agent.set_target(Vector3::new(1.5, 0.0, 8.5));
+    agent.update(0.0, &navmesh).unwrap();
+    assert!(agent.is_waiting_for_path());
+    agent.reset(expected, &navmesh);
+    assert!(!agent.is_waiting_for_path());
+    assert_eq!(navmesh.process_path_requests(usize::MAX), 0);
+}
+
+#[test]
+fn test_agent_follow_distance() {
+    let navmesh = make_grid_navmesh(10);
+
+    let mut agent = NavmeshAgent::new();
+    agent.set_follow_distance(2.0);
+    agent.set_position(Vector3::new(0.5, 0.0, 0.5));
+    agent.set_target(Vector3::new(8.5, 0.0, 0.5));
+    agent.update(0.0, &navmesh).unwrap();
+    let standoff = Vector3::new(6.5, 0.0, 0.5);
+    assert!(agent.path().last().unwrap().metric_distance(&standoff) < 1.0e-4);
+
+    for _ in 0..100 {
+        agent.update(0.1, &navmesh).unwrap();
+    }
+    assert!(agent.position().metric_distance(&standoff) < 0.2);
+    assert!(agent.target_reached());
+
+    // The target has moved closer, the agent stays in place.
+    let position = agent.position();
+    agent.set_target(Vector3::new(7.5, 0.0, 0.5));
+    for _ in 0..10 {
+        agent.update(0.1, &navmesh).unwrap();
+        assert_eq!(agent.position(), position);
+    }
+    assert_eq!(agent.path().len(), 1);
+    assert!(agent.target_reached());
+
+    // The target has moved away, the agent follows it.
+    let target = Vector3::new(8.5, 0.0, 8.5);
+    agent.set_target(target);
+    agent.update(0.0, &navmesh).unwrap();
+    let end = *agent.path().last().unwrap();
+    assert!((end.metric_distance(&target) - 2.0).abs() < 1.0e-3);
+    for _ in 0..100 {
+        agent.update(0.1, &navmesh).unwrap();
+    }
+    assert!((agent.position().metric_distance(&target) - 2.0).abs() < 0.2);
+}
+
+#[test]
+fn test_agent_acceleration() {
+    let navmesh = make_strip_navmesh();
+    let end = Vector3::new(2.75, 0.0, 0.5);
+
+    // Instant speed by default.
+    let mut agent = NavmeshAgent::new();
+    agent.set_speed(1.0);
+    agent.set_position(Vector3::new(0.25, 0.0, 0.5));
+    agent.set_target(end);
+    agent.update(0.1, &navmesh).unwrap();
+    assert_eq!(agent.current_speed(), 1.0);
+
+    let mut agent = NavmeshAgent::new();
+    agent.set_speed(1.0);
+    agent.set_acceleration(2.0);
+    agent.set_deceleration(1.0);
+    agent.set_position(Vector3::new(0.25, 0.0, 0.5));
+    agent.set_target(end);
+
+    agent.update(0.1, &navmesh).unwrap();
+    assert!((agent.current_speed() - 0.2).abs() < 1.0e-5);
+    agent.update(0.1, &navmesh).unwrap();
+    assert!((agent.current_speed() - 0.4).abs() < 1.0e-5);
+
+    let mut max_speed = 0.0f32;
+    let mut previous_speed = agent.current_speed();
+    let mut decelerating = false;
+    for _ in 0..200 {
+        agent.update(0.05, &navmesh).unwrap();
+        let speed = agent.current_speed();
+        max_speed = max_speed.max(speed);
+        if speed < previous_speed {
+            decelerating = true;
+        } else if decelerating {
+            // The agent does not speed up again once it started to brake.
+            assert!(speed <= previous_speed + 1.0e-5);
+        }
+        previous_speed = speed;
+        // No overshooting.
+        assert!(agent.position().x <= end.x + 1.0e-5);
+    }
+    assert_eq!(max_speed, 1.0);
+    assert!(decelerating);
+    assert!(agent.target_reached());
+    assert!(agent.position().metric_distance(&end) < 0.01);
+    assert_eq!(agent.current_speed(), 0.0);
+}
+
+#[test]
+fn test_turning_radius() {
+    let navmesh = make_grid_navmesh(10);
+
+    let heading_at = |agent: &mut NavmeshAgent, current: u32, interpolator: f32| {
+        agent.current = current;
+        agent.interpolator = interpolator;
+        agent.update(0.0, &navmesh).unwrap();
+        agent.heading()
+    };
+
+    let mut agent = NavmeshAgent::new();
+    agent.set_clamp_to_surface(false);
+    agent.path = vec![
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(4.0, 0.0, 0.0),
+        Vector3::new(4.0, 0.0, 4.0),
+        Vector3::new(4.0, 0.0, 3.0),
+    ];
+    agent.target = Vector3::new(4.0, 0.0, 3.0);
+    agent.path_dirty = false;
+
+    // The heading snaps at the waypoints without turning radius.
+    assert_eq!(heading_at(&mut agent, 0, 0.9), Vector3::x());
+    assert_eq!(heading_at(&mut agent, 1, 0.1), Vector3::z());
+
+    agent.set_turning_radius(1.0);
+    assert_eq!(agent.turning_radius(), 1.0);
+
+    // Far from the waypoints.
+    assert_eq!(heading_at(&mut agent, 0, 0.5), Vector3::x());
+
+    // The turn begins before the waypoint.
+    let heading = heading_at(&mut agent, 0, 0.9);
+    assert!(heading.x > 0.0 && heading.z > 0.0);
+    assert!((heading.norm() - 1.0).abs() < 1.0e-5);
+    assert!(agent.position().z.abs() < 1.0e-5);
+
+    // Halfway through the turn exactly at the waypoint.
+    let heading = heading_at(&mut agent, 1, 0.0);
+    assert!((heading.x - heading.z).abs() < 1.0e-5);
+
+    // The turn continues after the waypoint.
+    let heading = heading_at(&mut agent, 1, 0.1);
+    assert!(heading.x > 0.0 && heading.z > heading.x);
+    assert!((agent.position().x - 4.0).abs() < 1.0e-5);
+
+    // The hairpin at the end has a short segment, so the turn is tighter.
+    assert_eq!(heading_at(&mut agent, 1, 0.8), Vector3::z());
+    let heading = heading_at(&mut agent, 1, 0.95);
+    assert!(heading.x.abs() > 0.0);
+    assert!((heading.norm() - 1.0).abs() < 1.0e-5);
+    let heading = heading_at(&mut agent, 2, 0.6);
+    assert!(heading.z < -0.99);
+}
+
+#[test]
+fn test_clamp_to_surface() {
+    let navmesh = make_strip_navmesh();
+
+    let mut agent = NavmeshAgent::new();
+    assert!(agent.is_clamped_to_surface());
+    agent.set_position(Vector3::new(0.25, 0.0, 0.5));
+    agent.set_target(Vector3::new(2.75, 0.0, 0.5));
+    agent.update(1.0 / 60.0, &navmesh).unwrap();
+
+    // A path that leaves the navmesh, the agent must stay on the strip.
+    agent.path = vec![Vector3::new(0.25, 0.0, 0.5), Vector3::new(2.75, 0.0, 2.5)];
+    agent.current = 0;
+    agent.interpolator = 0.0;
+    for _ in 0..100 {
+        agent.update(0.1, &navmesh).unwrap();
+        let position = agent.position();
+        assert!(position.z <= 1.0 + 1.0e-5);
+        assert!((0.0..=3.0).contains(&position.x));
+        assert_eq!(position.y, 0.0);
+    }
+
+    agent.set_clamp_to_surface(false);
+    agent.current = 0;
+    agent.interpolator = 0.9;
+    agent.update(0.1, &navmesh).unwrap();
+    assert!(agent.position().z > 1.0);
+}
+
+#[test]
+fn test_agent_follows_slope() {
+    // A strip with a ramp in the middle.
+    let mut navmesh = make_strip_navmesh();
+    for vertex in navmesh.vertices.iter_mut() {
+        vertex.y = (vertex.x - 1.0).clamp(0.0, 1.0);
+    }
+    navmesh.rebuild();
+    let height_at = |x: f32| (x - 1.0).clamp(0.0, 1.0);
+
+    let mut agent = NavmeshAgent::new();
+    agent.set_speed(1.0);
+    agent.set_position(Vector3::new(0.25, 0.0, 0.5));
+    agent.set_target(Vector3::new(2.75, 1.0, 0.5));
+    let ramp_normal = Vector3::new(-1.0, 1.0, 0.0).normalize();
+    assert_eq!(agent.ground_normal(), Vector3::y());
+    for _ in 0..30 {
+        agent.update(0.1, &navmesh).unwrap();
+        let position = agent.position();
+        assert!((position.y - height_at(position.x)).abs() < 1.0e-4);
+        assert!((position.z - 0.5).abs() < 1.0e-4);
+
+        // The normal follows the slope.
+        let normal = agent.ground_normal();
+        if position.x < 0.9 || position.x > 2.1 {
+            assert!(normal.metric_distance(&Vector3::y()) < 1.0e-4);
+        } else if position.x > 1.1 && position.x < 1.9 {
+            assert!(normal.metric_distance(&ramp_normal) < 1.0e-4);
+        }
+    }
+    assert!(agent.position().x > 2.0);
+}
+
+#[test]
+fn test_agent_clearance() {
+    // A ring around a hole, the corridor below the hole is too narrow for the agent, the one
+    // above the hole is wide enough.
+    let xs = [0.0, 1.0, 2.0, 3.0, 4.0, 5.0];
+    let zs = [0.0, 0.5, 1.5, 2.5, 3.5];
+    let mut vertices = Vec::new();
+    for z in zs {
+        for x in xs {
+            vertices.push(Vector3::new(x, 0.0, z));
+        }
+    }
+    let mut triangles = Vec::new();
+    let row = xs.len() as u32;
+    for z in 0..zs.len() as u32 - 1 {
+        for x in 0..row - 1 {
+            if z == 1 && (1..4).contains(&x) {
+                continue;
+            }
+            let v00 = z * row + x;
+            let v10 = v00 + 1;
+            let v01 = v00 + row;
+            let v11 = v01 + 1;
+            triangles.push(TriangleDefinition([v00, v01, v10]));
+            triangles.push(TriangleDefinition([v01, v11, v10]));
+        }
+    }
+    let navmesh = Navmesh::new(triangles, vertices);
+
+    let src = Vector3::new(0.5, 0.0, 1.0);
+    let dest = Vector3::new(4.5, 0.0, 1.0);
+
+    let mut agent = NavmeshAgent::new();
+    agent.set_radius(0.4);
+    assert_eq!(
+        agent.calculate_path(&navmesh, src, dest),
+        Ok(PathKind::Full)
+    );
+    assert!(agent.path().iter().all(|p| p.z >= 0.5));
+
+    // The agent does not fit anywhere, but must still get a valid path.
+    agent.set_radius(2.0);
+    assert_eq!(
+        agent.calculate_path(&navmesh, src, dest),
+        Ok(PathKind::Full)
+    );
+    assert!(agent
+        .path()
+        .iter()
+        .all(|p| p.x.is_finite() && p.y.is_finite() && p.z.is_finite()));
+    assert_eq!(agent.path().last(), Some(&dest));
+}
+
+#[test]
+fn test_string_pull() {
+    let navmesh = make_grid_navmesh(2);
+
+    // Straight corridor.
+    let start = Vector3::new(0.2, 0.0, 0.2);
+    let end = Vector3::new(1.8, 0.0, 1.6);
+    assert_eq!(
+        navmesh.string_pull(&[0, 1, 2, 3, 6, 7], start, end),
+        vec![start, end]
+    );
+
+    // The corridor goes around the middle vertex and doubles back to the adjacent triangle.
+    let start = Vector3::new(0.8, 0.0, 0.8);
+    let end = Vector3::new(0.3, 0.0, 1.2);
+    assert_eq!(
+        navmesh.string_pull(&[1, 2, 3, 6, 5, 4], start, end),
+        vec![start, Vector3::new(1.0, 0.0, 1.0), end]
+    );
+
+    // Single triangle.
+    let start = Vector3::new(0.1, 0.0, 0.1);
+    let end = Vector3::new(0.2, 0.0, 0.3);
+    assert_eq!(navmesh.string_pull(&[0], start, end), vec![start, end]);
+}
+
+#[test]
+fn test_degenerate_portal() {
+    // Two triangles that share just a single vertex.
+    let navmesh = Navmesh::new(
+        vec![TriangleDefinition([0, 1, 2]), TriangleDefinition([2, 3, 4])],
+        vec![
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 1.0),
+            Vector3::new(1.0, 0.0, 1.0),
+            Vector3::new(2.0, 0.0, 1.0),
+            Vector3::new(2.0, 0.0, 2.0),
+        ],
+    );
+
+    let [left, right] = navmesh.portal_points(0, 1);
+    assert_eq!(left, Vector3::new(1.0, 0.0, 1.0));
+    assert_eq!(left, right);
+
+    let mut agent = NavmeshAgent::new();
+    agent.straighten_path(
+        &navmesh,
+        Vector3::new(0.25, 0.0, 0.5),
+        Vector3::new(1.75, 0.0, 1.5),
+        &[0, 1],
+    );
+    assert!(agent
+        .path()
+        .iter()
+        .all(|p| p.x.is_finite() && p.z.is_finite()));
+    assert!(agent.path().contains(&Vector3::new(1.0, 0.0, 1.0)));
+}
+
+#[test]
+fn test_triangle_costs() {
+    let mut navmesh = make_grid_navmesh(3);
+    let mut indices = Vec::new();
+
+    // Path along the bottom row goes through the middle quad.
+    navmesh
+        .graph
+        .build_indexed_path(4, 0, &mut indices)
+        .unwrap();
+    assert!(indices.contains(&2) || indices.contains(&3));
+
+    // Make the middle quad of the bottom row expensive, the path must go around it.
+    navmesh.set_triangle_cost(2, 100.0);
+    navmesh.set_triangle_cost(3, 100.0);
+    assert_eq!(navmesh.triangle_cost(2), Some(100.0));
+    assert_eq!(navmesh.triangle_cost(0), Some(1.0));
+
+    navmesh
+        .graph
+        .build_indexed_path(4, 0, &mut indices)
+        .unwrap();
+    assert!(!indices.contains(&2) && !indices.contains(&3));
+
+    // Costs must survive serialization.
+    let mut visitor = Visitor::new();
+    navmesh.visit("Navmesh", &mut visitor).unwrap();
+    let data = visitor.save_binary_to_vec().unwrap();
+    let mut visitor = Visitor::load_from_memory(&data).unwrap();
+    let mut loaded = Navmesh::default();
+    loaded.visit("Navmesh", &mut visitor).unwrap();
+    assert_eq!(loaded, navmesh);
+    assert_eq!(loaded.graph.vertices[2].g_penalty, 100.0);
+}
+
+#[test]
+fn test_triangle_flags() {
+    const ROAD: u32 = 1 << 0;
+    const HAZARD: u32 = 1 << 1;
+
+    let mut navmesh = make_grid_navmesh(3);
+    assert_eq!(navmesh.triangle_flags(0), Some(0));
+    assert_eq!(navmesh.triangle_flags(100), None);
+
+    // Middle quad is hazardous.
+    navmesh.set_triangle_flags(8, HAZARD);
+    navmesh.set_triangle_flags(9, HAZARD);
+    assert_eq!(navmesh.triangle_flags(8), Some(HAZARD));
+
+    // Empty filter passes everything.
+    let mut path = Vec::new();
+    assert_eq!(navmesh.build_path(8, 0, &mut path), Ok(PathKind::Full));
+    assert_eq!(
+        navmesh.build_path_filtered(0, 17, &mut path, &Default::default()),
+        Ok(PathKind::Full)
+    );
+
+    // Hazardous triangles can't be entered.
+    let avoid_hazard = NavmeshQueryFilter {
+        exclude_flags: HAZARD,
+        ..Default::default()
+    };
+    assert_ne!(
+        navmesh.build_path_filtered(0, 8, &mut path, &avoid_hazard),
+        Ok(PathKind::Full)
+    );
+    assert_eq!(
+        navmesh.build_path_filtered(0, 17, &mut path, &avoid_hazard),
+        Ok(PathKind::Full)
+    );
+
+    // Only the roads could be used.
+    let only_roads = NavmeshQueryFilter {
+        include_flags: ROAD,
+        ..Default::default()
+    };
+    assert_ne!(
+        navmesh.build_path_filtered(0, 17, &mut path, &only_roads),
+        Ok(PathKind::Full)
+    );
+
+    // Agents walk around the hazardous quad.
+    let mut agent = NavmeshAgent::new();
+    agent.set_radius(0.0);
+    agent.set_query_filter(avoid_hazard);
+    assert_eq!(agent.query_filter(), avoid_hazard);
+    agent.set_position(Vector3::new(0.5, 0.0, 0.5));
+    agent.set_target(Vector3::new(2.5, 0.0, 2.5));
+    agent.update(0.0, &navmesh).unwrap();
+    assert!(!agent.path().is_empty());
+    assert!(agent.path().windows(2).all(|segment| {
+        let middle = segment[0].lerp(&segment[1], 0.5);
+        !(middle.x > 1.0 && middle.x < 2.0 && middle.z > 1.0 && middle.z < 2.0)
+    }));
+
+    // Flags must survive serialization.
+    let mut visitor = Visitor::new();
+    navmesh.visit("Navmesh", &mut visitor).unwrap();
+    let data = visitor.save_binary_to_vec().unwrap();
+    let mut visitor = Visitor::load_from_memory(&data).unwrap();
+    let mut loaded = Navmesh::default();
+    loaded.visit("Navmesh", &mut visitor).unwrap();
+    assert_eq!(loaded, navmesh);
+    assert_eq!(loaded.triangle_flags(9), Some(HAZARD));
+}
+
+#[test]
+fn test_triangle_layers() {
+    const GROUND: u32 = 1 << 0;
+    const AIR: u32 = 1 << 1;
+
+    // Two floors, one right above the other.
+    let mut navmesh = make_strip_navmesh();
+    let lower = navmesh.clone();
+    let mut upper = Navmesh::new(
+        lower.triangles.clone(),
+        lower
+            .vertices
+            .iter()
+            .map(|v| v + Vector3::new(0.0, 1.0, 0.0))
+            .collect(),
+    );
+    for index in 0..upper.triangles.len() {
+        upper.set_triangle_layer(index, 1);
+    }
+    navmesh.append(&upper, 0.0);
+    assert_eq!(navmesh.triangle_layer(0), Some(0));
+    assert_eq!(navmesh.triangle_layer(6), Some(1));
+    assert_eq!(navmesh.triangle_layer(100), None);
+    navmesh.set_triangle_layer(0, 32);
+    assert_eq!(navmesh.triangle_layer(0), Some(0));
+
+    // The point is closer to the upper floor, but the lower one is requested.
+    let point = Vector3::new(0.5, 0.9, 0.5);
+    assert!(navmesh.query_closest(point).unwrap().1 >= 6);
+    let (closest, triangle) = navmesh.query_closest_in_layers(point, GROUND).unwrap();
+    assert!(triangle < 6);
+    assert_eq!(closest.y, 0.0);
+    assert!(navmesh.query_closest_in_layers(point, 0).is_none());
+
+    // Every agent stays on its own floor.
+    for (layer_mask, height) in [(GROUND, 0.0), (AIR, 1.0)] {
+        let mut agent = NavmeshAgent::new();
+        agent.set_radius(0.0);
+        agent.set_layer_mask(layer_mask);
+        assert_eq!(agent.layer_mask(), layer_mask);
+        assert_eq!(agent.query_filter().layer_mask, layer_mask);
+        agent.set_position(point);
+        agent.set_target(Vector3::new(2.5, 0.9, 0.5));
+        agent.update(0.0, &navmesh).unwrap();
+        assert!(!agent.path().is_empty());
+        assert!(agent.path().iter().all(|p| p.y == height));
+    }
+
+    // Triangles on other layers can't be entered.
+    navmesh.set_triangle_layer(2, 1);
+    navmesh.set_triangle_layer(3, 1);
+    let mut path = Vec::new();
+    assert_eq!(
+        navmesh.build_path_filtered(0, 5, &mut path, &Default::default()),
+        Ok(PathKind::Full)
+    );
+    let ground = NavmeshQueryFilter {
+        layer_mask: GROUND,
+        ..Default::default()
+    };
+    assert_ne!(
+        navmesh.build_path_filtered(0, 5, &mut path, &ground),
+        Ok(PathKind::Full)
+    );
+
+    // Layers must survive serialization.
+    let mut data = Vec::new();
+    navmesh.save_binary(&mut data).unwrap();
+    let loaded = Navmesh::load_binary(&mut data.as_slice()).unwrap();
+    assert_eq!(loaded, navmesh);
+    assert_eq!(loaded.triangle_layer(3), Some(1));
+}
+
+#[test]
+fn test_query_filter_costs() {
+    const GRASS: u32 = 1 << 3;
+
+    let mut navmesh = make_grid_navmesh(3);
+    // Middle quad of the bottom row is covered with grass.
+    navmesh.set_triangle_flags(2, GRASS);
+    navmesh.set_triangle_flags(3, GRASS);
+
+    let mut filter = NavmeshQueryFilter::default();
+    assert_eq!(filter.cost_multiplier(GRASS | 1), 1.0);
+
+    // Default filter does not change anything.
+    let (from, to) = (Vector3::new(0.5, 0.0, 0.5), Vector3::new(2.5, 0.0, 0.5));
+    let mut path = Vec::new();
+    assert_eq!(
+        navmesh.find_path_filtered(from, to, &mut path, &filter),
+        Ok(PathKind::Full)
+    );
+    assert!(path.iter().all(|p| (p.z - 0.5).abs() < 1.0e-5));
+
+    // Expensive grass must be avoided.
+    filter.set_flag_cost(GRASS, 100.0);
+    assert_eq!(filter.cost_multiplier(GRASS | 1), 100.0);
+    assert_eq!(
+        navmesh.find_path_filtered(from, to, &mut path, &filter),
+        Ok(PathKind::Full)
+    );
+    assert!(path.iter().any(|p| p.z >= 1.0 - 1.0e-5));
+    assert!(path.first().unwrap().metric_distance(&from) < 1.0e-5);
+    assert!(path.last().unwrap().metric_distance(&to) < 1.0e-5);
+}
+
+#[test]
+fn test_rebuild_tile() {
+    let mut navmesh = make_grid_navmesh(4);
+    navmesh.set_tile_size(2.0);
+    assert_eq!(navmesh.tile_size(), 2.0);
+    assert_eq!(
+        navmesh.tile_at(Vector3::new(3.5, 0.0, 0.5)),
+        Vector2::new(1, 0)
+    );
+    assert_eq!(navmesh.tile_triangles(Vector2::new(0, 0)).len(), 8);
+
+    // A one-way edge and an off-mesh link outside of the rebuilt tile, and a link, that is
+    // attached to the rebuilt tile.
+    let one_way_points = [Vector3::new(3.2, 0.0, 3.5), Vector3::new(3.5, 0.0, 3.8)];
+    let [a, b] = one_way_points.map(|p| navmesh.triangle_at(p, 0.1).unwrap());
+    assert!(navmesh.set_edge_direction(a, b, EdgeDirection::Forward));
+    let far_link = navmesh.add_offmesh_link(
+        Vector3::new(2.5, 0.0, 2.5),
+        Vector3::new(3.5, 0.0, 0.5),
+        false,
+    );
+    let near_link = navmesh.add_offmesh_link(
+        Vector3::new(0.5, 0.0, 0.5),
+        Vector3::new(3.5, 0.0, 3.5),
+        true,
+    );
+
+    // Replace the first tile with a single quad, its borders do not match the borders of the
+    // adjacent tiles, that are made of smaller quads.
+    navmesh.rebuild_tile(
+        Vector2::new(0, 0),
+        vec![TriangleDefinition([0, 1, 2]), TriangleDefinition([0, 2, 3])],
+        vec![
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 2.0),
+            Vector3::new(2.0, 0.0, 2.0),
+            Vector3::new(2.0, 0.0, 0.0),
+        ],
+    );
+
+    // The quad is split at the T-junctions with the adjacent tiles.
+    assert_eq!(navmesh.tile_triangles(Vector2::new(0, 0)).len(), 4);
+    assert_eq!(navmesh.triangles().len(), 28);
+    assert_eq!(navmesh.vertices().len(), 22);
+    assert_eq!(navmesh.connected_components().len(), 1);
+    assert_eq!(navmesh.tile_triangles(Vector2::new(1, 1)).len(), 8);
+
+    // Only the tile is rebuilt, but the result must match the full rebuild.
+    let [a, b] = one_way_points.map(|p| navmesh.triangle_at(p, 0.1).unwrap());
+    assert_eq!(navmesh.edge_direction(a, b), EdgeDirection::Forward);
+    let mut rebuilt = navmesh.clone();
+    rebuilt.rebuild();
+    let adjacency = |navmesh: &Navmesh| {
+        navmesh
+            .graph
+            .vertices
+            .iter()
+            .map(|vertex| {
+                let mut neighbours = vertex.neighbours.clone();
+                neighbours.sort_unstable();
+                (vertex.triangle_index, vertex.position, neighbours)
+            })
+            .collect::<Vec<_>>()
+    };
+    assert_eq!(adjacency(&navmesh), adjacency(&rebuilt));
+    for link in [far_link, near_link] {
+        let (link, rebuilt_link) = (navmesh.offmesh_link(link), rebuilt.offmesh_link(link));
+        assert_eq!(
+            link.map(|l| (l.from_triangle, l.to_triangle)),
+            rebuilt_link.map(|l| (l.from_triangle, l.to_triangle))
+        );
+    }
+    let traversals = |navmesh: &Navmesh| {
+        let mut traversals = navmesh
+            .offmesh_link_traversals
+            .iter()
+            .map(|(key, list)| (*key, list.len()))
+            .collect::<Vec<_>>();
+        traversals.sort_unstable();
+        traversals
+    };
+    assert_eq!(traversals(&navmesh), traversals(&rebuilt));
+
+    let mut path = Vec::new();
+    assert_eq!(
+        navmesh.find_path_filtered(
+            Vector3::new(0.5, 0.0, 0.5),
+            Vector3::new(3.5, 0.0, 3.5),
+            &mut path,
+            &Default::default()
+        ),
+        Ok(PathKind::Full)
+    );
+
+    // Tile size must survive serialization.
+    let mut visitor = Visitor::new();
+    navmesh.visit("Navmesh", &mut visitor).unwrap();
+    let data = visitor.save_binary_to_vec().unwrap();
+    let mut visitor = Visitor::load_from_memory(&data).unwrap();
+    let mut loaded = Navmesh::default();
+    loaded.visit("Navmesh", &mut visitor).unwrap();
+    assert_eq!(loaded.tile_size(), 2.0);
+}
+
+// Two disconnected squares, the second one is lower than the first one.
+pub(crate) fn make_two_squares_navmesh() -> Navmesh {
+    Navmesh::new(
+        vec![
+            TriangleDefinition([0, 1, 3]),
+            TriangleDefinition([1, 2, 3]),
+            TriangleDefinition([4, 5, 7]),
+            TriangleDefinition([5, 6, 7]),
+        ],
+        vec![
+            Vector3::new(0.0, 1.0, 0.0),
+            Vector3::new(0.0, 1.0, 1.0),
+            Vector3::new(1.0, 1.0, 1.0),
+            Vector3::new(1.0, 1.0, 0.0),
+            Vector3::new(2.0, 0.0, 0.0),
+            Vector3::new(2.0, 0.0, 1.0),
+            Vector3::new(3.0, 0.0, 1.0),
+            Vector3::new(3.0, 0.0, 0.0),
+        ],
+    )
+}
+
+#[test]
+fn test_path_errors() {
+    let mut navmesh = make_strip_navmesh();
+    let from = Vector3::new(0.5, 0.0, 0.5);
+    let to = Vector3::new(2.5, 0.0, 0.5);
+
+    let path = navmesh.find_path(from, to).unwrap();
+    assert_eq!(path.first(), Some(&from));
+    assert_eq!(path.last(), Some(&to));
+    assert_eq!(navmesh.find_path_opt(from, to), Some(path));
+    assert_eq!(
+        navmesh
+            .try_find_corridor(from, to, 0.0, &Default::default())
+            .ok(),
+        navmesh.find_corridor(from, to)
+    );
+
+    // The end point is on the excluded triangles.
+    navmesh.set_triangle_flags(4, 1);
+    navmesh.set_triangle_flags(5, 1);
+    let filter = NavmeshQueryFilter {
+        exclude_flags: 1,
+        ..Default::default()
+    };
+    let mut path = Vec::new();
+    assert_eq!(
+        navmesh.find_path_filtered(from, to, &mut path, &filter),
+        Err(NavmeshPathError::EndOffMesh)
+    );
+    assert!(path.is_empty());
+    navmesh.set_triangle_flags(4, 0);
+    navmesh.set_triangle_flags(5, 0);
+
+    // The end point is blocked by an obstacle.
+    let pillar = navmesh.add_obstacle(NavmeshObstacle::Cylinder {
+        center: to,
+        radius: 0.2,
+        height: 2.0,
+    });
+    assert_eq!(
+        navmesh.find_path(from, to),
+        Err(NavmeshPathError::EndOffMesh)
+    );
+    navmesh.remove_obstacle(pillar);
+    assert!(navmesh.find_path(from, to).is_ok());
+
+    // The end point is on another island.
+    let navmesh = make_two_squares_navmesh();
+    let from = Vector3::new(0.5, 1.0, 0.5);
+    let to = Vector3::new(2.5, 0.0, 0.5);
+    assert_eq!(
+        navmesh.find_path(from, to),
+        Err(NavmeshPathError::Disconnected)
+    );
+    assert_eq!(
+        navmesh.try_find_corridor(from, to, 0.0, &Default::default()),
+        Err(NavmeshPathError::Disconnected)
+    );
+    assert_eq!(navmesh.find_corridor(from, to), None);
+    assert_eq!(navmesh.find_path_opt(from, to), None);
+
+    // Nothing to walk on.
+    assert_eq!(
+        Navmesh::default().find_path(from, to),
+        Err(NavmeshPathError::StartOffMesh)
+    );
+
+    assert_eq!(
+        NavmeshPathError::from(PathError::HitMaxSearchIterations(10)),
+        NavmeshPathError::SearchLimitExceeded(10)
+    );
+    assert_eq!(
+        NavmeshPathError::from(PathError::Unreachable),
+        NavmeshPathError::Disconnected
+    );
+    // Callers, that handle the errors of the search, could map the errors back.
+    assert_eq!(
+        PathError::from(NavmeshPathError::SearchLimitExceeded(10)),
+        PathError::HitMaxSearchIterations(10)
+    );
+    assert_eq!(
+        PathError::from(NavmeshPathError::EndOffMesh),
+        PathError::Unreachable
+    );
+    assert_eq!(
+        PathError::from(NavmeshPathError::Search(PathError::InvalidIndex(3))),
+        PathError::InvalidIndex(3)
+    );
+    assert_eq!(
+        NavmeshPathError::Disconnected.to_string(),
+        "End point is unreachable from start point."
+    );
+}
+
+#[test]
+fn test_max_search_nodes() {
+    let mut navmesh = make_grid_navmesh(10);
+    let from = Vector3::new(0.5, 0.0, 0.5);
+    let to = Vector3::new(9.5, 0.0, 9.5);
+    assert_eq!(navmesh.max_search_nodes(), None);
+    assert!(navmesh.find_path(from, to).is_ok());
+
+    navmesh.set_max_search_nodes(Some(5));
+    assert_eq!(navmesh.max_search_nodes(), Some(5));
+    assert_eq!(
+        navmesh.find_path(from, to),
+        Err(NavmeshPathError::SearchExhausted(5))
+    );
+
+    // The agent walks along the best partial path.
+    let mut agent = NavmeshAgent::new();
+    agent.set_allow_partial_path(true);
+    agent.set_position(from);
+    agent.set_target(to);
+    assert_eq!(agent.update(0.0, &navmesh), Ok(PathKind::Partial));
+    assert!(agent.path().len() >= 2);
+    assert!(agent.is_path_partial());
+
+    agent.set_allow_partial_path(false);
+    assert_eq!(
+        agent.calculate_path(&navmesh, from, to),
+        Err(NavmeshPathError::SearchExhausted(5))
+    );
+
+    navmesh.set_max_search_nodes(None);
+    assert_eq!(agent.calculate_path(&navmesh, from, to), Ok(PathKind::Full));
+}
+
+#[test]
+fn test_deterministic_paths() {
+    let query = || {
+        // A grid has lots of paths of equal cost.
+        let navmesh = make_grid_navmesh(6);
+        let mut path = Vec::new();
+        navmesh
+            .find_path_filtered(
+                Vector3::new(0.1, 0.0, 0.1),
+                Vector3::new(5.9, 0.0, 5.9),
+                &mut path,
+                &Default::default(),
+            )
+            .unwrap();
+        let mut raw_path = Vec::new();
+        navmesh.build_path(0, 71, &mut raw_path).unwrap();
+        let corridor = navmesh
+            .find_corridor(Vector3::new(5.9, 0.0, 0.1), Vector3::new(0.1, 0.0, 5.9))
+            .unwrap();
+        (path, raw_path, corridor)
+    };
+
+    // The results are compared with the recorded ones, not only between the runs.
+    let navmesh = make_grid_navmesh(6);
+    let center = |triangle: usize| {
+        navmesh.triangles()[triangle]
+            .0
+            .iter()
+            .map(|index| navmesh.vertices()[*index as usize])
+            .sum::<Vector3<f32>>()
+            .scale(1.0 / 3.0)
+    };
+    let (path, raw_path, corridor) = query();
+    assert_eq!(
+        path,
+        vec![
+            Vector3::new(0.1, 0.0, 0.1),
+            Vector3::new(5.9, 0.0, 5.9),
+            Vector3::new(5.9, 0.0, 5.9)
+        ]
+    );
+    assert_eq!(
+        raw_path,
+        [71, 70, 59, 58, 57, 56, 45, 44, 43, 42, 31, 30, 29, 28, 17, 16, 15, 14, 3, 2, 1, 0]
+            .map(center)
+    );
+    assert_eq!(corridor, vec![11, 22, 21, 32, 31, 42, 41, 52, 51, 62, 61]);
+
+    let expected = query();
+    for _ in 0..20 {
+        assert_eq!(query(), expected);
+    }
+}
+
+#[test]
+fn test_agent_displacement() {
+    let navmesh = make_strip_navmesh();
+
+    let mut agent = NavmeshAgent::new();
+    // Small displacements must not cause path recalculation.
+    agent.set_threshold(10.0);
+    agent.set_position(Vector3::new(0.25, 0.0, 0.5));
+    agent.set_target(Vector3::new(2.75, 0.0, 0.5));
+    agent.update(0.0, &navmesh).unwrap();
+    for _ in 0..5 {
+        agent.update(0.1, &navmesh).unwrap();
+    }
+    let position = agent.position();
+
+    // Pushed forward and sideways - the agent continues from the closest point of the path.
+    agent.set_position(position + Vector3::new(0.5, 0.0, 0.2));
+    agent.update(0.0, &navmesh).unwrap();
+    let expected = position + Vector3::new(0.5, 0.0, 0.0);
+    assert!(agent.position().metric_distance(&expected) < 1.0e-4);
+
+    // Pushed backward - the agent does not go back to the passed part of the path.
+    let position = agent.position();
+    agent.set_position(position - Vector3::new(0.5, 0.0, 0.0));
+    agent.update(0.0, &navmesh).unwrap();
+    assert!(agent.position().metric_distance(&position) < 1.0e-4);
+    assert!(agent
+        .steering_target()
+        .is_some_and(|target| target.x > position.x));
+}
+
+#[test]
+fn test_agent_path_correction_strength() {
+    let navmesh = make_strip_navmesh();
+
+    let mut agent = NavmeshAgent::new();
+    assert_eq!(agent.path_correction_strength(), 1.0);
+    agent.set_threshold(10.0);
+    agent.set_position(Vector3::new(0.25, 0.0, 0.5));
+    agent.set_target(Vector3::new(2.75, 0.0, 0.5));
+    agent.update(0.0, &navmesh).unwrap();
+
+    // The agent drifts back to the path, halving the offset on every update.
+    agent.set_path_correction_strength(0.5);
+    let position = agent.position();
+    agent.set_position(position + Vector3::new(0.0, 0.0, 0.2));
+    agent.update(0.0, &navmesh).unwrap();
+    assert!((agent.position().z - 0.6).abs() < 1.0e-4);
+    agent.update(0.0, &navmesh).unwrap();
+    assert!((agent.position().z - 0.55).abs() < 1.0e-4);
+    // The agent keeps moving forward while correcting the offset.
+    agent.update(0.1, &navmesh).unwrap();
+    agent.update(0.0, &navmesh).unwrap();
+    assert!(agent.position().x > position.x);
+    assert!((agent.position().z - 0.5125).abs() < 1.0e-4);
+
+    // The offset is never corrected.
+    agent.set_path_correction_strength(0.0);
+    let position = agent.position();
+    agent.set_position(position + Vector3::new(0.0, 0.0, 0.2));
+    agent.update(0.0, &navmesh).unwrap();
+    agent.update(0.0, &navmesh).unwrap();
+    assert!((agent.position().z - (position.z + 0.2)).abs() < 1.0e-4);
+
+    // Path recalculation discards the offset.
+    agent.set_threshold(0.0);
+    agent.set_target(Vector3::new(2.5, 0.0, 0.5));
+    agent.update(0.0, &navmesh).unwrap();
+    assert_eq!(agent.path_offset, Vector3::default());
+
+    agent.set_path_correction_strength(2.0);
+    assert_eq!(agent.path_correction_strength(), 1.0);
+}
+
+#[test]
+fn test_agent_snapshot() {
+    let navmesh = make_strip_navmesh();
+
+    let mut agent = NavmeshAgent::new();
+    agent.set_position(Vector3::new(0.25, 0.0, 0.5));
+    agent.set_target(Vector3::new(2.75, 0.0, 0.5));
+    agent.update(0.1, &navmesh).unwrap();
+    let state = agent.snapshot();
+    assert_eq!(state.position(), agent.position());
+    assert_eq!(state.target(), Vector3::new(2.75, 0.0, 0.5));
+
+    let mut positions = Vec::new();
+    for _ in 0..5 {
+        agent.update(0.1, &navmesh).unwrap();
+        positions.push(agent.position());
+    }
+
+    // Undo the movement.
+    agent.restore(&state);
+    assert_eq!(agent.position(), state.position());
+    assert_eq!(agent.snapshot(), state);
+
+    // The replay is identical, even if the target was changed in between.
+    agent.set_target(Vector3::new(0.25, 0.0, 0.5));
+    agent.restore(&state);
+    for position in positions {
+        agent.update(0.1, &navmesh).unwrap();
+        assert_eq!(agent.position(), position);
+    }
+}
+
+#[test]
+fn test_agent_target_region() {
+    let navmesh = make_strip_navmesh();
+
+    let mut agent = NavmeshAgent::new();
+    agent.set_position(Vector3::new(0.25, 0.0, 0.5));
+    agent.set_target_region(&[5, 4]);
+    agent.update(0.0, &navmesh).unwrap();
+    // The agent stops at the border of the region.
+    assert_eq!(agent.target_region_triangle(), Some(4));
+    assert!(agent.target().metric_distance(&Vector3::new(2.0, 0.0, 0.5)) < 1.0e-5);
+    assert_eq!(agent.path().last(), Some(&agent.target()));
+
+    let mut events = Vec::new();
+    for _ in 0..30 {
+        agent.update(0.1, &navmesh).unwrap();
+        events.extend(std::iter::from_fn(|| agent.pop_event()));
+    }
+    assert!(agent.target_reached());
+    assert!(events.contains(&NavmeshAgentEvent::EnteredTargetRegion(4)));
+
+    // The agent is inside the region already.
+    agent.set_target_region(&[3, 4]);
+    agent.update(0.0, &navmesh).unwrap();
+    assert!(matches!(agent.target_region_triangle(), Some(3 | 4)));
+    assert!(agent.target().metric_distance(&agent.position()) < 1.0e-5);
+
+    // Unreachable region.
+    agent.set_target_region(&[100]);
+    assert_eq!(
+        agent.update(0.0, &navmesh),
+        Err(NavmeshPathError::Disconnected)
+    );
+    assert_eq!(agent.target_region_triangle(), None);
+
+    // Point target resets the region.
+    agent.set_target(Vector3::new(0.5, 0.0, 0.5));
+    assert!(agent.target_region().is_empty());
+    agent.update(0.0, &navmesh).unwrap();
+    assert_eq!(agent.target_region_triangle(), None);
+
+    // The agent walks towards a region on another island, if partial paths are allowed.
+    let navmesh = make_two_squares_navmesh();
+    let mut agent = NavmeshAgent::new();
+    agent.set_position(Vector3::new(0.25, 1.0, 0.5));
+    agent.set_target_region(&[2, 3]);
+    assert_eq!(agent.update(0.0, &navmesh), Ok(PathKind::Partial));
+    assert!(agent.is_path_partial());
+    assert_eq!(agent.target_region_triangle(), None);
+    assert_eq!(agent.target(), Vector3::new(2.0, 0.0, 0.5));
+    assert!((agent.path().last().unwrap().x - 1.0).abs() < 1.0e-5);
+
+    agent.set_allow_partial_path(false);
+    assert_eq!(
+        agent.update(0.0, &navmesh),
+        Err(NavmeshPathError::Disconnected)
+    );
+}
+
+#[test]
+fn test_agent_replan_backoff() {
+    let navmesh = make_two_squares_navmesh();
+
+    let count_failures = |agent: &mut NavmeshAgent| {
+        (0..50)
+            .filter(|_| agent.update(0.1, &navmesh).is_err())
+            .count()
+    };
+
+    let mut agent = NavmeshAgent::new();
+    agent.set_allow_partial_path(false);
+    agent.set_position(Vector3::new(0.25, 1.0, 0.25));
+    agent.set_target(Vector3::new(2.5, 0.0, 0.5));
+    assert_eq!(count_failures(&mut agent), 50);
+
+    agent.set_replan_backoff(0.5, 2.0);
+    assert_eq!(agent.replan_backoff(), (0.5, 2.0));
+    // Attempts at 0.0, 0.5, 1.5, 3.5 seconds (with some floating-point error).
+    let failures = count_failures(&mut agent);
+    assert!((3..=5).contains(&failures), "{failures}");
+    assert_eq!(agent.current_replan_backoff(), 2.0);
+
+    // Slight movement of the target does not reset the backoff.
+    agent.set_target(Vector3::new(2.55, 0.0, 0.5));
+    assert!(agent.update(0.0, &navmesh).is_ok());
+    assert_eq!(agent.current_replan_backoff(), 2.0);
+
+    // Invalidations do not reset the backoff and do not bypass the delay.
+    agent.invalidate_path();
+    assert!(agent.update(0.0, &navmesh).is_ok());
+    assert_eq!(agent.current_replan_backoff(), 2.0);
+    let mut navmesh = navmesh.clone();
+    navmesh.set_triangle_flags(0, 1);
+    assert!(agent.update(0.0, &navmesh).is_ok());
+    assert_eq!(agent.current_replan_backoff(), 2.0);
+
+    // The target moves to a reachable point, the path is found without waiting.
+    agent.set_target(Vector3::new(0.75, 1.0, 0.75));
+    assert!(agent.update(0.0, &navmesh).is_ok());
+    assert_eq!(agent.current_replan_backoff(), 0.0);
+    assert!(!agent.path().is_empty());
+}
+
+#[test]
+fn test_connect_steps() {
+    // Moves the second square to the first one and sets the height of the first square.
+    let make_navmesh = |height: f32, gap: f32| {
+        let mut navmesh = make_two_squares_navmesh();
+        let mut ctx = navmesh.modify();
+        for (i, vertex) in ctx.vertices_mut().iter_mut().enumerate() {
+            if i < 4 {
+                vertex.y = height;
+            } else {
+                vertex.x -= 1.0 - gap;
+            }
+        }
+        drop(ctx);
+        navmesh
+    };
+
+    let mut path = Vec::new();
+
+    // The gap is too high for a step and drops are disabled.
+    let mut navmesh = make_navmesh(1.0, 0.0);
+    assert!(navmesh.connect_steps(0.5, 0.0, 0.05).is_empty());
+    assert_eq!(
+        navmesh.build_path(0, 3, &mut path),
+        Err(PathError::Unreachable)
+    );
+
+    // Jump down from the ledge.
+    let links = navmesh.connect_steps(0.5, 2.0, 0.05);
+    assert_eq!(links.len(), 1);
+    let link = navmesh.offmesh_link(links[0]).unwrap();
+    assert!(!link.is_bidirectional());
+    assert!(link.from().metric_distance(&Vector3::new(1.0, 1.0, 0.5)) < 1.0e-5);
+    assert!(link.to().metric_distance(&Vector3::new(1.0, 0.0, 0.5)) < 1.0e-5);
+    assert_eq!(navmesh.build_path(0, 3, &mut path), Ok(PathKind::Full));
+    assert_eq!(
+        navmesh.build_path(3, 0, &mut path),
+        Err(PathError::Unreachable)
+    );
+
+    // A step could be walked in both directions.
+    let mut navmesh = make_navmesh(0.3, 0.03);
+    let links = navmesh.connect_steps(0.5, 2.0, 0.05);
+    assert_eq!(links.len(), 1);
+    assert!(navmesh.offmesh_link(links[0]).unwrap().is_bidirectional());
+    assert_eq!(navmesh.build_path(0, 3, &mut path), Ok(PathKind::Full));
+    assert_eq!(navmesh.build_path(3, 0, &mut path), Ok(PathKind::Full));
+
+    // The step is crossed at any point along the edges, not only in the middle.
+    let link = navmesh.offmesh_link(links[0]).unwrap();
+    assert!(link.span().x.abs() < 1.0e-5);
+    assert!((link.span().z.abs() - 0.5).abs() < 1.0e-5);
+    let mut path = Vec::new();
+    assert_eq!(
+        navmesh.find_path_filtered(
+            Vector3::new(0.5, 0.3, 0.3),
+            Vector3::new(1.5, 0.0, 0.3),
+            &mut path,
+            &Default::default()
+        ),
+        Ok(PathKind::Full)
+    );
+    assert!(path.iter().all(|point| (point.z - 0.3).abs() < 1.0e-4));
+
+    // The span survives serialization.
+    let mut data = Vec::new();
+    navmesh.save_binary(&mut data).unwrap();
+    let loaded = Navmesh::load_binary(&mut data.as_slice()).unwrap();
+    let (_, loaded_link) = loaded.offmesh_links().next().unwrap();
+    assert_eq!(loaded_link.span(), link.span());
+
+    // The edges are too far from each other.
+    let mut navmesh = make_navmesh(0.3, 0.03);
+    assert!(navmesh.connect_steps(0.5, 2.0, 0.01).is_empty());
+}
+
+#[test]
+fn test_offmesh_links() {
+    let mut navmesh = make_two_squares_navmesh();
+
+    let mut path = Vec::new();
+    assert_eq!(
+        navmesh.build_path(0, 3, &mut path),
+        Err(PathError::Unreachable)
+    );
+
+    let link = navmesh.add_offmesh_link(
+        Vector3::new(0.9, 1.0, 0.5),
+        Vector3::new(2.1, 0.0, 0.5),
+        false,
+    );
+    assert_eq!(navmesh.offmesh_links().count(), 1);
+
+    assert_eq!(navmesh.build_path(0, 3, &mut path), Ok(PathKind::Full));
+    // The link is one-way.
+    assert_eq!(
+        navmesh.build_path(3, 0, &mut path),
+        Err(PathError::Unreachable)
+    );
+
+    let mut agent = NavmeshAgent::new();
+    agent.set_position(Vector3::new(0.25, 1.0, 0.5));
+    agent.set_target(Vector3::new(2.75, 0.0, 0.5));
+    agent.update(0.0, &navmesh).unwrap();
+    assert!(agent.path().contains(&Vector3::new(0.9, 1.0, 0.5)));
+    assert!(agent.path().contains(&Vector3::new(2.1, 0.0, 0.5)));
+
+    let mut traversed = false;
+    for _ in 0..200 {
+        agent.update(1.0 / 30.0, &navmesh).unwrap();
+        if agent.current_offmesh_link() == Some(link) {
+            traversed = true;
+        }
+    }
+    assert!(traversed);
+    assert!(
+        agent
+            .position()
+            .metric_distance(&Vector3::new(2.75, 0.0, 0.5))
+            < 1.0e-3
+    );
+
+    assert!(navmesh.remove_offmesh_link(link).is_some());
+    assert_eq!(
+        navmesh.build_path(0, 3, &mut path),
+        Err(PathError::Unreachable)
+    );
+    assert_eq!(
+        navmesh.build_path(0, 4, &mut path),
+        Err(PathError::InvalidIndex(4))
+    );
+}
+
+#[test]
+fn test_parallel_offmesh_links() {
+    let mut navmesh = make_two_squares_navmesh();
+
+    // Two links between the same pair of triangles.
+    let (first_from, second_from) = (Vector3::new(0.9, 1.0, 0.5), Vector3::new(0.9, 1.0, 0.4));
+    let (first_to, second_to) = (Vector3::new(2.1, 0.0, 0.5), Vector3::new(2.1, 0.0, 0.4));
+    let triangle = |point| navmesh.query_closest(point).unwrap().1;
+    assert_eq!(triangle(first_from), triangle(second_from));
+    assert_eq!(triangle(first_to), triangle(second_to));
+    let first = navmesh.add_offmesh_link(first_from, first_to, false);
+    let second = navmesh.add_offmesh_link(second_from, second_to, false);
+    navmesh.set_offmesh_link_cost(first, 2.0);
+
+    // The cheapest link is used.
+    let mut agent = NavmeshAgent::new();
+    agent.set_position(Vector3::new(0.25, 1.0, 0.5));
+    agent.set_target(Vector3::new(2.75, 0.0, 0.5));
+    agent.update(0.0, &navmesh).unwrap();
+    assert!(agent.path().contains(&second_from));
+    assert!(!agent.path().contains(&first_from));
+
+    // Removing one of the links does not break the other one.
+    assert!(navmesh.remove_offmesh_link(second).is_some());
+    agent.invalidate_path();
+    agent.update(0.0, &navmesh).unwrap();
+    assert!(agent.path().contains(&first_from));
+    assert!(agent.path().contains(&first_to));
+}
+
+#[test]
+fn test_connected_components() {
+    assert_eq!(
+        Navmesh::default().connected_components(),
+        Vec::<Vec<usize>>::new()
+    );
+    assert_eq!(Navmesh::default().largest_component(), Vec::<usize>::new());
+
+    let navmesh = make_strip_navmesh();
+    assert_eq!(navmesh.connected_components(), vec![vec![0, 1, 2, 3, 4, 5]]);
+
+    // A grid with an extra separate triangle.
+    let mut navmesh = make_grid_navmesh(2);
+    let mut ctx = navmesh.modify();
+    let a = ctx.add_vertex(Vector3::new(5.0, 0.0, 5.0));
+    let b = ctx.add_vertex(Vector3::new(5.0, 0.0, 6.0));
+    let c = ctx.add_vertex(Vector3::new(6.0, 0.0, 5.0));
+    ctx.add_triangle(TriangleDefinition([a, b, c]));
+    drop(ctx);
+
+    assert_eq!(
+        navmesh.connected_components(),
+        vec![vec![0, 1, 2, 3, 4, 5, 6, 7], vec![8]]
+    );
+    assert_eq!(navmesh.largest_component(), vec![0, 1, 2, 3, 4, 5, 6, 7]);
+    assert_eq!(navmesh.triangle_component(3), Some(0));
+    assert_eq!(navmesh.triangle_component(8), Some(1));
+    assert_eq!(navmesh.triangle_component(9), None);
+
+    // Off-mesh links must not join the components.
+    navmesh.add_offmesh_link(
+        Vector3::new(1.0, 0.0, 1.0),
+        Vector3::new(5.2, 0.0, 5.2),
+        true,
+    );
+    assert_eq!(navmesh.connected_components().len(), 2);
+}
+
+#[test]
+fn test_can_reach() {
+    assert!(!Navmesh::default().can_reach(Vector3::default(), Vector3::default()));
+
+    // A grid with an extra separate triangle.
+    let mut navmesh = make_grid_navmesh(2);
+    let mut ctx = navmesh.modify();
+    let a = ctx.add_vertex(Vector3::new(5.0, 0.0, 5.0));
+    let b = ctx.add_vertex(Vector3::new(5.0, 0.0, 6.0));
+    let c = ctx.add_vertex(Vector3::new(6.0, 0.0, 5.0));
+    ctx.add_triangle(TriangleDefinition([a, b, c]));
+    drop(ctx);
+
+    let grid_point = Vector3::new(0.2, 0.0, 0.3);
+    let island_point = Vector3::new(5.2, 0.0, 5.2);
+    assert!(navmesh.can_reach(grid_point, Vector3::new(1.8, 0.0, 1.7)));
+    assert!(!navmesh.can_reach(grid_point, island_point));
+    assert!(!navmesh.can_reach(island_point, grid_point));
+
+    // One-way off-mesh link makes the island reachable only in one direction.
+    let link = navmesh.add_offmesh_link(Vector3::new(1.5, 0.0, 1.2), island_point, false);
+    assert!(navmesh.can_reach(grid_point, island_point));
+    assert!(!navmesh.can_reach(island_point, grid_point));
+
+    navmesh.remove_offmesh_link(link);
+    assert!(!navmesh.can_reach(grid_point, island_point));
+}
+
+#[test]
+fn test_partial_path() {
+    // A grid with an extra separate triangle.
+    let mut navmesh = make_grid_navmesh(3);
+    let mut ctx = navmesh.modify();
+    let a = ctx.add_vertex(Vector3::new(5.0, 0.0, 5.0));
+    let b = ctx.add_vertex(Vector3::new(5.0, 0.0, 6.0));
+    let c = ctx.add_vertex(Vector3::new(6.0, 0.0, 5.0));
+    ctx.add_triangle(TriangleDefinition([a, b, c]));
+    drop(ctx);
+
+    let start = Vector3::new(0.5, 0.0, 0.5);
+    let target = Vector3::new(5.3, 0.0, 5.3);
+
+    // Partial paths are allowed by default.
+    let mut agent = NavmeshAgent::new();
+    assert!(agent.is_partial_path_allowed());
+
+    // When disallowed, the agent must stay still.
+    agent.set_allow_partial_path(false);
+    agent.set_position(start);
+    agent.set_target(target);
+    assert_eq!(
+        agent.update(0.1, &navmesh),
+        Err(NavmeshPathError::Disconnected)
+    );
+    assert_eq!(
+        agent.pop_event(),
+        Some(NavmeshAgentEvent::PathFailed(
+            NavmeshPathError::Disconnected
+        ))
+    );
+    assert!(agent.path().is_empty());
+    assert!(agent.is_path_partial());
+    assert_eq!(agent.position(), start);
+    assert!(!agent.reached_full_target());
+
+    // The agent must walk to the closest reachable point.
+    agent.set_allow_partial_path(true);
+    for _ in 0..200 {
+        assert_eq!(agent.update(0.1, &navmesh), Ok(PathKind::Partial));
+    }
+    assert!(agent.is_path_partial());
+    assert!(!agent.reached_full_target());
+    assert_eq!(*agent.path().last().unwrap(), Vector3::new(3.0, 0.0, 3.0));
+    assert!(
+        agent
+            .position()
+            .metric_distance(&Vector3::new(3.0, 0.0, 3.0))
+            < 0.01
+    );
+
+    // Reachable target must be reached fully.
+    agent.set_target(Vector3::new(0.5, 0.0, 2.5));
+    for _ in 0..200 {
+        assert_eq!(agent.update(0.1, &navmesh), Ok(PathKind::Full));
+    }
+    assert!(!agent.is_path_partial());
+    assert!(agent.reached_full_target());
+}
+
+#[test]
+fn test_bake_from_graph() {
+    let mut graph = Graph::new();
+
+    // A floor with a unit cube on it.
+    let floor = MeshBuilder::new(BaseBuilder::new())
+        .with_surfaces(vec![SurfaceBuilder::new(SurfaceResource::new_ok(
+            ResourceKind::Embedded,
+            SurfaceData::make_quad(
+                &(Matrix4::new_rotation(Vector3::x() * std::f32::consts::FRAC_PI_2)
+                    * Matrix4::new_scaling(10.0)),
+            ),
+        ))
+        .build()])
+        .build(&mut graph);
+    let cube = MeshBuilder::new(
+        BaseBuilder::new().with_local_transform(
+            TransformBuilder::new()
+                .with_local_position(Vector3::new(0.0, 0.5, 0.0))
+                .build(),
+        ),
+    )
+    .with_surfaces(vec![SurfaceBuilder::new(SurfaceResource::new_ok(
+        ResourceKind::Embedded,
+        SurfaceData::make_cube(Matrix4::identity()),
+    ))
+    .build()])
+    .build(&mut graph);
+    graph.update_hierarchical_data();
+
+    // The floor and the top of the cube are walkable, the sides of the cube are too steep and
+    // the bottom of the cube faces down.
+    let navmesh = Navmesh::bake_from_graph(&graph, &[floor, cube], NavmeshBakeParams::default());
+    assert_eq!(navmesh.triangles().len(), 4);
+    assert!(navmesh
+        .vertices()
+        .iter()
+        .all(|v| v.y.abs() < 1e-5 || (v.y - 1.0).abs() < 1e-5));
+    assert_eq!(navmesh.connected_components().len(), 2);
+
+    // Large agents cannot walk near the cube.
+    let navmesh = Navmesh::bake_from_graph(
+        &graph,
+        &[floor, cube],
+        NavmeshBakeParams {
+            agent_radius: 2.0,
+            ..Default::default()
+        },
+    );
+    assert_eq!(navmesh.triangles().len(), 2);
+    assert!(navmesh.vertices().iter().all(|v| (v.y - 1.0).abs() < 1e-5));
+
+    // A ceiling is not walkable.
+    let ceiling = MeshBuilder::new(BaseBuilder::new())
+        .with_surfaces(vec![SurfaceBuilder::new(SurfaceResource::new_ok(
+            ResourceKind::Embedded,
+            SurfaceData::make_quad(
+                &(Matrix4::new_rotation(-Vector3::x() * std::f32::consts::FRAC_PI_2)
+                    * Matrix4::new_scaling(10.0)),
+            ),
+        ))
+        .build()])
+        .build(&mut graph);
+    graph.update_hierarchical_data();
+    let navmesh = Navmesh::bake_from_graph(&graph, &[ceiling], NavmeshBakeParams::default());
+    assert!(navmesh.triangles().is_empty());
+
+    // Invalid handles are ignored.
+    let navmesh = Navmesh::bake_from_graph(&graph, &[Handle::NONE], NavmeshBakeParams::default());
+    assert!(navmesh.triangles().is_empty());
+}
+
+#[test]
+fn test_navmesh_delta() {
+    let mut server = make_grid_navmesh(4);
+    let removed = server.add_obstacle(NavmeshObstacle::Cylinder {
+        center: Vector3::new(0.5, 0.0, 0.5),
+        radius: 0.2,
+        height: 2.0,
+    });
+    let disabled = server.add_obstacle(NavmeshObstacle::Box {
+        center: Vector3::new(3.5, 0.0, 3.5),
+        half_extents: Vector3::new(0.2, 1.0, 0.2),
+    });
+    let baseline = server.clone();
+    assert!(server.compute_delta(&baseline).is_empty());
+
+    server.set_triangle_flags(3, 0b11);
+    server.set_triangle_cost(5, 4.0);
+    assert!(server.set_edge_direction(0, 1, EdgeDirection::Forward));
+    server.remove_obstacle(removed);
+    server.set_obstacle_enabled(disabled, false);
+    let added = server.add_obstacle(NavmeshObstacle::Cylinder {
+        center: Vector3::new(2.5, 0.0, 1.5),
+        radius: 0.3,
+        height: 2.0,
+    });
+
+    let delta = server.compute_delta(&baseline);
+    assert!(!delta.is_empty());
+
+    // The delta survives the round trip.
+    let mut data = Vec::new();
+    delta.save_binary(&mut data).unwrap();
+    let loaded = NavmeshDelta::load_binary(&mut data.as_slice()).unwrap();
+    assert_eq!(loaded, delta);
+    assert!(NavmeshDelta::load_binary(&mut [0u8; 8].as_slice()).is_err());
+
+    let mut client = baseline.clone();
+    let mut conflicting = baseline.clone();
+    client.apply_delta(&loaded).unwrap();
+    assert_eq!(client.triangle_flags(3), Some(0b11));
+    assert_eq!(client.triangle_cost(5), Some(4.0));
+    assert_eq!(client.edge_direction(0, 1), EdgeDirection::Forward);
+    assert!(client.obstacle(removed).is_none());
+    assert!(!client.obstacle(disabled).unwrap().is_enabled());
+    assert_eq!(client.obstacle(added), server.obstacle(added));
+    for index in 0..server.triangles.len() {
+        assert_eq!(
+            client.is_triangle_blocked(index),
+            server.is_triangle_blocked(index)
+        );
+    }
+    assert!(server.compute_delta(&client).is_empty());
+
+    // Reverting the edge direction is a change too.
+    server.set_edge_direction(0, 1, EdgeDirection::Bidirectional);
+    let delta = server.compute_delta(&client);
+    client.apply_delta(&delta).unwrap();
+    assert_eq!(client.edge_direction(0, 1), EdgeDirection::Bidirectional);
+    assert!(server.compute_delta(&client).is_empty());
+
+    // A local obstacle occupies the slot of the added one, the rest is still applied.
+    let local_shape = NavmeshObstacle::Cylinder {
+        center: Vector3::new(1.5, 0.0, 2.5),
+        radius: 0.3,
+        height: 2.0,
+    };
+    conflicting.remove_obstacle(removed);
+    let temporary = conflicting.add_obstacle(local_shape);
+    conflicting.remove_obstacle(temporary);
+    let local = conflicting.add_obstacle(local_shape);
+    assert_eq!(local.index(), added.index());
+    assert_ne!(local, added);
+    assert_eq!(
+        conflicting.apply_delta(&loaded),
+        Err(NavmeshDeltaError::ObstacleHandlesOccupied(vec![added]))
+    );
+    assert_eq!(conflicting.triangle_flags(3), Some(0b11));
+    assert_eq!(conflicting.obstacle(local).unwrap().shape(), &local_shape);
+}
+
+#[test]
+fn test_binary_format() {
+    let mut navmesh = make_grid_navmesh(3);
+    navmesh.set_triangle_cost(4, 3.0);
+    navmesh.set_triangle_flags(5, 0b101);
+    let link = navmesh.add_offmesh_link(
+        Vector3::new(0.25, 0.0, 0.25),
+        Vector3::new(2.75, 0.0, 2.75),
+        true,
+    );
+    navmesh.set_offmesh_link_cost(link, 2.0);
+    navmesh.set_up_axis(Vector3::new(0.0, 2.0, 0.5));
+    navmesh.build_convex_polys(6);
+
+    let mut data = Vec::new();
+    navmesh.save_binary(&mut data).unwrap();
+    let loaded = Navmesh::load_binary(&mut data.as_slice()).unwrap();
+
+    assert_eq!(loaded, navmesh);
+    assert_eq!(loaded.tile_size, navmesh.tile_size);
+    assert_eq!(loaded.up_axis(), navmesh.up_axis());
+    assert_ne!(loaded.up_axis(), Vector3::y());
+    assert_eq!(loaded.max_polygon_vertices(), 6);
+    for (a, b) in loaded
+        .graph
+        .vertices
+        .iter()
+        .zip(navmesh.graph.vertices.iter())
+    {
+        assert_eq!(a.triangle_index, b.triangle_index);
+        assert_eq!(a.position, b.position);
+        assert_eq!(a.neighbours, b.neighbours);
+        assert_eq!(a.g_penalty, b.g_penalty);
+    }
+    let mut keys = loaded
+        .offmesh_link_traversals
+        .keys()
+        .cloned()
+        .collect::<Vec<_>>();
+    keys.sort();
+    assert_eq!(keys, vec![(0, 17), (17, 0)]);
+
+    let mut corrupted = data.clone();
+    corrupted[0] = b'X';
+    assert_eq!(
+        Navmesh::load_binary(&mut corrupted.as_slice())
+            .unwrap_err()
+            .kind(),
+        std::io::ErrorKind::InvalidData
+    );
+    assert_eq!(
+        Navmesh::load_binary(&mut &data[..data.len() - 1])
+            .unwrap_err()
+            .kind(),
+        std::io::ErrorKind::UnexpectedEof
+    );
+}
+
+#[test]
+fn test_one_way_edges() {
+    let mut navmesh = make_grid_navmesh(3);
+    let quad = |x: usize, z: usize| [2 * (z * 3 + x), 2 * (z * 3 + x) + 1];
+
+    // The edges between the first and the second rows could be crossed only from the first
+    // row, except the edge in the last column.
+    let mut edges = Vec::new();
+    for x in 0..2 {
+        for a in quad(x, 0) {
+            for b in navmesh.neighbors(a) {
+                if quad(x, 1).contains(&b) {
+                    edges.push((a, b));
+                }
+            }
+        }
+    }
+    assert_eq!(edges.len(), 2);
+    for &(a, b) in edges.iter() {
+        assert!(navmesh.set_edge_direction(a, b, EdgeDirection::Forward));
+        assert_eq!(navmesh.edge_direction(a, b), EdgeDirection::Forward);
+        assert_eq!(navmesh.edge_direction(b, a), EdgeDirection::Backward);
+    }
+    assert!(!navmesh.set_edge_direction(0, 17, EdgeDirection::Forward));
+
+    let upstream = Vector3::new(0.5, 0.0, 0.5);
+    let downstream = Vector3::new(0.5, 0.0, 1.5);
+    let mut path = Vec::new();
+
+    // The allowed direction is a straight line.
+    navmesh
+        .find_path_filtered(upstream, downstream, &mut path, &Default::default())
+        .unwrap();
+    assert!(path.iter().all(|point| point.x < 1.0));
+
+    // The opposite direction goes around through the last column.
+    assert_eq!(
+        navmesh
+            .find_path_filtered(downstream, upstream, &mut path, &Default::default())
+            .unwrap(),
+        PathKind::Full
+    );
+    assert!(path.iter().any(|point| point.x >= 2.0));
+
+    // Directions are preserved on serialization.
+    let mut data = Vec::new();
+    navmesh.save_binary(&mut data).unwrap();
+    let loaded = Navmesh::load_binary(&mut data.as_slice()).unwrap();
+    assert_eq!(loaded, navmesh);
+    let (a, b) = edges[0];
+    assert_eq!(loaded.edge_direction(a, b), EdgeDirection::Forward);
+
+    // Removal of a triangle shifts the indices of the triangles of the one-way edges.
+    let mut modified = navmesh.clone();
+    modified.modify().remove_triangle(0);
+    for &(a, b) in edges.iter() {
+        assert_eq!(
+            modified.edge_direction(a - 1, b - 1),
+            EdgeDirection::Forward
+        );
+    }
+    let mut modified = navmesh.clone();
+    modified.modify().remove_triangle(edges[0].0);
+    assert_eq!(modified.one_way_edges.len(), 1);
+
+    // Bidirectional edges are not stored.
+    for &(a, b) in edges.iter() {
+        assert!(navmesh.set_edge_direction(b, a, EdgeDirection::Bidirectional));
+    }
+    assert!(navmesh.one_way_edges.is_empty());
+    navmesh
+        .find_path_filtered(downstream, upstream, &mut path, &Default::default())
+        .unwrap();
+    assert!(path.iter().all(|point| point.x < 1.0));
+}
+
+#[test]
+fn test_write_obj() {
+    let mut navmesh = Navmesh::new(
+        vec![TriangleDefinition([0, 1, 2])],
+        vec![
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 1.0),
+            Vector3::new(1.0, 0.0, 0.0),
+        ],
+    );
+    navmesh.add_offmesh_link(
+        Vector3::new(0.1, 0.0, 0.1),
+        Vector3::new(2.0, 0.5, 2.0),
+        false,
+    );
+
+    let mut obj = Vec::new();
+    navmesh.write_obj(&mut obj).unwrap();
+    assert_eq!(
+        String::from_utf8(obj).unwrap(),
+        "# Fyrox navmesh\n\
+        o navmesh\n\
+        v 0 0 0\n\
+        v 0 0 1\n\
+        v 1 0 0\n\
+        f 1 2 3\n\
+        o offmesh_links\n\
+        v 0.1 0 0.1\n\
+        v 2 0.5 2\n\
+        l 4 5\n"
+    );
+}
+
+#[test]
+fn test_from_polygon_soup() {
+    let vertices = vec![
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(1.0, 0.0, 0.0),
+        Vector3::new(1.0, 0.0, 1.0),
+        Vector3::new(0.0, 0.0, 1.0),
+        Vector3::new(2.0, 0.0, 0.0),
+        Vector3::new(2.5, 0.0, 0.5),
+        Vector3::new(2.0, 0.0, 1.0),
+    ];
+
+    // A quad and a pentagon sharing an edge, both with "negative" winding.
+    let navmesh =
+        Navmesh::from_polygon_soup(&[vec![0, 1, 2, 3], vec![1, 4, 5, 6, 2]], &vertices).unwrap();
+    assert_eq!(navmesh.triangles().len(), 5);
+    assert!(navmesh.triangles().iter().all(|t| super::triangle_area_2d(
+        vertices[t[0] as usize],
+        vertices[t[1] as usize],
+        vertices[t[2] as usize]
+    ) > 0.0));
+    assert_eq!(navmesh.connected_components(), vec![vec![0, 1, 2, 3, 4]]);
+
+    assert_eq!(
+        Navmesh::from_polygon_soup(&[vec![0, 1]], &vertices),
+        Err(PolygonSoupError::TooFewVertices { polygon: 0 })
+    );
+    assert_eq!(
+        Navmesh::from_polygon_soup(&[vec![0, 1, 10]], &vertices),
+        Err(PolygonSoupError::InvalidIndex {
+            polygon: 0,
+            index: 10
+        })
+    );
+    assert_eq!(
+        Navmesh::from_polygon_soup(&[vec![0, 1, 4]], &vertices),
+        Err(PolygonSoupError::Degenerate { polygon: 0 })
+    );
+    assert_eq!(
+        Navmesh::from_polygon_soup(&[vec![0, 1, 2, 3], vec![1, 2, 4]], &vertices),
+        Err(PolygonSoupError::InconsistentWinding { polygon: 1 })
+    );
+    // An "arrow" shape.
+    assert_eq!(
+        Navmesh::from_polygon_soup(&[vec![0, 4, 6, 5]], &vertices),
+        Err(PolygonSoupError::NonConvex { polygon: 0 })
+    );
+}
+
+#[test]
+fn test_from_polygons_with_holes() {
+    let square = |min: f32, max: f32| {
+        Polygon::new(vec![
+            Vector3::new(min, 0.0, min),
+            Vector3::new(max, 0.0, min),
+            Vector3::new(max, 0.0, max),
+            Vector3::new(min, 0.0, max),
+        ])
+    };
+
+    // A room with a pillar in the middle, the outline has clockwise winding and a nearly
+    // collinear vertex.
+    let outline = Polygon::new(vec![
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(0.0, 0.0, 4.0),
+        Vector3::new(4.0, 0.0, 4.0),
+        Vector3::new(4.0, 0.0, 2.0),
+        Vector3::new(4.0 + 1.0e-6, 0.0, 1.0),
+        Vector3::new(4.0, 0.0, 0.0),
+    ]);
+    let navmesh = Navmesh::from_polygons_with_holes(&[outline], &[square(1.5, 2.5)]).unwrap();
+    assert!(navmesh.is_valid());
+    assert_eq!(navmesh.vertices().len(), 8);
+    assert_eq!(navmesh.triangles().len(), 8);
+    assert_eq!(navmesh.component_areas().len(), 1);
+    assert!((navmesh.total_area() - 15.0).abs() < 1.0e-4);
+    assert!(navmesh
+        .project_down(Vector3::new(2.0, 1.0, 2.0), 10.0)
+        .is_none());
+    assert!(navmesh
+        .project_down(Vector3::new(0.5, 1.0, 2.0), 10.0)
+        .is_some());
+
+    // A concave "L" shape.
+    let outline = Polygon::new(vec![
+        Vector3::new(0.0, 0.0, 0.0),
+        Vector3::new(3.0, 0.0, 0.0),
+        Vector3::new(3.0, 0.0, 1.0),
+        Vector3::new(1.0, 0.0, 1.0),
+        Vector3::new(1.0, 0.0, 3.0),
+        Vector3::new(0.0, 0.0, 3.0),
+    ]);
+    let navmesh = Navmesh::from_polygons_with_holes(&[outline], &[]).unwrap();
+    assert!(navmesh.is_valid());
+    assert_eq!(navmesh.triangles().len(), 4);
+    assert!((navmesh.total_area() - 5.0).abs() < 1.0e-4);
+    assert!(navmesh
+        .project_down(Vector3::new(2.0, 1.0, 2.0), 10.0)
+        .is_none());
+
+    assert_eq!(
+        Navmesh::from_polygons_with_holes(&[square(0.0, 1.0)], &[square(5.0, 6.0)]),
+        Err(PolygonSoupError::OrphanHole { polygon: 1 })
+    );
+    assert_eq!(
+        Navmesh::from_polygons_with_holes(
+            &[Polygon::new(vec![
+                Vector3::new(0.0, 0.0, 0.0),
+                Vector3::new(1.0, 0.0, 0.0),
+                Vector3::new(2.0, 0.0, 0.0),
+            ])],
+            &[]
+        ),
+        Err(PolygonSoupError::Degenerate { polygon: 0 })
+    );
+}
+
+#[test]
+fn test_triangle_at() {
+    let mut navmesh = make_grid_navmesh(2);
+
+    // A "bridge" triangle above the grid.
+    let mut ctx = navmesh.modify();
+    let a = ctx.add_vertex(Vector3::new(0.0, 1.0, 0.0));
+    let b = ctx.add_vertex(Vector3::new(0.0, 1.0, 2.0));
+    let c = ctx.add_vertex(Vector3::new(2.0, 1.0, 0.0));
+    ctx.add_triangle(TriangleDefinition([a, b, c]));
+    drop(ctx);
+
+    assert_eq!(
+        navmesh.triangle_at(Vector3::new(1.6, 0.1, 0.2), 0.5),
+        Some(2)
+    );
+    assert_eq!(
+        navmesh.triangle_at(Vector3::new(1.6, 0.1, 1.8), 0.5),
+        Some(7)
+    );
+    // The bridge is closer.
+    assert_eq!(
+        navmesh.triangle_at(Vector3::new(0.2, 0.7, 0.2), 1.0),
+        Some(8)
+    );
+    // The floor is closer.
+    assert_eq!(
+        navmesh.triangle_at(Vector3::new(0.2, 0.3, 0.2), 1.0),
+        Some(0)
+    );
+    // Too far vertically.
+    assert_eq!(navmesh.triangle_at(Vector3::new(0.2, 0.5, 0.2), 0.4), None);
+    // Outside of the navmesh.
+    assert_eq!(navmesh.triangle_at(Vector3::new(3.0, 0.0, 0.2), 1.0), None);
+}
+
+fn query_closest_linear(navmesh: &Navmesh, point: Vector3<f32>) -> Option<(Vector3<f32>, usize)> {
+    let mut closest = None;
+    let mut closest_distance = f32::MAX;
+    navmesh.query_closest_internal(
+        &mut closest,
+        &mut closest_distance,
+        0..navmesh.triangles.len(),
+        point,
+    );
+    closest
+}
+
+#[test]
+fn test_up_axis() {
+    let mut navmesh = make_grid_navmesh(2);
+    assert_eq!(navmesh.up_axis(), Vector3::y());
+
+    // Convert the navmesh to Z-up.
+    for vertex in navmesh.modify().vertices_mut() {
+        *vertex = Vector3::new(vertex.x, vertex.z, vertex.y);
+    }
+    let point = Vector3::new(0.5, 1.3, 0.3);
+    assert_eq!(navmesh.triangle_at(point, 0.5), None);
+    assert_eq!(navmesh.project_down(point, 1.0), None);
+
+    navmesh.set_up_axis(Vector3::new(0.0, 0.0, 2.0));
+    assert_eq!(navmesh.up_axis(), Vector3::z());
+    assert_eq!(navmesh.triangle_at(point, 0.5), Some(4));
+    // Distances to the border are measured on the horizontal plane.
+    let distance = navmesh.border_distance(Vector3::new(0.25, 1.0, 7.0));
+    assert!((distance - 0.25).abs() < 1.0e-5);
+
+    // Paths, raycasts and tiles work on the horizontal plane as well.
+    let from = Vector3::new(0.1, 0.1, 0.0);
+    let to = Vector3::new(1.9, 1.5, 0.0);
+    let expected = make_grid_navmesh(2)
+        .find_path(
+            Vector3::new(from.x, 0.0, from.y),
+            Vector3::new(to.x, 0.0, to.y),
+        )
+        .unwrap()
+        .into_iter()
+        .map(|point| Vector3::new(point.x, point.z, 0.0))
+        .collect::<Vec<_>>();
+    assert_eq!(navmesh.find_path(from, to), Ok(expected));
+    assert_eq!(navmesh.raycast(from, to), None);
+    let hit = navmesh.raycast(from, Vector3::new(2.5, 1.0, 0.0)).unwrap();
+    assert!((hit.position.x - 2.0).abs() < 1.0e-5);
+    navmesh.set_tile_size(1.0);
+    let triangle = navmesh.triangle_at(point, 0.5).unwrap();
+    assert!(navmesh
+        .tile_triangles(navmesh.tile_at(point))
+        .contains(&triangle));
+    assert_eq!(navmesh.triangle_at(point, 0.1), None);
+    let projection = navmesh.project_down(point, 1.0).unwrap();
+    assert!(projection.metric_distance(&Vector3::new(0.5, 1.3, 0.0)) < 1.0e-5);
+    assert_eq!(navmesh.project_down(point, 0.1), None);
+
+    // Heights are measured along the up axis.
+    navmesh.modify().vertices_mut()[4].z = 1.0;
+    let projection = navmesh
+        .project_down(Vector3::new(1.0, 1.0, 5.0), 10.0)
+        .unwrap();
+    assert!(projection.metric_distance(&Vector3::new(1.0, 1.0, 1.0)) < 1.0e-5);
+
+    // Zero axis is ignored.
+    navmesh.set_up_axis(Vector3::default());
+    assert_eq!(navmesh.up_axis(), Vector3::z());
+}
+
+#[test]
+fn test_acceleration_grid() {
+    let mut navmesh = make_grid_navmesh(8);
+
+    for z in -10..30 {
+        for x in -10..30 {
+            let point = Vector3::new(x as f32 * 0.37, (x - z) as f32 * 0.05, z as f32 * 0.41);
+            let (expected, _) = query_closest_linear(&navmesh, point).unwrap();
+            let (actual, _) = navmesh.query_closest(point).unwrap();
+            assert!(
+                (expected.metric_distance(&point) - actual.metric_distance(&point)).abs() < 1.0e-5
+            );
+        }
+    }
+
+    // The grid must be rebuilt after modification.
+    let mut ctx = navmesh.modify();
+    let a = ctx.add_vertex(Vector3::new(20.0, 0.0, 20.0));
+    let b = ctx.add_vertex(Vector3::new(20.0, 0.0, 21.0));
+    let c = ctx.add_vertex(Vector3::new(21.0, 0.0, 20.0));
+    ctx.add_triangle(TriangleDefinition([a, b, c]));
+    drop(ctx);
+    assert_eq!(
+        navmesh.triangle_at(Vector3::new(20.2, 0.0, 20.2), 0.1),
+        Some(128)
+    );
+
+    navmesh.rebuild_acceleration();
+    assert_eq!(
+        navmesh.query_closest(Vector3::new(25.0, 0.0, 25.0)),
+        Some((Vector3::new(20.5, 0.0, 20.5), 128))
+    );
+
+    assert_eq!(Navmesh::default().query_closest(Vector3::default()), None);
+    assert_eq!(
+        Navmesh::default().triangle_at(Vector3::default(), 1.0),
+        None
+    );
+}
+
+#[ignore = "takes multiple seconds to run"]
+#[test]
+fn acceleration_grid_benchmark() {
+    let navmesh = make_grid_navmesh(100);
+    let points = (0..100)
+        .map(|i| Vector3::new((i % 97) as f32 * 1.03, 0.3, (i % 89) as f32 * 1.11))
+        .collect::<Vec<_>>();
+
+    let start_time = std::time::Instant::now();
+    for point in points.iter() {
+        query_closest_linear(&navmesh, *point);
+    }
+    println!("linear scan: {:?}", start_time.elapsed());
+
+    // Build the grid first.
+    navmesh.query_closest(Vector3::default());
+
+    let start_time = std::time::Instant::now();
+    for point in points.iter() {
+        navmesh.query_closest(*point);
+    }
+    println!("grid: {:?}", start_time.elapsed());
+}
+
+#[test]
+fn test_async_path_requests() {
+    let navmesh = make_grid_navmesh(4);
+
+    let a = navmesh.request_path(Vector3::new(0.5, 0.0, 0.5), Vector3::new(3.5, 0.0, 3.5));
+    let b = navmesh.request_path(Vector3::new(0.5, 0.0, 0.5), Vector3::new(3.5, 0.0, 0.5));
+    let c = navmesh.request_path(Vector3::new(0.5, 0.0, 0.5), Vector3::new(0.5, 0.0, 3.5));
+    assert_eq!(navmesh.poll_path(a), PathStatus::Pending);
+
+    navmesh.cancel_path_request(c);
+    assert_eq!(navmesh.poll_path(c), PathStatus::Unknown);
+
+    assert_eq!(navmesh.process_path_requests(1), 1);
+    let PathStatus::Ready(path) = navmesh.poll_path(a) else {
+        panic!("The path must be ready.")
+    };
+    assert_eq!(path.first(), Some(&Vector3::new(0.5, 0.0, 0.5)));
+    assert_eq!(path.last(), Some(&Vector3::new(3.5, 0.0, 3.5)));
+    // The result is taken.
+    assert_eq!(navmesh.poll_path(a), PathStatus::Unknown);
+    assert_eq!(navmesh.poll_path(b), PathStatus::Pending);
+
+    assert_eq!(navmesh.process_path_requests(10), 1);
+    assert!(matches!(navmesh.poll_path(b), PathStatus::Ready(_)));
+
+    // Off-thread processing.
+    let navmesh = std::sync::Arc::new(navmesh);
+    let d = navmesh.request_path(Vector3::new(0.5, 0.0, 0.5), Vector3::new(3.5, 0.0, 3.5));
+    let worker_navmesh = navmesh.clone();
+    std::thread::spawn(move || worker_navmesh.process_path_requests(usize::MAX))
+        .join()
+        .unwrap();
+    assert!(matches!(navmesh.poll_path(d), PathStatus::Ready(_)));
+
+    // The agent keeps following its old path until the new one is ready.
+    let mut agent = NavmeshAgent::new();
+    agent.set_async_path_calculation(true);
+    agent.set_position(Vector3::new(0.5, 0.0, 0.5));
+    agent.set_target(Vector3::new(3.5, 0.0, 0.5));
+    agent.update(0.1, &navmesh).unwrap();
+    assert!(agent.is_waiting_for_path());
+    assert!(agent.path().is_empty());
+    navmesh.process_path_requests(1);
+    agent.update(0.1, &navmesh).unwrap();
+    assert!(!agent.is_waiting_for_path());
+    assert_eq!(agent.path().last(), Some(&Vector3::new(3.5, 0.0, 0.5)));
+
+    agent.set_target(Vector3::new(0.5, 0.0, 3.5));
+    agent.update(0.1, &navmesh).unwrap();
+    assert!(agent.is_waiting_for_path());
+    assert_eq!(agent.path().last(), Some(&Vector3::new(3.5, 0.0, 0.5)));
+    navmesh.process_path_requests(1);
+    agent.update(0.1, &navmesh).unwrap();
+    assert_eq!(agent.path().last(), Some(&Vector3::new(0.5, 0.0, 3.5)));
+}
+
+#[test]
+fn test_path_request_priority() {
+    let mut navmesh = make_grid_navmesh(4);
+    let (from, to) = (Vector3::new(0.5, 0.0, 0.5), Vector3::new(3.5, 0.0, 3.5));
+
+    let low = navmesh.request_path(from, to);
+    let high = navmesh.request_path_with_priority(from, to, 10);
+    let medium = navmesh.request_path_with_priority(from, to, 5);
+    let late_low = navmesh.request_path(from, to);
+    for expected in [high, medium, low, late_low] {
+        assert_eq!(navmesh.process_path_requests(1), 1);
+        assert!(matches!(navmesh.poll_path(expected), PathStatus::Ready(_)));
+    }
+
+    // The budget is spent by the first search.
+    navmesh.set_pathfinding_budget(Some(1));
+    assert_eq!(navmesh.pathfinding_budget(), Some(1));
+    let requests = [(); 3].map(|_| navmesh.request_path(from, to));
+    assert_eq!(navmesh.process_path_requests(usize::MAX), 1);
+    assert_eq!(navmesh.poll_path(requests[1]), PathStatus::Pending);
+    navmesh.set_pathfinding_budget(Some(0));
+    assert_eq!(navmesh.process_path_requests(usize::MAX), 0);
+    navmesh.set_pathfinding_budget(None);
+    assert_eq!(navmesh.process_path_requests(usize::MAX), 2);
+
+    // Requests of the agents with higher priority are calculated first.
+    let other = navmesh.request_path(from, to);
+    let mut agent = NavmeshAgent::new();
+    agent.set_async_path_calculation(true);
+    agent.set_path_priority(1);
+    agent.set_position(from);
+    agent.set_target(to);
+    agent.update(0.1, &navmesh).unwrap();
+    assert!(agent.is_waiting_for_path());
+    navmesh.process_path_requests(1);
+    assert_eq!(navmesh.poll_path(other), PathStatus::Pending);
+    agent.update(0.1, &navmesh).unwrap();
+    assert!(!agent.is_waiting_for_path());
+    assert_eq!(agent.path().last(), Some(&to));
+}
+
+#[test]
+fn test_obstacles() {
+    let mut navmesh = make_grid_navmesh(4);
+    let mut agent = NavmeshAgent::new();
+    agent.set_radius(0.0);
+    let src = Vector3::new(0.5, 0.0, 0.5);
+    let dest = Vector3::new(3.5, 0.0, 0.5);
+
+    agent.calculate_path(&navmesh, src, dest).unwrap();
+    assert!(agent.path().iter().all(|p| p.z == 0.5));
+
+    // A wall, that leaves a gap near the far border.
+    let wall = navmesh.add_obstacle(NavmeshObstacle::Box {
+        center: Vector3::new(2.0, 0.0, 1.5),
+        half_extents: Vector3::new(0.1, 1.0, 1.4),
+    });
+    assert!(navmesh.is_triangle_blocked(2));
+    assert!(!navmesh.is_triangle_blocked(28));
+    agent.calculate_path(&navmesh, src, dest).unwrap();
+    assert!(agent.path().iter().any(|p| p.z >= 3.0));
+
+    // Disabled obstacles do not block anything.
+    navmesh.set_obstacle_enabled(wall, false);
+    assert!(!navmesh.is_triangle_blocked(2));
+    agent.calculate_path(&navmesh, src, dest).unwrap();
+    assert!(agent.path().iter().all(|p| p.z == 0.5));
+
+    // A cylinder that blocks the target.
+    navmesh.set_obstacle_enabled(wall, true);
+    let pillar = navmesh.add_obstacle(NavmeshObstacle::Cylinder {
+        center: Vector3::new(3.5, 0.0, 3.5),
+        radius: 0.2,
+        height: 2.0,
+    });
+    assert!(navmesh.obstacle(pillar).unwrap().is_enabled());
+    assert_eq!(
+        agent.calculate_path(&navmesh, src, dest),
+        Ok(PathKind::Partial)
+    );
+
+    // The destination itself is blocked and partial paths are not allowed.
+    let blocker = navmesh.add_obstacle(NavmeshObstacle::Cylinder {
+        center: dest,
+        radius: 0.2,
+        height: 2.0,
+    });
+    agent.set_allow_partial_path(false);
+    assert_eq!(
+        agent.calculate_path(&navmesh, src, dest),
+        Err(NavmeshPathError::EndOffMesh)
+    );
+    agent.set_allow_partial_path(true);
+    navmesh.remove_obstacle(blocker);
+
+    // A cylinder high above the navmesh does not block anything.
+    navmesh.remove_obstacle(pillar);
+    navmesh.add_obstacle(NavmeshObstacle::Cylinder {
+        center: Vector3::new(3.5, 5.0, 3.5),
+        radius: 0.2,
+        height: 2.0,
+    });
+    assert_eq!(
+        agent.calculate_path(&navmesh, src, dest),
+        Ok(PathKind::Full)
+    );
+
+    assert_eq!(
+        navmesh.remove_obstacle(wall),
+        Some(NavmeshObstacle::Box {
+            center: Vector3::new(2.0, 0.0, 1.5),
+            half_extents: Vector3::new(0.1, 1.0, 1.4),
+        })
+    );
+    assert!((0..navmesh.triangles().len()).all(|i| !navmesh.is_triangle_blocked(i)));
+}
+
+#[test]
+fn test_obstacles_up_axis() {
+    let shapes = [
+        NavmeshObstacle::Box {
+            center: Vector3::new(2.0, 0.0, 1.5),
+            half_extents: Vector3::new(0.1, 1.0, 1.4),
+        },
+        NavmeshObstacle::Cylinder {
+            center: Vector3::new(3.5, 0.0, 3.5),
+            radius: 0.2,
+            height: 2.0,
+        },
+    ];
+    let blocked = |navmesh: &Navmesh| {
+        (0..navmesh.triangles().len())
+            .filter(|i| navmesh.is_triangle_blocked(*i))
+            .collect::<Vec<_>>()
+    };
+
+    let mut reference = make_grid_navmesh(4);
+    for shape in shapes {
+        reference.add_obstacle(shape);
+    }
+    let expected = blocked(&reference);
+    assert!(!expected.is_empty());
+
+    // The same navmesh and obstacles in Z-up space. The obstacles are aligned with the up axis
+    // again, when it is changed.
+    let rotation = UnitQuaternion::from_axis_angle(&Vector3::x_axis(), std::f32::consts::FRAC_PI_2);
+    let mut navmesh = make_grid_navmesh(4);
+    navmesh.transform(&rotation.to_homogeneous());
+    for shape in shapes {
+        navmesh.add_obstacle(match shape {
+            NavmeshObstacle::Box {
+                center,
+                half_extents,
+            } => NavmeshObstacle::Box {
+                center: rotation * center,
+                half_extents,
+            },
+            NavmeshObstacle::Cylinder {
+                center,
+                radius,
+                height,
+            } => NavmeshObstacle::Cylinder {
+                center: rotation * center,
+                radius,
+                height,
+            },
+        });
+    }
+    let revision = navmesh.revision();
+    navmesh.set_up_axis(Vector3::z());
+    assert_ne!(navmesh.revision(), revision);
+    assert_eq!(blocked(&navmesh), expected);
+}
+
+#[test]
+fn test_agent_replans_on_navmesh_change() {
+    let mut navmesh = make_grid_navmesh(4);
+    let mut agent = NavmeshAgent::new();
+    agent.set_radius(0.0);
+    agent.set_position(Vector3::new(0.5, 0.0, 0.5));
+    agent.set_target(Vector3::new(3.5, 0.0, 0.5));
+    agent.update(0.1, &navmesh).unwrap();
+    assert!(agent.path().iter().all(|p| p.z == 0.5));
+    while agent.pop_event().is_some() {}
+
+    // An obstacle away from the path does not cause recalculation.
+    let revision = navmesh.revision();
+    navmesh.add_obstacle(NavmeshObstacle::Cylinder {
+        center: Vector3::new(0.5, 0.0, 3.5),
+        radius: 0.2,
+        height: 2.0,
+    });
+    assert_ne!(navmesh.revision(), revision);
+    agent.update(0.1, &navmesh).unwrap();
+    assert_eq!(agent.pop_event(), None);
+
+    // A wall across the path, the path goes around its upper end.
+    navmesh.add_obstacle(NavmeshObstacle::Box {
+        center: Vector3::new(2.0, 0.0, 0.9),
+        half_extents: Vector3::new(0.1, 1.0, 0.9),
+    });
+    let position = agent.position();
+    agent.update(0.0, &navmesh).unwrap();
+    assert_eq!(agent.pop_event(), Some(NavmeshAgentEvent::PathRecalculated));
+    assert!(agent.path()[0].metric_distance(&position) < 1.0e-4);
+    assert!(agent.path().iter().any(|p| p.z >= 2.0));
+
+    // Manual invalidation ignores the recalculation interval.
+    agent.set_recalculation_interval(10.0);
+    agent.invalidate_path();
+    agent.update(0.1, &navmesh).unwrap();
+    assert_eq!(agent.pop_event(), Some(NavmeshAgentEvent::PathRecalculated));
+
+    // The corridor is not valid after a change of the topology, even if the path is still
+    // walkable.
+    let topology_revision = navmesh.topology_revision();
+    navmesh.rebuild();
+    assert_ne!(navmesh.topology_revision(), topology_revision);
+    agent.update(0.1, &navmesh).unwrap();
+    assert_eq!(agent.pop_event(), Some(NavmeshAgentEvent::PathRecalculated));
+    assert!(!agent.corridor().is_empty());
+}
+
+#[test]
+fn test_remaining_distance() {
+    let navmesh = make_grid_navmesh(4);
+
+    let mut agent = NavmeshAgent::new();
+    assert_eq!(agent.remaining_distance(), 0.0);
+    assert_eq!(agent.estimated_time(), 0.0);
+
+    agent.set_speed(2.0);
+    agent.set_position(Vector3::new(0.5, 0.0, 0.5));
+    agent.set_target(Vector3::new(3.5, 0.0, 0.5));
+    agent.update(0.0, &navmesh).unwrap();
+    assert!((agent.remaining_distance() - 3.0).abs() < 1.0e-5);
+    assert!((agent.estimated_time() - 1.5).abs() < 1.0e-5);
+
+    // Move the agent a bit.
+    agent.update(0.25, &navmesh).unwrap();
+    agent.update(0.0, &navmesh).unwrap();
+    assert!((agent.remaining_distance() - 2.5).abs() < 1.0e-5);
+
+    for _ in 0..100 {
+        agent.update(0.1, &navmesh).unwrap();
+    }
+    assert_eq!(agent.remaining_distance(), 0.0);
+    assert_eq!(agent.estimated_time(), 0.0);
+}
+
+#[test]
+fn test_desired_velocity() {
+    let navmesh = make_grid_navmesh(4);
+
+    let mut agent = NavmeshAgent::new();
+    assert_eq!(agent.desired_velocity(), Vector3::default());
+
+    agent.set_speed(2.0);
+    agent.set_slowdown_distance(1.0);
+    agent.set_position(Vector3::new(0.5, 0.0, 0.5));
+    agent.set_target(Vector3::new(3.5, 0.0, 0.5));
+    agent.update(0.0, &navmesh).unwrap();
+
+    // Far from the target, full speed towards the target.
+    let velocity = agent.desired_velocity();
+    assert!((velocity - Vector3::new(2.0, 0.0, 0.0)).norm() < 1.0e-5);
+    assert!(
+        (agent.steering_force(Vector3::new(1.0, 0.0, 1.0)) - Vector3::new(1.0, 0.0, -1.0)).norm()
+            < 1.0e-5
+    );
+
+    // Within the slowdown distance, the speed decreases.
+    while agent.remaining_distance() > 0.5 {
+        agent.update(0.05, &navmesh).unwrap();
+    }
+    let velocity = agent.desired_velocity();
+    assert!((velocity.norm() - 2.0 * agent.remaining_distance()).abs() < 1.0e-5);
+    assert!(velocity.x > 0.0);
+
+    for _ in 0..100 {
+        agent.update(0.1, &navmesh).unwrap();
+    }
+    assert_eq!(agent.desired_velocity(), Vector3::default());
+}
+
+#[test]
+fn test_agent_events() {
+    let mut navmesh = make_two_squares_navmesh();
+    let link = navmesh.add_offmesh_link(
+        Vector3::new(0.9, 1.0, 0.5),
+        Vector3::new(2.1, 0.0, 0.5),
+        false,
+    );
+
+    let mut agent = NavmeshAgent::new();
+    agent.set_arrival_radius(0.2);
+    assert_eq!(agent.arrival_radius(), 0.2);
+    agent.set_position(Vector3::new(0.25, 1.0, 0.5));
+    agent.set_target(Vector3::new(2.75, 0.0, 0.5));
+
+    let mut events = Vec::new();
+    for _ in 0..100 {
+        agent.update(0.1, &navmesh).unwrap();
+        while let Some(event) = agent.pop_event() {
+            events.push(event);
+        }
+    }
+    assert!(agent.target_reached());
+    assert_eq!(
+        events,
+        vec![
+            NavmeshAgentEvent::PathRecalculated,
+            NavmeshAgentEvent::EnteredOffMeshLink(link),
+            NavmeshAgentEvent::TargetReached
+        ]
+    );
+
+    // Unreachable target.
+    agent.set_target(Vector3::new(20.0, 0.0, 20.0));
+    assert!(agent.update(0.1, &Navmesh::default()).is_err());
+    assert_eq!(
+        agent.pop_event(),
+        Some(NavmeshAgentEvent::PathFailed(
+            NavmeshPathError::StartOffMesh
+        ))
+    );
+    assert!(!agent.target_reached());
+}
+
+#[test]
+fn test_agent_reacquire_radius() {
+    let navmesh = make_grid_navmesh(4);
+
+    let mut agent = NavmeshAgent::new();
+    agent.set_speed(1.0);
+    agent.set_threshold(0.0);
+    agent.set_reacquire_radius(0.5);
+    assert_eq!(agent.reacquire_radius(), 0.5);
+    agent.set_position(Vector3::new(0.5, 0.0, 0.5));
+    agent.set_target(Vector3::new(2.0, 0.0, 2.0));
+    for _ in 0..30 {
+        agent.update(0.1, &navmesh).unwrap();
+    }
+    assert!(agent.target_reached());
+    while agent.pop_event().is_some() {}
+
+    // The target hovers around a vertex, shared by multiple triangles.
+    let position = agent.position();
+    for i in 0..20 {
+        let offset = if i % 2 == 0 { 0.2 } else { -0.2 };
+        agent.set_target(Vector3::new(2.0 + offset, 0.0, 2.0 - offset));
+        agent.update(0.1, &navmesh).unwrap();
+        assert_eq!(agent.position(), position);
+    }
+    assert_eq!(agent.pop_event(), None);
+
+    // The target has moved away, the agent follows it right away.
+    agent.set_target(Vector3::new(3.5, 0.0, 3.5));
+    agent.update(0.1, &navmesh).unwrap();
+    assert_eq!(agent.pop_event(), Some(NavmeshAgentEvent::PathRecalculated));
+    assert!(!agent.target_reached());
+    for _ in 0..30 {
+        agent.update(0.1, &navmesh).unwrap();
+    }
+    assert!(agent.target_reached());
+    assert!(
+        agent
+            .position()
+            .metric_distance(&Vector3::new(3.5, 0.0, 3.5))
+            <= 0.1
+    );
+}
+
+#[test]
+fn test_recalculation_interval() {
+    let navmesh = make_grid_navmesh(10);
+
+    let mut agent = NavmeshAgent::new();
+    agent.set_threshold(0.1);
+    assert_eq!(agent.threshold(), 0.1);
+    agent.set_recalculation_interval(1.0);
+    agent.set_position(Vector3::new(0.5, 0.0, 0.5));
+
+    // A fast moving target.
+    let mut recalculations = 0;
+    for i in 0..30 {
+        agent.set_target(Vector3::new(5.5, 0.0, 0.5 + i as f32 * 0.2));
+        agent.update(0.1, &navmesh).unwrap();
+        while let Some(event) = agent.pop_event() {
+            if event == NavmeshAgentEvent::PathRecalculated {
+                recalculations += 1;
+            }
+        }
+        // The agent keeps following the previous path.
+        assert!(!agent.path().is_empty());
+    }
+    assert_eq!(recalculations, 3);
+    assert_eq!(agent.recalculation_interval(), 1.0);
+}
+
+#[test]
+fn test_agent_save_load() {
+    let navmesh = make_grid_navmesh(10);
+
+    let mut agent = NavmeshAgent::new();
+    agent.set_position(Vector3::new(0.5, 0.0, 0.5));
+    agent.set_target(Vector3::new(8.5, 0.0, 8.5));
+    for _ in 0..5 {
+        agent.update(0.1, &navmesh).unwrap();
+    }
+    while agent.pop_event().is_some() {}
+
+    let mut visitor = Visitor::new();
+    agent.visit("Agent", &mut visitor).unwrap();
+    let data = visitor.save_binary_to_vec().unwrap();
+    let mut visitor = Visitor::load_from_memory(&data).unwrap();
+    let mut loaded = NavmeshAgent::new();
+    loaded.visit("Agent", &mut visitor).unwrap();
+
+    assert_eq!(loaded.path(), agent.path());
+    assert_eq!(loaded.position(), agent.position());
+    assert_eq!(loaded.target(), agent.target());
+    assert_eq!(loaded.current, agent.current);
+    assert!(!loaded.path_dirty);
+
+    // The agent continues to follow the loaded path without recalculation.
+    loaded.update(0.1, &navmesh).unwrap();
+    agent.update(0.1, &navmesh).unwrap();
+    assert_eq!(loaded.position(), agent.position());
+    assert_eq!(loaded.pop_event(), None);
+
+    // Saves without the version marker are loaded with an outdated path.
+    let mut visitor = Visitor::new();
+    {
+        let mut region = visitor.enter_region("Agent").unwrap();
+        let mut position = Vector3::new(1.5f32, 0.0, 1.5);
+        position.visit("Position", &mut region).unwrap();
+    }
+    let data = visitor.save_binary_to_vec().unwrap();
+    let mut visitor = Visitor::load_from_memory(&data).unwrap();
+    let mut legacy = NavmeshAgent::new();
+    legacy.visit("Agent", &mut visitor).unwrap();
+    assert_eq!(legacy.position(), Vector3::new(1.5, 0.0, 1.5));
+    assert!(legacy.path_dirty);
+}
+
+#[test]
+fn test_append() {
+    // Two strips touching each other at x = 3.
+    let mut navmesh = make_strip_navmesh();
+    let mut other = make_strip_navmesh();
+    let mut ctx = other.modify();
+    for vertex in ctx.vertices_mut() {
+        vertex.x += 3.0 + 0.001;
+    }
+    drop(ctx);
+    other.set_triangle_cost(0, 5.0);
+
+    let vertex_count = navmesh.vertices().len();
+    let triangle_count = navmesh.triangles().len();
+
+    // Appending without welding gives two disconnected parts.
+    let mut disconnected = navmesh.clone();
+    disconnected.append(&other, 0.0);
+    assert_eq!(disconnected.vertices().len(), vertex_count * 2);
+    assert_eq!(disconnected.connected_components().len(), 2);
+
+    navmesh.append(&other, 0.01);
+    assert_eq!(navmesh.vertices().len(), vertex_count * 2 - 2);
+    assert_eq!(navmesh.triangles().len(), triangle_count * 2);
+    assert_eq!(navmesh.triangle_cost(triangle_count), Some(5.0));
+    assert_eq!(navmesh.connected_components().len(), 1);
+
+    let mut path = Vec::new();
+    assert_eq!(
+        navmesh.build_path(0, triangle_count * 2 - 1, &mut path),
+        Ok(PathKind::Full)
+    );
+
+    // Duplicate triangles, links and obstacles are skipped.
+    navmesh.add_offmesh_link(
+        Vector3::new(0.5, 0.0, 0.5),
+        Vector3::new(5.5, 0.0, 0.5),
+        true,
+    );
+    navmesh.add_obstacle(NavmeshObstacle::Cylinder {
+        center: Vector3::new(1.5, 0.0, 0.5),
+        radius: 0.2,
+        height: 2.0,
+    });
+    let copy = navmesh.clone();
+    navmesh.append(&copy, 0.01);
+    assert_eq!(navmesh.triangles().len(), triangle_count * 2);
+    assert_eq!(navmesh.vertices().len(), vertex_count * 2 - 2);
+    assert_eq!(navmesh.offmesh_links().count(), 1);
+    assert_eq!(navmesh.obstacles().count(), 1);
+}
+
+#[test]
+fn test_simplify() {
+    // A flat grid collapses into two triangles.
+    let mut navmesh = make_grid_navmesh(10);
+    let stats = navmesh.simplify(0.01);
+    assert_eq!(
+        stats,
+        NavmeshSimplifyStats {
+            triangles_before: 200,
+            triangles_after: 2,
+            vertices_before: 121,
+            vertices_after: 4,
+        }
+    );
+    let mut path = Vec::new();
+    assert_eq!(navmesh.build_path(0, 1, &mut path), Ok(PathKind::Full));
+
+    // Vertices, that are too far from the surface, are kept.
+    let mut navmesh = make_grid_navmesh(10);
+    navmesh.modify().vertices_mut()[60].y = 1.0;
+    navmesh.simplify(0.01);
+    assert!(navmesh.vertices().contains(&Vector3::new(5.0, 1.0, 5.0)));
+    assert!(navmesh.triangles().len() > 2);
+
+    // Areas with different costs keep their shape.
+    let mut navmesh = make_grid_navmesh(10);
+    navmesh.set_triangle_cost(0, 5.0);
+    navmesh.simplify(0.01);
+    assert_eq!(
+        (0..navmesh.triangles().len())
+            .filter(|i| navmesh.triangle_cost(*i) == Some(5.0))
+            .count(),
+        1
+    );
+
+    // One-way edges are kept.
+    let mut navmesh = make_grid_navmesh(10);
+    assert!(navmesh.set_edge_direction(1, 20, EdgeDirection::Forward));
+    let edge = [1, 20].map(|i| navmesh.triangle_centroid(i).unwrap());
+    navmesh.simplify(0.01);
+    let [a, b] = edge.map(|point| navmesh.query_closest(point).unwrap().1);
+    assert_eq!(navmesh.edge_direction(a, b), EdgeDirection::Forward);
+    assert_eq!(navmesh.one_way_edges.len(), 1);
+
+    // Islands are preserved.
+    let mut navmesh = make_grid_navmesh(4);
+    let mut other = make_grid_navmesh(4);
+    for vertex in other.modify().vertices_mut() {
+        vertex.x += 10.0;
+    }
+    navmesh.append(&other, 0.0);
+    navmesh.simplify(0.01);
+    assert_eq!(navmesh.connected_components().len(), 2);
+    assert_eq!(navmesh.triangles().len(), 4);
+}
+
+#[test]
+fn test_subdivide() {
+    let max_edge_length = 0.5;
+    let is_fine = |navmesh: &Navmesh, triangle: usize| {
+        navmesh.triangles()[triangle].edges().iter().all(|edge| {
+            navmesh.vertices()[edge.a as usize]
+                .metric_distance(&navmesh.vertices()[edge.b as usize])
+                <= max_edge_length + 1.0e-5
+        })
+    };
+
+    let mut navmesh = make_grid_navmesh(2);
+    navmesh.set_triangle_cost(0, 5.0);
+    navmesh.set_triangle_flags(0, 2);
+    assert!(navmesh.set_edge_direction(0, 1, EdgeDirection::Forward));
+    let far_triangle = navmesh.triangles()[7];
+
+    let pieces = navmesh.subdivide_triangle(0, max_edge_length);
+    assert!(pieces.len() > 1);
+    assert!(pieces.contains(&0));
+    let area = pieces
+        .iter()
+        .map(|i| navmesh.triangle_area(*i).unwrap())
+        .sum::<f32>();
+    assert!((area - 0.5).abs() < 1.0e-5);
+    for &piece in pieces.iter() {
+        assert!(is_fine(&navmesh, piece));
+        assert_eq!(navmesh.triangle_cost(piece), Some(5.0));
+        assert_eq!(navmesh.triangle_flags(piece), Some(2));
+    }
+    // The adjacent triangles are split to avoid T-junctions, the rest is untouched.
+    assert!(navmesh.triangles().len() > 8 + pieces.len() - 1);
+    assert_eq!(navmesh.triangles()[7], far_triangle);
+    assert!(navmesh.is_valid());
+    assert_eq!(navmesh.boundary_loops().len(), 1);
+    assert_eq!(navmesh.connected_components().len(), 1);
+    assert!((navmesh.total_area() - 4.0).abs() < 1.0e-4);
+    // The one-way edge is moved to the pieces.
+    assert!(!navmesh.one_way_edges.is_empty());
+    for [a, b] in navmesh.one_way_edges.iter() {
+        assert!(pieces.contains(&(*a as usize)));
+        assert_eq!(
+            navmesh.edge_direction(*a as usize, *b as usize),
+            EdgeDirection::Forward
+        );
+    }
+
+    // Small triangles are not split.
+    assert_eq!(navmesh.subdivide_triangle(0, max_edge_length), vec![0]);
+    assert!(navmesh.subdivide_triangle(1000, max_edge_length).is_empty());
+
+    let mut navmesh = make_grid_navmesh(2);
+    assert!(navmesh.subdivide(max_edge_length) > 0);
+    assert!((0..navmesh.triangles().len()).all(|i| is_fine(&navmesh, i)));
+    assert!(navmesh.is_valid());
+    assert_eq!(navmesh.boundary_loops().len(), 1);
+    assert!((navmesh.total_area() - 4.0).abs() < 1.0e-4);
+    assert_eq!(navmesh.subdivide(max_edge_length), 0);
+    let mut path = Vec::new();
+    assert_eq!(
+        navmesh.build_path(0, navmesh.triangles().len() - 1, &mut path),
+        Ok(PathKind::Full)
+    );
+}
+
+#[test]
+fn test_reachable_within() {
+    let mut navmesh = make_strip_navmesh();
+
+    let all = navmesh.reachable_within(Vector3::new(0.25, 0.0, 0.5), f32::MAX);
+    assert_eq!(all.len(), navmesh.triangles().len());
+    assert_eq!(
+        navmesh.reachable_within(Vector3::new(0.25, 0.0, 0.5), 0.0),
+        vec![all[0]]
+    );
+
+    // The range is limited by the cost.
+    let near = navmesh.reachable_within(Vector3::new(0.25, 0.0, 0.5), 1.0);
+    assert!(near.len() > 1 && near.len() < all.len());
+    assert_eq!(near[..], all[..near.len()]);
+
+    // Expensive triangles shrink the range.
+    navmesh.set_triangle_cost(near[1], 1000.0);
+    let expensive = navmesh.reachable_within(Vector3::new(0.25, 0.0, 0.5), 1.0);
+    assert!(!expensive.contains(&near[1]));
+}
+
+#[test]
+fn test_edges() {
+    let mut navmesh = make_strip_navmesh();
+
+    let edges = navmesh.edges().collect::<Vec<_>>();
+    // 6 triangles, 5 shared edges.
+    assert_eq!(edges.len(), 6 * 3 - 5);
+    assert_eq!(edges.iter().filter(|e| e.is_border()).count(), 8);
+    assert!(edges.contains(&NavmeshEdge {
+        vertices: [1, 3],
+        triangle: 0,
+        neighbor: Some(1)
+    }));
+
+    assert_eq!(navmesh.neighbors(0).collect::<Vec<_>>(), vec![1]);
+    let mut neighbors = navmesh.neighbors(2).collect::<Vec<_>>();
+    neighbors.sort_unstable();
+    assert_eq!(neighbors, vec![1, 3]);
+    assert_eq!(navmesh.neighbors(100).count(), 0);
+
+    // Off-mesh links are not edges.
+    navmesh.add_offmesh_link(
+        Vector3::new(0.1, 0.0, 0.1),
+        Vector3::new(2.9, 0.0, 0.9),
+        true,
+    );
+    assert_eq!(navmesh.neighbors(0).collect::<Vec<_>>(), vec![1]);
+    assert_eq!(navmesh.edges().count(), 6 * 3 - 5);
+}
+
+#[test]
+fn test_agent_max_path_cost() {
+    let mut navmesh = make_strip_navmesh();
+    // The middle quad is twice as expensive.
+    navmesh.set_triangle_cost(2, 2.0);
+    navmesh.set_triangle_cost(3, 2.0);
+
+    let mut agent = NavmeshAgent::new();
+    agent.set_radius(0.0);
+    agent.set_speed(1.0);
+    agent.set_max_path_cost(1.5);
+    agent.set_position(Vector3::new(0.25, 0.0, 0.5));
+    agent.set_target(Vector3::new(2.75, 0.0, 0.5));
+
+    // The first quad costs 0.75, the rest of the budget is enough for 0.375 meters of the
+    // middle quad.
+    for _ in 0..100 {
+        agent.update(0.1, &navmesh).unwrap();
+    }
+    assert!(agent.reached_budget_limit());
+    assert!(!agent.target_reached());
+    assert!(!agent.reached_full_target());
+    let end = *agent.path().last().unwrap();
+    assert!(end.metric_distance(&Vector3::new(1.375, 0.0, 0.5)) < 1.0e-3);
+    assert!(agent.position().metric_distance(&end) <= 0.1);
+
+    // The budget is spent already, a new path does not restore it. The full budget would be
+    // enough to reach the new target from the middle quad.
+    agent.set_target(Vector3::new(2.0, 0.0, 0.5));
+    for _ in 0..100 {
+        agent.update(0.1, &navmesh).unwrap();
+    }
+    assert!(agent.reached_budget_limit());
+    assert!(agent.position().x < 1.375 + 1.0e-3);
+
+    // The whole path costs 0.75 + 2 * 1.0 = 2.75, which is within the budget.
+    agent.set_max_path_cost(3.0);
+    agent.set_position(Vector3::new(0.25, 0.0, 0.5));
+    for _ in 0..100 {
+        agent.update(0.1, &navmesh).unwrap();
+    }
+    assert!(!agent.reached_budget_limit());
+    assert!(agent.reached_full_target());
+}
+
+#[test]
+fn test_agent_max_path_cost_off_corridor() {
+    // The budget is the walked distance, the finer triangulation does not change it.
+    for size in [4, 8] {
+        let navmesh = make_grid_navmesh(size);
+        let scale = 4.0 / size as f32;
+        let mut agent = NavmeshAgent::new();
+        agent.set_radius(0.0);
+        agent.set_speed(1.0);
+        agent.set_max_path_cost(2.0);
+        agent.set_position(Vector3::new(0.5, 0.0, 0.5).scale(1.0 / scale));
+        agent.set_target(Vector3::new(3.5, 0.0, 0.5).scale(1.0 / scale));
+        agent.update(0.0, &navmesh).unwrap();
+        let end = *agent.path().last().unwrap();
+        let expected = Vector3::new(0.5 + 2.0 * scale, 0.0, 0.5).scale(1.0 / scale);
+        assert!(end.metric_distance(&expected) < 1.0e-3);
+    }
+
+    let navmesh = make_grid_navmesh(4);
+    let mut agent = NavmeshAgent::new();
+    agent.set_radius(0.0);
+    agent.set_speed(1.0);
+    agent.set_max_path_cost(2.0);
+    agent.set_position(Vector3::new(0.5, 0.0, 0.5));
+    agent.set_target(Vector3::new(3.5, 0.0, 0.5));
+    for _ in 0..10 {
+        agent.update(0.1, &navmesh).unwrap();
+    }
+    assert!(
+        agent
+            .position()
+            .metric_distance(&Vector3::new(1.5, 0.0, 0.5))
+            < 0.11
+    );
+
+    // The agent is moved away from its corridor, the walked meter is still spent.
+    agent.set_position(Vector3::new(1.3, 0.0, 2.6));
+    agent.set_target(Vector3::new(1.3, 0.0, 3.9));
+    for _ in 0..30 {
+        agent.update(0.1, &navmesh).unwrap();
+    }
+    assert!(agent.reached_budget_limit());
+    let end = *agent.path().last().unwrap();
+    assert!(end.metric_distance(&Vector3::new(1.3, 0.0, 3.6)) < 1.0e-3);
+}
+
+#[test]
+fn test_find_path_with_cost() {
+    let mut navmesh = make_strip_navmesh();
+    navmesh.set_triangle_cost(2, 2.0);
+    navmesh.set_triangle_cost(3, 2.0);
+    navmesh.set_triangle_flags(4, 1);
+    navmesh.set_triangle_flags(5, 1);
+    let (from, to) = (Vector3::new(0.25, 0.0, 0.5), Vector3::new(2.75, 0.0, 0.5));
+
+    // The distances between the centers of the triangles are d inside the quads and e between
+    // them: d + 2 * e + 2 * d + e + d.
+    let (d, e) = (2.0f32.sqrt() / 3.0, 5.0f32.sqrt() / 3.0);
+    let path = navmesh
+        .find_path_with_cost(from, to, &Default::default())
+        .unwrap();
+    assert!((path.cost - (4.0 * d + 3.0 * e)).abs() < 1.0e-4);
+    assert_eq!(path.points, navmesh.find_path(from, to).unwrap());
+
+    // The cost multipliers of the filter are accounted: d + 2 * e + 2 * d + 3 * e + 3 * d.
+    let mut filter = NavmeshQueryFilter::default();
+    filter.set_flag_cost(1, 3.0);
+    let path = navmesh.find_path_with_cost(from, to, &filter).unwrap();
+    assert!((path.cost - (6.0 * d + 5.0 * e)).abs() < 1.0e-4);
+
+    // Cached paths keep the cost of the search.
+    navmesh.set_path_cache_capacity(4);
+    for _ in 0..2 {
+        let path = navmesh.find_path_with_cost(from, to, &filter).unwrap();
+        assert!((path.cost - (6.0 * d + 5.0 * e)).abs() < 1.0e-4);
+    }
+    assert_eq!(navmesh.path_cache_len(), 1);
+
+    let filter = NavmeshQueryFilter {
+        exclude_flags: 1,
+        ..Default::default()
+    };
+    assert_eq!(
+        navmesh.find_path_with_cost(from, to, &filter),
+        Err(NavmeshPathError::EndOffMesh)
+    );
+}
+
+#[test]
+fn test_agent_update_with_neighbors() {
+    let navmesh = make_grid_navmesh(5);
+    let start = Vector3::new(0.5, 0.0, 2.5);
+    let target = Vector3::new(4.5, 0.0, 2.5);
+    let neighbor = Vector3::new(2.5, 0.0, 2.5);
+
+    let mut agent = NavmeshAgent::new();
+    agent.set_speed(1.0);
+    agent.set_position(start);
+    agent.set_target(target);
+    let mut closest_distance = f32::MAX;
+    for _ in 0..200 {
+        agent
+            .update_with_neighbors(0.05, &navmesh, &[(neighbor, 0.3)])
+            .unwrap();
+        closest_distance = closest_distance.min(agent.position().metric_distance(&neighbor));
+    }
+    assert!(agent.target_reached());
+    // The agent must step around the neighbor, that stands right on its path.
+    assert!(closest_distance > 0.3);
+
+    // A wall of neighbors must not stop the agent.
+    let wall = (0..5)
+        .map(|z| (Vector3::new(2.5, 0.0, z as f32 + 0.5), 0.3))
+        .collect::<Vec<_>>();
+    agent.reset(start, &navmesh);
+    for _ in 0..200 {
+        agent.update_with_neighbors(0.05, &navmesh, &wall).unwrap();
+    }
+    assert!(agent.target_reached());
+}
+
+#[test]
+fn test_agent_update_with_moving_obstacles() {
+    let navmesh = make_grid_navmesh(10);
+    let start = Vector3::new(1.0, 0.0, 5.0);
+    let target = Vector3::new(9.0, 0.0, 5.0);
+    // A boulder rolls right toward the agent.
+    let boulder = MovingObstacle {
+        position: Vector3::new(8.0, 0.0, 5.0),
+        velocity: Vector3::new(-2.0, 0.0, 0.0),
+        radius: 0.5,
+    };
+    let simulate = |agent: &mut NavmeshAgent| {
+        let mut boulder = boulder;
+        let mut closest_distance = f32::MAX;
+        for _ in 0..300 {
+            agent
+                .update_with_moving_obstacles(0.05, &navmesh, &[boulder])
+                .unwrap();
+            boulder.position += boulder.velocity.scale(0.05);
+            closest_distance =
+                closest_distance.min(agent.position().metric_distance(&boulder.position));
+        }
+        closest_distance
+    };
+
+    let mut agent = NavmeshAgent::new();
+    assert_eq!(agent.avoidance_horizon(), 2.0);
+    agent.set_speed(1.0);
+    agent.set_position(start);
+    agent.set_target(target);
+    // The agent dodges the boulder and continues to its target.
+    assert!(simulate(&mut agent) > agent.radius() + boulder.radius - 0.05);
+    assert!(agent.target_reached());
+
+    // Without the avoidance the boulder hits the agent.
+    agent.set_avoidance_horizon(0.0);
+    agent.reset(start, &navmesh);
+    assert!(simulate(&mut agent) < agent.radius() + boulder.radius);
+    assert!(agent.target_reached());
+}
+
+#[test]
+fn test_agent_spline_smoothing() {
+    // A 3x3 grid with a hole in the middle, so the path has to turn around its corner.
+    let grid = make_grid_navmesh(3);
+    let triangles = grid
+        .triangles()
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| index / 2 != 4)
+        .map(|(_, triangle)| *triangle)
+        .collect::<Vec<_>>();
+    let navmesh = Navmesh::new(triangles, grid.vertices().to_vec());
+
+    let mut agent = NavmeshAgent::new();
+    assert_eq!(agent.smoothing_mode(), SmoothingMode::Funnel);
+    agent.set_smoothing_mode(SmoothingMode::None);
+    assert!(!agent.is_path_smoothing_enabled());
+    agent.set_smoothing_mode(SmoothingMode::Spline);
+    assert_eq!(agent.smoothing_mode(), SmoothingMode::Spline);
+    assert!(agent.is_path_smoothing_enabled());
+
+    agent.set_speed(1.0);
+    agent.set_position(Vector3::new(0.5, 0.0, 0.5));
+    agent.set_target(Vector3::new(2.5, 0.0, 2.5));
+
+    // The steps are short enough to sample the rounded corner of the path.
+    let mut previous_heading = None;
+    let mut max_turn = 0.0f32;
+    let mut steps = Vec::new();
+    for _ in 0..400 {
+        let position = agent.position();
+        agent.update(0.02, &navmesh).unwrap();
+        // The agent must stay on the navmesh, even if the spline cuts the corner.
+        let (closest, _) = navmesh.query_closest(agent.position()).unwrap();
+        assert!(closest.metric_distance(&agent.position()) < 1.0e-4);
+        if let Some(previous) = previous_heading {
+            max_turn = max_turn.max(agent.heading().angle(&previous));
+        }
+        previous_heading = Some(agent.heading());
+        if !agent.target_reached() {
+            steps.push(agent.position().metric_distance(&position));
+        }
+    }
+    assert!(agent.target_reached());
+    assert!(agent.path().len() > 2);
+    // The heading changes continuously, there are no sharp turns at the waypoints.
+    assert!(max_turn < 15.0f32.to_radians());
+    // The spline is parametrized by its length, so the speed does not depend on the lengths
+    // of the segments (except the steps, that end at the waypoints).
+    let even_steps = steps
+        .iter()
+        .filter(|step| (*step - 0.02).abs() < 2.5e-3)
+        .count();
+    assert!(even_steps >= steps.len() * 9 / 10);
+
+    agent.set_path_smoothing(false);
+    assert_eq!(agent.smoothing_mode(), SmoothingMode::None);
+}
+
+#[test]
+fn test_boundary_loops() {
+    // A 3x3 grid with a hole in the middle and a separate triangle.
+    let grid = make_grid_navmesh(3);
+    let mut triangles = grid
+        .triangles()
+        .iter()
+        .enumerate()
+        .filter(|(index, _)| index / 2 != 4)
+        .map(|(_, triangle)| *triangle)
+        .collect::<Vec<_>>();
+    let mut vertices = grid.vertices().to_vec();
+    vertices.extend([
+        Vector3::new(5.0, 0.0, 0.0),
+        Vector3::new(5.0, 0.0, 1.0),
+        Vector3::new(6.0, 0.0, 0.0),
+    ]);
+    triangles.push(TriangleDefinition([16, 17, 18]));
+    let navmesh = Navmesh::new(triangles, vertices);
+
+    let loops = navmesh.boundary_loops();
+    assert_eq!(loops.len(), 3);
+    assert_eq!(loops[0].len(), 12);
+    let mut hole = loops[1].clone();
+    hole.sort_unstable();
+    assert_eq!(hole, vec![5, 6, 9, 10]);
+    let mut island = loops[2].clone();
+    island.sort_unstable();
+    assert_eq!(island, vec![16, 17, 18]);
+
+    for ring in loops.iter() {
+        for (i, &vertex) in ring.iter().enumerate() {
+            let next = ring[(i + 1) % ring.len()];
+            assert!(navmesh.vertices()[vertex].metric_distance(&navmesh.vertices()[next]) <= 1.5);
+        }
+    }
+
+    // The outer loop has the winding of the triangles, the hole has the opposite one.
+    let area = |ring: &[usize]| {
+        let ring = ring.iter().map(|index| *index as u32).collect::<Vec<_>>();
+        ring_area_2d(&ring, navmesh.vertices())
+    };
+    assert_eq!(area(&loops[0]), 9.0);
+    assert_eq!(area(&loops[1]), -1.0);
+    assert_eq!(area(&loops[2]), 0.5);
+
+    assert!(Navmesh::default().boundary_loops().is_empty());
+}
+
+#[test]
+fn test_reflection() {
+    let mut navmesh = make_strip_navmesh();
+
+    navmesh.fields_info(&mut |fields| {
+        let field = |name: &str| fields.iter().find(|f| f.name == name).unwrap();
+        assert!(field("triangles").read_only);
+        assert!(field("vertices").read_only);
+        assert!(!field("triangle_costs").read_only);
+        assert!(field("triangle_costs").immutable_collection);
+        assert!(!fields.iter().any(|f| f.name == "octree"));
+    });
+
+    navmesh.get_resolve_path_mut::<f32>("triangle_costs[2]", &mut |cost| {
+        *cost.unwrap() = 4.0;
+    });
+    navmesh.rebuild();
+
+    assert_eq!(navmesh.triangle_cost(2), Some(4.0));
+    assert_eq!(navmesh.graph.vertices[2].g_penalty, 4.0);
+}
+
+#[test]
+fn test_agent_reflection() {
+    let mut agent = NavmeshAgent::new();
+
+    agent.fields_info(&mut |fields| {
+        let has_field = |name: &str| fields.iter().any(|f| f.name == name);
+        assert!(has_field("speed"));
+        assert!(has_field("radius"));
+        assert!(has_field("arrival_radius"));
+        assert!(has_field("query_filter"));
+        // The state of the path is hidden.
+        assert!(!has_field("path"));
+        assert!(!has_field("target"));
+        assert!(!has_field("corridor"));
+    });
+
+    agent.get_resolve_path_mut::<f32>("speed", &mut |speed| {
+        *speed.unwrap() = 3.0;
+    });
+    agent.set_field("smoothing", Box::new(SmoothingMode::None), &mut |result| {
+        let prev = result.unwrap().take::<SmoothingMode>().unwrap();
+        assert_eq!(prev, SmoothingMode::Funnel);
+    });
+    assert_eq!(agent.speed(), 3.0);
+    assert_eq!(agent.smoothing_mode(), SmoothingMode::None);
+
+    // Edits go through the setters, so the path is recalculated when the radius changes.
+    agent.path_dirty = false;
+    agent.set_field("radius", Box::new(0.5f32), &mut |result| {
+        assert!(result.is_ok());
+    });
+    assert_eq!(agent.radius(), 0.5);
+    assert!(agent.path_dirty);
+
+    // The clamps of the setters are applied as well.
+    agent.set_field("turning_radius", Box::new(-1.0f32), &mut |result| {
+        assert!(result.is_ok());
+    });
+    assert_eq!(agent.turning_radius(), 0.0);
+
+    // Nested fields of the filter are edited without the setter, but still invalidate the path.
+    agent.path_dirty = false;
+    agent.get_resolve_path_mut::<u32>("query_filter.layer_mask", &mut |mask| {
+        *mask.unwrap() = 0b10;
+    });
+    let navmesh = Navmesh::new(
+        vec![TriangleDefinition([0, 1, 2])],
+        vec![
+            Vector3::new(0.0, 0.0, 0.0),
+            Vector3::new(0.0, 0.0, 1.0),
+            Vector3::new(1.0, 0.0, 0.0),
+        ],
+    );
+    // Keep the previous path for a while, so the dirty flag could be observed.
+    agent.path = vec![Vector3::default(); 2];
+    agent.set_recalculation_interval(100.0);
+    agent.time_since_recalculation = 0.0;
+    agent.update_path(0.0, &navmesh).unwrap();
+    assert_eq!(agent.layer_mask(), 0b10);
+    assert!(agent.path_dirty);
+
+    // The configuration survives serialization.
+    let mut visitor = Visitor::new();
+    agent.visit("Agent", &mut visitor).unwrap();
+    let data = visitor.save_binary_to_vec().unwrap();
+    let mut visitor = Visitor::load_from_memory(&data).unwrap();
+    let mut loaded = NavmeshAgent::new();
+    loaded.visit("Agent", &mut visitor).unwrap();
+    assert_eq!(loaded.speed(), 3.0);
+    assert_eq!(loaded.smoothing_mode(), SmoothingMode::None);
+}